@@ -1,3 +1,9 @@
+mod tool_kill_switch;
 mod tool_pump_fun_buy;
+mod tool_scheduled_orders;
+mod tool_trade_history;
 
+pub use tool_kill_switch::*;
 pub use tool_pump_fun_buy::*;
+pub use tool_scheduled_orders::*;
+pub use tool_trade_history::*;
@@ -0,0 +1,82 @@
+use crate::trading::RiskEngine;
+use anyhow::Result;
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+#[derive(Deserialize, Debug)]
+pub struct KillSwitchArgs {
+    /// "halt", "resume" or "status"
+    action: String,
+    /// Why the buys are halted
+    reason: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum KillSwitchError {
+    #[error("Unknown action: {0}")]
+    UnknownAction(String),
+}
+
+pub struct ToolKillSwitch {
+    risk: Arc<RiskEngine>,
+}
+
+impl ToolKillSwitch {
+    pub fn new(risk: Arc<RiskEngine>) -> Self {
+        Self { risk }
+    }
+
+    fn status(&self) -> String {
+        match self.risk.halted() {
+            Some(reason) => format!("New buys are halted: {reason}"),
+            None => "New buys are allowed".to_string(),
+        }
+    }
+}
+
+impl Tool for ToolKillSwitch {
+    const NAME: &'static str = "kill_switch";
+    type Error = KillSwitchError;
+    type Args = KillSwitchArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Halt or resume all the new buys of the wallet, or tell whether they are halted and why. Buys are also halted automatically for the rest of the UTC day once the daily loss cap or the failed sends limit is reached. Sells are never halted.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "required": ["action"],
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["halt", "resume", "status"],
+                        "description": "Whether to halt the new buys until they are resumed, resume them or report whether they are halted."
+                    },
+                    "reason": {
+                        "type": "string",
+                        "description": "Why the buys are halted, reported while they are. If the reason is not provided, the tool will use \"halted by the user\"."
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        println!("[ToolKillSwitch] Args {:?}", args);
+
+        match args.action.as_str() {
+            "halt" => {
+                self.risk
+                    .halt(args.reason.as_deref().unwrap_or("halted by the user"));
+            }
+            "resume" => self.risk.resume(),
+            "status" => {}
+            action => return Err(KillSwitchError::UnknownAction(action.to_string())),
+        }
+
+        Ok(self.status())
+    }
+}
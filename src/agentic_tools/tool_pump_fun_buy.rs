@@ -1,12 +1,15 @@
-use crate::{bot::PumpFunTxSend, db::Database};
+use crate::{
+    bot::{Route, Router, TradeSide, VenueContext},
+    db::Database,
+    errors::{RiskError, SafetyError},
+    trading::{RiskEngine, TokenSafety},
+};
 use anyhow::Result;
-use helius::Helius;
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::Deserialize;
 use serde_json::json;
 use solana_sdk::pubkey::Pubkey;
 use std::{str::FromStr, sync::Arc};
-use tokio::sync::Mutex;
 
 #[derive(Deserialize, Debug)]
 pub struct PumpFunBuyArgs {
@@ -16,6 +19,9 @@ pub struct PumpFunBuyArgs {
     max_sol: f64,
     /// Maximum slippage percentage (0-100)
     slippage: f64,
+    /// Only quote the buy, to report its price impact before buying
+    #[serde(default)]
+    quote_only: bool,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -26,24 +32,43 @@ pub enum PumpFunError {
     InvalidSlippage(f64),
     #[error("Transaction error: {0}")]
     TransactionError(String),
+    #[error("Invalid mint address: {0}")]
+    InvalidMint(String),
     #[error("No accounts configured for mint address: {0}")]
     NoAccountsConfigured(String),
+    #[error("Rejected by the risk engine: {0}")]
+    RiskRejected(RiskError),
+    #[error("Blocked by the safety checks: {0}")]
+    Unsafe(SafetyError),
     #[error("Join error: {0}")]
     JoinError(tokio::task::JoinError),
 }
 
 pub struct ToolPumpFunBuy {
-    helius: Arc<Mutex<Helius>>,
+    venue_context: VenueContext,
+    router: Arc<Router>,
     signer_prv_key: Arc<String>,
     db: Arc<Database>,
+    risk: Arc<RiskEngine>,
+    safety: Arc<TokenSafety>,
 }
 
 impl ToolPumpFunBuy {
-    pub fn new(helius: Arc<Mutex<Helius>>, signer_prv_key: Arc<String>, db: Arc<Database>) -> Self {
+    pub fn new(
+        venue_context: VenueContext,
+        router: Arc<Router>,
+        signer_prv_key: Arc<String>,
+        db: Arc<Database>,
+        risk: Arc<RiskEngine>,
+        safety: Arc<TokenSafety>,
+    ) -> Self {
         Self {
-            helius,
+            venue_context,
+            router,
             signer_prv_key,
             db,
+            risk,
+            safety,
         }
     }
 }
@@ -57,7 +82,7 @@ impl Tool for ToolPumpFunBuy {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         serde_json::from_value(json!({
             "name": "pump_fun_buy",
-            "description": "Buy a meme coin on Pump.fun using SOL",
+            "description": "Buy a meme coin using SOL, on Pump.fun or on the AMM pools it trades on, whichever gives the most tokens",
             "parameters": {
                 "type": "object",
                 "required": ["mint", "max_sol", "slippage"],
@@ -73,6 +98,10 @@ impl Tool for ToolPumpFunBuy {
                     "slippage": {
                         "type": "number",
                         "description": "Maximum slippage percentage (0-100). If the slippage is not provided, the tool will use the default value of 10."
+                    },
+                    "quote_only": {
+                        "type": "boolean",
+                        "description": "When true, the buy is only quoted: the tokens expected and the price impact of the order are returned and nothing is bought. Quote first and report the expected price impact to the user before buying."
                     }
                 }
             }
@@ -81,9 +110,12 @@ impl Tool for ToolPumpFunBuy {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        let helius = Arc::clone(&self.helius);
+        let venue_context = self.venue_context.clone();
+        let router = Arc::clone(&self.router);
         let signer_prv_key = Arc::clone(&self.signer_prv_key);
         let db = Arc::clone(&self.db);
+        let risk = Arc::clone(&self.risk);
+        let safety = Arc::clone(&self.safety);
 
         // Spawing a new tokio task to comply with the trait bounds of Send + Sync
         tokio::spawn(async move {
@@ -122,34 +154,85 @@ impl Tool for ToolPumpFunBuy {
             // Convert SOL to lamports (1 SOL = 1_000_000_000 lamports)
             let max_sol_lamports = (args.max_sol * 1_000_000_000.0) as u64;
 
-            // Convert SOL price to lamports
-            let price_in_sol_lamports = (accounts.price * 1_000_000_000.0) as f64;
-
-            // Convert slippage to basis points (1% = 100 bps)
-            let slippage_bps = (args.slippage * 100.0) as u64;
-
-            let bonding_curve = Pubkey::from_str(&accounts.bonding_curve).unwrap();
-            let associated_bonding_curve =
-                Pubkey::from_str(&accounts.associated_bonding_curve).unwrap();
-            let mint = Pubkey::from_str(&accounts.mint_address).unwrap();
-
-            match PumpFunTxSend::buy(
-                helius,
-                signer_prv_key,
-                bonding_curve,
-                associated_bonding_curve,
-                mint,
-                accounts.decimals,
-                price_in_sol_lamports,
-                max_sol_lamports,
-                slippage_bps,
-            )
-            .await
-            {
-                Ok(tx_id) => Ok(format!(
-                    "Pump.fun buy transaction sent successfully, https://solscan.io/tx/{}",
-                    tx_id
-                )),
+            // Swaps take whole percents, rounding up keeps the buy from failing
+            let slippage_percent = args.slippage.ceil() as u64;
+
+            let mint = Pubkey::from_str(&accounts.mint_address)
+                .map_err(|_| PumpFunError::InvalidMint(accounts.mint_address.clone()))?;
+
+            // Routed like the buy, so the impact reported is the one it would have
+            if args.quote_only {
+                let route = router
+                    .route(&venue_context, &mint, TradeSide::Buy, max_sol_lamports)
+                    .await
+                    .map_err(|e| PumpFunError::TransactionError(e.to_string()))?;
+                println!("[ToolPumpFunBuy] {route}");
+                return Ok(format!(
+                    "Quote for {} SOL of {mint}: {} raw tokens on {}, expected price impact {:.2}%{}",
+                    route.amount_in as f64 / 1_000_000_000.0,
+                    route.amount_out(),
+                    route_venues(&route),
+                    route.price_impact() * 100.0,
+                    if route.amount_in < max_sol_lamports {
+                        " (shrunk to fit the max price impact)"
+                    } else {
+                        ""
+                    }
+                ));
+            }
+
+            safety
+                .check_buy(&mint)
+                .await
+                .map_err(|e| PumpFunError::TransactionError(e.to_string()))?
+                .map_err(PumpFunError::Unsafe)?;
+
+            // Kept until the position of the buy is recorded
+            let _permit = risk
+                .approve_buy(&mint, max_sol_lamports)
+                .await
+                .map_err(|e| PumpFunError::TransactionError(e.to_string()))?
+                .map_err(PumpFunError::RiskRejected)?;
+
+            // The router quotes every venue trading the mint
+            let result = router
+                .buy(
+                    &venue_context,
+                    signer_prv_key,
+                    &mint,
+                    max_sol_lamports,
+                    slippage_percent,
+                )
+                .await;
+            risk.record_send(result.is_ok());
+
+            match result {
+                Ok((route, signatures)) => {
+                    let positions = risk.positions();
+                    let (tokens, net_lamports) = positions
+                        .ledger()
+                        .book_all(&signatures, &mint, None, "agent")
+                        .await;
+                    let lamports = if tokens > 0 {
+                        net_lamports.unsigned_abs()
+                    } else {
+                        max_sol_lamports
+                    };
+
+                    if let Err(e) = positions.record_route_buy(&route, lamports).await {
+                        eprintln!("[ToolPumpFunBuy] Error recording the position: {e:?}");
+                    }
+                    Ok(format!(
+                        "Buy transaction(s) sent successfully on {}, expected price impact {:.2}%, {}",
+                        route_venues(&route),
+                        route.price_impact() * 100.0,
+                        signatures
+                            .iter()
+                            .map(|tx_id| format!("https://solscan.io/tx/{tx_id}"))
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    ))
+                }
                 Err(e) => Err(PumpFunError::TransactionError(e.to_string())),
             }
         })
@@ -157,3 +240,13 @@ impl Tool for ToolPumpFunBuy {
         .map_err(PumpFunError::JoinError)?
     }
 }
+
+/// Returns the venues of the legs of a route, comma separated
+fn route_venues(route: &Route) -> String {
+    route
+        .legs
+        .iter()
+        .map(|leg| leg.venue.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,320 @@
+use crate::{
+    bot::TradeSide,
+    db::Database,
+    trading::{OrderSchedule, OrderScheduler},
+};
+use anyhow::Result;
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::Deserialize;
+use serde_json::json;
+use solana_sdk::pubkey::Pubkey;
+use std::{str::FromStr, sync::Arc};
+
+#[derive(Deserialize, Debug)]
+pub struct ScheduledOrdersArgs {
+    /// "create", "list" or "cancel"
+    action: String,
+    /// The mint address or the name of the meme coin
+    mint: Option<String>,
+    /// "buy" or "sell"
+    side: Option<String>,
+    /// "twap", "dca" or "scale_out"
+    kind: Option<String>,
+    /// SOL spent on a buy
+    sol: Option<f64>,
+    /// Percentage of the tokens held sold on a sell
+    percent: Option<f64>,
+    /// Minutes a TWAP is spread over
+    duration_minutes: Option<f64>,
+    /// Minutes between two DCA children
+    interval_minutes: Option<f64>,
+    /// Children a TWAP or DCA is split in
+    slices: Option<u64>,
+    /// Price rise triggering a scale out sell, in percent
+    rise_percent: Option<f64>,
+    /// Percentage of the order sold on each scale out sell
+    sell_percent: Option<f64>,
+    /// Maximum slippage percentage (0-100) of each child
+    slippage: Option<f64>,
+    /// The order to cancel
+    id: Option<i64>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduledOrdersError {
+    #[error("Unknown action: {0}")]
+    UnknownAction(String),
+    #[error("Missing argument: {0}")]
+    MissingArgument(&'static str),
+    #[error("Invalid argument {0}: {1}")]
+    InvalidArgument(&'static str, String),
+    #[error("Invalid mint address: {0}")]
+    InvalidMint(String),
+    #[error("No accounts configured for mint address: {0}")]
+    NoAccountsConfigured(String),
+    #[error("No active scheduled order #{0}")]
+    NotFound(i64),
+    #[error("Scheduler error: {0}")]
+    SchedulerError(String),
+    #[error("Join error: {0}")]
+    JoinError(tokio::task::JoinError),
+}
+
+pub struct ToolScheduledOrders {
+    scheduler: Arc<OrderScheduler>,
+    db: Arc<Database>,
+}
+
+impl ToolScheduledOrders {
+    pub fn new(scheduler: Arc<OrderScheduler>, db: Arc<Database>) -> Self {
+        Self { scheduler, db }
+    }
+}
+
+impl Tool for ToolScheduledOrders {
+    const NAME: &'static str = "scheduled_orders";
+    type Error = ScheduledOrdersError;
+    type Args = ScheduledOrdersArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Create, list or cancel scheduled orders splitting a larger buy or sell of a meme coin into smaller swaps over time: a TWAP spreads it evenly over a duration, a DCA sends a slice at a fixed interval, and a scale out sells part of the tokens every time the price rises by a percentage. Orders survive restarts.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "required": ["action"],
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["create", "list", "cancel"],
+                        "description": "Whether to create an order, list the orders or cancel an active order."
+                    },
+                    "mint": {
+                        "type": "string",
+                        "description": "The mint address of the meme coin or the meme coin name, required to create an order. If the meme coin name is provided the mint address should be retrieved from the local cache."
+                    },
+                    "side": {
+                        "type": "string",
+                        "enum": ["buy", "sell"],
+                        "description": "Whether the order buys or sells the meme coin, required to create an order. Scale outs only sell."
+                    },
+                    "kind": {
+                        "type": "string",
+                        "enum": ["twap", "dca", "scale_out"],
+                        "description": "How the order is split, required to create an order."
+                    },
+                    "sol": {
+                        "type": "number",
+                        "description": "Total SOL spent by a buy order."
+                    },
+                    "percent": {
+                        "type": "number",
+                        "description": "Percentage (0-100) of the tokens held sold by a sell order. If the percent is not provided, the tool will sell all the tokens held."
+                    },
+                    "duration_minutes": {
+                        "type": "number",
+                        "description": "Minutes a TWAP order is spread over."
+                    },
+                    "interval_minutes": {
+                        "type": "number",
+                        "description": "Minutes between two slices of a DCA order."
+                    },
+                    "slices": {
+                        "type": "number",
+                        "description": "Number of slices a TWAP or DCA order is split in."
+                    },
+                    "rise_percent": {
+                        "type": "number",
+                        "description": "Price rise in percent triggering each sell of a scale out order, e.g. 20."
+                    },
+                    "sell_percent": {
+                        "type": "number",
+                        "description": "Percentage of the order sold on each rise of a scale out order, e.g. 10."
+                    },
+                    "slippage": {
+                        "type": "number",
+                        "description": "Maximum slippage percentage (0-100) of each slice. If the slippage is not provided, the tool will use the default value of 10."
+                    },
+                    "id": {
+                        "type": "number",
+                        "description": "The id of the order to cancel."
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let scheduler = Arc::clone(&self.scheduler);
+        let db = Arc::clone(&self.db);
+
+        // Spawing a new tokio task to comply with the trait bounds of Send + Sync
+        tokio::spawn(async move {
+            println!("[ToolScheduledOrders] Args {:?}", args);
+
+            let action = args.action.clone();
+            match action.as_str() {
+                "list" => {
+                    let orders = scheduler
+                        .orders()
+                        .await
+                        .map_err(|e| ScheduledOrdersError::SchedulerError(e.to_string()))?;
+                    if orders.is_empty() {
+                        return Ok("No scheduled orders".to_string());
+                    }
+                    Ok(orders
+                        .iter()
+                        .map(|order| order.to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n"))
+                }
+                "cancel" => {
+                    let id = args.id.ok_or(ScheduledOrdersError::MissingArgument("id"))?;
+                    let order = scheduler
+                        .cancel(id)
+                        .await
+                        .map_err(|e| ScheduledOrdersError::SchedulerError(e.to_string()))?
+                        .ok_or(ScheduledOrdersError::NotFound(id))?;
+                    Ok(format!("Cancelled scheduled order {order}"))
+                }
+                "create" => create(&scheduler, &db, args).await,
+                action => Err(ScheduledOrdersError::UnknownAction(action.to_string())),
+            }
+        })
+        .await
+        .map_err(ScheduledOrdersError::JoinError)?
+    }
+}
+
+/// Creates the order described by the tool arguments
+async fn create(
+    scheduler: &OrderScheduler,
+    db: &Database,
+    args: ScheduledOrdersArgs,
+) -> Result<String, ScheduledOrdersError> {
+    let name = args
+        .mint
+        .ok_or(ScheduledOrdersError::MissingArgument("mint"))?;
+    let accounts = match db
+        .get_pump_fun_coin_accounts_by_mint_address(&name)
+        .await
+        .map_err(|_| ScheduledOrdersError::NoAccountsConfigured(name.clone()))?
+    {
+        Some(accounts) => accounts,
+        None => db
+            .get_pump_fun_coin_accounts_by_name(&name.to_ascii_uppercase())
+            .await
+            .map_err(|_| ScheduledOrdersError::NoAccountsConfigured(name.clone()))?
+            .ok_or(ScheduledOrdersError::NoAccountsConfigured(name))?,
+    };
+    let mint = Pubkey::from_str(&accounts.mint_address)
+        .map_err(|_| ScheduledOrdersError::InvalidMint(accounts.mint_address.clone()))?;
+
+    let side = match args.side.as_deref() {
+        Some("buy") => TradeSide::Buy,
+        Some("sell") => TradeSide::Sell,
+        Some(side) => {
+            return Err(ScheduledOrdersError::InvalidArgument(
+                "side",
+                side.to_string(),
+            ))
+        }
+        None => return Err(ScheduledOrdersError::MissingArgument("side")),
+    };
+
+    let schedule = match args.kind.as_deref() {
+        Some("twap") => OrderSchedule::Twap {
+            duration_secs: minutes_to_secs(
+                "duration_minutes",
+                args.duration_minutes
+                    .ok_or(ScheduledOrdersError::MissingArgument("duration_minutes"))?,
+            )?,
+            slices: args
+                .slices
+                .ok_or(ScheduledOrdersError::MissingArgument("slices"))?,
+        },
+        Some("dca") => OrderSchedule::Dca {
+            interval_secs: minutes_to_secs(
+                "interval_minutes",
+                args.interval_minutes
+                    .ok_or(ScheduledOrdersError::MissingArgument("interval_minutes"))?,
+            )?,
+            slices: args
+                .slices
+                .ok_or(ScheduledOrdersError::MissingArgument("slices"))?,
+        },
+        Some("scale_out") => OrderSchedule::ScaleOut {
+            rise_percent: args
+                .rise_percent
+                .ok_or(ScheduledOrdersError::MissingArgument("rise_percent"))?,
+            sell_percent: args
+                .sell_percent
+                .ok_or(ScheduledOrdersError::MissingArgument("sell_percent"))?,
+        },
+        Some(kind) => {
+            return Err(ScheduledOrdersError::InvalidArgument(
+                "kind",
+                kind.to_string(),
+            ))
+        }
+        None => return Err(ScheduledOrdersError::MissingArgument("kind")),
+    };
+
+    let slippage = args.slippage.unwrap_or(10.0);
+    if !(0.0..=100.0).contains(&slippage) {
+        return Err(ScheduledOrdersError::InvalidArgument(
+            "slippage",
+            slippage.to_string(),
+        ));
+    }
+    // Swaps take whole percents, rounding up keeps the children from failing
+    let slippage_percent = slippage.ceil() as u64;
+
+    let total = match side {
+        TradeSide::Buy => {
+            let sol = args
+                .sol
+                .ok_or(ScheduledOrdersError::MissingArgument("sol"))?;
+            if sol <= 0.0 {
+                return Err(ScheduledOrdersError::InvalidArgument(
+                    "sol",
+                    sol.to_string(),
+                ));
+            }
+            // Convert SOL to lamports (1 SOL = 1_000_000_000 lamports)
+            (sol * 1_000_000_000.0) as u64
+        }
+        TradeSide::Sell => {
+            let percent = args.percent.unwrap_or(100.0);
+            if percent <= 0.0 || percent > 100.0 {
+                return Err(ScheduledOrdersError::InvalidArgument(
+                    "percent",
+                    percent.to_string(),
+                ));
+            }
+            let held = scheduler
+                .held(&mint)
+                .await
+                .map_err(|e| ScheduledOrdersError::SchedulerError(e.to_string()))?;
+            (held as f64 * percent / 100.0) as u64
+        }
+    };
+
+    let order = scheduler
+        .create(mint, side, schedule, total, slippage_percent)
+        .await
+        .map_err(|e| ScheduledOrdersError::SchedulerError(e.to_string()))?;
+    Ok(format!("Scheduled order created: {order}"))
+}
+
+/// Converts a positive number of minutes to seconds
+fn minutes_to_secs(name: &'static str, minutes: f64) -> Result<u64, ScheduledOrdersError> {
+    if minutes <= 0.0 {
+        return Err(ScheduledOrdersError::InvalidArgument(
+            name,
+            minutes.to_string(),
+        ));
+    }
+    Ok((minutes * 60.0).round() as u64)
+}
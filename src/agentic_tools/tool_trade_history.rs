@@ -0,0 +1,100 @@
+use crate::trading::PositionManager;
+use anyhow::Result;
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+#[derive(Deserialize, Debug)]
+pub struct TradeHistoryArgs {
+    /// How many of the latest fills to list
+    limit: Option<usize>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TradeHistoryError {
+    #[error("Ledger error: {0}")]
+    LedgerError(String),
+    #[error("Join error: {0}")]
+    JoinError(tokio::task::JoinError),
+}
+
+pub struct ToolTradeHistory {
+    positions: Arc<PositionManager>,
+}
+
+impl ToolTradeHistory {
+    pub fn new(positions: Arc<PositionManager>) -> Self {
+        Self { positions }
+    }
+}
+
+impl Tool for ToolTradeHistory {
+    const NAME: &'static str = "trade_history";
+    type Error = TradeHistoryError;
+    type Args = TradeHistoryArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "List the latest confirmed trades of the wallet with their fees, and the realized and unrealized profit and loss per coin, copied leader and strategy. Amounts are in lamports (1 SOL = 1_000_000_000 lamports).".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "limit": {
+                        "type": "number",
+                        "description": "How many of the latest trades to list. If the limit is not provided, the tool will list the last 10 trades."
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let positions = Arc::clone(&self.positions);
+
+        // Spawing a new tokio task to comply with the trait bounds of Send + Sync
+        tokio::spawn(async move {
+            println!("[ToolTradeHistory] Args {:?}", args);
+            let ledger = positions.ledger();
+
+            let fills = ledger
+                .history()
+                .await
+                .map_err(|e| TradeHistoryError::LedgerError(e.to_string()))?;
+            let marks = positions.mark_prices().await;
+            let report = ledger
+                .report(&marks)
+                .await
+                .map_err(|e| TradeHistoryError::LedgerError(e.to_string()))?;
+
+            let limit = args.limit.unwrap_or(10);
+            let trades = fills
+                .iter()
+                .rev()
+                .take(limit)
+                .map(|fill| {
+                    format!(
+                        "{} {} tokens of {} for {} lamports, costs {} lamports, {} https://solscan.io/tx/{}",
+                        fill.side,
+                        fill.tokens,
+                        fill.mint,
+                        fill.sol_amount,
+                        fill.costs(),
+                        fill.strategy,
+                        fill.signature
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            Ok(format!(
+                "Latest trades ({} in total):\n{trades}\n\nProfit and loss in lamports:\n{report}",
+                fills.len()
+            ))
+        })
+        .await
+        .map_err(TradeHistoryError::JoinError)?
+    }
+}
@@ -0,0 +1,219 @@
+use rand::{rngs::StdRng, Rng};
+use serde::Deserialize;
+use std::{collections::HashSet, error::Error};
+
+use crate::{
+    constants::SOL_DECIMALS,
+    copy_trading::{CopyTradingConfig, SignalFilter},
+    trading::ExitRules,
+};
+
+/// What places the buys of a backtest
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum BacktestStrategy {
+    /// Copies the trades of the leaders with their sizing and slippage
+    Copy(CopyTradingConfig),
+    /// Buys every mint on its first recorded buy
+    Snipe { sol: f64, slippage_percent: u64 },
+}
+
+/// Delay between a recorded trade and the simulated order landing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct LatencyModel {
+    /// Milliseconds every order takes
+    pub base_ms: u64,
+    /// Up to this many more milliseconds, drawn uniformly per order
+    pub jitter_ms: u64,
+}
+
+impl LatencyModel {
+    /// Returns the milliseconds an order takes to land
+    ///
+    /// * `rng` - The seeded generator of the backtest
+    pub fn delay_ms(&self, rng: &mut StdRng) -> u64 {
+        if self.jitter_ms == 0 {
+            return self.base_ms;
+        }
+        self.base_ms + rng.gen_range(0..=self.jitter_ms)
+    }
+}
+
+/// Execution worse than the replayed pool, on top of its price impact
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct SlippageModel {
+    /// Basis points taken off what every fill receives
+    pub adverse_bps: u64,
+}
+
+impl SlippageModel {
+    /// Returns what a fill receives out of the pool quote
+    ///
+    /// * `amount_out` - What the replayed pool gives
+    pub fn apply(&self, amount_out: u64) -> u64 {
+        (amount_out as u128 * 10_000u64.saturating_sub(self.adverse_bps) as u128 / 10_000) as u64
+    }
+}
+
+/// Settings replayed against a recording, e.g.
+///
+/// ```json
+/// [{
+///     "name": "whale 10%, tp 50 / sl 20",
+///     "strategy": { "mode": "copy", "leaders": [{
+///         "wallet": "6pURJRF6meemMHSdkuypCMrzUfDk1YKYa8MersBrpump",
+///         "sizing": { "mode": "leader_proportional", "ratio": 0.1, "max_sol": 0.5 },
+///         "slippage_percent": 10
+///     }] },
+///     "signal_filter": { "max_curve_progress": 60.0 },
+///     "exit_rules": { "take_profit_percent": 50.0, "stop_loss_percent": 20.0 },
+///     "latency": { "base_ms": 400, "jitter_ms": 400 },
+///     "slippage": { "adverse_bps": 50 },
+///     "balance_sol": 5.0
+/// }]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct BacktestConfig {
+    pub name: String,
+    pub strategy: BacktestStrategy,
+    /// Amounts in lamports
+    #[serde(default)]
+    pub signal_filter: SignalFilter,
+    #[serde(default)]
+    pub exit_rules: ExitRules,
+    #[serde(default)]
+    pub latency: LatencyModel,
+    #[serde(default)]
+    pub slippage: SlippageModel,
+    /// SOL the backtest starts with
+    #[serde(default = "default_balance_sol")]
+    pub balance_sol: f64,
+    /// Lamports every transaction pays in base, priority fees and tips,
+    /// landed or not
+    #[serde(default = "default_fee_lamports")]
+    pub fee_lamports: u64,
+    /// Seed of the latency jitter, runs with the same seed fill the same
+    #[serde(default)]
+    pub seed: u64,
+}
+
+fn default_balance_sol() -> f64 {
+    10.0
+}
+
+fn default_fee_lamports() -> u64 {
+    105_000
+}
+
+impl BacktestConfig {
+    /// Returns the lamports the backtest starts with
+    pub fn balance_lamports(&self) -> u64 {
+        (self.balance_sol * SOL_DECIMALS as f64) as u64
+    }
+
+    /// Parses and validates a JSON array of configs
+    ///
+    /// * `json` - The configs JSON
+    pub fn from_json(json: &str) -> Result<Vec<Self>, Box<dyn Error + Send + Sync>> {
+        let configs: Vec<Self> = serde_json::from_str(json)?;
+
+        let mut names = HashSet::new();
+        for config in &configs {
+            if !names.insert(config.name.as_str()) {
+                return Err(format!("Backtest {} is configured twice", config.name).into());
+            }
+            if config.balance_sol <= 0.0 {
+                return Err(format!("Invalid balance for backtest {}", config.name).into());
+            }
+            match &config.strategy {
+                BacktestStrategy::Copy(copy) => copy.validate()?,
+                BacktestStrategy::Snipe {
+                    sol,
+                    slippage_percent,
+                } => {
+                    if *sol <= 0.0 || *slippage_percent > 100 {
+                        return Err(format!("Invalid snipe for backtest {}", config.name).into());
+                    }
+                }
+            }
+        }
+        if configs.is_empty() {
+            return Err("No backtest configured".into());
+        }
+
+        Ok(configs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_from_json() {
+        let configs = BacktestConfig::from_json(
+            r#"[{
+                "name": "copy",
+                "strategy": { "mode": "copy", "leaders": [{
+                    "wallet": "6pURJRF6meemMHSdkuypCMrzUfDk1YKYa8MersBrpump",
+                    "sizing": { "mode": "fixed_sol", "sol": 0.1 },
+                    "slippage_percent": 10
+                }] },
+                "signal_filter": { "max_curve_progress": 60.0 },
+                "exit_rules": { "take_profit_percent": 50.0 },
+                "latency": { "base_ms": 400 }
+            }, {
+                "name": "snipe",
+                "strategy": { "mode": "snipe", "sol": 0.05, "slippage_percent": 25 },
+                "slippage": { "adverse_bps": 100 },
+                "balance_sol": 1.0
+            }]"#,
+        )
+        .unwrap();
+
+        assert_eq!(configs.len(), 2);
+        assert!(matches!(configs[0].strategy, BacktestStrategy::Copy(_)));
+        assert_eq!(configs[0].signal_filter.max_curve_progress, Some(60.0));
+        assert_eq!(configs[0].exit_rules.take_profit_percent, Some(50.0));
+        assert_eq!(configs[0].exit_rules.slippage_percent, 10);
+        assert_eq!(configs[0].latency.base_ms, 400);
+        assert_eq!(configs[0].balance_lamports(), 10_000_000_000);
+        assert_eq!(configs[0].fee_lamports, 105_000);
+        assert_eq!(configs[1].slippage.apply(1_000_000), 990_000);
+        assert_eq!(configs[1].balance_lamports(), 1_000_000_000);
+
+        // Same name twice
+        assert!(BacktestConfig::from_json(
+            r#"[{ "name": "a", "strategy": { "mode": "snipe", "sol": 0.1, "slippage_percent": 10 } },
+                { "name": "a", "strategy": { "mode": "snipe", "sol": 0.2, "slippage_percent": 10 } }]"#
+        )
+        .is_err());
+        // Copying nobody
+        assert!(BacktestConfig::from_json(
+            r#"[{ "name": "a", "strategy": { "mode": "copy", "leaders": [] } }]"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_latency_model() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let fixed = LatencyModel {
+            base_ms: 300,
+            jitter_ms: 0,
+        };
+        assert_eq!(fixed.delay_ms(&mut rng), 300);
+
+        let jittered = LatencyModel {
+            base_ms: 300,
+            jitter_ms: 200,
+        };
+        for _ in 0..100 {
+            let delay = jittered.delay_ms(&mut rng);
+            assert!((300..=500).contains(&delay));
+        }
+    }
+}
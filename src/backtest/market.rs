@@ -0,0 +1,272 @@
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+
+use crate::{
+    bot::{ParsedTrade, PumpFunBondingCurve, TradeSide, Venue, VenueContext},
+    constants::{PUMP_FUN_FEES, RAYDIUM_AMM_AUTHORITY, RAYDIUM_CPMM_AUTHORITY, WSOL_MINT},
+};
+
+use super::RecordedNotification;
+
+/// Swap fee of the constant product pools, in basis points. The pools can
+/// be configured with others, 25 is the most common.
+const CONSTANT_PRODUCT_FEE_BPS: u64 = 25;
+
+/// The state of the pool of a mint, rebuilt from the token balances a
+/// transaction left it with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PoolState {
+    /// A Pump.fun bonding curve
+    Curve(PumpFunBondingCurve),
+    /// A PumpSwap, Raydium V4 or Raydium CPMM pool
+    ConstantProduct {
+        sol_reserves: u64,
+        token_reserves: u64,
+        fee_bps: u64,
+    },
+}
+
+impl PoolState {
+    /// Rebuilds the pool a trade left from its transaction notification.
+    /// None when the pool token accounts are missing from the balances or
+    /// the venue is not a curve nor a constant product pool.
+    ///
+    /// * `json` - The transaction notification JSON
+    /// * `trade` - The trade parsed out of it
+    pub fn from_notification(json: &Value, trade: &ParsedTrade) -> Option<Self> {
+        let balances =
+            json["params"]["result"]["transaction"]["meta"]["postTokenBalances"].as_array()?;
+        let balance_of = |owner: &Pubkey, mint: &Pubkey| {
+            let (owner, mint) = (owner.to_string(), mint.to_string());
+            balances
+                .iter()
+                .find(|balance| {
+                    balance["owner"].as_str() == Some(owner.as_str())
+                        && balance["mint"].as_str() == Some(mint.as_str())
+                })
+                .and_then(|balance| balance["uiTokenAmount"]["amount"].as_str()?.parse().ok())
+        };
+
+        // The vaults are owned by the pool, or by the program authority
+        let owner = match trade.venue {
+            Venue::PumpFun => {
+                let real_token_reserves = balance_of(&trade.pool_id, &trade.mint)?;
+                return Some(Self::Curve(PumpFunBondingCurve::from_real_token_reserves(
+                    real_token_reserves,
+                )));
+            }
+            Venue::PumpSwap => trade.pool_id,
+            Venue::RaydiumV4 => RAYDIUM_AMM_AUTHORITY,
+            Venue::RaydiumCpmm => RAYDIUM_CPMM_AUTHORITY,
+            _ => return None,
+        };
+
+        Some(Self::ConstantProduct {
+            sol_reserves: balance_of(&owner, &WSOL_MINT)?,
+            token_reserves: balance_of(&owner, &trade.mint)?,
+            fee_bps: CONSTANT_PRODUCT_FEE_BPS,
+        })
+    }
+
+    /// Returns what a swap receives, fees included: tokens for `amount_in`
+    /// lamports on a buy, lamports for `amount_in` tokens on a sell
+    ///
+    /// * `side` - The direction of the swap
+    /// * `amount_in` - The lamports or tokens spent
+    pub fn quote(&self, side: TradeSide, amount_in: u64) -> u64 {
+        match self {
+            Self::Curve(curve) => match side {
+                // The fee is paid on top of the lamports swapped
+                TradeSide::Buy => {
+                    curve.quote_buy((amount_in as f64 / (1.0 + PUMP_FUN_FEES)) as u64)
+                }
+                TradeSide::Sell => {
+                    (curve.quote_sell(amount_in) as f64 * (1.0 - PUMP_FUN_FEES)) as u64
+                }
+            },
+            Self::ConstantProduct {
+                sol_reserves,
+                token_reserves,
+                fee_bps,
+            } => {
+                let (reserve_in, reserve_out) = match side {
+                    TradeSide::Buy => (*sol_reserves, *token_reserves),
+                    TradeSide::Sell => (*token_reserves, *sol_reserves),
+                };
+                let amount_in = amount_in as u128 * (10_000 - fee_bps) as u128 / 10_000;
+                let denominator = reserve_in as u128 + amount_in;
+                if denominator == 0 {
+                    return 0;
+                }
+                (reserve_out as u128 * amount_in / denominator) as u64
+            }
+        }
+    }
+
+    /// Returns the lamports the pool trades against: the real SOL reserves
+    /// of a curve, the SOL vault of a pool
+    pub fn liquidity(&self) -> u64 {
+        match self {
+            Self::Curve(curve) => curve.real_sol_reserves,
+            Self::ConstantProduct { sol_reserves, .. } => *sol_reserves,
+        }
+    }
+
+    /// Returns the percent of the curve sold, None off a curve
+    pub fn curve_progress(&self) -> Option<f64> {
+        match self {
+            Self::Curve(curve) => Some(curve.progress()),
+            Self::ConstantProduct { .. } => None,
+        }
+    }
+
+    /// Returns the raw token supply, only known on a curve
+    pub fn supply(&self) -> Option<u64> {
+        match self {
+            Self::Curve(curve) => Some(curve.token_total_supply),
+            Self::ConstantProduct { .. } => None,
+        }
+    }
+}
+
+/// A recorded trade, decoded once and replayed for every config
+#[derive(Debug, Clone)]
+pub struct ReplayEvent {
+    /// Unix timestamp in milliseconds the notification was received at
+    pub received_at_ms: u64,
+    /// The transaction notification JSON
+    pub json: Value,
+    pub trade: ParsedTrade,
+    /// The pool after the trade, None when it can't be rebuilt
+    pub pool: Option<PoolState>,
+}
+
+impl ReplayEvent {
+    /// Returns the unix timestamp in seconds of the event
+    pub fn now(&self) -> u64 {
+        self.received_at_ms / 1_000
+    }
+}
+
+/// Decodes the trades of a recording with the decoder of the venue each
+/// notification invoked, the same as the copy engine. Notifications
+/// without a trade, and those received twice, are dropped.
+///
+/// * `ctx` - The venue context, some decoders validate the mints over RPC
+/// * `recording` - The notifications in the order they were received
+pub async fn decode_recording(
+    ctx: &VenueContext,
+    recording: &[RecordedNotification],
+) -> Vec<ReplayEvent> {
+    let mut signatures = HashSet::new();
+    let mut events = Vec::new();
+
+    for notification in recording {
+        let Ok(json) = serde_json::from_str::<Value>(&notification.payload) else {
+            continue;
+        };
+        if let Some(signature) = json["params"]["result"]["signature"].as_str() {
+            if !signatures.insert(signature.to_string()) {
+                continue;
+            }
+        }
+        let Some(trade) = Venue::parse_trade(ctx, &notification.payload).await else {
+            continue;
+        };
+
+        let pool = PoolState::from_notification(&json, &trade);
+        events.push(ReplayEvent {
+            received_at_ms: notification.received_at_ms,
+            json,
+            trade,
+            pool,
+        });
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::PUMP_FUN_INITIAL_REAL_TOKEN_RESERVES;
+    use serde_json::json;
+
+    fn trade(venue: Venue, pool_id: Pubkey, mint: Pubkey) -> ParsedTrade {
+        ParsedTrade {
+            venue,
+            signature: None,
+            side: TradeSide::Buy,
+            mint,
+            pool_id,
+            sol_amount: 1_000_000_000,
+            token_amount: 1_000_000,
+            compute_unit_limit: 0,
+        }
+    }
+
+    fn notification(balances: &[(Pubkey, Pubkey, u64)]) -> Value {
+        let balances: Vec<Value> = balances
+            .iter()
+            .map(|(owner, mint, amount)| {
+                json!({
+                    "owner": owner.to_string(),
+                    "mint": mint.to_string(),
+                    "uiTokenAmount": { "amount": amount.to_string() }
+                })
+            })
+            .collect();
+        json!({ "params": { "result": { "transaction": { "meta": {
+            "postTokenBalances": balances
+        } } } } })
+    }
+
+    #[test]
+    fn test_curve_from_notification() {
+        let (bonding_curve, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let json = notification(&[
+            (Pubkey::new_unique(), mint, 5_000_000),
+            (bonding_curve, mint, PUMP_FUN_INITIAL_REAL_TOKEN_RESERVES),
+        ]);
+
+        let pool = PoolState::from_notification(&json, &trade(Venue::PumpFun, bonding_curve, mint))
+            .unwrap();
+        let curve =
+            PumpFunBondingCurve::from_real_token_reserves(PUMP_FUN_INITIAL_REAL_TOKEN_RESERVES);
+        assert_eq!(pool, PoolState::Curve(curve));
+        assert_eq!(pool.liquidity(), 0);
+        assert_eq!(pool.curve_progress(), Some(0.0));
+
+        // 1% of the lamports goes to the fee
+        let fee_free = curve.quote_buy(1_000_000_000);
+        let tokens = pool.quote(TradeSide::Buy, 1_010_000_000);
+        assert!(fee_free.abs_diff(tokens) < 1_000);
+
+        let other = trade(Venue::PumpFun, Pubkey::new_unique(), mint);
+        assert_eq!(PoolState::from_notification(&json, &other), None);
+    }
+
+    #[test]
+    fn test_constant_product_from_notification() {
+        let mint = Pubkey::new_unique();
+        let json = notification(&[
+            (RAYDIUM_AMM_AUTHORITY, WSOL_MINT, 100_000_000_000),
+            (RAYDIUM_AMM_AUTHORITY, mint, 1_000_000_000),
+        ]);
+
+        let pool = PoolState::from_notification(
+            &json,
+            &trade(Venue::RaydiumV4, Pubkey::new_unique(), mint),
+        )
+        .unwrap();
+        assert_eq!(pool.liquidity(), 100_000_000_000);
+        assert_eq!(pool.curve_progress(), None);
+
+        // 1 SOL in, 0.25% fee: 1_000_000_000 * 997_500_000 / 100_997_500_000
+        assert_eq!(pool.quote(TradeSide::Buy, 1_000_000_000), 9_876_482);
+        assert!(pool.quote(TradeSide::Sell, 10_000_000) < 1_000_000_000);
+
+        let whirlpool = trade(Venue::OrcaWhirlpool, Pubkey::new_unique(), mint);
+        assert_eq!(PoolState::from_notification(&json, &whirlpool), None);
+    }
+}
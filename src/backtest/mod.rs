@@ -0,0 +1,11 @@
+mod config;
+mod market;
+mod recording;
+mod report;
+mod simulator;
+
+pub use config::*;
+pub use market::*;
+pub use recording::*;
+pub use report::*;
+pub use simulator::*;
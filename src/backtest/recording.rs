@@ -0,0 +1,128 @@
+use serde_json::{json, Value};
+use std::{
+    error::Error,
+    fs::{self, File, OpenOptions},
+    io::{LineWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A `transactionSubscribe` notification as it was received
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedNotification {
+    /// Unix timestamp in milliseconds of the websocket frame
+    pub received_at_ms: u64,
+    /// The notification JSON, untouched
+    pub payload: String,
+}
+
+impl RecordedNotification {
+    /// Returns the JSON line the notification is recorded as
+    pub fn to_line(&self) -> String {
+        json!({
+            "received_at_ms": self.received_at_ms,
+            "payload": self.payload,
+        })
+        .to_string()
+    }
+
+    /// Parses a recorded JSON line, None when it is not one
+    ///
+    /// * `line` - A line of a recording
+    pub fn from_line(line: &str) -> Option<Self> {
+        let json = serde_json::from_str::<Value>(line).ok()?;
+        Some(Self {
+            received_at_ms: json["received_at_ms"].as_u64()?,
+            payload: json["payload"].as_str()?.to_string(),
+        })
+    }
+}
+
+/// Appends the notifications received to a JSON lines file, to be replayed
+/// by the backtester
+#[derive(Debug)]
+pub struct Recorder {
+    file: Mutex<LineWriter<File>>,
+}
+
+impl Recorder {
+    /// Opens the recording at `path`, new notifications are appended
+    ///
+    /// * `path` - The recording file, created when missing
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(LineWriter::new(file)),
+        })
+    }
+
+    /// Appends a notification, stamped with the current time
+    ///
+    /// * `payload` - The notification JSON
+    pub fn record(&self, payload: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let received_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let notification = RecordedNotification {
+            received_at_ms,
+            payload: payload.to_string(),
+        };
+
+        let mut file = self.file.lock().map_err(|e| e.to_string())?;
+        writeln!(file, "{}", notification.to_line())?;
+        Ok(())
+    }
+}
+
+/// Reads a recording in the order the notifications were received, lines
+/// that do not parse are skipped
+///
+/// * `path` - The recording file
+pub fn read_recording(
+    path: &Path,
+) -> Result<Vec<RecordedNotification>, Box<dyn Error + Send + Sync>> {
+    let mut notifications: Vec<RecordedNotification> = fs::read_to_string(path)?
+        .lines()
+        .filter_map(RecordedNotification::from_line)
+        .collect();
+    notifications.sort_by_key(|notification| notification.received_at_ms);
+    Ok(notifications)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recording() {
+        let path =
+            std::env::temp_dir().join(format!("bfg9000_recording_{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let recorder = Recorder::open(&path).unwrap();
+        recorder
+            .record(r#"{"params":{"result":{"slot":2}}}"#)
+            .unwrap();
+        recorder
+            .record(r#"{"params":{"result":{"slot":3}}}"#)
+            .unwrap();
+        drop(recorder);
+
+        let notifications = read_recording(&path).unwrap();
+        assert_eq!(notifications.len(), 2);
+        assert_eq!(
+            notifications[1].payload,
+            r#"{"params":{"result":{"slot":3}}}"#
+        );
+        assert!(notifications[0].received_at_ms <= notifications[1].received_at_ms);
+        assert_eq!(
+            RecordedNotification::from_line(&notifications[0].to_line()),
+            Some(notifications[0].clone())
+        );
+        assert_eq!(RecordedNotification::from_line("not json"), None);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
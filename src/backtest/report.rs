@@ -0,0 +1,74 @@
+use std::fmt;
+
+/// What a config would have made over a recording. Amounts in lamports,
+/// fees included.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BacktestReport {
+    pub name: String,
+    /// Orders landed
+    pub buys: usize,
+    pub sells: usize,
+    /// Orders landed past their slippage tolerance, their fees are lost
+    pub reverted: usize,
+    /// Orders never sent: no pool to price them on or not enough SOL
+    pub unfilled: usize,
+    /// Buy signals the signal filter or the cooldown skipped
+    pub filtered: usize,
+    /// Positions sold in full, and those sold at a profit
+    pub round_trips: usize,
+    pub wins: usize,
+    pub realized: i64,
+    /// Open positions valued at a sell on their last replayed pool
+    pub unrealized: i64,
+    pub open_positions: usize,
+    /// Fees of every transaction sent, landed or reverted
+    pub fees: u64,
+    /// Largest fall of the SOL balance plus the open positions from a peak
+    pub max_drawdown: u64,
+    pub max_drawdown_percent: f64,
+}
+
+impl BacktestReport {
+    /// Returns the realized plus the unrealized PnL
+    pub fn pnl(&self) -> i64 {
+        self.realized + self.unrealized
+    }
+
+    /// Returns the share of the round trips closed at a profit, None
+    /// before the first one
+    pub fn hit_rate(&self) -> Option<f64> {
+        if self.round_trips == 0 {
+            return None;
+        }
+        Some(self.wins as f64 / self.round_trips as f64)
+    }
+}
+
+impl fmt::Display for BacktestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: PnL {} (realized {} | unrealized {} on {} open) | fees {}",
+            self.name,
+            self.pnl(),
+            self.realized,
+            self.unrealized,
+            self.open_positions,
+            self.fees
+        )?;
+        let hit_rate = match self.hit_rate() {
+            Some(hit_rate) => format!("{:.1}%", hit_rate * 100.0),
+            None => "-".to_string(),
+        };
+        writeln!(
+            f,
+            "  round trips {} | hit rate {hit_rate} | max drawdown {} ({:.2}%)",
+            self.round_trips, self.max_drawdown, self.max_drawdown_percent
+        )?;
+        writeln!(
+            f,
+            "  buys {} | sells {} | reverted {} | unfilled {} | filtered {}",
+            self.buys, self.sells, self.reverted, self.unfilled, self.filtered
+        )
+    }
+}
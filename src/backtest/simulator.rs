@@ -0,0 +1,681 @@
+use rand::{rngs::StdRng, SeedableRng};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+use crate::{
+    bot::{TradeSide, Venue},
+    constants::SOL_DECIMALS,
+    copy_trading::{
+        market_cap, mirrored_sell_amount, BuyCooldown, LeaderBalances, LeaderHoldings,
+        SignalSnapshot,
+    },
+    trading::Position,
+};
+
+use super::{BacktestConfig, BacktestReport, BacktestStrategy, PoolState, ReplayEvent};
+
+/// A simulated order, waiting for its latency to pass
+#[derive(Debug, Clone)]
+struct Order {
+    /// Unix timestamp in milliseconds the order lands at
+    lands_at_ms: u64,
+    venue: Venue,
+    pool_id: Pubkey,
+    mint: Pubkey,
+    side: TradeSide,
+    /// Lamports spent on a buy, tokens on a sell
+    amount_in: u64,
+    /// Least the order receives before it reverts
+    min_out: u64,
+}
+
+/// Replays decoded trades through a config: the buys its strategy and
+/// signal filter would have placed, and the sells of the leaders and of
+/// the exit rules.
+///
+/// Orders are sized and given their slippage tolerance when the trade
+/// they follow is replayed, then land once their latency passed, against
+/// the pool the trades replayed until then left. Fills do not move the
+/// replayed pools.
+pub struct Backtest<'a> {
+    config: &'a BacktestConfig,
+    rng: StdRng,
+    /// Lamports held
+    balance: u64,
+    positions: HashMap<Pubkey, Position>,
+    /// Realized since each open position was opened
+    round_trips: HashMap<Pubkey, i64>,
+    /// The last pool replayed per mint
+    pools: HashMap<Pubkey, PoolState>,
+    /// When each mint was first replayed, in milliseconds
+    first_seen: HashMap<Pubkey, u64>,
+    orders: Vec<Order>,
+    holdings: LeaderHoldings,
+    cooldown: BuyCooldown,
+    peak_equity: u64,
+    report: BacktestReport,
+}
+
+impl<'a> Backtest<'a> {
+    pub fn new(config: &'a BacktestConfig) -> Self {
+        let balance = config.balance_lamports();
+        Self {
+            config,
+            rng: StdRng::seed_from_u64(config.seed),
+            balance,
+            positions: HashMap::new(),
+            round_trips: HashMap::new(),
+            pools: HashMap::new(),
+            first_seen: HashMap::new(),
+            orders: Vec::new(),
+            holdings: LeaderHoldings::default(),
+            cooldown: BuyCooldown::default(),
+            peak_equity: balance,
+            report: BacktestReport {
+                name: config.name.clone(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Replays a trade: lands the orders due before it, updates its pool,
+    /// then places the orders it triggers
+    ///
+    /// * `event` - The next trade of the recording
+    pub fn replay(&mut self, event: &ReplayEvent) {
+        self.land_orders(Some(event.received_at_ms));
+
+        let mint = event.trade.mint;
+        let first_trade = !self.first_seen.contains_key(&mint);
+        self.first_seen.entry(mint).or_insert(event.received_at_ms);
+        if let Some(pool) = event.pool {
+            self.pools.insert(mint, pool);
+        }
+
+        self.follow(event, first_trade);
+        self.check_exits(event.received_at_ms);
+        self.mark();
+    }
+
+    /// Lands the orders left and values the open positions on their last
+    /// pool
+    pub fn finish(mut self) -> BacktestReport {
+        self.land_orders(None);
+        self.mark();
+
+        self.report.unrealized = self
+            .positions
+            .values()
+            .map(|position| self.value(position) as i64 - position.cost_basis as i64)
+            .sum();
+        self.report.open_positions = self.positions.len();
+        self.report
+    }
+
+    /// Places the orders the strategy takes on a trade
+    fn follow(&mut self, event: &ReplayEvent, first_trade: bool) {
+        let config = self.config;
+        let trade = &event.trade;
+
+        match &config.strategy {
+            BacktestStrategy::Copy(copy) => {
+                // Every leader trade is tracked, copied or not
+                let Some(leader) = copy.leader_of(&event.json) else {
+                    return;
+                };
+                let balances =
+                    LeaderBalances::from_notification(&event.json, &leader.wallet, &trade.mint);
+                let sold_fraction = self.holdings.observe(&leader.wallet, trade, &balances);
+                if !leader.allows(trade.venue) {
+                    return;
+                }
+
+                match trade.side {
+                    TradeSide::Buy => {
+                        if !self.passes_filter(event) {
+                            return;
+                        }
+                        let lamports = leader.sizing.buy_lamports(
+                            trade.sol_amount,
+                            balances.sol_pre.unwrap_or_default(),
+                            self.balance,
+                        );
+                        self.place_buy(event, lamports, leader.slippage_percent);
+                    }
+                    TradeSide::Sell if leader.copy_sells => {
+                        let Some(position) = self.positions.get(&trade.mint) else {
+                            return;
+                        };
+                        let tokens = mirrored_sell_amount(position.size, sold_fraction);
+                        self.place(event, TradeSide::Sell, tokens, leader.slippage_percent);
+                    }
+                    TradeSide::Sell => {}
+                }
+            }
+            BacktestStrategy::Snipe {
+                sol,
+                slippage_percent,
+            } => {
+                if first_trade && trade.side == TradeSide::Buy && self.passes_filter(event) {
+                    let lamports = (sol * SOL_DECIMALS as f64) as u64;
+                    self.place_buy(event, lamports, *slippage_percent);
+                }
+            }
+        }
+    }
+
+    /// Runs a buy signal through the signal filter, on the market the
+    /// replay knows of. Tokens are dated from their first replayed trade.
+    fn passes_filter(&mut self, event: &ReplayEvent) -> bool {
+        let filter = &self.config.signal_filter;
+        let trade = &event.trade;
+        let pool = self.pools.get(&trade.mint);
+
+        let snapshot = SignalSnapshot {
+            token_age_secs: self
+                .first_seen
+                .get(&trade.mint)
+                .map(|first_seen| event.received_at_ms.saturating_sub(*first_seen) / 1_000),
+            curve_progress: pool.and_then(PoolState::curve_progress),
+            liquidity: pool.map(PoolState::liquidity),
+            market_cap: pool
+                .and_then(PoolState::supply)
+                .and_then(|supply| market_cap(trade, supply)),
+        };
+
+        let passed = filter.check_leader_trade(trade).is_ok() && filter.check(&snapshot).is_ok();
+        if !passed {
+            self.report.filtered += 1;
+        }
+        passed
+    }
+
+    /// Places a buy, unless the mint was bought within the cooldown
+    fn place_buy(&mut self, event: &ReplayEvent, lamports: u64, slippage_percent: u64) {
+        if lamports == 0 {
+            return;
+        }
+        let cooldown = self.config.signal_filter.check_cooldown(
+            &self.cooldown,
+            &event.trade.mint,
+            event.now(),
+        );
+        if cooldown.is_err() {
+            self.report.filtered += 1;
+            return;
+        }
+        self.place(event, TradeSide::Buy, lamports, slippage_percent);
+    }
+
+    /// Quotes an order on the pool of the trade it follows, it lands after
+    /// the latency of the config
+    fn place(
+        &mut self,
+        event: &ReplayEvent,
+        side: TradeSide,
+        amount_in: u64,
+        slippage_percent: u64,
+    ) {
+        if amount_in == 0 {
+            return;
+        }
+        let mint = event.trade.mint;
+        let Some(pool) = self.pools.get(&mint) else {
+            self.report.unfilled += 1;
+            return;
+        };
+        let expected = pool.quote(side, amount_in);
+
+        let lands_at_ms = event.received_at_ms + self.config.latency.delay_ms(&mut self.rng);
+        let (venue, pool_id) = match self.positions.get(&mint) {
+            // Sold where it was bought
+            Some(position) if side == TradeSide::Sell => (position.venue, position.pool_id),
+            _ => (event.trade.venue, event.trade.pool_id),
+        };
+        self.orders.push(Order {
+            lands_at_ms,
+            venue,
+            pool_id,
+            mint,
+            side,
+            amount_in,
+            min_out: expected * 100u64.saturating_sub(slippage_percent) / 100,
+        });
+    }
+
+    /// Sells the positions an exit rule fires on, priced at a sell of the
+    /// whole position on their last pool
+    fn check_exits(&mut self, now_ms: u64) {
+        if self.config.exit_rules.is_empty() {
+            return;
+        }
+
+        let mut exits = Vec::new();
+        for (mint, position) in self.positions.iter_mut() {
+            let selling = self
+                .orders
+                .iter()
+                .any(|order| order.mint == *mint && order.side == TradeSide::Sell);
+            let Some(pool) = self.pools.get(mint) else {
+                continue;
+            };
+            if selling || position.size == 0 {
+                continue;
+            }
+
+            let price = pool.quote(TradeSide::Sell, position.size) as f64 / position.size as f64;
+            position.observe(price);
+            if self
+                .config
+                .exit_rules
+                .evaluate(position, price, now_ms / 1_000)
+                .is_some()
+            {
+                exits.push((*mint, position.size));
+            }
+        }
+
+        for (mint, size) in exits {
+            let expected = self
+                .pools
+                .get(&mint)
+                .map(|pool| pool.quote(TradeSide::Sell, size))
+                .unwrap_or_default();
+            let Some(position) = self.positions.get(&mint) else {
+                continue;
+            };
+            self.orders.push(Order {
+                lands_at_ms: now_ms + self.config.latency.delay_ms(&mut self.rng),
+                venue: position.venue,
+                pool_id: position.pool_id,
+                mint,
+                side: TradeSide::Sell,
+                amount_in: size,
+                min_out: expected * 100u64.saturating_sub(self.config.exit_rules.slippage_percent)
+                    / 100,
+            });
+        }
+    }
+
+    /// Lands the orders due at `until_ms` in the order they land, all of
+    /// them when None
+    fn land_orders(&mut self, until_ms: Option<u64>) {
+        self.orders.sort_by_key(|order| order.lands_at_ms);
+        let due = self
+            .orders
+            .iter()
+            .take_while(|order| match until_ms {
+                Some(until_ms) => order.lands_at_ms <= until_ms,
+                None => true,
+            })
+            .count();
+
+        let due: Vec<Order> = self.orders.drain(..due).collect();
+        for order in &due {
+            self.land(order);
+        }
+    }
+
+    fn land(&mut self, order: &Order) {
+        let fee = self.config.fee_lamports;
+        let Some(pool) = self.pools.get(&order.mint).copied() else {
+            self.report.unfilled += 1;
+            return;
+        };
+
+        match order.side {
+            TradeSide::Buy => {
+                if self.balance < order.amount_in + fee {
+                    self.report.unfilled += 1;
+                    return;
+                }
+                self.pay_fee(fee);
+
+                let tokens = self
+                    .config
+                    .slippage
+                    .apply(pool.quote(TradeSide::Buy, order.amount_in));
+                if tokens == 0 || tokens < order.min_out {
+                    self.report.reverted += 1;
+                    self.report.realized -= fee as i64;
+                    return;
+                }
+
+                // The fee is part of the cost of the tokens
+                self.balance -= order.amount_in;
+                let cost = order.amount_in + fee;
+                self.positions
+                    .entry(order.mint)
+                    .and_modify(|position| position.add(tokens, cost))
+                    .or_insert_with(|| {
+                        Position::open(
+                            order.venue,
+                            order.pool_id,
+                            order.mint,
+                            tokens,
+                            cost,
+                            order.lands_at_ms / 1_000,
+                        )
+                    });
+                self.round_trips.entry(order.mint).or_default();
+                self.report.buys += 1;
+            }
+            TradeSide::Sell => {
+                let Some(size) = self
+                    .positions
+                    .get(&order.mint)
+                    .map(|position| position.size)
+                else {
+                    return;
+                };
+                let tokens = order.amount_in.min(size);
+                if tokens == 0 {
+                    return;
+                }
+                self.pay_fee(fee);
+
+                let lamports = self
+                    .config
+                    .slippage
+                    .apply(pool.quote(TradeSide::Sell, tokens));
+                let min_out =
+                    (order.min_out as u128 * tokens as u128 / order.amount_in as u128) as u64;
+                let round_trip = self.round_trips.entry(order.mint).or_default();
+                if lamports < min_out {
+                    *round_trip -= fee as i64;
+                    self.report.reverted += 1;
+                    self.report.realized -= fee as i64;
+                    return;
+                }
+
+                self.balance += lamports;
+                let Some(position) = self.positions.get_mut(&order.mint) else {
+                    return;
+                };
+                let sold_cost = position.reduce(size - tokens);
+                let pnl = lamports as i64 - fee as i64 - sold_cost as i64;
+                *round_trip += pnl;
+                self.report.realized += pnl;
+                self.report.sells += 1;
+
+                if position.size == 0 {
+                    self.positions.remove(&order.mint);
+                    let round_trip = self.round_trips.remove(&order.mint).unwrap_or_default();
+                    self.report.round_trips += 1;
+                    if round_trip > 0 {
+                        self.report.wins += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    fn pay_fee(&mut self, fee: u64) {
+        self.balance = self.balance.saturating_sub(fee);
+        self.report.fees += fee;
+    }
+
+    /// Returns what selling the whole position on its last pool receives
+    fn value(&self, position: &Position) -> u64 {
+        match self.pools.get(&position.mint) {
+            Some(pool) => self
+                .config
+                .slippage
+                .apply(pool.quote(TradeSide::Sell, position.size)),
+            None => position.cost_basis,
+        }
+    }
+
+    /// Records the equity, the SOL balance plus the open positions, for the
+    /// drawdown
+    fn mark(&mut self) {
+        let equity = self.balance
+            + self
+                .positions
+                .values()
+                .map(|position| self.value(position))
+                .sum::<u64>();
+
+        self.peak_equity = self.peak_equity.max(equity);
+        let drawdown = self.peak_equity - equity;
+        if drawdown > self.report.max_drawdown {
+            self.report.max_drawdown = drawdown;
+            self.report.max_drawdown_percent = drawdown as f64 * 100.0 / self.peak_equity as f64;
+        }
+    }
+}
+
+/// Replays the decoded trades of a recording through every config
+///
+/// * `configs` - The settings compared
+/// * `events` - The trades of the recording, see `decode_recording`
+pub fn run_backtests(configs: &[BacktestConfig], events: &[ReplayEvent]) -> Vec<BacktestReport> {
+    configs
+        .iter()
+        .map(|config| {
+            let mut backtest = Backtest::new(config);
+            for event in events {
+                backtest.replay(event);
+            }
+            backtest.finish()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backtest::{LatencyModel, SlippageModel},
+        bot::{ParsedTrade, PumpFunBondingCurve},
+        constants::PUMP_FUN_INITIAL_REAL_TOKEN_RESERVES,
+        copy_trading::{CopyTradingConfig, LeaderConfig, SignalFilter, SizingMode},
+        trading::ExitRules,
+    };
+    use serde_json::{json, Value};
+    use std::collections::HashSet;
+
+    /// A Pump.fun trade leaving `sold` tokens sold off the curve
+    fn event(at_ms: u64, mint: Pubkey, side: TradeSide, sold: u64, json: Value) -> ReplayEvent {
+        ReplayEvent {
+            received_at_ms: at_ms,
+            json,
+            trade: ParsedTrade {
+                venue: Venue::PumpFun,
+                signature: None,
+                side,
+                mint,
+                pool_id: PumpFunBondingCurve::address(&mint),
+                sol_amount: 1_000_000_000,
+                token_amount: 10_000_000_000_000,
+                compute_unit_limit: 0,
+            },
+            pool: Some(PoolState::Curve(
+                PumpFunBondingCurve::from_real_token_reserves(
+                    PUMP_FUN_INITIAL_REAL_TOKEN_RESERVES - sold,
+                ),
+            )),
+        }
+    }
+
+    fn config(strategy: BacktestStrategy, exit_rules: ExitRules) -> BacktestConfig {
+        BacktestConfig {
+            name: "test".to_string(),
+            strategy,
+            signal_filter: SignalFilter::default(),
+            exit_rules,
+            latency: LatencyModel {
+                base_ms: 500,
+                jitter_ms: 0,
+            },
+            slippage: SlippageModel::default(),
+            balance_sol: 1.0,
+            fee_lamports: 100_000,
+            seed: 0,
+        }
+    }
+
+    fn snipe() -> BacktestStrategy {
+        BacktestStrategy::Snipe {
+            sol: 0.1,
+            slippage_percent: 10,
+        }
+    }
+
+    fn exit_rules() -> ExitRules {
+        ExitRules {
+            take_profit_percent: Some(50.0),
+            stop_loss_percent: Some(15.0),
+            slippage_percent: 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_take_profit() {
+        let mint = Pubkey::new_unique();
+        let events = [
+            // Sniped on the first buy, landing before the next trade
+            event(0, mint, TradeSide::Buy, 10_000_000_000_000, Value::Null),
+            // The price more than doubles, the take profit fires
+            event(
+                1_000,
+                mint,
+                TradeSide::Buy,
+                260_000_000_000_000,
+                Value::Null,
+            ),
+            event(
+                2_000,
+                mint,
+                TradeSide::Buy,
+                260_000_000_000_000,
+                Value::Null,
+            ),
+        ];
+
+        let report = &run_backtests(&[config(snipe(), exit_rules())], &events)[0];
+        assert_eq!((report.buys, report.sells, report.reverted), (1, 1, 0));
+        assert_eq!((report.round_trips, report.wins), (1, 1));
+        assert_eq!(report.hit_rate(), Some(1.0));
+        assert!(report.realized > 50_000_000);
+        assert_eq!((report.unrealized, report.open_positions), (0, 0));
+        assert_eq!(report.fees, 200_000);
+        // The fee of the exit
+        assert_eq!(report.max_drawdown, 100_000);
+    }
+
+    #[test]
+    fn test_stop_loss() {
+        let mint = Pubkey::new_unique();
+        let events = [
+            event(0, mint, TradeSide::Buy, 100_000_000_000_000, Value::Null),
+            event(
+                1_000,
+                mint,
+                TradeSide::Buy,
+                100_000_000_000_000,
+                Value::Null,
+            ),
+            // Sold back to the launch price, the stop loss fires
+            event(2_000, mint, TradeSide::Sell, 0, Value::Null),
+            event(3_000, mint, TradeSide::Sell, 0, Value::Null),
+        ];
+
+        let report = &run_backtests(&[config(snipe(), exit_rules())], &events)[0];
+        assert_eq!((report.buys, report.sells), (1, 1));
+        assert_eq!((report.round_trips, report.wins), (1, 0));
+        assert_eq!(report.hit_rate(), Some(0.0));
+        assert!(report.realized < -15_000_000);
+        assert!(report.max_drawdown >= report.realized.unsigned_abs() - 1_000_000);
+        assert!(report.max_drawdown_percent > 1.5);
+    }
+
+    #[test]
+    fn test_slippage_and_latency() {
+        let mint = Pubkey::new_unique();
+        let events = [
+            event(0, mint, TradeSide::Buy, 10_000_000_000_000, Value::Null),
+            event(1_000, mint, TradeSide::Buy, 10_000_000_000_000, Value::Null),
+        ];
+
+        // 20% worse than the pool with a 10% tolerance
+        let mut adverse = config(snipe(), ExitRules::default());
+        adverse.slippage.adverse_bps = 2_000;
+        // A large buy lands first, the price 70% above the signal
+        let mut late = config(snipe(), ExitRules::default());
+        late.latency.base_ms = 1_500;
+        let late_events = [
+            event(0, mint, TradeSide::Buy, 10_000_000_000_000, Value::Null),
+            event(
+                1_000,
+                mint,
+                TradeSide::Buy,
+                260_000_000_000_000,
+                Value::Null,
+            ),
+            event(
+                2_000,
+                mint,
+                TradeSide::Buy,
+                260_000_000_000_000,
+                Value::Null,
+            ),
+        ];
+
+        let report = &run_backtests(&[adverse], &events)[0];
+        assert_eq!((report.buys, report.reverted), (0, 1));
+        assert_eq!((report.realized, report.fees), (-100_000, 100_000));
+
+        let report = &run_backtests(&[late], &late_events)[0];
+        assert_eq!((report.buys, report.reverted), (0, 1));
+
+        // Filtered past 5% of the curve
+        let mut filtered = config(snipe(), ExitRules::default());
+        filtered.signal_filter.max_curve_progress = Some(5.0);
+        let report = &run_backtests(&[filtered], &late_events[1..])[0];
+        assert_eq!((report.buys, report.filtered), (0, 1));
+    }
+
+    #[test]
+    fn test_copy() {
+        let (leader, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let copy = CopyTradingConfig {
+            leaders: vec![LeaderConfig {
+                wallet: leader,
+                label: None,
+                sizing: SizingMode::FixedSol { sol: 0.1 },
+                slippage_percent: 10,
+                venues: HashSet::new(),
+                copy_sells: true,
+                enabled: true,
+            }],
+        };
+        let signed = json!({ "params": { "result": { "transaction": { "transaction": {
+            "message": { "accountKeys": [{ "pubkey": leader.to_string(), "signer": true }] }
+        } } } } });
+
+        let events = [
+            // Someone else buys first, then the leader buys and sells it all
+            event(0, mint, TradeSide::Buy, 10_000_000_000_000, Value::Null),
+            event(
+                1_000,
+                mint,
+                TradeSide::Buy,
+                20_000_000_000_000,
+                signed.clone(),
+            ),
+            event(2_000, mint, TradeSide::Sell, 10_000_000_000_000, signed),
+            event(3_000, mint, TradeSide::Buy, 10_000_000_000_000, Value::Null),
+        ];
+
+        let report = &run_backtests(
+            &[config(BacktestStrategy::Copy(copy), ExitRules::default())],
+            &events,
+        )[0];
+        assert_eq!((report.buys, report.sells), (1, 1));
+        assert_eq!((report.round_trips, report.wins), (1, 0));
+        assert_eq!(report.open_positions, 0);
+        assert!(report.realized < 0);
+    }
+}
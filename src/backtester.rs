@@ -0,0 +1,60 @@
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::panic)]
+#![deny(unused_must_use)]
+mod backtest;
+mod bot;
+mod constants;
+mod copy_trading;
+mod db;
+mod errors;
+mod fast_websocket_client;
+mod trading;
+
+use backtest::{decode_recording, read_recording, run_backtests, BacktestConfig};
+use bot::{JupiterClient, PriceImpactGuard, TokenFilter, VenueContext, WsolMode};
+use dotenv::dotenv;
+use fast_websocket_client::OpCode;
+use helius::{types::Cluster, Helius};
+use std::{env, error::Error, fs, path::Path, sync::Arc};
+use tokio::sync::Mutex;
+
+/// Replays a recording of the copy listener through every config of
+/// `backtest_configs` and prints what each would have made:
+///
+/// * `backtest_recording` - The notifications recorded with `record_path`
+/// * `backtest_configs` - A JSON array of `BacktestConfig`
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    dotenv().ok();
+
+    let configs = BacktestConfig::from_json(&fs::read_to_string(env::var("backtest_configs")?)?)?;
+    let recording = read_recording(Path::new(&env::var("backtest_recording")?))?;
+    println!(
+        "Replaying {} notifications through {} configs",
+        recording.len(),
+        configs.len()
+    );
+
+    // The Raydium decoder validates the mints over RPC, as when copying
+    let helius = Arc::new(Mutex::new(Helius::new_with_async_solana(
+        env::var("helius_prod_api_key")?.as_str(),
+        Cluster::MainnetBeta,
+    )?));
+    let venue_context = VenueContext {
+        helius,
+        token_filter: Arc::new(TokenFilter::from_env()?),
+        wsol_mode: WsolMode::from_env()?,
+        jupiter: JupiterClient::from_env(),
+        price_impact: PriceImpactGuard::default(),
+    };
+
+    // Decoded once, replayed for every config
+    let events = decode_recording(&venue_context, &recording).await;
+    println!("Trades decoded: {}\n", events.len());
+
+    for report in run_backtests(&configs, &events) {
+        println!("{report}");
+    }
+    Ok(())
+}
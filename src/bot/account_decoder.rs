@@ -0,0 +1,150 @@
+use bytemuck::Pod;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use crate::errors::AccountDecodeError;
+
+/// Decodes on-chain account data into a `#[repr(C, packed)]` state struct
+/// without copying nor `unsafe`: the packed layout has an alignment of 1,
+/// so the bytes can be borrowed as the struct once the length is checked.
+///
+/// Implementors only describe where the state lives in the account:
+/// the programs allowed to own it, the bytes around it and whether
+/// newer versions of the account may carry more bytes.
+pub trait AccountDecoder: Pod {
+    /// Programs allowed to own the account
+    const OWNERS: &'static [Pubkey];
+    /// Bytes in front of the state, e.g. an Anchor discriminator
+    const HEAD: &'static [u8] = &[];
+    /// Bytes right after the state, e.g. the Serum `padding` blob
+    const TAIL: &'static [u8] = &[];
+    /// True if the account may hold more bytes after the state,
+    /// like fields appended by a program upgrade or Token-2022 extensions
+    const EXTENDABLE: bool = false;
+
+    /// Decodes the account data, borrowing the state from it
+    ///
+    /// * `data` - The raw account data
+    fn decode(data: &[u8]) -> Result<&Self, AccountDecodeError> {
+        let state_len = std::mem::size_of::<Self>();
+        let expected = Self::HEAD.len() + state_len + Self::TAIL.len();
+
+        if data.len() < expected || (!Self::EXTENDABLE && data.len() != expected) {
+            return Err(AccountDecodeError::InvalidLength {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        if !data.starts_with(Self::HEAD) {
+            return Err(AccountDecodeError::InvalidDiscriminator);
+        }
+
+        let state_end = Self::HEAD.len() + state_len;
+        if !data[state_end..].starts_with(Self::TAIL) {
+            return Err(AccountDecodeError::InvalidPadding);
+        }
+
+        bytemuck::try_from_bytes(&data[Self::HEAD.len()..state_end]).map_err(|_| {
+            AccountDecodeError::InvalidLength {
+                expected,
+                actual: data.len(),
+            }
+        })
+    }
+
+    /// Decodes an account after checking it is owned by one of `OWNERS`
+    ///
+    /// * `account` - The account fetched from the RPC
+    fn decode_account(account: &Account) -> Result<&Self, AccountDecodeError> {
+        if !Self::OWNERS.contains(&account.owner) {
+            return Err(AccountDecodeError::InvalidOwner(account.owner));
+        }
+
+        Self::decode(&account.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    #[repr(C, packed)]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    struct TestState {
+        value: u64,
+    }
+
+    const TEST_OWNER: Pubkey = Pubkey::from_str_const("11111111111111111111111111111111");
+
+    impl AccountDecoder for TestState {
+        const OWNERS: &'static [Pubkey] = &[TEST_OWNER];
+        const HEAD: &'static [u8] = b"head";
+        const TAIL: &'static [u8] = b"tail";
+    }
+
+    #[repr(C, packed)]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    struct ExtendableState {
+        value: u64,
+    }
+
+    impl AccountDecoder for ExtendableState {
+        const OWNERS: &'static [Pubkey] = &[TEST_OWNER];
+        const EXTENDABLE: bool = true;
+    }
+
+    #[test]
+    fn test_decode() {
+        let data = [b"head".as_slice(), &7u64.to_le_bytes(), b"tail"].concat();
+        assert_eq!({ TestState::decode(&data).unwrap().value }, 7);
+
+        // Unaligned on purpose, the packed state borrows from any offset
+        let shifted = [&[0u8][..], &data].concat();
+        assert_eq!({ TestState::decode(&shifted[1..]).unwrap().value }, 7);
+
+        assert_eq!(
+            TestState::decode(&data[..data.len() - 1]).err(),
+            Some(AccountDecodeError::InvalidLength {
+                expected: 16,
+                actual: 15
+            })
+        );
+
+        let bad_head = [b"HEAD".as_slice(), &7u64.to_le_bytes(), b"tail"].concat();
+        assert_eq!(
+            TestState::decode(&bad_head).err(),
+            Some(AccountDecodeError::InvalidDiscriminator)
+        );
+
+        let bad_tail = [b"head".as_slice(), &7u64.to_le_bytes(), b"TAIL"].concat();
+        assert_eq!(
+            TestState::decode(&bad_tail).err(),
+            Some(AccountDecodeError::InvalidPadding)
+        );
+    }
+
+    #[test]
+    fn test_decode_extendable() {
+        let data = [7u64.to_le_bytes(), 8u64.to_le_bytes()].concat();
+        assert_eq!({ ExtendableState::decode(&data).unwrap().value }, 7);
+        assert!(ExtendableState::decode(&data[..7]).is_err());
+        assert!(TestState::decode(&[b"head".as_slice(), &data, b"tail"].concat()).is_err());
+    }
+
+    #[test]
+    fn test_decode_account_checks_owner() {
+        let mut account = Account {
+            owner: TEST_OWNER,
+            data: 7u64.to_le_bytes().to_vec(),
+            ..Default::default()
+        };
+        assert!(ExtendableState::decode_account(&account).is_ok());
+
+        account.owner = Pubkey::new_unique();
+        assert_eq!(
+            ExtendableState::decode_account(&account).err(),
+            Some(AccountDecodeError::InvalidOwner(account.owner))
+        );
+    }
+}
@@ -37,6 +37,14 @@ impl TransactionSubscribe {
           ]
         }))
     }
+
+    /// Also matches the transactions touching any of `accounts_included`
+    ///
+    /// * `accounts_included` - The accounts, any of which a transaction must touch
+    pub fn include_accounts(mut self, accounts_included: &[&str]) -> Self {
+        self.0["params"][0]["accountInclude"] = serde_json::json!(accounts_included);
+        self
+    }
 }
 
 #[allow(dead_code)]
@@ -60,6 +68,7 @@ pub async fn subscribe_pump_fun(
     Ok(())
 }
 
+#[allow(dead_code)]
 pub async fn subscribe_raydium(
     client: &mut client::Online,
     started_at: Instant,
@@ -81,3 +90,27 @@ pub async fn subscribe_raydium(
     .await??;
     Ok(())
 }
+
+/// Subscribes to the transactions of any of `wallets`, in a single subscription
+///
+/// * `client` - The websocket client
+/// * `started_at` - The listener start, the subscription id
+/// * `wallets` - The wallets followed
+pub async fn subscribe_wallets(
+    client: &mut client::Online,
+    started_at: Instant,
+    wallets: &[String],
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let wallets: Vec<&str> = wallets.iter().map(String::as_str).collect();
+
+    tokio::time::timeout(
+        Duration::from_millis(0),
+        client.send_json(
+            &TransactionSubscribe::new(started_at, &[], &[])
+                .include_accounts(&wallets)
+                .0,
+        ),
+    )
+    .await??;
+    Ok(())
+}
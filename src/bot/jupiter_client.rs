@@ -0,0 +1,432 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    address_lookup_table::{state::AddressLookupTable, AddressLookupTableAccount},
+    compute_budget,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use std::{env, error::Error, str::FromStr};
+
+use crate::constants::JUPITER_API_URL;
+
+/// A quote of the Jupiter quote API
+#[derive(Debug, Clone)]
+pub struct JupiterQuote {
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub in_amount: u64,
+    pub out_amount: u64,
+    /// Minimum output once the slippage is applied
+    pub other_amount_threshold: u64,
+    pub slippage_bps: u64,
+    /// Price impact of the route, a fraction despite its name
+    pub price_impact_pct: f64,
+    /// Labels of the AMMs the route goes through
+    pub route_labels: Vec<String>,
+    /// The quote as received, posted back to build the swap
+    pub response: Value,
+}
+
+impl JupiterQuote {
+    /// Parses a quote response
+    ///
+    /// * `response` - The quote API response
+    pub fn from_response(response: Value) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let route_labels = response["routePlan"]
+            .as_array()
+            .map(|route_plan| {
+                route_plan
+                    .iter()
+                    .filter_map(|step| step["swapInfo"]["label"].as_str())
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            input_mint: parse_pubkey(&response["inputMint"])?,
+            output_mint: parse_pubkey(&response["outputMint"])?,
+            in_amount: parse_amount(&response["inAmount"])?,
+            out_amount: parse_amount(&response["outAmount"])?,
+            other_amount_threshold: parse_amount(&response["otherAmountThreshold"])?,
+            slippage_bps: response["slippageBps"].as_u64().unwrap_or_default(),
+            price_impact_pct: response["priceImpactPct"]
+                .as_str()
+                .and_then(|pct| pct.parse().ok())
+                .unwrap_or_default(),
+            route_labels,
+            response,
+        })
+    }
+
+    /// Returns the quote with another slippage, the minimum output being
+    /// recomputed the way the quote API does for an exact input swap
+    ///
+    /// * `slippage_bps` - The slippage tolerated, in bps
+    pub fn with_slippage(&self, slippage_bps: u64) -> Self {
+        let slippage_bps = slippage_bps.min(10_000);
+        let other_amount_threshold =
+            (self.out_amount as u128 * (10_000 - slippage_bps) as u128 / 10_000) as u64;
+
+        let mut quote = self.clone();
+        quote.slippage_bps = slippage_bps;
+        quote.other_amount_threshold = other_amount_threshold;
+        quote.response["slippageBps"] = json!(slippage_bps);
+        quote.response["otherAmountThreshold"] = json!(other_amount_threshold.to_string());
+        quote
+    }
+}
+
+/// The instructions of a swap, as returned by the swap-instructions API
+#[derive(Debug, Clone)]
+pub struct JupiterSwapInstructions {
+    /// Setup, swap and cleanup instructions. The compute budget ones are left
+    /// out, `bfg9000_send_smart_tx` sets its own.
+    pub instructions: Vec<Instruction>,
+    pub lookup_table_addresses: Vec<Pubkey>,
+}
+
+impl JupiterSwapInstructions {
+    /// Parses a swap-instructions response
+    ///
+    /// * `response` - The swap-instructions API response
+    pub fn from_response(response: &Value) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut instructions = vec![];
+        for setup_instruction in response["setupInstructions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            instructions.push(parse_instruction(setup_instruction)?);
+        }
+        instructions.push(parse_instruction(&response["swapInstruction"])?);
+        if !response["cleanupInstruction"].is_null() {
+            instructions.push(parse_instruction(&response["cleanupInstruction"])?);
+        }
+        for other_instruction in response["otherInstructions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            instructions.push(parse_instruction(other_instruction)?);
+        }
+        instructions.retain(|instruction| instruction.program_id != compute_budget::id());
+
+        let lookup_table_addresses = response["addressLookupTableAddresses"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(parse_pubkey)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            instructions,
+            lookup_table_addresses,
+        })
+    }
+}
+
+/// Client of the Jupiter quote and swap-instructions HTTP API, a fallback
+/// route for tokens no venue of ours trades
+#[derive(Debug, Clone)]
+pub struct JupiterClient {
+    base_url: String,
+    http: reqwest::Client,
+}
+
+impl JupiterClient {
+    /// * `base_url` - The API root, e.g. "https://lite-api.jup.ag/swap/v1"
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Loads the API root from `jupiter_api_url`, the public API by default
+    pub fn from_env() -> Self {
+        Self::new(&env::var("jupiter_api_url").unwrap_or_else(|_| JUPITER_API_URL.to_string()))
+    }
+
+    /// Quotes an exact input swap
+    ///
+    /// * `input_mint` - The mint spent
+    /// * `output_mint` - The mint received
+    /// * `amount` - The raw amount spent
+    /// * `slippage_bps` - The slippage tolerated, in bps
+    pub async fn quote(
+        &self,
+        input_mint: &Pubkey,
+        output_mint: &Pubkey,
+        amount: u64,
+        slippage_bps: u64,
+    ) -> Result<JupiterQuote, Box<dyn Error + Send + Sync>> {
+        let request = self.http.get(format!("{}/quote", self.base_url)).query(&[
+            ("inputMint", input_mint.to_string()),
+            ("outputMint", output_mint.to_string()),
+            ("amount", amount.to_string()),
+            ("slippageBps", slippage_bps.to_string()),
+            ("swapMode", "ExactIn".to_string()),
+        ]);
+
+        JupiterQuote::from_response(Self::send(request).await?)
+    }
+
+    /// Gets the instructions swapping a quote for a wallet. SOL is wrapped
+    /// and unwrapped by the swap itself.
+    ///
+    /// * `quote` - The quote to swap
+    /// * `user` - The wallet swapping
+    pub async fn swap_instructions(
+        &self,
+        quote: &JupiterQuote,
+        user: &Pubkey,
+    ) -> Result<JupiterSwapInstructions, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .http
+            .post(format!("{}/swap-instructions", self.base_url))
+            .json(&json!({
+                "quoteResponse": quote.response,
+                "userPublicKey": user.to_string(),
+                "wrapAndUnwrapSol": true,
+            }));
+
+        JupiterSwapInstructions::from_response(&Self::send(request).await?)
+    }
+
+    async fn send(request: reqwest::RequestBuilder) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            return Err(format!("Jupiter API error {status}: {body}").into());
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+}
+
+/// Loads the lookup tables a V0 transaction is compiled against
+///
+/// * `connection` - The RPC client
+/// * `addresses` - The lookup table addresses
+pub async fn fetch_lookup_tables(
+    connection: &RpcClient,
+    addresses: &[Pubkey],
+) -> Result<Vec<AddressLookupTableAccount>, Box<dyn Error + Send + Sync>> {
+    if addresses.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let accounts = connection.get_multiple_accounts(addresses).await?;
+    addresses
+        .iter()
+        .zip(accounts)
+        .map(
+            |(key, account)| -> Result<AddressLookupTableAccount, Box<dyn Error + Send + Sync>> {
+                let account = account.ok_or_else(|| format!("Lookup table not found: {key}"))?;
+                let table = AddressLookupTable::deserialize(&account.data)?;
+                Ok(AddressLookupTableAccount {
+                    key: *key,
+                    addresses: table.addresses.to_vec(),
+                })
+            },
+        )
+        .collect()
+}
+
+/// Parses an instruction of the swap-instructions API
+fn parse_instruction(json: &Value) -> Result<Instruction, Box<dyn Error + Send + Sync>> {
+    let accounts = json["accounts"]
+        .as_array()
+        .ok_or("Instruction without accounts")?
+        .iter()
+        .map(|account| {
+            Ok(AccountMeta {
+                pubkey: parse_pubkey(&account["pubkey"])?,
+                is_signer: account["isSigner"].as_bool().unwrap_or_default(),
+                is_writable: account["isWritable"].as_bool().unwrap_or_default(),
+            })
+        })
+        .collect::<Result<_, Box<dyn Error + Send + Sync>>>()?;
+
+    Ok(Instruction {
+        program_id: parse_pubkey(&json["programId"])?,
+        accounts,
+        data: STANDARD.decode(json["data"].as_str().ok_or("Instruction without data")?)?,
+    })
+}
+
+fn parse_pubkey(json: &Value) -> Result<Pubkey, Box<dyn Error + Send + Sync>> {
+    let pubkey = json.as_str().ok_or("Missing address")?;
+    Ok(Pubkey::from_str(pubkey)?)
+}
+
+/// Amounts are sent as strings to keep their u64 precision
+fn parse_amount(json: &Value) -> Result<u64, Box<dyn Error + Send + Sync>> {
+    Ok(json.as_str().ok_or("Missing amount")?.parse()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::WSOL_MINT;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::mpsc,
+        thread,
+    };
+
+    const QUOTE_RESPONSE: &str = include_str!("../../tests/fixtures/jupiter/quote.json");
+    const SWAP_INSTRUCTIONS_RESPONSE: &str =
+        include_str!("../../tests/fixtures/jupiter/swap_instructions.json");
+
+    /// A request received by the mock server
+    struct RecordedRequest {
+        request_line: String,
+        body: String,
+    }
+
+    /// Starts a local stand-in for the Jupiter API replaying the recorded
+    /// responses, one per connection. Returns its URL and the requests it got.
+    fn mock_jupiter(
+        responses: Vec<(u16, &'static str)>,
+    ) -> (String, mpsc::Receiver<RecordedRequest>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut request_body = vec![0; content_length];
+                reader.read_exact(&mut request_body).unwrap();
+
+                let response = format!(
+                    "HTTP/1.1 {status} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+
+                sender
+                    .send(RecordedRequest {
+                        request_line: request_line.trim().to_string(),
+                        body: String::from_utf8(request_body).unwrap(),
+                    })
+                    .unwrap();
+            }
+        });
+
+        (url, receiver)
+    }
+
+    #[tokio::test]
+    async fn test_quote_and_swap_instructions() {
+        let (url, requests) = mock_jupiter(vec![
+            (200, QUOTE_RESPONSE),
+            (200, SWAP_INSTRUCTIONS_RESPONSE),
+        ]);
+        let client = JupiterClient::new(&format!("{url}/"));
+        let mint = Pubkey::from_str("FWv5hiQqoUahjMyRFzz78q5ajmtwZ9vrn8tytgdFpump").unwrap();
+
+        let quote = client
+            .quote(&WSOL_MINT, &mint, 10_000_000, 100)
+            .await
+            .unwrap();
+        assert_eq!((quote.input_mint, quote.output_mint), (WSOL_MINT, mint));
+        assert_eq!(
+            (quote.in_amount, quote.out_amount),
+            (10_000_000, 352_648_217_091)
+        );
+        assert_eq!(quote.other_amount_threshold, 349_121_734_920);
+        assert_eq!(quote.route_labels, vec!["Pump.fun Amm".to_string()]);
+
+        let request = requests.recv().unwrap();
+        assert!(request.request_line.starts_with("GET /quote?"));
+        assert!(request
+            .request_line
+            .contains(&format!("inputMint={WSOL_MINT}")));
+        assert!(request.request_line.contains("amount=10000000"));
+        assert!(request.request_line.contains("slippageBps=100"));
+
+        let user = Pubkey::from_str("5p8mtXXa5rGNwwuHZ7bDH6QKCkPNDqmHT2YTyM6wZbZ5").unwrap();
+        let swap = client
+            .swap_instructions(&quote.with_slippage(500), &user)
+            .await
+            .unwrap();
+
+        // Setup, swap and cleanup, the compute budget instructions dropped
+        assert_eq!(swap.instructions.len(), 3);
+        assert_eq!(
+            swap.instructions[0].program_id,
+            spl_associated_token_account::id()
+        );
+        assert_eq!(
+            swap.instructions[1].program_id.to_string(),
+            "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4"
+        );
+        assert_eq!(swap.instructions[1].accounts[1].pubkey, user);
+        assert!(swap.instructions[1].accounts[1].is_signer);
+        assert_eq!(swap.instructions[2].program_id, spl_token::id());
+        assert_eq!(swap.instructions[2].data, vec![9]);
+        assert_eq!(swap.lookup_table_addresses.len(), 1);
+
+        let request = requests.recv().unwrap();
+        assert!(request.request_line.starts_with("POST /swap-instructions"));
+        let body: Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(body["userPublicKey"], user.to_string());
+        assert_eq!(body["quoteResponse"]["slippageBps"], 500);
+        assert_eq!(
+            body["quoteResponse"]["otherAmountThreshold"],
+            "335015806236"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_error() {
+        let (url, _requests) = mock_jupiter(vec![(
+            400,
+            r#"{"error":"Could not find any route","errorCode":"COULD_NOT_FIND_ANY_ROUTE"}"#,
+        )]);
+        let client = JupiterClient::new(&url);
+
+        let error = client
+            .quote(&WSOL_MINT, &Pubkey::new_unique(), 1_000, 100)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("COULD_NOT_FIND_ANY_ROUTE"));
+    }
+
+    #[test]
+    fn test_with_slippage() {
+        let quote =
+            JupiterQuote::from_response(serde_json::from_str(QUOTE_RESPONSE).unwrap()).unwrap();
+
+        let quote = quote.with_slippage(250);
+        assert_eq!(quote.slippage_bps, 250);
+        assert_eq!(quote.other_amount_threshold, 343_832_011_663);
+        assert_eq!(quote.response["otherAmountThreshold"], "343832011663");
+
+        assert_eq!(quote.with_slippage(20_000).other_amount_threshold, 0);
+    }
+}
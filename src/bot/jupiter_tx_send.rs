@@ -0,0 +1,96 @@
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use std::error::Error;
+
+use crate::constants::JUPITER_COMPUTE_UNIT_LIMIT;
+
+use super::{
+    fetch_lookup_tables, tx_common::SendSmartTx, ParsedTrade, QuoteDetails, Swap, SwapQuote,
+    SwapTransaction, TradeSide, Venue, VenueContext,
+};
+
+pub struct JupiterTxSend {}
+
+impl SendSmartTx for JupiterTxSend {}
+
+impl Swap for JupiterTxSend {
+    const VENUE: Venue = Venue::Jupiter;
+    const COMPUTE_UNIT_LIMIT: u32 = JUPITER_COMPUTE_UNIT_LIMIT;
+
+    /// Jupiter routes across pools itself, its quotes carry no pool
+    async fn find_pool(
+        _ctx: &VenueContext,
+        _mint: &Pubkey,
+    ) -> Result<Pubkey, Box<dyn Error + Send + Sync>> {
+        Ok(Pubkey::default())
+    }
+
+    async fn quote_pool(
+        ctx: &VenueContext,
+        _pool_id: &Pubkey,
+        mint: &Pubkey,
+        side: TradeSide,
+        amount: u64,
+    ) -> Result<SwapQuote, Box<dyn Error + Send + Sync>> {
+        let (input_mint, output_mint) = side.mints(mint);
+
+        // The slippage is set on the quote when the swap is built
+        let quote = ctx
+            .jupiter
+            .quote(&input_mint, &output_mint, amount, 0)
+            .await?;
+
+        Ok(SwapQuote {
+            venue: Self::VENUE,
+            pool_id: Pubkey::default(),
+            mint: *mint,
+            side,
+            amount_in: quote.in_amount,
+            amount_out: quote.out_amount,
+            creator: None,
+            quote_liquidity: 0,
+            pool_open_time: None,
+            price_impact: quote.price_impact_pct,
+            details: QuoteDetails::Jupiter(quote),
+        })
+    }
+
+    async fn build_swap(
+        ctx: &VenueContext,
+        quote: &SwapQuote,
+        wallet: &Pubkey,
+        slippage_percent: u64,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let (instructions, _) =
+            Self::build_swap_transaction(ctx, quote, wallet, slippage_percent).await?;
+        Ok(instructions)
+    }
+
+    /// Jupiter routes only fit in a V0 transaction using the route lookup tables
+    async fn build_swap_transaction(
+        ctx: &VenueContext,
+        quote: &SwapQuote,
+        wallet: &Pubkey,
+        slippage_percent: u64,
+    ) -> Result<SwapTransaction, Box<dyn Error + Send + Sync>> {
+        let QuoteDetails::Jupiter(details) = &quote.details else {
+            return Err("Not a Jupiter quote".into());
+        };
+
+        let swap = ctx
+            .jupiter
+            .swap_instructions(&details.with_slippage(slippage_percent * 100), wallet)
+            .await?;
+
+        let lookup_tables = {
+            let connection = ctx.helius.lock().await.async_connection()?;
+            fetch_lookup_tables(&connection, &swap.lookup_table_addresses).await?
+        };
+
+        Ok((swap.instructions, Some(lookup_tables)))
+    }
+
+    /// Leader trades routed through Jupiter are not decoded
+    async fn parse_trade(_ctx: &VenueContext, _payload: &str) -> Option<ParsedTrade> {
+        None
+    }
+}
@@ -0,0 +1,393 @@
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Upper bounds of the histogram buckets, the last bucket is unbounded
+const HISTOGRAM_BUCKETS: [u64; 15] = [
+    1, 2, 5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000,
+];
+
+/// A point in the life of a copied order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LatencyStage {
+    /// The websocket frame of the leader transaction was received
+    Received,
+    Decoded,
+    Filtered,
+    /// The swap instructions were built, quote included
+    Built,
+    /// The final transaction was signed, fee estimate included
+    Signed,
+    Submitted,
+    Confirmed,
+}
+
+impl LatencyStage {
+    /// Returns the name of the interval ending at the stage
+    pub fn interval(self) -> &'static str {
+        match self {
+            Self::Received => "receive",
+            Self::Decoded => "decode",
+            Self::Filtered => "filter",
+            Self::Built => "build",
+            Self::Signed => "sign",
+            Self::Submitted => "submit",
+            Self::Confirmed => "confirm",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct TraceStamps {
+    stamps: BTreeMap<LatencyStage, Instant>,
+    leader_slot: Option<u64>,
+    landed_slot: Option<u64>,
+}
+
+/// Timestamps of a copied order, from the websocket frame of the leader
+/// transaction to the slot our copy landed in.
+///
+/// The first stamp of a stage wins, so a route split in several legs is
+/// timed on its first leg.
+#[derive(Debug)]
+pub struct OrderTrace {
+    inner: Mutex<TraceStamps>,
+}
+
+impl OrderTrace {
+    /// Starts a trace at the receipt of the websocket frame
+    ///
+    /// * `received_at` - When the frame was received
+    pub fn new(received_at: Instant) -> Self {
+        let trace = Self {
+            inner: Mutex::new(TraceStamps::default()),
+        };
+        trace.stamp_at(LatencyStage::Received, received_at);
+        trace
+    }
+
+    /// Records that the order reached `stage` now
+    pub fn stamp(&self, stage: LatencyStage) {
+        self.stamp_at(stage, Instant::now());
+    }
+
+    /// Records that the order reached `stage` at `at`
+    pub fn stamp_at(&self, stage: LatencyStage, at: Instant) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.stamps.entry(stage).or_insert(at);
+        }
+    }
+
+    /// Records the slot of the leader transaction
+    pub fn set_leader_slot(&self, slot: u64) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.leader_slot.get_or_insert(slot);
+        }
+    }
+
+    /// Records the slot our transaction landed in
+    pub fn set_landed_slot(&self, slot: u64) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.landed_slot.get_or_insert(slot);
+        }
+    }
+
+    pub fn leader_slot(&self) -> Option<u64> {
+        self.inner.lock().ok()?.leader_slot
+    }
+
+    pub fn landed_slot(&self) -> Option<u64> {
+        self.inner.lock().ok()?.landed_slot
+    }
+
+    /// Returns the time from the frame receipt to every stage reached
+    pub fn offsets(&self) -> Vec<(LatencyStage, Duration)> {
+        let Ok(inner) = self.inner.lock() else {
+            return Vec::new();
+        };
+        let Some(received_at) = inner.stamps.get(&LatencyStage::Received) else {
+            return Vec::new();
+        };
+
+        inner
+            .stamps
+            .iter()
+            .map(|(stage, at)| (*stage, at.saturating_duration_since(*received_at)))
+            .collect()
+    }
+
+    /// Returns the time spent reaching every stage from the previous stage
+    /// reached, named after the interval
+    pub fn intervals(&self) -> Vec<(&'static str, Duration)> {
+        let Ok(inner) = self.inner.lock() else {
+            return Vec::new();
+        };
+
+        inner
+            .stamps
+            .iter()
+            .zip(inner.stamps.iter().skip(1))
+            .map(|((_, from), (stage, to))| (stage.interval(), to.saturating_duration_since(*from)))
+            .collect()
+    }
+
+    /// Returns how many slots after the leader our transaction landed
+    pub fn slot_lag(&self) -> Option<u64> {
+        let inner = self.inner.lock().ok()?;
+        Some(inner.landed_slot?.saturating_sub(inner.leader_slot?))
+    }
+
+    /// Returns the stage offsets in milliseconds and the slots, as stored
+    /// with the order
+    pub fn to_json(&self) -> Value {
+        let offsets: serde_json::Map<String, Value> = self
+            .offsets()
+            .into_iter()
+            .map(|(stage, offset)| {
+                (
+                    stage.interval().to_string(),
+                    json!(offset.as_millis() as u64),
+                )
+            })
+            .collect();
+
+        json!({
+            "offsets_ms": offsets,
+            "leader_slot": self.leader_slot(),
+            "landed_slot": self.landed_slot(),
+        })
+    }
+}
+
+impl fmt::Display for OrderTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let intervals: Vec<String> = self
+            .intervals()
+            .into_iter()
+            .map(|(interval, duration)| format!("{interval} {}ms", duration.as_millis()))
+            .collect();
+        write!(f, "{}", intervals.join(" | "))?;
+        if let Some(slot_lag) = self.slot_lag() {
+            write!(f, " | landed {slot_lag} slots after the leader")?;
+        }
+        Ok(())
+    }
+}
+
+/// Counts of values per bucket
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Histogram {
+    /// One count per bucket of `HISTOGRAM_BUCKETS`, then the overflow
+    counts: [u64; HISTOGRAM_BUCKETS.len() + 1],
+    count: u64,
+    sum: u64,
+    max: u64,
+}
+
+impl Histogram {
+    pub fn record(&mut self, value: u64) {
+        let bucket = HISTOGRAM_BUCKETS
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(HISTOGRAM_BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum = self.sum.saturating_add(value);
+        self.max = self.max.max(value);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean(&self) -> Option<u64> {
+        (self.count > 0).then(|| self.sum / self.count)
+    }
+
+    /// Returns the upper bound of the bucket holding the `percentile`,
+    /// the max for the overflow bucket
+    ///
+    /// * `percentile` - Between 0 and 100
+    pub fn percentile(&self, percentile: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((percentile / 100.0) * self.count as f64).ceil().max(1.0) as u64;
+
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(HISTOGRAM_BUCKETS.get(bucket).copied().unwrap_or(self.max));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+/// Latency histograms of the copied orders, per stage interval in
+/// milliseconds and for the slots between the leader and our landing
+#[derive(Debug, Default)]
+pub struct LatencyHistograms {
+    histograms: Mutex<BTreeMap<&'static str, Histogram>>,
+}
+
+impl LatencyHistograms {
+    /// Histogram of the slots between the leader transaction and ours
+    pub const SLOT_LAG: &'static str = "slot_lag";
+    /// Histogram of the time from the frame receipt to the last stage reached
+    pub const TOTAL: &'static str = "total";
+
+    /// Records every interval of an order trace
+    pub fn record(&self, trace: &OrderTrace) {
+        let Ok(mut histograms) = self.histograms.lock() else {
+            return;
+        };
+
+        for (interval, duration) in trace.intervals() {
+            histograms
+                .entry(interval)
+                .or_default()
+                .record(duration.as_millis() as u64);
+        }
+        if let Some((_, total)) = trace.offsets().last() {
+            histograms
+                .entry(Self::TOTAL)
+                .or_default()
+                .record(total.as_millis() as u64);
+        }
+        if let Some(slot_lag) = trace.slot_lag() {
+            histograms
+                .entry(Self::SLOT_LAG)
+                .or_default()
+                .record(slot_lag);
+        }
+    }
+
+    /// Returns a copy of the histogram of an interval
+    ///
+    /// * `name` - The interval name, `TOTAL` or `SLOT_LAG`
+    pub fn get(&self, name: &str) -> Option<Histogram> {
+        self.histograms.lock().ok()?.get(name).cloned()
+    }
+}
+
+impl fmt::Display for LatencyHistograms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Ok(histograms) = self.histograms.lock() else {
+            return Ok(());
+        };
+
+        for (name, histogram) in histograms.iter() {
+            let unit = if *name == Self::SLOT_LAG {
+                " slots"
+            } else {
+                "ms"
+            };
+            let value = |value: Option<u64>| value.unwrap_or_default();
+            writeln!(
+                f,
+                "{name}: n={} mean={}{unit} p50<={}{unit} p90<={}{unit} p99<={}{unit} max={}{unit}",
+                histogram.count(),
+                value(histogram.mean()),
+                value(histogram.percentile(50.0)),
+                value(histogram.percentile(90.0)),
+                value(histogram.percentile(99.0)),
+                histogram.max,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order_trace() {
+        let received_at = Instant::now();
+        let trace = OrderTrace::new(received_at);
+        trace.stamp_at(
+            LatencyStage::Decoded,
+            received_at + Duration::from_millis(3),
+        );
+        trace.stamp_at(LatencyStage::Built, received_at + Duration::from_millis(40));
+        // The first stamp wins
+        trace.stamp_at(LatencyStage::Built, received_at + Duration::from_millis(90));
+        trace.stamp_at(
+            LatencyStage::Confirmed,
+            received_at + Duration::from_millis(1_500),
+        );
+        trace.set_leader_slot(100);
+        trace.set_landed_slot(102);
+
+        assert_eq!(
+            trace.intervals(),
+            vec![
+                ("decode", Duration::from_millis(3)),
+                ("build", Duration::from_millis(37)),
+                ("confirm", Duration::from_millis(1_460)),
+            ]
+        );
+        assert_eq!(trace.slot_lag(), Some(2));
+        assert_eq!(
+            trace.to_json(),
+            json!({
+                "offsets_ms": { "receive": 0, "decode": 3, "build": 40, "confirm": 1_500 },
+                "leader_slot": 100,
+                "landed_slot": 102,
+            })
+        );
+    }
+
+    #[test]
+    fn test_histogram() {
+        let mut histogram = Histogram::default();
+        assert_eq!(histogram.percentile(50.0), None);
+
+        for value in [1, 3, 4, 8, 40, 200, 90_000] {
+            histogram.record(value);
+        }
+        assert_eq!(histogram.count(), 7);
+        assert_eq!(histogram.percentile(50.0), Some(10));
+        assert_eq!(histogram.percentile(0.0), Some(1));
+        // The overflow bucket reports the max
+        assert_eq!(histogram.percentile(100.0), Some(90_000));
+    }
+
+    #[test]
+    fn test_latency_histograms() {
+        let received_at = Instant::now();
+        let trace = OrderTrace::new(received_at);
+        trace.stamp_at(
+            LatencyStage::Decoded,
+            received_at + Duration::from_millis(2),
+        );
+        trace.stamp_at(
+            LatencyStage::Submitted,
+            received_at + Duration::from_millis(300),
+        );
+        trace.set_leader_slot(10);
+        trace.set_landed_slot(13);
+
+        let histograms = LatencyHistograms::default();
+        histograms.record(&trace);
+
+        assert_eq!(histograms.get("decode").map(|h| h.count()), Some(1));
+        assert_eq!(histograms.get("submit").map(|h| h.max), Some(298));
+        assert_eq!(
+            histograms.get(LatencyHistograms::TOTAL).map(|h| h.max),
+            Some(300)
+        );
+        assert_eq!(
+            histograms.get(LatencyHistograms::SLOT_LAG).map(|h| h.max),
+            Some(3)
+        );
+        assert_eq!(histograms.get("confirm"), None);
+    }
+}
@@ -0,0 +1,469 @@
+use bytemuck::{Pod, Zeroable};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::constants::{
+    METEORA_DLMM_BASIS_POINT_MAX, METEORA_DLMM_BINS_PER_ARRAY,
+    METEORA_DLMM_BIN_ARRAY_DISCRIMINATOR, METEORA_DLMM_BIN_ARRAY_SEED, METEORA_DLMM_FEE_PRECISION,
+    METEORA_DLMM_LB_PAIR_DISCRIMINATOR, METEORA_DLMM_MAX_FEE_RATE, METEORA_DLMM_PROGRAM,
+};
+
+use super::AccountDecoder;
+
+/// Meteora DLMM pair parameters set at creation
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct DlmmStaticParameters {
+    /// Used for base fee calculation. base_fee_rate = base_factor * bin_step * 10 * 10^base_fee_power_factor
+    pub base_factor: u16,
+    pub filter_period: u16,
+    pub decay_period: u16,
+    pub reduction_factor: u16,
+    /// Used to scale the variable fee component depending on the dynamic of the market
+    pub variable_fee_control: u32,
+    pub max_volatility_accumulator: u32,
+    /// Min bin id supported by the pool based on the configured bin step.
+    pub min_bin_id: i32,
+    /// Max bin id supported by the pool based on the configured bin step.
+    pub max_bin_id: i32,
+    /// Portion of swap fees retained by the protocol, in basis points
+    pub protocol_share: u16,
+    pub base_fee_power_factor: u8,
+    pub padding: [u8; 5],
+}
+
+/// Meteora DLMM pair parameters updated by the swaps
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct DlmmVariableParameters {
+    /// Volatility accumulator measure the number of bin crossed since reference bin ID.
+    pub volatility_accumulator: u32,
+    pub volatility_reference: u32,
+    pub index_reference: i32,
+    pub padding: [u8; 4],
+    pub last_update_timestamp: i64,
+    pub padding1: [u8; 8],
+}
+
+/// Meteora DLMM reward information, part of the pair state
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct DlmmRewardInfo {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub funder: Pubkey,
+    pub reward_duration: u64,
+    pub reward_duration_end: u64,
+    pub reward_rate: u128,
+    pub last_update_time: u64,
+    pub cumulative_seconds_with_empty_liquidity_reward: u64,
+}
+
+/// Meteora DLMM pair (liquidity book) state.
+/// Based on https://github.com/MeteoraAg/dlmm-sdk/blob/main/programs/lb_clmm/src/state/lb_pair/mod.rs
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct DlmmLbPair {
+    pub parameters: DlmmStaticParameters,
+    pub v_parameters: DlmmVariableParameters,
+    pub bump_seed: [u8; 1],
+    pub bin_step_seed: [u8; 2],
+    pub pair_type: u8,
+    /// Active bin id, the bin holding the current price
+    pub active_id: i32,
+    /// Price increment between two bins, in basis points
+    pub bin_step: u16,
+    /// 0 enabled, 1 disabled
+    pub status: u8,
+    pub require_base_factor_seed: u8,
+    pub base_factor_seed: [u8; 2],
+    /// 0 slot, 1 timestamp
+    pub activation_type: u8,
+    pub creator_pool_on_off_control: u8,
+    pub token_x_mint: Pubkey,
+    pub token_y_mint: Pubkey,
+    pub reserve_x: Pubkey,
+    pub reserve_y: Pubkey,
+    pub protocol_fee_amount_x: u64,
+    pub protocol_fee_amount_y: u64,
+    pub padding1: [u8; 32],
+    pub reward_infos: [DlmmRewardInfo; 2],
+    pub oracle: Pubkey,
+    /// Packed initialized bin array state
+    pub bin_array_bitmap: [u64; 16],
+    pub last_updated_at: i64,
+    pub padding2: [u8; 32],
+    pub pre_activation_swap_address: Pubkey,
+    pub base_key: Pubkey,
+    /// Slot or timestamp, per `activation_type`, the pair opens for swaps at
+    pub activation_point: u64,
+    pub pre_activation_duration: u64,
+    pub padding3: [u8; 8],
+    pub padding4: u64,
+    /// Pair creator, default for pairs created before it was stored
+    pub creator: Pubkey,
+    pub token_mint_x_program_flag: u8,
+    pub token_mint_y_program_flag: u8,
+    pub reserved: [u8; 22],
+}
+
+/// A single bin of a bin array
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct DlmmBin {
+    /// Amount of token X in the bin
+    pub amount_x: u64,
+    /// Amount of token Y in the bin
+    pub amount_y: u64,
+    /// Bin price, Q64.64
+    pub price: u128,
+    pub liquidity_supply: u128,
+    pub reward_per_token_stored: [u128; 2],
+    pub fee_amount_x_per_token_stored: u128,
+    pub fee_amount_y_per_token_stored: u128,
+    pub amount_x_in: u128,
+    pub amount_y_in: u128,
+}
+
+/// A DLMM bin array, holding `METEORA_DLMM_BINS_PER_ARRAY` consecutive bins
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct DlmmBinArray {
+    pub index: i64,
+    pub version: u8,
+    pub padding: [u8; 7],
+    pub lb_pair: Pubkey,
+    pub bins: [DlmmBin; 70],
+}
+
+/// The result of walking the bins for an exact input swap
+#[derive(Debug, Clone, PartialEq)]
+pub struct DlmmSwapQuote {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    /// Relative difference between the active bin price and the execution price, fees excluded
+    pub price_impact: f64,
+    /// Indexes of the bin arrays the swap goes through, in order
+    pub bin_array_indexes: Vec<i64>,
+}
+
+/// `DlmmLbPair::activation_type` of pairs activated at a timestamp
+const DLMM_ACTIVATION_TYPE_TIMESTAMP: u8 = 1;
+/// Number of bin arrays tracked by `DlmmLbPair::bin_array_bitmap`, half on each side of 0
+const DLMM_BIN_ARRAY_BITMAP_SIZE: i64 = 1024;
+
+impl AccountDecoder for DlmmLbPair {
+    const OWNERS: &'static [Pubkey] = &[METEORA_DLMM_PROGRAM];
+    const HEAD: &'static [u8] = METEORA_DLMM_LB_PAIR_DISCRIMINATOR;
+}
+
+impl AccountDecoder for DlmmBinArray {
+    const OWNERS: &'static [Pubkey] = &[METEORA_DLMM_PROGRAM];
+    const HEAD: &'static [u8] = METEORA_DLMM_BIN_ARRAY_DISCRIMINATOR;
+}
+
+impl DlmmLbPair {
+    /// Returns true if swaps are currently allowed on the pair
+    pub fn is_swap_enabled(&self) -> bool {
+        self.status == 0
+    }
+
+    /// Returns the pair creator, None for pairs created before it was stored
+    pub fn creator(&self) -> Option<Pubkey> {
+        Some(self.creator).filter(|creator| *creator != Pubkey::default())
+    }
+
+    /// Returns the unix timestamp the pair opens at, None for pairs
+    /// activated at a slot
+    pub fn open_time(&self) -> Option<u64> {
+        (self.activation_type == DLMM_ACTIVATION_TYPE_TIMESTAMP).then_some(self.activation_point)
+    }
+
+    /// Returns the index of the bin array holding `bin_id`
+    pub fn bin_array_index(bin_id: i32) -> i64 {
+        bin_id.div_euclid(METEORA_DLMM_BINS_PER_ARRAY) as i64
+    }
+
+    /// Returns true if the bin array `index` is flagged as initialized in the
+    /// pair bitmap. Arrays outside the bitmap range (tracked by the bitmap
+    /// extension account) are reported as not initialized.
+    pub fn is_bin_array_initialized(&self, index: i64) -> bool {
+        let offset = index + DLMM_BIN_ARRAY_BITMAP_SIZE / 2;
+        if !(0..DLMM_BIN_ARRAY_BITMAP_SIZE).contains(&offset) {
+            return false;
+        }
+
+        let bitmap = self.bin_array_bitmap;
+        bitmap[(offset / 64) as usize] & (1u64 << (offset % 64)) != 0
+    }
+
+    /// Returns the indexes of the initialized bin arrays a swap walks
+    /// through, starting with the array holding the active bin.
+    ///
+    /// * `swap_for_y` - true when token X is sold to the pair (price goes down)
+    /// * `max` - The maximum number of bin arrays to return
+    pub fn swap_bin_array_indexes(&self, swap_for_y: bool, max: usize) -> Vec<i64> {
+        let step = if swap_for_y { -1 } else { 1 };
+        let mut index = Self::bin_array_index(self.active_id);
+        let mut indexes = vec![];
+
+        for _ in 0..DLMM_BIN_ARRAY_BITMAP_SIZE {
+            if indexes.len() >= max {
+                break;
+            }
+            if self.is_bin_array_initialized(index) {
+                indexes.push(index);
+            }
+            index += step;
+        }
+
+        indexes
+    }
+
+    /// Derives the bin array account address
+    ///
+    /// * `lb_pair` - The pair account
+    /// * `index` - The bin array index
+    pub fn bin_array_address(lb_pair: &Pubkey, index: i64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                METEORA_DLMM_BIN_ARRAY_SEED,
+                lb_pair.as_ref(),
+                &index.to_le_bytes(),
+            ],
+            &METEORA_DLMM_PROGRAM,
+        )
+        .0
+    }
+
+    /// Returns the price of a bin as token Y per token X, raw amounts
+    ///
+    /// * `bin_id` - The bin id
+    pub fn bin_price(&self, bin_id: i32) -> f64 {
+        (1.0 + self.bin_step as f64 / METEORA_DLMM_BASIS_POINT_MAX as f64).powi(bin_id)
+    }
+
+    /// Returns the total fee rate charged on the input, base fee plus the
+    /// variable fee of the current volatility, in `METEORA_DLMM_FEE_PRECISION`
+    pub fn total_fee_rate(&self) -> u64 {
+        let parameters = self.parameters;
+        let volatility_accumulator = self.v_parameters.volatility_accumulator;
+
+        let base_fee_rate = parameters.base_factor as u128
+            * self.bin_step as u128
+            * 10
+            * 10u128.pow(parameters.base_fee_power_factor as u32);
+
+        let square_vfa_bin = (volatility_accumulator as u128 * self.bin_step as u128).pow(2);
+        let variable_fee_rate =
+            (parameters.variable_fee_control as u128 * square_vfa_bin).div_ceil(100_000_000_000);
+
+        (base_fee_rate + variable_fee_rate).min(METEORA_DLMM_MAX_FEE_RATE as u128) as u64
+    }
+
+    /// Quotes an exact input swap by walking the bins of the given bin
+    /// arrays, from the active bin in the swap direction. Returns None when
+    /// the bins run out of liquidity before the input is filled: the swap
+    /// would need more bin arrays.
+    ///
+    /// * `amount_in` - The exact input amount
+    /// * `swap_for_y` - true when token X is sold to the pair
+    /// * `bin_arrays` - The bin arrays returned for `swap_bin_array_indexes`
+    pub fn quote_exact_in(
+        &self,
+        amount_in: u64,
+        swap_for_y: bool,
+        bin_arrays: &[DlmmBinArray],
+    ) -> Option<DlmmSwapQuote> {
+        let fee = (amount_in as u128 * self.total_fee_rate() as u128)
+            .div_ceil(METEORA_DLMM_FEE_PRECISION as u128) as u64;
+        let amount_in_less_fee = amount_in.saturating_sub(fee) as f64;
+
+        let active_id = self.active_id;
+
+        // Bins in the swap direction, closest first
+        let mut bins: Vec<(i32, u64, u64, i64)> = bin_arrays
+            .iter()
+            .flat_map(|bin_array| {
+                let index = bin_array.index;
+                let bins = bin_array.bins;
+                bins.into_iter()
+                    .enumerate()
+                    .map(move |(i, bin)| {
+                        (
+                            (index * METEORA_DLMM_BINS_PER_ARRAY as i64 + i as i64) as i32,
+                            bin.amount_x,
+                            bin.amount_y,
+                            index,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|(bin_id, _, _, _)| {
+                if swap_for_y {
+                    *bin_id <= active_id
+                } else {
+                    *bin_id >= active_id
+                }
+            })
+            .collect();
+        if swap_for_y {
+            bins.sort_by(|a, b| b.0.cmp(&a.0));
+        } else {
+            bins.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+
+        let mut remaining = amount_in_less_fee;
+        let mut amount_out = 0f64;
+        let mut bin_array_indexes = vec![Self::bin_array_index(active_id)];
+
+        // Within a bin the price is constant, the swap drains the output
+        // side of the bin before moving to the next one
+        for (bin_id, amount_x, amount_y, index) in bins {
+            if remaining <= 0.0 {
+                break;
+            }
+
+            let price = self.bin_price(bin_id);
+            let (max_in, max_out) = if swap_for_y {
+                (amount_y as f64 / price, amount_y as f64)
+            } else {
+                (amount_x as f64 * price, amount_x as f64)
+            };
+            if max_out <= 0.0 {
+                continue;
+            }
+
+            if !bin_array_indexes.contains(&index) {
+                bin_array_indexes.push(index);
+            }
+
+            if remaining <= max_in {
+                amount_out += if swap_for_y {
+                    remaining * price
+                } else {
+                    remaining / price
+                };
+                remaining = 0.0;
+            } else {
+                amount_out += max_out;
+                remaining -= max_in;
+            }
+        }
+
+        if remaining > 0.0 {
+            return None;
+        }
+
+        let spot_price = if swap_for_y {
+            self.bin_price(active_id)
+        } else {
+            1.0 / self.bin_price(active_id)
+        };
+        let execution_price = if amount_in_less_fee > 0.0 {
+            amount_out / amount_in_less_fee
+        } else {
+            spot_price
+        };
+        let price_impact = if spot_price > 0.0 {
+            (1.0 - execution_price / spot_price).max(0.0)
+        } else {
+            0.0
+        };
+
+        Some(DlmmSwapQuote {
+            amount_in,
+            amount_out: amount_out.floor() as u64,
+            price_impact,
+            bin_array_indexes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lb_pair(active_id: i32) -> DlmmLbPair {
+        let mut lb_pair: DlmmLbPair = Zeroable::zeroed();
+        lb_pair.active_id = active_id;
+        lb_pair.bin_step = 100;
+        lb_pair.parameters = DlmmStaticParameters {
+            base_factor: 10_000,
+            ..Default::default()
+        };
+        lb_pair
+    }
+
+    fn bin_array(index: i64, bins: &[(usize, u64, u64)]) -> DlmmBinArray {
+        let mut bin_array: DlmmBinArray = Zeroable::zeroed();
+        bin_array.index = index;
+        let mut array_bins = bin_array.bins;
+        for (i, amount_x, amount_y) in bins {
+            array_bins[*i] = DlmmBin {
+                amount_x: *amount_x,
+                amount_y: *amount_y,
+                ..Default::default()
+            };
+        }
+        bin_array.bins = array_bins;
+        bin_array
+    }
+
+    #[test]
+    fn test_account_sizes() {
+        assert_eq!(8 + std::mem::size_of::<DlmmLbPair>(), 904);
+        assert_eq!(8 + std::mem::size_of::<DlmmBinArray>(), 10136);
+    }
+
+    #[test]
+    fn test_bin_array_index() {
+        assert_eq!(DlmmLbPair::bin_array_index(0), 0);
+        assert_eq!(DlmmLbPair::bin_array_index(69), 0);
+        assert_eq!(DlmmLbPair::bin_array_index(70), 1);
+        assert_eq!(DlmmLbPair::bin_array_index(-1), -1);
+    }
+
+    #[test]
+    fn test_swap_bin_array_indexes() {
+        let mut lb_pair = lb_pair(0);
+        // arrays -2, 0 and 1 are initialized
+        let mut bitmap = [0u64; 16];
+        for offset in [510, 512, 513] {
+            bitmap[offset / 64] |= 1u64 << (offset % 64);
+        }
+        lb_pair.bin_array_bitmap = bitmap;
+
+        assert_eq!(lb_pair.swap_bin_array_indexes(true, 3), vec![0, -2]);
+        assert_eq!(lb_pair.swap_bin_array_indexes(false, 3), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_total_fee_rate() {
+        // base_factor 10_000 * bin_step 100 * 10 = 1% of 1e9
+        assert_eq!(lb_pair(0).total_fee_rate(), 10_000_000);
+    }
+
+    #[test]
+    fn test_quote_exact_in() {
+        // Bin 0 at price 1 holds 1_000 Y, bin -1 at price 1/1.01 holds 10_000 Y
+        let lb_pair = lb_pair(0);
+        let bin_arrays = [
+            bin_array(0, &[(0, 0, 1_000)]),
+            bin_array(-1, &[(69, 0, 10_000)]),
+        ];
+
+        // 1_000 X in, 10 X of fee: the active bin fills it
+        let quote = lb_pair.quote_exact_in(1_000, true, &bin_arrays).unwrap();
+        assert_eq!(quote.amount_out, 990);
+        assert_eq!(quote.bin_array_indexes, vec![0]);
+
+        // 2_000 X in, 20 X of fee: 1_000 Y from bin 0, 980 X at 1/1.01 from bin -1
+        let quote = lb_pair.quote_exact_in(2_000, true, &bin_arrays).unwrap();
+        assert_eq!(quote.amount_out, 1_970);
+        assert_eq!(quote.bin_array_indexes, vec![0, -1]);
+        assert!(quote.price_impact > 0.0);
+
+        // More than the bins hold
+        assert!(lb_pair.quote_exact_in(100_000, true, &bin_arrays).is_none());
+    }
+}
@@ -0,0 +1,452 @@
+//! Given a transaction having among it's instructions a Meteora DLMM swap
+//! (`swap`, `swap_exact_out`, `swap_with_price_impact` or their `2` versions),
+//! this module extracts the swap accounts, amounts and direction.
+//!
+//! The pair mints are listed by the instruction, the direction is read from
+//! the reserve the input is transferred to. The parsed transaction has the
+//! same shape as `RaydiumClmmTx`.
+
+use serde::Serialize;
+use serde_json::{from_str, Value};
+
+use crate::bot::tx_common::GetSignature;
+use crate::constants::{
+    BOT_RAYDIUM_OPERATION_BUY, BOT_RAYDIUM_OPERATION_SELL, METEORA_DLMM_ACCOUNTS_LEN_SWAP,
+    METEORA_DLMM_ACCOUNTS_LEN_SWAP2, METEORA_DLMM_ACTION_SWAP, METEORA_DLMM_ACTION_SWAP2,
+    METEORA_DLMM_ACTION_SWAP_EXACT_OUT, METEORA_DLMM_ACTION_SWAP_EXACT_OUT2,
+    METEORA_DLMM_ACTION_SWAP_WITH_PRICE_IMPACT, METEORA_DLMM_ACTION_SWAP_WITH_PRICE_IMPACT2,
+    METEORA_DLMM_PROGRAM, RAYDIUM_SWAP_BASE_IN_INSTRUCTION, RAYDIUM_SWAP_BASE_OUT_INSTRUCTION,
+    SPL_TOKEN_2022_PROGRAM, WSOL_MINT,
+};
+
+use super::raydium_meme_tx::{
+    InnerIxData, RaydiumMemeTradeData, RaydiumSwapBaseInData, RaydiumSwapBaseOutData,
+    RaydiumSwapData,
+};
+use super::tx_common::{GetComputeData, GetTxCommon};
+
+/// Meteora DLMM swap instruction accounts
+/// Based on https://github.com/MeteoraAg/dlmm-sdk/blob/main/programs/lb_clmm/src/instructions/swap.rs
+#[derive(Debug, Serialize, PartialEq)]
+pub struct MeteoraDlmmAccounts {
+    pub lb_pair: String,
+    /// None when the swapper passed the program id in place of the extension
+    pub bin_array_bitmap_extension: Option<String>,
+    pub reserve_x: String,
+    pub reserve_y: String,
+    pub user_token_in: String,
+    pub user_token_out: String,
+    pub token_x_mint: String,
+    pub token_y_mint: String,
+    pub oracle: String,
+    pub user: String,
+    pub token_x_program: String,
+    pub token_y_program: String,
+    /// Bin arrays (and the transfer hook accounts of the `2` versions, if any) passed by the swapper
+    pub remaining_accounts: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct MeteoraDlmmTx {
+    pub signature: Option<String>,
+    pub accounts: Option<MeteoraDlmmAccounts>,
+    pub ix_data: Option<RaydiumSwapData>,
+    pub inner_ix_data: Option<InnerIxData>,
+    pub meme_trade_data: Option<RaydiumMemeTradeData>,
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64,
+}
+
+impl GetSignature for MeteoraDlmmTx {}
+
+impl GetComputeData for MeteoraDlmmTx {}
+
+impl GetTxCommon for MeteoraDlmmTx {}
+
+impl MeteoraDlmmTx {
+    /// Creates a new MeteoraDlmmTx instance from a transaction payload.
+    ///
+    /// * `payload` - The transaction payload
+    pub fn new(payload: String) -> Self {
+        let json: Value = match from_str(payload.as_str()) {
+            Ok(json) => json,
+            Err(_) => return Self::default(),
+        };
+
+        let signature = match Self::get_signature(&json) {
+            Some(signature) => signature,
+            None => return Self::default(),
+        };
+
+        let swap_ix = match Self::get_dlmm_swap_instruction(&json) {
+            Some(swap_ix) => swap_ix,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let (ix_data, is_v2) = match swap_ix["data"]
+            .as_str()
+            .and_then(Self::get_instruction_data)
+        {
+            Some(ix_data) => ix_data,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let accounts = match Self::get_accounts(swap_ix, is_v2) {
+            Some(accounts) => accounts,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    ix_data: Some(ix_data),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let (source_amount, dest_amount, swap_for_y) = match Self::get_amounts(&json, &accounts) {
+            Some(amounts) => amounts,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    accounts: Some(accounts),
+                    ix_data: Some(ix_data),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let meme_trade_data = Self::get_meme_trade_data(&accounts, swap_for_y);
+
+        let (compute_unit_limit, compute_unit_price) =
+            Self::get_compute_data(&json).unwrap_or((0, 0));
+
+        Self {
+            signature: Some(signature),
+            accounts: Some(accounts),
+            ix_data: Some(ix_data),
+            inner_ix_data: Some(InnerIxData {
+                source_amount,
+                dest_amount,
+            }),
+            meme_trade_data,
+            compute_unit_limit,
+            compute_unit_price,
+        }
+    }
+
+    /// Finds the DLMM swap instruction in the transaction,
+    /// either as a main instruction or as an inner instruction.
+    fn get_dlmm_swap_instruction(json: &Value) -> Option<&Value> {
+        let is_dlmm_swap = |instruction: &Value| {
+            instruction["programId"]
+                .as_str()
+                .map_or(false, |id| id == METEORA_DLMM_PROGRAM.to_string())
+                && instruction["data"]
+                    .as_str()
+                    .and_then(Self::get_instruction_data)
+                    .is_some()
+        };
+
+        // Check main instructions
+        let instructions = json["params"]["result"]["transaction"]["transaction"]["message"]
+            ["instructions"]
+            .as_array()?;
+
+        if let Some(instruction) = instructions.iter().find(|&ix| is_dlmm_swap(ix)) {
+            return Some(instruction);
+        }
+
+        // Check inner instructions
+        json["params"]["result"]["transaction"]["meta"]["innerInstructions"]
+            .as_array()?
+            .iter()
+            .find_map(|inner_ix_group| {
+                inner_ix_group["instructions"]
+                    .as_array()?
+                    .iter()
+                    .find(|&ix| is_dlmm_swap(ix))
+            })
+    }
+
+    /// Extracts the data from the DLMM swap instruction,
+    /// returns the swap data and whether it is a `2` version.
+    /// `swap_with_price_impact` has no minimum output, it is reported as 0.
+    ///
+    /// * `input` - The base58 instruction data
+    fn get_instruction_data(input: &str) -> Option<(RaydiumSwapData, bool)> {
+        let input = bs58::decode(input).into_vec().ok()?;
+
+        // discriminator, amount in (or max amount in), then the minimum
+        // amount out (or amount out) but for `swap_with_price_impact`
+        if input.len() < 16 {
+            return None;
+        }
+
+        let first = u64::from_le_bytes(input[8..16].try_into().ok()?);
+        let second = input
+            .get(16..24)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u64::from_le_bytes);
+
+        let base_in = |minimum_amount_out| {
+            RaydiumSwapData::BaseIn(RaydiumSwapBaseInData {
+                instruction: RAYDIUM_SWAP_BASE_IN_INSTRUCTION,
+                amount_in: first,
+                minimum_amount_out,
+            })
+        };
+        let base_out = |amount_out| {
+            RaydiumSwapData::BaseOut(RaydiumSwapBaseOutData {
+                instruction: RAYDIUM_SWAP_BASE_OUT_INSTRUCTION,
+                max_amount_in: first,
+                amount_out,
+            })
+        };
+
+        match &input[..8] {
+            METEORA_DLMM_ACTION_SWAP => Some((base_in(second?), false)),
+            METEORA_DLMM_ACTION_SWAP2 => Some((base_in(second?), true)),
+            METEORA_DLMM_ACTION_SWAP_EXACT_OUT => Some((base_out(second?), false)),
+            METEORA_DLMM_ACTION_SWAP_EXACT_OUT2 => Some((base_out(second?), true)),
+            METEORA_DLMM_ACTION_SWAP_WITH_PRICE_IMPACT => Some((base_in(0), false)),
+            METEORA_DLMM_ACTION_SWAP_WITH_PRICE_IMPACT2 => Some((base_in(0), true)),
+            _ => None,
+        }
+    }
+
+    /// Extracts the DLMM swap accounts.
+    ///
+    /// * `swap_instruction` - The DLMM swap instruction
+    /// * `is_v2` - Whether the instruction is a `2` version
+    fn get_accounts(swap_instruction: &Value, is_v2: bool) -> Option<MeteoraDlmmAccounts> {
+        let accounts = swap_instruction["accounts"].as_array()?;
+
+        let fixed_len = if is_v2 {
+            METEORA_DLMM_ACCOUNTS_LEN_SWAP2
+        } else {
+            METEORA_DLMM_ACCOUNTS_LEN_SWAP
+        };
+
+        if accounts.len() < fixed_len {
+            return None;
+        }
+
+        let bin_array_bitmap_extension = Some(Self::pubkey_to_string(&accounts[1]))
+            .filter(|extension| *extension != METEORA_DLMM_PROGRAM.to_string());
+
+        // account[9] - Host fee, account[13] - Memo Program (`2` versions) or Event Authority
+        Some(MeteoraDlmmAccounts {
+            lb_pair: Self::pubkey_to_string(&accounts[0]),
+            bin_array_bitmap_extension,
+            reserve_x: Self::pubkey_to_string(&accounts[2]),
+            reserve_y: Self::pubkey_to_string(&accounts[3]),
+            user_token_in: Self::pubkey_to_string(&accounts[4]),
+            user_token_out: Self::pubkey_to_string(&accounts[5]),
+            token_x_mint: Self::pubkey_to_string(&accounts[6]),
+            token_y_mint: Self::pubkey_to_string(&accounts[7]),
+            oracle: Self::pubkey_to_string(&accounts[8]),
+            user: Self::pubkey_to_string(&accounts[10]),
+            token_x_program: Self::pubkey_to_string(&accounts[11]),
+            token_y_program: Self::pubkey_to_string(&accounts[12]),
+            remaining_accounts: accounts[fixed_len..]
+                .iter()
+                .map(Self::pubkey_to_string)
+                .collect(),
+        })
+    }
+
+    /// Extracts the exact amounts swapped from the inner token transfers,
+    /// returns (source amount, destination amount, swap_for_y)
+    ///
+    /// * `json` - The transaction JSON
+    /// * `accounts` - The DLMM swap accounts
+    fn get_amounts(json: &Value, accounts: &MeteoraDlmmAccounts) -> Option<(u64, u64, bool)> {
+        let inner_instructions =
+            json["params"]["result"]["transaction"]["meta"]["innerInstructions"].as_array()?;
+
+        let token_programs = [
+            spl_token::id().to_string(),
+            SPL_TOKEN_2022_PROGRAM.to_string(),
+        ];
+
+        let default_array: Vec<Value> = Vec::new();
+        let transfers: Vec<(&str, &str, u64)> = inner_instructions
+            .iter()
+            .flat_map(|inner_ix| {
+                inner_ix["instructions"]
+                    .as_array()
+                    .unwrap_or(&default_array)
+            })
+            .filter(|ix| {
+                ix["programId"].as_str().map_or(false, |id| {
+                    token_programs.iter().any(|program| program == id)
+                })
+            })
+            .filter_map(|ix| {
+                let info = &ix["parsed"]["info"];
+                let amount = info["amount"]
+                    .as_str()
+                    .or_else(|| info["tokenAmount"]["amount"].as_str())?
+                    .parse::<u64>()
+                    .ok()?;
+
+                Some((
+                    info["source"].as_str()?,
+                    info["destination"].as_str()?,
+                    amount,
+                ))
+            })
+            .collect();
+
+        // The reserve receiving the input tells the direction
+        let swap_for_y = transfers.iter().find_map(|(source, destination, _)| {
+            if *source != accounts.user_token_in {
+                None
+            } else if *destination == accounts.reserve_x {
+                Some(true)
+            } else if *destination == accounts.reserve_y {
+                Some(false)
+            } else {
+                None
+            }
+        })?;
+
+        let (input_reserve, output_reserve) = if swap_for_y {
+            (&accounts.reserve_x, &accounts.reserve_y)
+        } else {
+            (&accounts.reserve_y, &accounts.reserve_x)
+        };
+
+        let (source_amount, dest_amount) = transfers
+            .iter()
+            .filter_map(|(source, destination, amount)| {
+                if *source == accounts.user_token_in && destination == input_reserve {
+                    Some((*amount, 0))
+                } else if source == output_reserve && *destination == accounts.user_token_out {
+                    Some((0, *amount))
+                } else {
+                    None
+                }
+            })
+            .fold((0, 0), |(src, dst), (s, d)| (src + s, dst + d));
+
+        Some((source_amount, dest_amount, swap_for_y))
+    }
+
+    /// Detects the meme mint and the trade direction from the pair mints.
+    /// Only SOL paired swaps are considered.
+    ///
+    /// * `accounts` - The DLMM swap accounts
+    /// * `swap_for_y` - Whether token X is sold to the pair
+    fn get_meme_trade_data(
+        accounts: &MeteoraDlmmAccounts,
+        swap_for_y: bool,
+    ) -> Option<RaydiumMemeTradeData> {
+        let wsol = WSOL_MINT.to_string();
+        let (input_mint, output_mint) = if swap_for_y {
+            (&accounts.token_x_mint, &accounts.token_y_mint)
+        } else {
+            (&accounts.token_y_mint, &accounts.token_x_mint)
+        };
+
+        if *input_mint == wsol && *output_mint != wsol {
+            Some(RaydiumMemeTradeData {
+                meme_mint: output_mint.parse().ok()?,
+                operation: BOT_RAYDIUM_OPERATION_BUY,
+            })
+        } else if *output_mint == wsol && *input_mint != wsol {
+            Some(RaydiumMemeTradeData {
+                meme_mint: input_mint.parse().ok()?,
+                operation: BOT_RAYDIUM_OPERATION_SELL,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a formatted string containing transaction details
+    pub fn format_tx_info(&self) -> String {
+        if let (Some(trade_data), Some(inner_ix), Some(sig)) =
+            (&self.meme_trade_data, &self.inner_ix_data, &self.signature)
+        {
+            let operation = if trade_data.operation == BOT_RAYDIUM_OPERATION_BUY {
+                "Buy"
+            } else {
+                "Sell"
+            };
+
+            format!(
+                "DLMM {} | Mint: {} | Source Amount: {} | Destination Amount: {} | TX: https://solscan.io/tx/{}",
+                operation, trade_data.meme_mint, inner_ix.source_amount, inner_ix.dest_amount, sig
+            )
+        } else {
+            "MeteoraDlmmTx: Error formatting transaction data".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Synthetic DLMM `swap` sell: 2_000 meme (token X) in, 0.1 WSOL (token Y) out
+    const TEST_JSON_DLMM_SELL: &str = r#"{"jsonrpc":"2.0","method":"transactionNotification","params":{"subscription":1,"result":{"transaction":{"transaction":{"signatures":["5tQ2wE4rT6yU8iO1pA3sD5fG7hJ9kL2zX4cV6bN8mQ1wE3rT5yU7iO9pA2sD4fG6hJ8kL1zX3cV5bN7mQ9wE2r"],"message":{"accountKeys":[{"pubkey":"2YMMFViTPWQwETmqnFrxosby4QbtYZ4ZNcjnFc8uaHGW","writable":true,"signer":true,"source":"transaction"}],"instructions":[{"programId":"LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo","accounts":["G3FF63fqTwoB6WRHRwLRjx16wUADqfPRQcjm9MYMBVx2","LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo","8f4MrVgmEPgZvbxV5bDcH3MV5fi6a6ZbpMgaXqJrUAF3","5yR3Lwn1Mp9E7UhkrcAFe4Ubau9pqadk4uCJcJdtJvVs","92sS5d6h6C6eWNgybNAW1y7DWGWecGpEoQNSvLAFNEhx","D7QEBdetThd7B6oBYhRfvFp9LJi7LKtWmv99tiHnv1hM","GHkAfgYcvohbDGBL4F1H4BCTvMMpxNKScithjsbVHBzF","So11111111111111111111111111111111111111112","uXZv2uit9tp8h1hEoGPN8rrz3hBsMZ9ahVuytWk3fRo","LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo","2YMMFViTPWQwETmqnFrxosby4QbtYZ4ZNcjnFc8uaHGW","TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","D1ZN9Wj1fRSUQfCjhvnu1hqDMT7hzjzBBpi12nVniYD6","LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo","8gmsknCjpqw7swpkiTqjTLj1MyXN8QG7GH9KNACSssDd"],"data":"PgQWtn8oziwproL4bNEzMB5ZiQtmM4soM","stackHeight":null}]}},"meta":{"err":null,"innerInstructions":[{"index":0,"instructions":[{"program":"spl-token","programId":"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","parsed":{"info":{"authority":"2YMMFViTPWQwETmqnFrxosby4QbtYZ4ZNcjnFc8uaHGW","destination":"8f4MrVgmEPgZvbxV5bDcH3MV5fi6a6ZbpMgaXqJrUAF3","mint":"GHkAfgYcvohbDGBL4F1H4BCTvMMpxNKScithjsbVHBzF","source":"92sS5d6h6C6eWNgybNAW1y7DWGWecGpEoQNSvLAFNEhx","tokenAmount":{"amount":"2000000000","decimals":6}},"type":"transferChecked"},"stackHeight":2},{"program":"spl-token","programId":"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","parsed":{"info":{"authority":"G3FF63fqTwoB6WRHRwLRjx16wUADqfPRQcjm9MYMBVx2","destination":"D7QEBdetThd7B6oBYhRfvFp9LJi7LKtWmv99tiHnv1hM","mint":"So11111111111111111111111111111111111111112","source":"5yR3Lwn1Mp9E7UhkrcAFe4Ubau9pqadk4uCJcJdtJvVs","tokenAmount":{"amount":"100000000","decimals":9}},"type":"transferChecked"},"stackHeight":2}]}],"preTokenBalances":[],"postTokenBalances":[]},"version":0},"signature":"5tQ2wE4rT6yU8iO1pA3sD5fG7hJ9kL2zX4cV6bN8mQ1wE3rT5yU7iO9pA2sD4fG6hJ8kL1zX3cV5bN7mQ9wE2r","slot":1}}}"#;
+
+    #[test]
+    fn test_get_instruction_data() {
+        let json: Value = from_str(TEST_JSON_DLMM_SELL).unwrap();
+        let swap_ix = MeteoraDlmmTx::get_dlmm_swap_instruction(&json).unwrap();
+        let (ix_data, is_v2) =
+            MeteoraDlmmTx::get_instruction_data(swap_ix["data"].as_str().unwrap()).unwrap();
+        assert!(!is_v2);
+        assert!(matches!(
+            ix_data,
+            RaydiumSwapData::BaseIn(RaydiumSwapBaseInData {
+                amount_in: 2_000_000_000,
+                minimum_amount_out: 95_000_000,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_get_accounts() {
+        let json: Value = from_str(TEST_JSON_DLMM_SELL).unwrap();
+        let swap_ix = MeteoraDlmmTx::get_dlmm_swap_instruction(&json).unwrap();
+        let accounts = MeteoraDlmmTx::get_accounts(swap_ix, false).unwrap();
+        assert_eq!(accounts.bin_array_bitmap_extension, None);
+        assert_eq!(
+            accounts.user,
+            "2YMMFViTPWQwETmqnFrxosby4QbtYZ4ZNcjnFc8uaHGW"
+        );
+        assert_eq!(
+            accounts.remaining_accounts,
+            vec!["8gmsknCjpqw7swpkiTqjTLj1MyXN8QG7GH9KNACSssDd".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_new_sell() {
+        let dlmm_tx = MeteoraDlmmTx::new(TEST_JSON_DLMM_SELL.to_string());
+
+        let inner_ix_data = dlmm_tx.inner_ix_data.unwrap();
+        assert_eq!(inner_ix_data.source_amount, 2_000_000_000);
+        assert_eq!(inner_ix_data.dest_amount, 100_000_000);
+
+        let meme_trade_data = dlmm_tx.meme_trade_data.unwrap();
+        assert_eq!(meme_trade_data.operation, BOT_RAYDIUM_OPERATION_SELL);
+        assert_eq!(
+            meme_trade_data.meme_mint.to_string(),
+            "GHkAfgYcvohbDGBL4F1H4BCTvMMpxNKScithjsbVHBzF"
+        );
+    }
+}
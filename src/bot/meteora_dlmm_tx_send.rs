@@ -0,0 +1,340 @@
+use helius::Helius;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use std::{error::Error, sync::Arc};
+use tokio::sync::Mutex;
+
+use crate::constants::{
+    METEORA_DLMM_ACTION_SWAP, METEORA_DLMM_COMPUTE_UNIT_LIMIT, METEORA_DLMM_EVENT_AUTHORITY,
+    METEORA_DLMM_MAX_BIN_ARRAYS, METEORA_DLMM_PROGRAM, WSOL_MINT,
+};
+
+use super::{
+    tx_common::SendSmartTx,
+    venue::{deepest_sol_pool, find_sol_paired_pools},
+    wsol::{Wsol, WsolMode},
+    AccountDecoder, DlmmBinArray, DlmmLbPair, DlmmSwapQuote, MeteoraDlmmTx, ParsedTrade,
+    QuoteDetails, SplTokenAccountState, Swap, SwapQuote, TradeSide, Venue, VenueContext,
+};
+
+/// A DLMM exact input quote, with everything needed to build the swap
+#[derive(Debug, Clone)]
+pub struct DlmmQuote {
+    pub pool_id: Pubkey,
+    pub lb_pair: DlmmLbPair,
+    pub swap_for_y: bool,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub input_token_program: Pubkey,
+    pub output_token_program: Pubkey,
+    /// Balance of the input reserve, the pair liquidity on the sold side
+    pub input_reserve_amount: u64,
+    pub swap: DlmmSwapQuote,
+}
+
+impl DlmmQuote {
+    /// Returns (token X program, token Y program)
+    fn token_programs(&self) -> (Pubkey, Pubkey) {
+        if self.swap_for_y {
+            (self.input_token_program, self.output_token_program)
+        } else {
+            (self.output_token_program, self.input_token_program)
+        }
+    }
+}
+
+pub struct MeteoraDlmmTxSend {}
+
+impl SendSmartTx for MeteoraDlmmTxSend {}
+
+impl MeteoraDlmmTxSend {
+    /// Quotes an exact input swap on a DLMM pair by walking the bins of the
+    /// bin arrays the swap would cross.
+    ///
+    /// * `helius` - The Helius client
+    /// * `pool_id` - The DLMM pair account
+    /// * `input_mint` - The mint being sold to the pair
+    /// * `amount_in` - The exact input amount
+    pub async fn quote(
+        helius: Arc<Mutex<Helius>>,
+        pool_id: &Pubkey,
+        input_mint: &Pubkey,
+        amount_in: u64,
+    ) -> Result<DlmmQuote, Box<dyn Error + Send + Sync>> {
+        let connection = helius.lock().await.async_connection()?;
+
+        let lb_pair = *DlmmLbPair::decode_account(&connection.get_account(pool_id).await?)?;
+
+        if !lb_pair.is_swap_enabled() {
+            return Err("Swaps are disabled on this DLMM pair".into());
+        }
+
+        let (token_x_mint, token_y_mint) = (lb_pair.token_x_mint, lb_pair.token_y_mint);
+        let swap_for_y = if token_x_mint == *input_mint {
+            true
+        } else if token_y_mint == *input_mint {
+            false
+        } else {
+            return Err("Mint is not traded by this DLMM pair".into());
+        };
+        let (output_mint, input_reserve) = if swap_for_y {
+            (token_y_mint, lb_pair.reserve_x)
+        } else {
+            (token_x_mint, lb_pair.reserve_y)
+        };
+
+        let indexes = lb_pair.swap_bin_array_indexes(swap_for_y, METEORA_DLMM_MAX_BIN_ARRAYS);
+        if indexes.is_empty() {
+            return Err("No initialized bin array in the swap direction".into());
+        }
+
+        // Both mints (for their token programs), the input reserve and the
+        // bin arrays in one round trip
+        let mut keys = vec![*input_mint, output_mint, input_reserve];
+        keys.extend(
+            indexes
+                .iter()
+                .map(|index| DlmmLbPair::bin_array_address(pool_id, *index)),
+        );
+        let accounts = connection.get_multiple_accounts(&keys).await?;
+
+        let input_token_program = accounts[0].as_ref().ok_or("Input mint not found")?.owner;
+        let output_token_program = accounts[1].as_ref().ok_or("Output mint not found")?.owner;
+        let input_reserve_amount = SplTokenAccountState::decode_account(
+            accounts[2].as_ref().ok_or("DLMM input reserve not found")?,
+        )?
+        .amount;
+
+        let bin_arrays = accounts[3..]
+            .iter()
+            .map(|account| {
+                let account = account.as_ref().ok_or("DLMM bin array not found")?;
+                Ok(*DlmmBinArray::decode_account(account)?)
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+
+        let swap = lb_pair
+            .quote_exact_in(amount_in, swap_for_y, &bin_arrays)
+            .ok_or("Not enough liquidity in the DLMM bin arrays")?;
+
+        Ok(DlmmQuote {
+            pool_id: *pool_id,
+            lb_pair,
+            swap_for_y,
+            input_mint: *input_mint,
+            output_mint,
+            input_token_program,
+            output_token_program,
+            input_reserve_amount,
+            swap,
+        })
+    }
+
+    /// Builds the DLMM `swap` exact input instruction for the given owner,
+    /// passing the bin arrays picked by the quote as remaining accounts.
+    ///
+    /// * `quote` - The quote to execute
+    /// * `owner` - The wallet swapping
+    /// * `minimum_amount_out` - The minimum output accepted
+    pub fn swap_instruction(
+        quote: &DlmmQuote,
+        owner: &Pubkey,
+        minimum_amount_out: u64,
+    ) -> Instruction {
+        let lb_pair = quote.lb_pair;
+        let (token_x_program, token_y_program) = quote.token_programs();
+
+        let mut data = METEORA_DLMM_ACTION_SWAP.to_vec();
+        data.extend_from_slice(&quote.swap.amount_in.to_le_bytes());
+        data.extend_from_slice(&minimum_amount_out.to_le_bytes());
+
+        // The program id stands for the optional bitmap extension and host fee accounts
+        let mut accounts = vec![
+            AccountMeta::new(quote.pool_id, false),
+            AccountMeta::new_readonly(METEORA_DLMM_PROGRAM, false),
+            AccountMeta::new(lb_pair.reserve_x, false),
+            AccountMeta::new(lb_pair.reserve_y, false),
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(
+                    owner,
+                    &quote.input_mint,
+                    &quote.input_token_program,
+                ),
+                false,
+            ),
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(
+                    owner,
+                    &quote.output_mint,
+                    &quote.output_token_program,
+                ),
+                false,
+            ),
+            AccountMeta::new_readonly(lb_pair.token_x_mint, false),
+            AccountMeta::new_readonly(lb_pair.token_y_mint, false),
+            AccountMeta::new(lb_pair.oracle, false),
+            AccountMeta::new_readonly(METEORA_DLMM_PROGRAM, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(token_x_program, false),
+            AccountMeta::new_readonly(token_y_program, false),
+            AccountMeta::new_readonly(METEORA_DLMM_EVENT_AUTHORITY, false),
+            AccountMeta::new_readonly(METEORA_DLMM_PROGRAM, false),
+        ];
+        accounts.extend(quote.swap.bin_array_indexes.iter().map(|index| {
+            AccountMeta::new(DlmmLbPair::bin_array_address(&quote.pool_id, *index), false)
+        }));
+
+        Instruction {
+            program_id: METEORA_DLMM_PROGRAM,
+            accounts,
+            data,
+        }
+    }
+
+    /// Builds the instruction list executing a quote for the given owner.
+    ///
+    /// * `helius` - The Helius client
+    /// * `quote` - The quote to execute
+    /// * `owner` - The wallet swapping
+    /// * `slippage_percent` - The slippage applied to the quoted output
+    /// * `wsol_mode` - The WSOL funding mode
+    pub async fn build_swap(
+        helius: Arc<Mutex<Helius>>,
+        quote: &DlmmQuote,
+        owner: &Pubkey,
+        slippage_percent: u64,
+        wsol_mode: WsolMode,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let minimum_amount_out = (quote.swap.amount_out as f64 * (100.0 - slippage_percent as f64)
+            / 100.0)
+            .floor() as u64;
+
+        let (pre_swap_instructions, post_swap_instructions) = if quote.input_mint == WSOL_MINT {
+            Wsol::buy_instructions(helius, owner, wsol_mode, quote.swap.amount_in).await?
+        } else if quote.output_mint == WSOL_MINT {
+            Wsol::sell_instructions(owner, wsol_mode)?
+        } else {
+            (vec![], vec![])
+        };
+
+        let mut instructions = pre_swap_instructions;
+        if quote.output_mint != WSOL_MINT {
+            instructions.push(create_associated_token_account_idempotent(
+                owner,
+                owner,
+                &quote.output_mint,
+                &quote.output_token_program,
+            ));
+        }
+        instructions.push(Self::swap_instruction(quote, owner, minimum_amount_out));
+        instructions.extend(post_swap_instructions);
+
+        Ok(instructions)
+    }
+}
+
+impl Swap for MeteoraDlmmTxSend {
+    const VENUE: Venue = Venue::MeteoraDlmm;
+    const COMPUTE_UNIT_LIMIT: u32 = METEORA_DLMM_COMPUTE_UNIT_LIMIT;
+
+    async fn find_pool(
+        ctx: &VenueContext,
+        mint: &Pubkey,
+    ) -> Result<Pubkey, Box<dyn Error + Send + Sync>> {
+        let connection = ctx.helius.lock().await.async_connection()?;
+
+        let pools = find_sol_paired_pools::<DlmmLbPair>(
+            &connection,
+            &METEORA_DLMM_PROGRAM,
+            [
+                std::mem::offset_of!(DlmmLbPair, token_x_mint),
+                std::mem::offset_of!(DlmmLbPair, token_y_mint),
+            ],
+            mint,
+        )
+        .await?
+        .into_iter()
+        .filter(|(_, pool)| pool.is_swap_enabled())
+        .map(|(pool_id, pool)| {
+            let wsol_vault = if pool.token_x_mint == WSOL_MINT {
+                pool.reserve_x
+            } else {
+                pool.reserve_y
+            };
+            (pool_id, wsol_vault)
+        })
+        .collect();
+
+        deepest_sol_pool(&connection, pools).await
+    }
+
+    async fn quote_pool(
+        ctx: &VenueContext,
+        pool_id: &Pubkey,
+        mint: &Pubkey,
+        side: TradeSide,
+        amount: u64,
+    ) -> Result<SwapQuote, Box<dyn Error + Send + Sync>> {
+        let (input_mint, output_mint) = side.mints(mint);
+        let quote = Self::quote(Arc::clone(&ctx.helius), pool_id, &input_mint, amount).await?;
+        if quote.output_mint != output_mint {
+            return Err("Only SOL paired DLMM pools are traded".into());
+        }
+
+        Ok(SwapQuote {
+            venue: Self::VENUE,
+            pool_id: *pool_id,
+            mint: *mint,
+            side,
+            amount_in: quote.swap.amount_in,
+            amount_out: quote.swap.amount_out,
+            creator: quote.lb_pair.creator(),
+            quote_liquidity: match side {
+                TradeSide::Buy => quote.input_reserve_amount,
+                TradeSide::Sell => 0,
+            },
+            pool_open_time: quote.lb_pair.open_time(),
+            price_impact: quote.swap.price_impact,
+            details: QuoteDetails::MeteoraDlmm(quote),
+        })
+    }
+
+    async fn build_swap(
+        ctx: &VenueContext,
+        quote: &SwapQuote,
+        wallet: &Pubkey,
+        slippage_percent: u64,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let QuoteDetails::MeteoraDlmm(details) = &quote.details else {
+            return Err("Not a Meteora DLMM quote".into());
+        };
+
+        Self::build_swap(
+            Arc::clone(&ctx.helius),
+            details,
+            wallet,
+            slippage_percent,
+            ctx.wsol_mode,
+        )
+        .await
+    }
+
+    async fn parse_trade(_ctx: &VenueContext, payload: &str) -> Option<ParsedTrade> {
+        let tx = MeteoraDlmmTx::new(payload.to_string());
+
+        ParsedTrade::from_meme_trade(
+            Self::VENUE,
+            tx.signature.clone(),
+            &tx.accounts.as_ref()?.lb_pair,
+            tx.meme_trade_data.as_ref()?,
+            tx.inner_ix_data.as_ref()?,
+            tx.compute_unit_limit,
+        )
+    }
+}
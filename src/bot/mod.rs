@@ -1,14 +1,72 @@
+mod account_decoder;
 mod helius_subscriptions;
+mod jupiter_client;
+mod jupiter_tx_send;
+mod latency;
+mod meteora_dlmm_state;
+mod meteora_dlmm_tx;
+mod meteora_dlmm_tx_send;
+mod orca_whirlpool_state;
+mod orca_whirlpool_tx;
+mod orca_whirlpool_tx_send;
+mod price_impact;
+mod pump_fun_state;
 mod pump_fun_tx;
 mod pump_fun_tx_send;
-// mod raydium_meme_tx;
-// mod raydium_meme_tx_send;
-// mod raydium_v4_state;
+mod pump_swap_state;
+mod pump_swap_tx;
+mod pump_swap_tx_send;
+mod raydium_clmm_state;
+mod raydium_clmm_tx;
+mod raydium_clmm_tx_send;
+mod raydium_cpmm_state;
+mod raydium_cpmm_tx;
+mod raydium_cpmm_tx_send;
+mod raydium_meme_tx;
+mod raydium_meme_tx_send;
+mod raydium_pool_discovery;
+mod raydium_v4_state;
+mod router;
+mod serum_market_state;
+mod spl_token_state;
+mod token_filter;
+mod token_metadata_state;
 mod tx_common;
+mod venue;
+mod wsol;
 
-// pub use helius_subscriptions::*;
+pub use account_decoder::*;
+pub use helius_subscriptions::*;
+pub use jupiter_client::*;
+pub use jupiter_tx_send::*;
+pub use latency::*;
+pub use meteora_dlmm_state::*;
+pub use meteora_dlmm_tx::*;
+pub use meteora_dlmm_tx_send::*;
+pub use orca_whirlpool_state::*;
+pub use orca_whirlpool_tx::*;
+pub use orca_whirlpool_tx_send::*;
+pub use price_impact::*;
+pub use pump_fun_state::*;
 pub use pump_fun_tx::*;
 pub use pump_fun_tx_send::*;
-// pub use raydium_meme_tx::*;
-// pub use raydium_meme_tx_send::*;
-// pub use raydium_v4_state::*;
+pub use pump_swap_state::*;
+pub use pump_swap_tx::*;
+pub use pump_swap_tx_send::*;
+pub use raydium_clmm_state::*;
+pub use raydium_clmm_tx::*;
+pub use raydium_clmm_tx_send::*;
+pub use raydium_cpmm_state::*;
+pub use raydium_cpmm_tx::*;
+pub use raydium_cpmm_tx_send::*;
+pub use raydium_meme_tx::*;
+pub use raydium_meme_tx_send::*;
+pub use raydium_pool_discovery::*;
+pub use raydium_v4_state::*;
+pub use router::*;
+pub use serum_market_state::*;
+pub use spl_token_state::*;
+pub use token_filter::*;
+pub use token_metadata_state::*;
+pub use venue::*;
+pub use wsol::*;
@@ -0,0 +1,372 @@
+use bytemuck::{Pod, Zeroable};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::constants::{
+    ORCA_WHIRLPOOL_DISCRIMINATOR, ORCA_WHIRLPOOL_FEE_RATE_DENOMINATOR, ORCA_WHIRLPOOL_ORACLE_SEED,
+    ORCA_WHIRLPOOL_PROGRAM, ORCA_WHIRLPOOL_SWAP_TICK_ARRAYS,
+    ORCA_WHIRLPOOL_TICK_ARRAY_DISCRIMINATOR, ORCA_WHIRLPOOL_TICK_ARRAY_SEED,
+    ORCA_WHIRLPOOL_TICK_ARRAY_SIZE,
+};
+
+use super::AccountDecoder;
+
+/// Orca Whirlpool reward information, part of the pool state
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct WhirlpoolRewardInfo {
+    /// Reward token mint.
+    pub mint: Pubkey,
+    /// Reward vault token account.
+    pub vault: Pubkey,
+    /// Authority account that has permission to initialize the reward and set emissions.
+    pub authority: Pubkey,
+    /// Q64.64 number that indicates how many tokens per second are earned per unit of liquidity.
+    pub emissions_per_second_x64: u128,
+    /// Q64.64 number that tracks the total tokens earned per unit of liquidity since the reward
+    /// emissions were turned on.
+    pub growth_global_x64: u128,
+}
+
+/// Orca Whirlpool pool state.
+/// Based on https://github.com/orca-so/whirlpools/blob/main/programs/whirlpool/src/state/whirlpool.rs
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct WhirlpoolState {
+    /// The config the pool belongs to
+    pub whirlpools_config: Pubkey,
+    pub whirlpool_bump: [u8; 1],
+    /// The minimum number of ticks between initialized ticks
+    pub tick_spacing: u16,
+    pub fee_tier_index_seed: [u8; 2],
+    /// The trade fee, denominated in hundredths of a bip (10^-6)
+    pub fee_rate: u16,
+    /// Portion of the fee going to the protocol, in basis points
+    pub protocol_fee_rate: u16,
+    /// The currently in range liquidity available to the pool.
+    pub liquidity: u128,
+    /// The current price of the pool as a sqrt(token_b/token_a) Q64.64 value
+    pub sqrt_price: u128,
+    /// The current tick of the pool
+    pub tick_current_index: i32,
+    pub protocol_fee_owed_a: u64,
+    pub protocol_fee_owed_b: u64,
+    /// Token pair of the pool, where token_mint_a address < token_mint_b address
+    pub token_mint_a: Pubkey,
+    pub token_vault_a: Pubkey,
+    pub fee_growth_global_a: u128,
+    pub token_mint_b: Pubkey,
+    pub token_vault_b: Pubkey,
+    pub fee_growth_global_b: u128,
+    pub reward_last_updated_timestamp: u64,
+    pub reward_infos: [WhirlpoolRewardInfo; 3],
+}
+
+/// A single tick of a tick array
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct WhirlpoolTick {
+    pub initialized: u8,
+    /// Amount of net liquidity added (subtracted) when tick is crossed from left to right (right to left)
+    pub liquidity_net: i128,
+    /// The total position liquidity that references this tick
+    pub liquidity_gross: u128,
+    /// Fee growth per unit of liquidity on the _other_ side of this tick (relative to the current tick)
+    pub fee_growth_outside_a: u128,
+    pub fee_growth_outside_b: u128,
+    /// Reward growth per unit of liquidity like fee, array of Q64.64
+    pub reward_growths_outside: [u128; 3],
+}
+
+/// A Whirlpool tick array, holding `ORCA_WHIRLPOOL_TICK_ARRAY_SIZE` ticks
+/// spaced by the pool tick spacing
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct WhirlpoolTickArray {
+    pub start_tick_index: i32,
+    pub ticks: [WhirlpoolTick; 88],
+    pub whirlpool: Pubkey,
+}
+
+/// The result of walking the ticks for an exact input swap
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhirlpoolSwapQuote {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    /// Relative difference between the spot price and the execution price, fees excluded
+    pub price_impact: f64,
+}
+
+impl AccountDecoder for WhirlpoolState {
+    const OWNERS: &'static [Pubkey] = &[ORCA_WHIRLPOOL_PROGRAM];
+    const HEAD: &'static [u8] = ORCA_WHIRLPOOL_DISCRIMINATOR;
+}
+
+impl AccountDecoder for WhirlpoolTickArray {
+    const OWNERS: &'static [Pubkey] = &[ORCA_WHIRLPOOL_PROGRAM];
+    const HEAD: &'static [u8] = ORCA_WHIRLPOOL_TICK_ARRAY_DISCRIMINATOR;
+}
+
+impl WhirlpoolState {
+    /// Number of ticks covered by one tick array
+    pub fn ticks_in_array(&self) -> i32 {
+        self.tick_spacing as i32 * ORCA_WHIRLPOOL_TICK_ARRAY_SIZE
+    }
+
+    /// Returns the start index of the tick array holding `tick`
+    pub fn tick_array_start_index(&self, tick: i32) -> i32 {
+        tick.div_euclid(self.ticks_in_array()) * self.ticks_in_array()
+    }
+
+    /// Returns the start indexes of the three consecutive tick arrays a swap
+    /// is given, starting with the array holding the current tick. A b to a
+    /// swap starts one tick spacing ahead, like the Orca SDK does, since the
+    /// current tick may sit on the last tick of its array.
+    ///
+    /// * `a_to_b` - true when token a is sold to the pool (price goes down)
+    pub fn swap_tick_array_start_indexes(&self, a_to_b: bool) -> Vec<i32> {
+        let (shift, step) = if a_to_b {
+            (0, -self.ticks_in_array())
+        } else {
+            (self.tick_spacing as i32, self.ticks_in_array())
+        };
+        let start_index = self.tick_array_start_index(self.tick_current_index + shift);
+
+        (0..ORCA_WHIRLPOOL_SWAP_TICK_ARRAYS as i32)
+            .map(|i| start_index + i * step)
+            .collect()
+    }
+
+    /// Derives the tick array account address
+    ///
+    /// * `whirlpool` - The pool account
+    /// * `start_index` - The tick array start index
+    pub fn tick_array_address(whirlpool: &Pubkey, start_index: i32) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                ORCA_WHIRLPOOL_TICK_ARRAY_SEED,
+                whirlpool.as_ref(),
+                start_index.to_string().as_bytes(),
+            ],
+            &ORCA_WHIRLPOOL_PROGRAM,
+        )
+        .0
+    }
+
+    /// Derives the oracle account address
+    ///
+    /// * `whirlpool` - The pool account
+    pub fn oracle_address(whirlpool: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[ORCA_WHIRLPOOL_ORACLE_SEED, whirlpool.as_ref()],
+            &ORCA_WHIRLPOOL_PROGRAM,
+        )
+        .0
+    }
+
+    /// Returns the current price as token b per token a, raw amounts
+    pub fn price(&self) -> f64 {
+        let sqrt_price = self.sqrt_price as f64 / 2f64.powi(64);
+        sqrt_price * sqrt_price
+    }
+
+    /// Quotes an exact input swap by walking the initialized ticks of the
+    /// given tick arrays, in floating point like `ClmmPoolState::quote_base_input`.
+    ///
+    /// * `amount_in` - The exact input amount
+    /// * `a_to_b` - true when token a is sold to the pool
+    /// * `tick_arrays` - The initialized arrays among `swap_tick_array_start_indexes`
+    pub fn quote_base_input(
+        &self,
+        amount_in: u64,
+        a_to_b: bool,
+        tick_arrays: &[WhirlpoolTickArray],
+    ) -> WhirlpoolSwapQuote {
+        let fee = (amount_in as u128 * self.fee_rate as u128)
+            .div_ceil(ORCA_WHIRLPOOL_FEE_RATE_DENOMINATOR as u128) as u64;
+        let amount_in_less_fee = amount_in.saturating_sub(fee) as f64;
+
+        let tick_spacing = self.tick_spacing as i32;
+        let tick_current_index = self.tick_current_index;
+
+        // Initialized ticks in the swap direction, closest first
+        let mut ticks: Vec<(i32, i128)> = tick_arrays
+            .iter()
+            .flat_map(|tick_array| {
+                let start_tick_index = tick_array.start_tick_index;
+                let ticks = tick_array.ticks;
+                ticks
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, tick)| tick.initialized != 0)
+                    .map(move |(i, tick)| {
+                        (
+                            start_tick_index + i as i32 * tick_spacing,
+                            tick.liquidity_net,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|(tick, _)| {
+                if a_to_b {
+                    *tick <= tick_current_index
+                } else {
+                    *tick > tick_current_index
+                }
+            })
+            .collect();
+        if a_to_b {
+            ticks.sort_by(|a, b| b.0.cmp(&a.0));
+        } else {
+            ticks.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+
+        let mut sqrt_price = self.sqrt_price as f64 / 2f64.powi(64);
+        let mut liquidity = self.liquidity as f64;
+        let mut remaining = amount_in_less_fee;
+        let mut amount_out = 0f64;
+
+        for (tick, liquidity_net) in ticks {
+            if remaining <= 0.0 {
+                break;
+            }
+
+            let target_sqrt_price = Self::tick_to_sqrt_price(tick);
+            if liquidity > 0.0 {
+                // Input needed to move the price to the next initialized tick
+                let step_in = if a_to_b {
+                    liquidity * (1.0 / target_sqrt_price - 1.0 / sqrt_price)
+                } else {
+                    liquidity * (target_sqrt_price - sqrt_price)
+                };
+
+                if remaining < step_in {
+                    break;
+                }
+
+                amount_out += if a_to_b {
+                    liquidity * (sqrt_price - target_sqrt_price)
+                } else {
+                    liquidity * (1.0 / sqrt_price - 1.0 / target_sqrt_price)
+                };
+                remaining -= step_in;
+            }
+
+            // Cross the tick
+            sqrt_price = target_sqrt_price;
+            liquidity += if a_to_b {
+                -(liquidity_net as f64)
+            } else {
+                liquidity_net as f64
+            };
+        }
+
+        // Whatever is left is swapped within the current range
+        if remaining > 0.0 && liquidity > 0.0 {
+            if a_to_b {
+                let next_sqrt_price = 1.0 / (1.0 / sqrt_price + remaining / liquidity);
+                amount_out += liquidity * (sqrt_price - next_sqrt_price);
+            } else {
+                let next_sqrt_price = sqrt_price + remaining / liquidity;
+                amount_out += liquidity * (1.0 / sqrt_price - 1.0 / next_sqrt_price);
+            }
+        }
+
+        let spot_price = if a_to_b {
+            self.price()
+        } else {
+            1.0 / self.price()
+        };
+        let execution_price = if amount_in_less_fee > 0.0 {
+            amount_out / amount_in_less_fee
+        } else {
+            spot_price
+        };
+        let price_impact = if spot_price > 0.0 {
+            (1.0 - execution_price / spot_price).max(0.0)
+        } else {
+            0.0
+        };
+
+        WhirlpoolSwapQuote {
+            amount_in,
+            amount_out: amount_out.floor() as u64,
+            price_impact,
+        }
+    }
+
+    /// sqrt(1.0001^tick)
+    fn tick_to_sqrt_price(tick: i32) -> f64 {
+        1.0001f64.powf(tick as f64 / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn whirlpool(tick_current_index: i32, liquidity: u128) -> WhirlpoolState {
+        WhirlpoolState {
+            tick_spacing: 64,
+            fee_rate: 3_000,
+            liquidity,
+            sqrt_price: (WhirlpoolState::tick_to_sqrt_price(tick_current_index) * 2f64.powi(64))
+                as u128,
+            tick_current_index,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_account_sizes() {
+        assert_eq!(8 + std::mem::size_of::<WhirlpoolState>(), 653);
+        assert_eq!(8 + std::mem::size_of::<WhirlpoolTickArray>(), 9988);
+    }
+
+    #[test]
+    fn test_swap_tick_array_start_indexes() {
+        // 64 * 88 = 5632 ticks per array
+        let whirlpool = whirlpool(5631, 0);
+        assert_eq!(
+            whirlpool.swap_tick_array_start_indexes(true),
+            vec![0, -5632, -11264]
+        );
+        // b to a from the last tick of an array starts on the next one
+        assert_eq!(
+            whirlpool.swap_tick_array_start_indexes(false),
+            vec![5632, 11264, 16896]
+        );
+        assert_eq!(whirlpool.tick_array_start_index(-1), -5632);
+    }
+
+    #[test]
+    fn test_quote_within_range() {
+        // Price 1:1, deep liquidity: the output is the input less the 0.3% fee
+        let whirlpool = whirlpool(0, 1_000_000_000_000_000);
+        let quote = whirlpool.quote_base_input(1_000_000, true, &[]);
+        assert!(quote.amount_out <= 997_000);
+        assert!(quote.amount_out > 996_990);
+        assert!(quote.price_impact < 0.0001);
+    }
+
+    #[test]
+    fn test_quote_crosses_tick() {
+        // All the liquidity leaves at tick -64: nothing is left past it
+        let whirlpool = whirlpool(0, 1_000_000_000);
+        let mut tick_array: WhirlpoolTickArray = Zeroable::zeroed();
+        tick_array.start_tick_index = -5632;
+        let mut ticks = tick_array.ticks;
+        ticks[87] = WhirlpoolTick {
+            initialized: 1,
+            liquidity_net: 1_000_000_000,
+            liquidity_gross: 1_000_000_000,
+            ..Default::default()
+        };
+        tick_array.ticks = ticks;
+
+        let unbounded = whirlpool.quote_base_input(100_000_000, true, &[]);
+        let bounded = whirlpool.quote_base_input(100_000_000, true, &[tick_array]);
+        assert!(bounded.amount_out < unbounded.amount_out);
+        // sqrt(1.0001^-64) is about 0.9968, 1e9 * (1 - 0.9968)
+        assert!((3_190_000..3_210_000).contains(&bounded.amount_out));
+    }
+}
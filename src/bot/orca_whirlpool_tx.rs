@@ -0,0 +1,469 @@
+//! Given a transaction having among it's instructions an Orca Whirlpool swap (`swap` or `swap_v2`),
+//! this module extracts the swap accounts, amounts and direction.
+//!
+//! Whirlpool accounts are listed as token a / token b, they are resolved to
+//! the input and output sides with the `a_to_b` flag of the instruction.
+//! The parsed transaction has the same shape as `RaydiumClmmTx`.
+
+use serde::Serialize;
+use serde_json::{from_str, Value};
+
+use crate::bot::tx_common::GetSignature;
+use crate::constants::{
+    BOT_RAYDIUM_OPERATION_BUY, BOT_RAYDIUM_OPERATION_SELL, ORCA_WHIRLPOOL_ACCOUNTS_LEN_SWAP,
+    ORCA_WHIRLPOOL_ACCOUNTS_LEN_SWAP_V2, ORCA_WHIRLPOOL_ACTION_SWAP, ORCA_WHIRLPOOL_ACTION_SWAP_V2,
+    ORCA_WHIRLPOOL_PROGRAM, RAYDIUM_SWAP_BASE_IN_INSTRUCTION, RAYDIUM_SWAP_BASE_OUT_INSTRUCTION,
+    SPL_TOKEN_2022_PROGRAM, WSOL_MINT,
+};
+
+use super::raydium_meme_tx::{
+    InnerIxData, RaydiumMemeTradeData, RaydiumSwapBaseInData, RaydiumSwapBaseOutData,
+    RaydiumSwapData,
+};
+use super::tx_common::{GetComputeData, GetTxCommon};
+
+/// Orca Whirlpool swap instruction accounts, resolved to the swap direction
+/// Based on https://github.com/orca-so/whirlpools/blob/main/programs/whirlpool/src/instructions/v2/swap.rs
+#[derive(Debug, Serialize, PartialEq)]
+pub struct OrcaWhirlpoolAccounts {
+    pub token_authority: String,
+    pub whirlpool: String,
+    pub input_token_account: String,
+    pub output_token_account: String,
+    pub input_vault: String,
+    pub output_vault: String,
+    pub input_vault_mint: Option<String>,
+    pub output_vault_mint: Option<String>,
+    pub tick_arrays: Vec<String>,
+    pub oracle: String,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct OrcaWhirlpoolTx {
+    pub signature: Option<String>,
+    pub accounts: Option<OrcaWhirlpoolAccounts>,
+    pub ix_data: Option<RaydiumSwapData>,
+    pub inner_ix_data: Option<InnerIxData>,
+    pub meme_trade_data: Option<RaydiumMemeTradeData>,
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64,
+}
+
+impl GetSignature for OrcaWhirlpoolTx {}
+
+impl GetComputeData for OrcaWhirlpoolTx {}
+
+impl GetTxCommon for OrcaWhirlpoolTx {}
+
+impl OrcaWhirlpoolTx {
+    /// Creates a new OrcaWhirlpoolTx instance from a transaction payload.
+    ///
+    /// * `payload` - The transaction payload
+    pub fn new(payload: String) -> Self {
+        let json: Value = match from_str(payload.as_str()) {
+            Ok(json) => json,
+            Err(_) => return Self::default(),
+        };
+
+        let signature = match Self::get_signature(&json) {
+            Some(signature) => signature,
+            None => return Self::default(),
+        };
+
+        let swap_ix = match Self::get_whirlpool_swap_instruction(&json) {
+            Some(swap_ix) => swap_ix,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let (ix_data, is_v2, a_to_b) = match swap_ix["data"]
+            .as_str()
+            .and_then(Self::get_instruction_data)
+        {
+            Some(ix_data) => ix_data,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let accounts = match Self::get_accounts(&json, swap_ix, is_v2, a_to_b) {
+            Some(accounts) => accounts,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    ix_data: Some(ix_data),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let (source_amount, dest_amount) = match Self::get_amounts(&json, &accounts) {
+            Some(amounts) => amounts,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    accounts: Some(accounts),
+                    ix_data: Some(ix_data),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let meme_trade_data = Self::get_meme_trade_data(&accounts);
+
+        let (compute_unit_limit, compute_unit_price) =
+            Self::get_compute_data(&json).unwrap_or((0, 0));
+
+        Self {
+            signature: Some(signature),
+            accounts: Some(accounts),
+            ix_data: Some(ix_data),
+            inner_ix_data: Some(InnerIxData {
+                source_amount,
+                dest_amount,
+            }),
+            meme_trade_data,
+            compute_unit_limit,
+            compute_unit_price,
+        }
+    }
+
+    /// Finds the Whirlpool swap instruction in the transaction,
+    /// either as a main instruction or as an inner instruction.
+    fn get_whirlpool_swap_instruction(json: &Value) -> Option<&Value> {
+        let is_whirlpool_swap = |instruction: &Value| {
+            instruction["programId"]
+                .as_str()
+                .map_or(false, |id| id == ORCA_WHIRLPOOL_PROGRAM.to_string())
+                && instruction["data"]
+                    .as_str()
+                    .and_then(Self::get_instruction_data)
+                    .is_some()
+        };
+
+        // Check main instructions
+        let instructions = json["params"]["result"]["transaction"]["transaction"]["message"]
+            ["instructions"]
+            .as_array()?;
+
+        if let Some(instruction) = instructions.iter().find(|&ix| is_whirlpool_swap(ix)) {
+            return Some(instruction);
+        }
+
+        // Check inner instructions
+        json["params"]["result"]["transaction"]["meta"]["innerInstructions"]
+            .as_array()?
+            .iter()
+            .find_map(|inner_ix_group| {
+                inner_ix_group["instructions"]
+                    .as_array()?
+                    .iter()
+                    .find(|&ix| is_whirlpool_swap(ix))
+            })
+    }
+
+    /// Extracts the data from the Whirlpool swap instruction,
+    /// returns the swap data, whether it is a `swap_v2` and the `a_to_b` flag.
+    ///
+    /// * `input` - The base58 instruction data
+    fn get_instruction_data(input: &str) -> Option<(RaydiumSwapData, bool, bool)> {
+        let input = bs58::decode(input).into_vec().ok()?;
+
+        // discriminator, amount, other_amount_threshold, sqrt_price_limit,
+        // amount_specified_is_input, a_to_b
+        if input.len() < 42 {
+            return None;
+        }
+
+        let is_v2 = match &input[..8] {
+            ORCA_WHIRLPOOL_ACTION_SWAP => false,
+            ORCA_WHIRLPOOL_ACTION_SWAP_V2 => true,
+            _ => return None,
+        };
+
+        let amount = u64::from_le_bytes(input[8..16].try_into().ok()?);
+        let other_amount_threshold = u64::from_le_bytes(input[16..24].try_into().ok()?);
+        let amount_specified_is_input = input[40] != 0;
+        let a_to_b = input[41] != 0;
+
+        let ix_data = if amount_specified_is_input {
+            RaydiumSwapData::BaseIn(RaydiumSwapBaseInData {
+                instruction: RAYDIUM_SWAP_BASE_IN_INSTRUCTION,
+                amount_in: amount,
+                minimum_amount_out: other_amount_threshold,
+            })
+        } else {
+            RaydiumSwapData::BaseOut(RaydiumSwapBaseOutData {
+                instruction: RAYDIUM_SWAP_BASE_OUT_INSTRUCTION,
+                max_amount_in: other_amount_threshold,
+                amount_out: amount,
+            })
+        };
+
+        Some((ix_data, is_v2, a_to_b))
+    }
+
+    /// Extracts the Whirlpool swap accounts, input side first.
+    ///
+    /// * `json` - The transaction JSON, used to resolve the mints of `swap`
+    /// * `swap_instruction` - The Whirlpool swap instruction
+    /// * `is_v2` - Whether the instruction is a `swap_v2`
+    /// * `a_to_b` - Whether token a is sold to the pool
+    fn get_accounts(
+        json: &Value,
+        swap_instruction: &Value,
+        is_v2: bool,
+        a_to_b: bool,
+    ) -> Option<OrcaWhirlpoolAccounts> {
+        let accounts = swap_instruction["accounts"].as_array()?;
+
+        // `swap_v2` lists the token programs, the memo program and the mints up front
+        let (len, offset) = if is_v2 {
+            (ORCA_WHIRLPOOL_ACCOUNTS_LEN_SWAP_V2, 3)
+        } else {
+            (ORCA_WHIRLPOOL_ACCOUNTS_LEN_SWAP, 1)
+        };
+
+        if accounts.len() < len {
+            return None;
+        }
+
+        let token_authority = Self::pubkey_to_string(&accounts[offset]);
+        let whirlpool = Self::pubkey_to_string(&accounts[offset + 1]);
+        let sides = if is_v2 { offset + 4 } else { offset + 2 };
+        let token_owner_account_a = Self::pubkey_to_string(&accounts[sides]);
+        let token_vault_a = Self::pubkey_to_string(&accounts[sides + 1]);
+        let token_owner_account_b = Self::pubkey_to_string(&accounts[sides + 2]);
+        let token_vault_b = Self::pubkey_to_string(&accounts[sides + 3]);
+
+        let (token_mint_a, token_mint_b) = if is_v2 {
+            (
+                Some(Self::pubkey_to_string(&accounts[offset + 2])),
+                Some(Self::pubkey_to_string(&accounts[offset + 3])),
+            )
+        } else {
+            (
+                Self::get_token_account_mint(json, &token_owner_account_a),
+                Self::get_token_account_mint(json, &token_owner_account_b),
+            )
+        };
+
+        let tick_arrays = accounts[sides + 4..sides + 7]
+            .iter()
+            .map(Self::pubkey_to_string)
+            .collect();
+        let oracle = Self::pubkey_to_string(&accounts[sides + 7]);
+
+        let (
+            input_token_account,
+            output_token_account,
+            input_vault,
+            output_vault,
+            input_vault_mint,
+            output_vault_mint,
+        ) = if a_to_b {
+            (
+                token_owner_account_a,
+                token_owner_account_b,
+                token_vault_a,
+                token_vault_b,
+                token_mint_a,
+                token_mint_b,
+            )
+        } else {
+            (
+                token_owner_account_b,
+                token_owner_account_a,
+                token_vault_b,
+                token_vault_a,
+                token_mint_b,
+                token_mint_a,
+            )
+        };
+
+        Some(OrcaWhirlpoolAccounts {
+            token_authority,
+            whirlpool,
+            input_token_account,
+            output_token_account,
+            input_vault,
+            output_vault,
+            input_vault_mint,
+            output_vault_mint,
+            tick_arrays,
+            oracle,
+        })
+    }
+
+    /// Extracts the exact amounts swapped from the inner token transfers
+    ///
+    /// * `json` - The transaction JSON
+    /// * `accounts` - The Whirlpool swap accounts
+    fn get_amounts(json: &Value, accounts: &OrcaWhirlpoolAccounts) -> Option<(u64, u64)> {
+        let inner_instructions =
+            json["params"]["result"]["transaction"]["meta"]["innerInstructions"].as_array()?;
+
+        let token_programs = [
+            spl_token::id().to_string(),
+            SPL_TOKEN_2022_PROGRAM.to_string(),
+        ];
+
+        let default_array: Vec<Value> = Vec::new();
+        let (source_amount, dest_amount) = inner_instructions
+            .iter()
+            .flat_map(|inner_ix| {
+                inner_ix["instructions"]
+                    .as_array()
+                    .unwrap_or(&default_array)
+            })
+            .filter(|ix| {
+                ix["programId"].as_str().map_or(false, |id| {
+                    token_programs.iter().any(|program| program == id)
+                })
+            })
+            .filter_map(|ix| {
+                let info = &ix["parsed"]["info"];
+                let amount = info["amount"]
+                    .as_str()
+                    .or_else(|| info["tokenAmount"]["amount"].as_str())?
+                    .parse::<u64>()
+                    .ok()?;
+
+                if info["source"].as_str() == Some(&accounts.input_token_account)
+                    && info["destination"].as_str() == Some(&accounts.input_vault)
+                {
+                    Some((amount, 0))
+                } else if info["source"].as_str() == Some(&accounts.output_vault)
+                    && info["destination"].as_str() == Some(&accounts.output_token_account)
+                {
+                    Some((0, amount))
+                } else {
+                    None
+                }
+            })
+            .fold((0, 0), |(src, dst), (s, d)| (src + s, dst + d));
+
+        Some((source_amount, dest_amount))
+    }
+
+    /// Detects the meme mint and the trade direction from the vault mints.
+    /// Only SOL paired swaps are considered.
+    ///
+    /// * `accounts` - The Whirlpool swap accounts
+    fn get_meme_trade_data(accounts: &OrcaWhirlpoolAccounts) -> Option<RaydiumMemeTradeData> {
+        let wsol = WSOL_MINT.to_string();
+        let input_mint = accounts.input_vault_mint.as_ref()?;
+        let output_mint = accounts.output_vault_mint.as_ref()?;
+
+        if *input_mint == wsol && *output_mint != wsol {
+            Some(RaydiumMemeTradeData {
+                meme_mint: output_mint.parse().ok()?,
+                operation: BOT_RAYDIUM_OPERATION_BUY,
+            })
+        } else if *output_mint == wsol && *input_mint != wsol {
+            Some(RaydiumMemeTradeData {
+                meme_mint: input_mint.parse().ok()?,
+                operation: BOT_RAYDIUM_OPERATION_SELL,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a formatted string containing transaction details
+    pub fn format_tx_info(&self) -> String {
+        if let (Some(trade_data), Some(inner_ix), Some(sig)) =
+            (&self.meme_trade_data, &self.inner_ix_data, &self.signature)
+        {
+            let operation = if trade_data.operation == BOT_RAYDIUM_OPERATION_BUY {
+                "Buy"
+            } else {
+                "Sell"
+            };
+
+            format!(
+                "Whirlpool {} | Mint: {} | Source Amount: {} | Destination Amount: {} | TX: https://solscan.io/tx/{}",
+                operation, trade_data.meme_mint, inner_ix.source_amount, inner_ix.dest_amount, sig
+            )
+        } else {
+            "OrcaWhirlpoolTx: Error formatting transaction data".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Synthetic Whirlpool `swap_v2` buy: 0.1 WSOL (token a) in, 4_900 meme (token b) out
+    const TEST_JSON_WHIRLPOOL_BUY: &str = r#"{"jsonrpc":"2.0","method":"transactionNotification","params":{"subscription":1,"result":{"transaction":{"transaction":{"signatures":["3kF8a5Vx2bQ7sY1nLmP9cR4tW6uZ8dE2gH5jK7mN9pQ1rS3tU5vW7xY9zA2bC4dE6fG8hJ1kL3mN5pQ7rS9tU2v"],"message":{"accountKeys":[{"pubkey":"oXMNkDLNWnbTuxrsjSuDwrbntSFWjdxgQMnG18rWrHe","writable":true,"signer":true,"source":"transaction"}],"instructions":[{"programId":"whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc","accounts":["TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr","oXMNkDLNWnbTuxrsjSuDwrbntSFWjdxgQMnG18rWrHe","9ahRw4HvxQhpdwbypsD5bGRhCrhvaMbeLdQ5hgXY63JK","So11111111111111111111111111111111111111112","245trtqFtcKBpqyyRYdGYxt1MCRcMTcahMoMbREVNDFT","EAL9GK3feQT7G44DwDq1QJs7d7bWenxNnWGA2RbwaHx1","7GfYqsSvKQdCFAQTZkQnehMDyZ1KDAxrSk9z6bAKAkxY","4fXjnFN2194QbnYZWDvNSFmdPTpZqAQLkgq2chSaGorm","HzouEMnz2JRyHqhuNXxKf6E3SgtkLdLPr3UPqxQPp8p","HXeF88AB5vyp546eSvP5KfQnRWWtcS1kcJtsg9qrY1xm","J3QdvkEYDdaQGTcz44s36JbLAHP3eNMuRmzz7nWWPnTZ","2M82hSM2b4pQRk3D7KC7PvTWRhmTFDdF82zuCzonHVC3","7rAVgoNAsbFgTMAqryEdYvnakrA9GSxkTyCVRFvvxM3q"],"data":"ieFNRMtM7cbPNnQSwCFhKZRhiC1VCFXMbQyS7fiZJ2xT2jZgqFgzAd5uS","stackHeight":null}]}},"meta":{"err":null,"innerInstructions":[{"index":0,"instructions":[{"program":"spl-token","programId":"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","parsed":{"info":{"authority":"oXMNkDLNWnbTuxrsjSuDwrbntSFWjdxgQMnG18rWrHe","destination":"7GfYqsSvKQdCFAQTZkQnehMDyZ1KDAxrSk9z6bAKAkxY","mint":"So11111111111111111111111111111111111111112","source":"EAL9GK3feQT7G44DwDq1QJs7d7bWenxNnWGA2RbwaHx1","tokenAmount":{"amount":"100000000","decimals":9}},"type":"transferChecked"},"stackHeight":2},{"program":"spl-token","programId":"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","parsed":{"info":{"authority":"9ahRw4HvxQhpdwbypsD5bGRhCrhvaMbeLdQ5hgXY63JK","destination":"4fXjnFN2194QbnYZWDvNSFmdPTpZqAQLkgq2chSaGorm","mint":"245trtqFtcKBpqyyRYdGYxt1MCRcMTcahMoMbREVNDFT","source":"HzouEMnz2JRyHqhuNXxKf6E3SgtkLdLPr3UPqxQPp8p","tokenAmount":{"amount":"4900000000","decimals":6}},"type":"transferChecked"},"stackHeight":2}]}],"preTokenBalances":[],"postTokenBalances":[]},"version":0},"signature":"3kF8a5Vx2bQ7sY1nLmP9cR4tW6uZ8dE2gH5jK7mN9pQ1rS3tU5vW7xY9zA2bC4dE6fG8hJ1kL3mN5pQ7rS9tU2v","slot":1}}}"#;
+
+    #[test]
+    fn test_get_instruction_data() {
+        let json: Value = from_str(TEST_JSON_WHIRLPOOL_BUY).unwrap();
+        let swap_ix = OrcaWhirlpoolTx::get_whirlpool_swap_instruction(&json).unwrap();
+        let (ix_data, is_v2, a_to_b) =
+            OrcaWhirlpoolTx::get_instruction_data(swap_ix["data"].as_str().unwrap()).unwrap();
+        assert!(is_v2);
+        assert!(a_to_b);
+        assert!(matches!(
+            ix_data,
+            RaydiumSwapData::BaseIn(RaydiumSwapBaseInData {
+                amount_in: 100_000_000,
+                minimum_amount_out: 5_000_000_000,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_get_accounts_resolves_sides() {
+        let json: Value = from_str(TEST_JSON_WHIRLPOOL_BUY).unwrap();
+        let swap_ix = OrcaWhirlpoolTx::get_whirlpool_swap_instruction(&json).unwrap();
+
+        let accounts = OrcaWhirlpoolTx::get_accounts(&json, swap_ix, true, true).unwrap();
+        assert_eq!(
+            accounts.input_token_account,
+            "EAL9GK3feQT7G44DwDq1QJs7d7bWenxNnWGA2RbwaHx1"
+        );
+        assert_eq!(
+            accounts.output_vault,
+            "HzouEMnz2JRyHqhuNXxKf6E3SgtkLdLPr3UPqxQPp8p"
+        );
+        assert_eq!(accounts.tick_arrays.len(), 3);
+        assert_eq!(
+            accounts.oracle,
+            "7rAVgoNAsbFgTMAqryEdYvnakrA9GSxkTyCVRFvvxM3q"
+        );
+
+        // Same accounts read b to a
+        let accounts = OrcaWhirlpoolTx::get_accounts(&json, swap_ix, true, false).unwrap();
+        assert_eq!(
+            accounts.input_vault_mint.as_deref(),
+            Some("245trtqFtcKBpqyyRYdGYxt1MCRcMTcahMoMbREVNDFT")
+        );
+    }
+
+    #[test]
+    fn test_new_buy() {
+        let whirlpool_tx = OrcaWhirlpoolTx::new(TEST_JSON_WHIRLPOOL_BUY.to_string());
+
+        let inner_ix_data = whirlpool_tx.inner_ix_data.unwrap();
+        assert_eq!(inner_ix_data.source_amount, 100_000_000);
+        assert_eq!(inner_ix_data.dest_amount, 4_900_000_000);
+
+        let meme_trade_data = whirlpool_tx.meme_trade_data.unwrap();
+        assert_eq!(meme_trade_data.operation, BOT_RAYDIUM_OPERATION_BUY);
+        assert_eq!(
+            meme_trade_data.meme_mint.to_string(),
+            "245trtqFtcKBpqyyRYdGYxt1MCRcMTcahMoMbREVNDFT"
+        );
+    }
+}
@@ -0,0 +1,349 @@
+use helius::Helius;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use std::{error::Error, sync::Arc};
+use tokio::sync::Mutex;
+
+use crate::constants::{
+    ORCA_WHIRLPOOL_ACTION_SWAP_V2, ORCA_WHIRLPOOL_COMPUTE_UNIT_LIMIT,
+    ORCA_WHIRLPOOL_MAX_SQRT_PRICE_X64, ORCA_WHIRLPOOL_MIN_SQRT_PRICE_X64, ORCA_WHIRLPOOL_PROGRAM,
+    SPL_MEMO_PROGRAM, WSOL_MINT,
+};
+
+use super::{
+    tx_common::SendSmartTx,
+    venue::{deepest_sol_pool, find_sol_paired_pools},
+    wsol::{Wsol, WsolMode},
+    AccountDecoder, OrcaWhirlpoolTx, ParsedTrade, QuoteDetails, SplTokenAccountState, Swap,
+    SwapQuote, TradeSide, Venue, VenueContext, WhirlpoolState, WhirlpoolSwapQuote,
+    WhirlpoolTickArray,
+};
+
+/// A Whirlpool exact input quote, with everything needed to build the swap
+#[derive(Debug, Clone)]
+pub struct WhirlpoolQuote {
+    pub pool_id: Pubkey,
+    pub whirlpool: WhirlpoolState,
+    pub a_to_b: bool,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub input_token_program: Pubkey,
+    pub output_token_program: Pubkey,
+    /// Balance of the input vault, the pool liquidity on the sold side
+    pub input_vault_amount: u64,
+    /// Start indexes of the three tick arrays passed to the swap
+    pub tick_array_start_indexes: Vec<i32>,
+    pub swap: WhirlpoolSwapQuote,
+}
+
+impl WhirlpoolQuote {
+    /// Returns (token a program, token b program)
+    fn token_programs(&self) -> (Pubkey, Pubkey) {
+        if self.a_to_b {
+            (self.input_token_program, self.output_token_program)
+        } else {
+            (self.output_token_program, self.input_token_program)
+        }
+    }
+}
+
+pub struct OrcaWhirlpoolTxSend {}
+
+impl SendSmartTx for OrcaWhirlpoolTxSend {}
+
+impl OrcaWhirlpoolTxSend {
+    /// Quotes an exact input swap on a Whirlpool by walking the tick arrays
+    /// the swap is given.
+    ///
+    /// * `helius` - The Helius client
+    /// * `pool_id` - The Whirlpool account
+    /// * `input_mint` - The mint being sold to the pool
+    /// * `amount_in` - The exact input amount
+    pub async fn quote(
+        helius: Arc<Mutex<Helius>>,
+        pool_id: &Pubkey,
+        input_mint: &Pubkey,
+        amount_in: u64,
+    ) -> Result<WhirlpoolQuote, Box<dyn Error + Send + Sync>> {
+        let connection = helius.lock().await.async_connection()?;
+
+        let whirlpool = *WhirlpoolState::decode_account(&connection.get_account(pool_id).await?)?;
+
+        let (token_mint_a, token_mint_b) = (whirlpool.token_mint_a, whirlpool.token_mint_b);
+        let a_to_b = if token_mint_a == *input_mint {
+            true
+        } else if token_mint_b == *input_mint {
+            false
+        } else {
+            return Err("Mint is not traded by this Whirlpool".into());
+        };
+        let (output_mint, input_vault) = if a_to_b {
+            (token_mint_b, whirlpool.token_vault_a)
+        } else {
+            (token_mint_a, whirlpool.token_vault_b)
+        };
+
+        let tick_array_start_indexes = whirlpool.swap_tick_array_start_indexes(a_to_b);
+
+        // Both mints (for their token programs), the input vault and the
+        // tick arrays in one round trip
+        let mut keys = vec![*input_mint, output_mint, input_vault];
+        keys.extend(
+            tick_array_start_indexes
+                .iter()
+                .map(|start_index| WhirlpoolState::tick_array_address(pool_id, *start_index)),
+        );
+        let accounts = connection.get_multiple_accounts(&keys).await?;
+
+        let input_token_program = accounts[0].as_ref().ok_or("Input mint not found")?.owner;
+        let output_token_program = accounts[1].as_ref().ok_or("Output mint not found")?.owner;
+        let input_vault_amount = SplTokenAccountState::decode_account(
+            accounts[2]
+                .as_ref()
+                .ok_or("Whirlpool input vault not found")?,
+        )?
+        .amount;
+
+        // Uninitialized tick arrays hold no liquidity, the program accepts them
+        let tick_arrays = accounts[3..]
+            .iter()
+            .flatten()
+            .map(|account| Ok(*WhirlpoolTickArray::decode_account(account)?))
+            .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+
+        let swap = whirlpool.quote_base_input(amount_in, a_to_b, &tick_arrays);
+
+        Ok(WhirlpoolQuote {
+            pool_id: *pool_id,
+            whirlpool,
+            a_to_b,
+            input_mint: *input_mint,
+            output_mint,
+            input_token_program,
+            output_token_program,
+            input_vault_amount,
+            tick_array_start_indexes,
+            swap,
+        })
+    }
+
+    /// Builds the Whirlpool `swap_v2` exact input instruction for the given owner.
+    ///
+    /// * `quote` - The quote to execute
+    /// * `owner` - The wallet swapping
+    /// * `minimum_amount_out` - The minimum output accepted
+    pub fn swap_v2_instruction(
+        quote: &WhirlpoolQuote,
+        owner: &Pubkey,
+        minimum_amount_out: u64,
+    ) -> Instruction {
+        // Packed fields are copied out before being borrowed
+        let whirlpool = quote.whirlpool;
+        let (token_mint_a, token_mint_b) = (whirlpool.token_mint_a, whirlpool.token_mint_b);
+        let (token_program_a, token_program_b) = quote.token_programs();
+
+        // No price limit, the minimum output protects the trade
+        let sqrt_price_limit = if quote.a_to_b {
+            ORCA_WHIRLPOOL_MIN_SQRT_PRICE_X64
+        } else {
+            ORCA_WHIRLPOOL_MAX_SQRT_PRICE_X64
+        };
+
+        let mut data = ORCA_WHIRLPOOL_ACTION_SWAP_V2.to_vec();
+        data.extend_from_slice(&quote.swap.amount_in.to_le_bytes());
+        data.extend_from_slice(&minimum_amount_out.to_le_bytes());
+        data.extend_from_slice(&sqrt_price_limit.to_le_bytes());
+        // amount_specified_is_input, a_to_b, no remaining accounts info
+        data.extend_from_slice(&[1, quote.a_to_b as u8, 0]);
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(token_program_a, false),
+            AccountMeta::new_readonly(token_program_b, false),
+            AccountMeta::new_readonly(SPL_MEMO_PROGRAM, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(quote.pool_id, false),
+            AccountMeta::new_readonly(token_mint_a, false),
+            AccountMeta::new_readonly(token_mint_b, false),
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(
+                    owner,
+                    &token_mint_a,
+                    &token_program_a,
+                ),
+                false,
+            ),
+            AccountMeta::new(whirlpool.token_vault_a, false),
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(
+                    owner,
+                    &token_mint_b,
+                    &token_program_b,
+                ),
+                false,
+            ),
+            AccountMeta::new(whirlpool.token_vault_b, false),
+        ];
+        accounts.extend(quote.tick_array_start_indexes.iter().map(|start_index| {
+            AccountMeta::new(
+                WhirlpoolState::tick_array_address(&quote.pool_id, *start_index),
+                false,
+            )
+        }));
+        accounts.push(AccountMeta::new(
+            WhirlpoolState::oracle_address(&quote.pool_id),
+            false,
+        ));
+
+        Instruction {
+            program_id: ORCA_WHIRLPOOL_PROGRAM,
+            accounts,
+            data,
+        }
+    }
+
+    /// Builds the instruction list executing a quote for the given owner.
+    ///
+    /// * `helius` - The Helius client
+    /// * `quote` - The quote to execute
+    /// * `owner` - The wallet swapping
+    /// * `slippage_percent` - The slippage applied to the quoted output
+    /// * `wsol_mode` - The WSOL funding mode
+    pub async fn build_swap(
+        helius: Arc<Mutex<Helius>>,
+        quote: &WhirlpoolQuote,
+        owner: &Pubkey,
+        slippage_percent: u64,
+        wsol_mode: WsolMode,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let minimum_amount_out = (quote.swap.amount_out as f64 * (100.0 - slippage_percent as f64)
+            / 100.0)
+            .floor() as u64;
+
+        let (pre_swap_instructions, post_swap_instructions) = if quote.input_mint == WSOL_MINT {
+            Wsol::buy_instructions(helius, owner, wsol_mode, quote.swap.amount_in).await?
+        } else if quote.output_mint == WSOL_MINT {
+            Wsol::sell_instructions(owner, wsol_mode)?
+        } else {
+            (vec![], vec![])
+        };
+
+        let mut instructions = pre_swap_instructions;
+        if quote.output_mint != WSOL_MINT {
+            instructions.push(create_associated_token_account_idempotent(
+                owner,
+                owner,
+                &quote.output_mint,
+                &quote.output_token_program,
+            ));
+        }
+        instructions.push(Self::swap_v2_instruction(quote, owner, minimum_amount_out));
+        instructions.extend(post_swap_instructions);
+
+        Ok(instructions)
+    }
+}
+
+impl Swap for OrcaWhirlpoolTxSend {
+    const VENUE: Venue = Venue::OrcaWhirlpool;
+    const COMPUTE_UNIT_LIMIT: u32 = ORCA_WHIRLPOOL_COMPUTE_UNIT_LIMIT;
+
+    async fn find_pool(
+        ctx: &VenueContext,
+        mint: &Pubkey,
+    ) -> Result<Pubkey, Box<dyn Error + Send + Sync>> {
+        let connection = ctx.helius.lock().await.async_connection()?;
+
+        let pools = find_sol_paired_pools::<WhirlpoolState>(
+            &connection,
+            &ORCA_WHIRLPOOL_PROGRAM,
+            [
+                std::mem::offset_of!(WhirlpoolState, token_mint_a),
+                std::mem::offset_of!(WhirlpoolState, token_mint_b),
+            ],
+            mint,
+        )
+        .await?
+        .into_iter()
+        .map(|(pool_id, pool)| {
+            let wsol_vault = if pool.token_mint_a == WSOL_MINT {
+                pool.token_vault_a
+            } else {
+                pool.token_vault_b
+            };
+            (pool_id, wsol_vault)
+        })
+        .collect();
+
+        deepest_sol_pool(&connection, pools).await
+    }
+
+    async fn quote_pool(
+        ctx: &VenueContext,
+        pool_id: &Pubkey,
+        mint: &Pubkey,
+        side: TradeSide,
+        amount: u64,
+    ) -> Result<SwapQuote, Box<dyn Error + Send + Sync>> {
+        let (input_mint, output_mint) = side.mints(mint);
+        let quote = Self::quote(Arc::clone(&ctx.helius), pool_id, &input_mint, amount).await?;
+        if quote.output_mint != output_mint {
+            return Err("Only SOL paired Whirlpool pools are traded".into());
+        }
+
+        Ok(SwapQuote {
+            venue: Self::VENUE,
+            pool_id: *pool_id,
+            mint: *mint,
+            side,
+            amount_in: quote.swap.amount_in,
+            amount_out: quote.swap.amount_out,
+            creator: None,
+            quote_liquidity: match side {
+                TradeSide::Buy => quote.input_vault_amount,
+                TradeSide::Sell => 0,
+            },
+            pool_open_time: None,
+            price_impact: quote.swap.price_impact,
+            details: QuoteDetails::OrcaWhirlpool(quote),
+        })
+    }
+
+    async fn build_swap(
+        ctx: &VenueContext,
+        quote: &SwapQuote,
+        wallet: &Pubkey,
+        slippage_percent: u64,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let QuoteDetails::OrcaWhirlpool(details) = &quote.details else {
+            return Err("Not a Orca Whirlpool quote".into());
+        };
+
+        Self::build_swap(
+            Arc::clone(&ctx.helius),
+            details,
+            wallet,
+            slippage_percent,
+            ctx.wsol_mode,
+        )
+        .await
+    }
+
+    async fn parse_trade(_ctx: &VenueContext, payload: &str) -> Option<ParsedTrade> {
+        let tx = OrcaWhirlpoolTx::new(payload.to_string());
+
+        ParsedTrade::from_meme_trade(
+            Self::VENUE,
+            tx.signature.clone(),
+            &tx.accounts.as_ref()?.whirlpool,
+            tx.meme_trade_data.as_ref()?,
+            tx.inner_ix_data.as_ref()?,
+            tx.compute_unit_limit,
+        )
+    }
+}
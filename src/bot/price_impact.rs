@@ -0,0 +1,247 @@
+use std::{env, error::Error};
+
+use super::venue::{SwapQuote, TradeSide, VenueContext};
+use crate::errors::PriceImpactError;
+
+/// Quotes tried at most when shrinking an order under the max price impact
+const PRICE_IMPACT_SHRINK_STEPS: usize = 8;
+
+/// Price impact of an exact input swap on a constant product pool, virtual
+/// reserves included: the input moves the price by
+/// `amount_in / (reserve_in + amount_in)`
+///
+/// * `amount_in` - The input swapped against the reserves. When the fee is
+///   taken from it, it is left in, overstating the impact by its share.
+/// * `reserve_in` - The pool reserve of the input token
+pub fn constant_product_price_impact(amount_in: u64, reserve_in: u64) -> f64 {
+    let denominator = reserve_in as f64 + amount_in as f64;
+    if denominator == 0.0 {
+        return 0.0;
+    }
+
+    amount_in as f64 / denominator
+}
+
+/// What is done with an order moving the price more than the max
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PriceImpactAction {
+    /// The order is not sent
+    #[default]
+    Refuse,
+    /// The order is sent for the largest amount fitting under the max
+    Shrink,
+}
+
+impl PriceImpactAction {
+    pub fn from_config(action: &str) -> Option<Self> {
+        match action.trim().to_ascii_lowercase().as_str() {
+            "refuse" => Some(Self::Refuse),
+            "shrink" => Some(Self::Shrink),
+            _ => None,
+        }
+    }
+}
+
+/// Bounds the price impact of our own buys against the current reserves,
+/// on top of the slippage tolerated once they are sent.
+///
+/// Sells are never refused nor shrunk, whatever sends them: a position has
+/// to stay exitable however thin its pool got.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PriceImpactGuard {
+    /// Price impact tolerated, in bps, None to not guard the orders
+    pub max_bps: Option<u64>,
+    pub action: PriceImpactAction,
+}
+
+impl PriceImpactGuard {
+    /// Loads the guard from the environment, unset variables keep the defaults:
+    ///
+    /// * `max_price_impact_bps` - Price impact tolerated on buys, in bps, unset to not guard them
+    /// * `price_impact_action` - "refuse" (default) or "shrink" the buys above it
+    pub fn from_env() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut guard = Self::default();
+
+        if let Ok(max_bps) = env::var("max_price_impact_bps") {
+            guard.max_bps = Some(max_bps.parse()?);
+        }
+        if let Ok(action) = env::var("price_impact_action") {
+            guard.action = PriceImpactAction::from_config(&action)
+                .ok_or_else(|| format!("Unknown price impact action: {action}"))?;
+        }
+
+        Ok(guard)
+    }
+
+    /// Checks the price impact of a buy quote against the max, sell quotes
+    /// always pass
+    ///
+    /// * `quote` - The quote about to be sent
+    pub fn check(&self, quote: &SwapQuote) -> Result<(), PriceImpactError> {
+        match self.max_bps {
+            Some(max_bps)
+                if quote.side == TradeSide::Buy && impact_bps(quote.price_impact) > max_bps =>
+            {
+                Err(PriceImpactError::TooHigh {
+                    venue: quote.venue,
+                    side: quote.side,
+                    mint: quote.mint,
+                    impact_bps: impact_bps(quote.price_impact),
+                    max_bps,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the quote if its price impact is under the max. Above it,
+    /// the order is refused or quoted again on the same pool for smaller
+    /// amounts until it fits, depending on the action.
+    ///
+    /// * `ctx` - The venue context
+    /// * `quote` - The quote about to be sent
+    pub async fn guard(
+        &self,
+        ctx: &VenueContext,
+        quote: SwapQuote,
+    ) -> Result<SwapQuote, Box<dyn Error + Send + Sync>> {
+        let Err(e) = self.check(&quote) else {
+            return Ok(quote);
+        };
+        if self.action == PriceImpactAction::Refuse {
+            return Err(e.into());
+        }
+        let max_bps = self.max_bps.unwrap_or_default();
+
+        let mut shrunk = quote.clone();
+        for _ in 0..PRICE_IMPACT_SHRINK_STEPS {
+            let amount = shrunk_amount(shrunk.amount_in, shrunk.price_impact, max_bps);
+            if amount == 0 {
+                break;
+            }
+            shrunk = quote
+                .venue
+                .quote_pool(ctx, &quote.pool_id, &quote.mint, quote.side, amount)
+                .await?;
+            if self.check(&shrunk).is_ok() {
+                println!(
+                    "{} {} of {} shrunk from {} to {} | Price impact: {:.2}%",
+                    quote.venue,
+                    quote.side,
+                    quote.mint,
+                    quote.amount_in,
+                    shrunk.amount_in,
+                    shrunk.price_impact * 100.0
+                );
+                return Ok(shrunk);
+            }
+        }
+
+        Err(PriceImpactError::CannotShrink {
+            venue: quote.venue,
+            side: quote.side,
+            mint: quote.mint,
+            max_bps,
+        }
+        .into())
+    }
+}
+
+/// Returns a price impact in bps, rounded
+fn impact_bps(price_impact: f64) -> u64 {
+    (price_impact * 10_000.0).round() as u64
+}
+
+/// Returns the input expected to move the price by the max. The impact
+/// falls slower than the input, so the input is scaled down 5% below the
+/// ratio of the max to the impact, and shrunk again if still above it.
+///
+/// * `amount_in` - The input of the last quote
+/// * `price_impact` - The price impact of the last quote
+/// * `max_bps` - The price impact tolerated, in bps
+fn shrunk_amount(amount_in: u64, price_impact: f64, max_bps: u64) -> u64 {
+    if price_impact <= 0.0 {
+        return amount_in;
+    }
+
+    let ratio = (max_bps as f64 / 10_000.0 / price_impact * 0.95).min(1.0);
+    let amount = (amount_in as f64 * ratio).floor() as u64;
+    // Always smaller, so that the shrinking ends
+    amount.min(amount_in.saturating_sub(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::{test_quote, TradeSide, Venue};
+
+    fn quote(amount_in: u64, reserve_in: u64) -> SwapQuote {
+        SwapQuote {
+            quote_liquidity: reserve_in,
+            price_impact: constant_product_price_impact(amount_in, reserve_in),
+            ..test_quote(Venue::RaydiumV4, amount_in, 0)
+        }
+    }
+
+    #[test]
+    fn test_constant_product_price_impact() {
+        assert_eq!(constant_product_price_impact(1_000, 99_000), 0.01);
+        assert_eq!(constant_product_price_impact(100_000, 100_000), 0.5);
+        assert_eq!(constant_product_price_impact(0, 100_000), 0.0);
+        assert_eq!(constant_product_price_impact(0, 0), 0.0);
+    }
+
+    #[test]
+    fn test_check() {
+        let guard = PriceImpactGuard {
+            max_bps: Some(100),
+            action: PriceImpactAction::Refuse,
+        };
+        assert_eq!(guard.check(&quote(1_000, 99_000)), Ok(()));
+
+        let large = quote(2_000, 98_000);
+        assert_eq!(
+            guard.check(&large),
+            Err(PriceImpactError::TooHigh {
+                venue: Venue::RaydiumV4,
+                side: TradeSide::Buy,
+                mint: large.mint,
+                impact_bps: 200,
+                max_bps: 100,
+            })
+        );
+        assert_eq!(PriceImpactGuard::default().check(&large), Ok(()));
+        // Sells are never blocked
+        let sell = SwapQuote {
+            side: TradeSide::Sell,
+            ..large
+        };
+        assert_eq!(guard.check(&sell), Ok(()));
+    }
+
+    #[test]
+    fn test_shrunk_amount() {
+        // 2% impact shrunk towards 1%, which the next quote lands under
+        let amount = shrunk_amount(2_000, 0.02, 100);
+        assert!(amount > 900 && amount < 1_000);
+        assert!(constant_product_price_impact(amount, 98_000) < 0.01);
+
+        assert_eq!(shrunk_amount(2_000, 0.0, 100), 2_000);
+        assert_eq!(shrunk_amount(1, 0.5, 100), 0);
+        // Never grows, even when the impact is already under the max
+        assert_eq!(shrunk_amount(2_000, 0.001, 100), 1_999);
+    }
+
+    #[test]
+    fn test_price_impact_action_from_config() {
+        assert_eq!(
+            PriceImpactAction::from_config(" Shrink"),
+            Some(PriceImpactAction::Shrink)
+        );
+        assert_eq!(
+            PriceImpactAction::from_config("refuse"),
+            Some(PriceImpactAction::Refuse)
+        );
+        assert_eq!(PriceImpactAction::from_config("ignore"), None);
+    }
+}
@@ -0,0 +1,286 @@
+use bytemuck::{Pod, Zeroable};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::constants::{
+    PUMP_FUN_BONDING_CURVE_DISCRIMINATOR, PUMP_FUN_BONDING_CURVE_SEED,
+    PUMP_FUN_GLOBAL_DISCRIMINATOR, PUMP_FUN_INITIAL_REAL_TOKEN_RESERVES,
+    PUMP_FUN_INITIAL_VIRTUAL_SOL_RESERVES, PUMP_FUN_INITIAL_VIRTUAL_TOKEN_RESERVES,
+    PUMP_FUN_PROGRAM, PUMP_FUN_TOKEN_TOTAL_SUPPLY,
+};
+
+use super::AccountDecoder;
+
+/// Pump.fun bonding curve of a mint, trading happens against the virtual reserves
+/// until the real token reserves are sold out and the curve completes.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PumpFunBondingCurve {
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub token_total_supply: u64,
+    /// 1 once the curve migrated, 0 otherwise
+    pub complete: u8,
+}
+
+/// Pump.fun global configuration, the parameters every new curve starts with
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PumpFunGlobal {
+    pub initialized: u8,
+    pub authority: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub initial_virtual_token_reserves: u64,
+    pub initial_virtual_sol_reserves: u64,
+    pub initial_real_token_reserves: u64,
+    pub token_total_supply: u64,
+    pub fee_basis_points: u64,
+}
+
+// Newer versions of the program append fields (e.g. the curve creator)
+impl AccountDecoder for PumpFunBondingCurve {
+    const OWNERS: &'static [Pubkey] = &[PUMP_FUN_PROGRAM];
+    const HEAD: &'static [u8] = PUMP_FUN_BONDING_CURVE_DISCRIMINATOR;
+    const EXTENDABLE: bool = true;
+}
+
+impl AccountDecoder for PumpFunGlobal {
+    const OWNERS: &'static [Pubkey] = &[PUMP_FUN_PROGRAM];
+    const HEAD: &'static [u8] = PUMP_FUN_GLOBAL_DISCRIMINATOR;
+    const EXTENDABLE: bool = true;
+}
+
+impl PumpFunBondingCurve {
+    /// Derives the bonding curve address of a mint
+    ///
+    /// * `mint` - The token traded on the curve
+    pub fn address(mint: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[PUMP_FUN_BONDING_CURVE_SEED, mint.as_ref()],
+            &PUMP_FUN_PROGRAM,
+        )
+        .0
+    }
+
+    /// Reads the curve creator from the raw account data,
+    /// None for accounts created before the program stored it
+    ///
+    /// * `data` - The raw bonding curve account data
+    pub fn creator(data: &[u8]) -> Option<Pubkey> {
+        let offset = Self::HEAD.len() + std::mem::size_of::<Self>();
+        let creator = data.get(offset..offset + 32)?;
+        Pubkey::try_from(creator).ok()
+    }
+
+    /// Rebuilds a curve launched with the initial parameters of the global
+    /// account out of the tokens it has left, e.g. the curve token account
+    /// balance of a transaction. The virtual reserves keep the constant
+    /// product of the launch, so they can be a few lamports off the account.
+    ///
+    /// * `real_token_reserves` - The raw tokens the curve has left to sell
+    pub fn from_real_token_reserves(real_token_reserves: u64) -> Self {
+        let real_token_reserves = real_token_reserves.min(PUMP_FUN_INITIAL_REAL_TOKEN_RESERVES);
+        let virtual_token_reserves = real_token_reserves
+            + (PUMP_FUN_INITIAL_VIRTUAL_TOKEN_RESERVES - PUMP_FUN_INITIAL_REAL_TOKEN_RESERVES);
+        let k = PUMP_FUN_INITIAL_VIRTUAL_TOKEN_RESERVES as u128
+            * PUMP_FUN_INITIAL_VIRTUAL_SOL_RESERVES as u128;
+        let virtual_sol_reserves = (k / virtual_token_reserves as u128) as u64;
+
+        Self {
+            virtual_token_reserves,
+            virtual_sol_reserves,
+            real_token_reserves,
+            real_sol_reserves: virtual_sol_reserves - PUMP_FUN_INITIAL_VIRTUAL_SOL_RESERVES,
+            token_total_supply: PUMP_FUN_TOKEN_TOTAL_SUPPLY,
+            complete: (real_token_reserves == 0) as u8,
+        }
+    }
+
+    /// Returns true once the curve migrated, it can't be traded anymore
+    pub fn is_complete(&self) -> bool {
+        self.complete != 0
+    }
+
+    /// Returns the percent of the curve tokens sold, 100 once complete
+    pub fn progress(&self) -> f64 {
+        if self.is_complete() {
+            return 100.0;
+        }
+        let real_token_reserves = self.real_token_reserves;
+        let sold = PUMP_FUN_INITIAL_REAL_TOKEN_RESERVES.saturating_sub(real_token_reserves);
+        sold as f64 * 100.0 / PUMP_FUN_INITIAL_REAL_TOKEN_RESERVES as f64
+    }
+
+    /// Returns the current price in lamports per raw token
+    pub fn price(&self) -> f64 {
+        let (virtual_sol_reserves, virtual_token_reserves) =
+            (self.virtual_sol_reserves, self.virtual_token_reserves);
+
+        if virtual_token_reserves == 0 {
+            return 0.0;
+        }
+
+        virtual_sol_reserves as f64 / virtual_token_reserves as f64
+    }
+
+    /// Tokens received for `sol_amount` lamports, fees excluded
+    ///
+    /// * `sol_amount` - The lamports spent on the curve
+    pub fn quote_buy(&self, sol_amount: u64) -> u64 {
+        let (virtual_sol_reserves, virtual_token_reserves, real_token_reserves) = (
+            self.virtual_sol_reserves,
+            self.virtual_token_reserves,
+            self.real_token_reserves,
+        );
+
+        let denominator = virtual_sol_reserves as u128 + sol_amount as u128;
+        if denominator == 0 {
+            return 0;
+        }
+
+        let tokens = virtual_token_reserves as u128 * sol_amount as u128 / denominator;
+        (tokens as u64).min(real_token_reserves)
+    }
+
+    /// Lamports received for `token_amount` tokens, fees excluded
+    ///
+    /// * `token_amount` - The raw tokens sold to the curve
+    pub fn quote_sell(&self, token_amount: u64) -> u64 {
+        let (virtual_sol_reserves, virtual_token_reserves, real_sol_reserves) = (
+            self.virtual_sol_reserves,
+            self.virtual_token_reserves,
+            self.real_sol_reserves,
+        );
+
+        let denominator = virtual_token_reserves as u128 + token_amount as u128;
+        if denominator == 0 {
+            return 0;
+        }
+
+        let sol = virtual_sol_reserves as u128 * token_amount as u128 / denominator;
+        (sol as u64).min(real_sol_reserves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Bonding curve right after launch, laid out by hand from the initial
+    // parameters of the Pump.fun global account: discriminator, the five u64
+    // reserves/supply and the `complete` flag.
+    const LAUNCH_BONDING_CURVE: [u8; 49] = [
+        23, 183, 248, 55, 96, 216, 172, 96, //
+        0, 16, 216, 71, 227, 207, 3, 0, //
+        0, 172, 35, 252, 6, 0, 0, 0, //
+        0, 120, 197, 251, 81, 209, 2, 0, //
+        0, 0, 0, 0, 0, 0, 0, 0, //
+        0, 128, 198, 164, 126, 141, 3, 0, //
+        0,
+    ];
+
+    #[test]
+    fn test_decode_bonding_curve() {
+        let curve = PumpFunBondingCurve::decode(&LAUNCH_BONDING_CURVE).unwrap();
+        assert_eq!({ curve.virtual_token_reserves }, 1_073_000_000_000_000);
+        assert_eq!({ curve.virtual_sol_reserves }, 30_000_000_000);
+        assert_eq!({ curve.real_token_reserves }, 793_100_000_000_000);
+        assert_eq!({ curve.real_sol_reserves }, 0);
+        assert_eq!({ curve.token_total_supply }, 1_000_000_000_000_000);
+        assert!(!curve.is_complete());
+
+        // Accounts created by newer versions carry the creator after the flag
+        let creator = Pubkey::new_unique();
+        let mut with_creator = LAUNCH_BONDING_CURVE.to_vec();
+        with_creator.extend_from_slice(creator.as_ref());
+        assert_eq!(PumpFunBondingCurve::decode(&with_creator), Ok(curve));
+        assert_eq!(PumpFunBondingCurve::creator(&with_creator), Some(creator));
+        assert_eq!(PumpFunBondingCurve::creator(&LAUNCH_BONDING_CURVE), None);
+
+        // The global account is not a bonding curve
+        let mut global = PUMP_FUN_GLOBAL_DISCRIMINATOR.to_vec();
+        global.extend_from_slice(&LAUNCH_BONDING_CURVE[8..]);
+        assert!(PumpFunBondingCurve::decode(&global).is_err());
+    }
+
+    #[test]
+    fn test_from_real_token_reserves() {
+        let launch = PumpFunBondingCurve::decode(&LAUNCH_BONDING_CURVE).unwrap();
+        assert_eq!(
+            PumpFunBondingCurve::from_real_token_reserves(PUMP_FUN_INITIAL_REAL_TOKEN_RESERVES),
+            *launch
+        );
+
+        // A 1 SOL buy off the launch curve
+        let tokens = launch.quote_buy(1_000_000_000);
+        let curve = PumpFunBondingCurve::from_real_token_reserves(
+            PUMP_FUN_INITIAL_REAL_TOKEN_RESERVES - tokens,
+        );
+        assert!(({ curve.real_sol_reserves } as i64 - 1_000_000_000).abs() < 10);
+        assert!(curve.price() > launch.price());
+
+        let sold_out = PumpFunBondingCurve::from_real_token_reserves(0);
+        assert!(sold_out.is_complete());
+        assert_eq!(sold_out.progress(), 100.0);
+    }
+
+    #[test]
+    fn test_decode_global() {
+        let fee_recipient = Pubkey::new_unique();
+        let mut data = PUMP_FUN_GLOBAL_DISCRIMINATOR.to_vec();
+        data.push(1);
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(fee_recipient.as_ref());
+        for value in [
+            1_073_000_000_000_000u64,
+            30_000_000_000,
+            793_100_000_000_000,
+            1_000_000_000_000_000,
+            100,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let global = PumpFunGlobal::decode(&data).unwrap();
+        assert_eq!({ global.fee_recipient }, fee_recipient);
+        assert_eq!({ global.initial_virtual_sol_reserves }, 30_000_000_000);
+        assert_eq!({ global.fee_basis_points }, 100);
+    }
+
+    #[test]
+    fn test_quotes() {
+        let curve = *PumpFunBondingCurve::decode(&LAUNCH_BONDING_CURVE).unwrap();
+
+        // 1 SOL at launch buys ~34.6M tokens
+        assert_eq!(curve.quote_buy(1_000_000_000), 34_612_903_225_806);
+        // Nothing to sell against before anyone bought
+        assert_eq!(curve.quote_sell(1_000_000), 0);
+
+        let bought = PumpFunBondingCurve {
+            real_sol_reserves: 1_000_000_000,
+            ..curve
+        };
+        assert!(bought.quote_sell(1_000_000) > 0);
+        assert!(curve.price() > 0.0);
+    }
+
+    #[test]
+    fn test_progress() {
+        let curve = *PumpFunBondingCurve::decode(&LAUNCH_BONDING_CURVE).unwrap();
+        assert_eq!(curve.progress(), 0.0);
+
+        let half = PumpFunBondingCurve {
+            real_token_reserves: 396_550_000_000_000,
+            ..curve
+        };
+        assert!((half.progress() - 50.0).abs() < 1e-9);
+
+        let complete = PumpFunBondingCurve {
+            real_token_reserves: 0,
+            complete: 1,
+            ..curve
+        };
+        assert_eq!(complete.progress(), 100.0);
+    }
+}
@@ -1,94 +1,63 @@
-use std::{error::Error, sync::Arc};
+use std::error::Error;
 
-use helius::Helius;
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
-    signature::Keypair,
-    signer::Signer,
     system_program,
 };
 use spl_associated_token_account::{
     get_associated_token_address, instruction::create_associated_token_account_idempotent,
 };
-use tokio::sync::Mutex;
 
 use crate::constants::*;
 
-use super::{tx_common::SendSmartTx, PumpFunTx};
+use super::{
+    price_impact::constant_product_price_impact, tx_common::SendSmartTx, AccountDecoder,
+    ParsedTrade, PumpFunBondingCurve, PumpFunTx, QuoteDetails, Swap, SwapQuote, TradeSide, Venue,
+    VenueContext,
+};
+
+/// The bonding curve part of a Pump.fun quote
+#[derive(Debug, Clone)]
+pub struct PumpFunQuote {
+    pub associated_bonding_curve: Pubkey,
+    pub curve: PumpFunBondingCurve,
+}
 
 pub struct PumpFunTxSend {}
 
 impl SendSmartTx for PumpFunTxSend {}
 
 impl PumpFunTxSend {
-    pub async fn buy(
-        helius: Arc<Mutex<Helius>>,
-        signer_prv_key: Arc<String>,
-        bonding_curve: Pubkey,
-        associated_bonding_curve: Pubkey,
-        mint: Pubkey,
-        decimals: u32,
-        price_in_sol_lamports: f64,
-        max_sol_lamports: u64,
-        slippage_percent: u64,
-    ) -> Result<String, Box<dyn Error + Send + Sync>> {
-        let signer = Keypair::from_base58_string(signer_prv_key.as_str());
-        let signer_pub_key = signer.try_pubkey().unwrap();
-        let user_ata = get_associated_token_address(&signer_pub_key, &mint);
-
-        // Prepare buy instruction data
-        let mut data: Vec<u8> = PUMP_FUN_ACTION_BUY.to_vec();
-        let amount = (max_sol_lamports as f64 / price_in_sol_lamports) as u64;
-        let amount_with_decimals = amount * 10u64.pow(decimals as u32);
-        println!(
-            "Amount: {:?}, Amount with decimals: {:?}",
-            amount, amount_with_decimals
-        );
-        data.extend_from_slice(&amount_with_decimals.to_le_bytes());
-
-        let fees = (max_sol_lamports as f64 * PUMP_FUN_FEES).round() as u64;
-        let slippage = (max_sol_lamports as f64 * slippage_percent as f64 / 100.0).round() as u64;
-        let final_max_sol_buy = max_sol_lamports + slippage + fees;
-        println!("Final max sol buy: {:?}", final_max_sol_buy);
-        data.extend_from_slice(&final_max_sol_buy.to_le_bytes());
-
-        Self::send_pump_fun_tx(
-            helius,
-            signer_prv_key,
-            mint,
-            bonding_curve,
-            associated_bonding_curve,
-            user_ata,
-            data,
-            true, // is_buy
-        )
-        .await
-    }
-
-    async fn send_pump_fun_tx(
-        helius: Arc<Mutex<Helius>>,
-        signer_prv_key: Arc<String>,
-        mint: Pubkey,
-        bonding_curve: Pubkey,
-        associated_bonding_curve: Pubkey,
-        user_ata: Pubkey,
+    /// Builds the bonding curve buy or sell instruction, preceded on a buy
+    /// by the creation of the token ATA.
+    ///
+    /// * `owner` - The wallet swapping
+    /// * `mint` - The token traded on the curve
+    /// * `bonding_curve` - The bonding curve of the mint
+    /// * `associated_bonding_curve` - The token account of the bonding curve
+    /// * `user_ata` - The token account of the wallet
+    /// * `data` - The buy or sell instruction data
+    /// * `is_buy` - The swap direction
+    fn swap_instructions(
+        owner: &Pubkey,
+        mint: &Pubkey,
+        bonding_curve: &Pubkey,
+        associated_bonding_curve: &Pubkey,
+        user_ata: &Pubkey,
         data: Vec<u8>,
         is_buy: bool,
-    ) -> Result<String, Box<dyn Error + Send + Sync>> {
-        let signer = Keypair::from_base58_string(signer_prv_key.as_str());
-        let signer_pub_key = signer.try_pubkey().unwrap();
-
+    ) -> Vec<Instruction> {
         let pump_fun_ix = Instruction {
             program_id: PUMP_FUN_PROGRAM,
             accounts: vec![
                 AccountMeta::new_readonly(PUMP_FUN_GLOBAL, false),
                 AccountMeta::new(PUMP_FUN_FEE_RECIPIENT, false),
-                AccountMeta::new_readonly(mint, false),
-                AccountMeta::new(bonding_curve, false),
-                AccountMeta::new(associated_bonding_curve, false),
-                AccountMeta::new(user_ata, false),
-                AccountMeta::new(signer_pub_key, true),
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new(*bonding_curve, false),
+                AccountMeta::new(*associated_bonding_curve, false),
+                AccountMeta::new(*user_ata, false),
+                AccountMeta::new(*owner, true),
                 AccountMeta::new_readonly(system_program::id(), false),
                 AccountMeta::new_readonly(
                     if is_buy {
@@ -112,113 +81,145 @@ impl PumpFunTxSend {
             data,
         };
 
-        let create_ata_ix = if is_buy {
-            Some(create_associated_token_account_idempotent(
-                &signer_pub_key,
-                &signer_pub_key,
-                &mint,
-                &spl_token::id(),
-            ))
+        if is_buy {
+            vec![
+                create_associated_token_account_idempotent(owner, owner, mint, &spl_token::id()),
+                pump_fun_ix,
+            ]
         } else {
-            None
-        };
+            vec![pump_fun_ix]
+        }
+    }
+}
+
+impl Swap for PumpFunTxSend {
+    const VENUE: Venue = Venue::PumpFun;
+    const COMPUTE_UNIT_LIMIT: u32 = DEFAULT_COMPUTE_UNIT_LIMIT;
 
-        let instructions = match create_ata_ix {
-            Some(create_ata_ix) => vec![create_ata_ix, pump_fun_ix],
-            None => vec![pump_fun_ix],
+    /// Every mint has a single bonding curve, derived from it
+    async fn find_pool(
+        _ctx: &VenueContext,
+        mint: &Pubkey,
+    ) -> Result<Pubkey, Box<dyn Error + Send + Sync>> {
+        Ok(PumpFunBondingCurve::address(mint))
+    }
+
+    /// Buys are quoted fees excluded, the fees being paid on top of the
+    /// amount in, sells are quoted net of fees.
+    async fn quote_pool(
+        ctx: &VenueContext,
+        pool_id: &Pubkey,
+        mint: &Pubkey,
+        side: TradeSide,
+        amount: u64,
+    ) -> Result<SwapQuote, Box<dyn Error + Send + Sync>> {
+        let account = {
+            let connection = ctx.helius.lock().await.async_connection()?;
+            connection.get_account(pool_id).await?
+        };
+        let curve = *PumpFunBondingCurve::decode_account(&account)?;
+        if curve.is_complete() {
+            return Err("The bonding curve is complete, the token migrated".into());
+        }
+
+        let reserve_in = match side {
+            TradeSide::Buy => curve.virtual_sol_reserves,
+            TradeSide::Sell => curve.virtual_token_reserves,
+        };
+        let amount_out = match side {
+            TradeSide::Buy => curve.quote_buy(amount),
+            TradeSide::Sell => {
+                let sol_amount = curve.quote_sell(amount);
+                sol_amount.saturating_sub((sol_amount as f64 * PUMP_FUN_FEES).round() as u64)
+            }
         };
 
-        Self::bfg9000_send_smart_tx(
-            helius,
-            instructions,
-            Some(DEFAULT_COMPUTE_UNIT_LIMIT), // You'll need to define this constant
-            None,
-            signer_prv_key.as_str().to_string(),
-            None, // You might want to pass this as a parameter
-        )
-        .await
+        Ok(SwapQuote {
+            venue: Self::VENUE,
+            pool_id: *pool_id,
+            mint: *mint,
+            side,
+            amount_in: amount,
+            amount_out,
+            creator: PumpFunBondingCurve::creator(&account.data),
+            quote_liquidity: curve.real_sol_reserves,
+            pool_open_time: None,
+            price_impact: constant_product_price_impact(amount, reserve_in),
+            details: QuoteDetails::PumpFun(PumpFunQuote {
+                associated_bonding_curve: get_associated_token_address(pool_id, mint),
+                curve,
+            }),
+        })
     }
 
-    pub async fn compose_and_send(
-        helius: Arc<Mutex<Helius>>,
-        signer_prv_key: Arc<String>,
-        max_sol_buy: u64,
+    async fn build_swap(
+        _ctx: &VenueContext,
+        quote: &SwapQuote,
+        wallet: &Pubkey,
         slippage_percent: u64,
-        payload: String,
-    ) -> Result<String, Box<dyn Error + Send + Sync>> {
-        let pump_fun_tx = PumpFunTx::new(payload);
-
-        let accounts = pump_fun_tx
-            .accounts
-            .as_ref()
-            .ok_or("Unknown tx by meme accounts")?;
-
-        let mint = Pubkey::from_str_const(accounts.mint.as_str());
-        let bonding_curve = Pubkey::from_str_const(accounts.bonding_curve.as_str());
-        let associated_bonding_curve =
-            Pubkey::from_str_const(accounts.associated_bonding_curve.as_str());
-
-        let signer = Keypair::from_base58_string(signer_prv_key.as_str());
-        let signer_pub_key = signer.try_pubkey().unwrap();
-        let user_ata = get_associated_token_address(&signer_pub_key, &mint);
-
-        let ix_data = pump_fun_tx
-            .ix_data
-            .as_ref()
-            .ok_or("Unknown tx by instruction data")?;
-
-        let mut data = ix_data.instruction.clone();
-        let is_buy = match ix_data.instruction.as_slice() {
-            PUMP_FUN_ACTION_BUY => {
-                let inner_ix_data = pump_fun_tx
-                    .inner_ix_data
-                    .as_ref()
-                    .ok_or("Unknown tx by inner instructions")?;
-
-                let copied_amount = inner_ix_data.amount as f64;
-                let copied_sol = inner_ix_data.sol as f64;
-                let computed_price = copied_sol / copied_amount;
-                let amount = (max_sol_buy as f64 / computed_price) as u64;
-
-                data.extend_from_slice(&amount.to_le_bytes());
-
-                let fees = (max_sol_buy as f64 * PUMP_FUN_FEES).round() as u64;
-                let slippage =
-                    (max_sol_buy as f64 * slippage_percent as f64 / 100.0).round() as u64;
-                let final_max_sol_buy = max_sol_buy + slippage + fees;
-                data.extend_from_slice(&final_max_sol_buy.to_le_bytes());
-                true
-            }
-            PUMP_FUN_ACTION_SELL => {
-                let copied_amount = ix_data.amount as f64;
-                let copied_min_sol_ouput = ix_data.sol as f64;
-                let amount = copied_amount as u64;
-
-                data.extend_from_slice(&amount.to_le_bytes());
-
-                let min_sol_output =
-                    (amount as f64 * (copied_min_sol_ouput / copied_amount)).round() as u64;
-                let fees = (min_sol_output as f64 * PUMP_FUN_FEES).round() as u64;
-                let slippage =
-                    (min_sol_output as f64 * slippage_percent as f64 / 100.0).round() as u64;
-                let final_min_sol_output = min_sol_output - slippage - fees;
-
-                data.extend_from_slice(&final_min_sol_output.to_le_bytes());
-                false
-            }
-            _ => return Err("Unknown instruction".into()),
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let QuoteDetails::PumpFun(details) = &quote.details else {
+            return Err("Not a Pump.fun quote".into());
         };
 
-        Self::send_pump_fun_tx(
-            helius,
-            signer_prv_key,
-            mint,
-            bonding_curve,
-            associated_bonding_curve,
-            user_ata,
+        let (mut data, is_buy) = match quote.side {
+            TradeSide::Buy => (PUMP_FUN_ACTION_BUY.to_vec(), true),
+            TradeSide::Sell => (PUMP_FUN_ACTION_SELL.to_vec(), false),
+        };
+        if is_buy {
+            // Exact tokens out, the SOL cost may grow by the slippage and fees
+            let fees = (quote.amount_in as f64 * PUMP_FUN_FEES).round() as u64;
+            let slippage =
+                (quote.amount_in as f64 * slippage_percent as f64 / 100.0).round() as u64;
+            data.extend_from_slice(&quote.amount_out.to_le_bytes());
+            data.extend_from_slice(&(quote.amount_in + slippage + fees).to_le_bytes());
+        } else {
+            let min_sol_output = (quote.amount_out as f64 * (100.0 - slippage_percent as f64)
+                / 100.0)
+                .floor() as u64;
+            data.extend_from_slice(&quote.amount_in.to_le_bytes());
+            data.extend_from_slice(&min_sol_output.to_le_bytes());
+        }
+
+        Ok(Self::swap_instructions(
+            wallet,
+            &quote.mint,
+            &quote.pool_id,
+            &details.associated_bonding_curve,
+            &get_associated_token_address(wallet, &quote.mint),
             data,
             is_buy,
-        )
-        .await
+        ))
+    }
+
+    async fn parse_trade(_ctx: &VenueContext, payload: &str) -> Option<ParsedTrade> {
+        let pump_fun_tx = PumpFunTx::new(payload.to_string());
+        let accounts = pump_fun_tx.accounts.as_ref()?;
+        let ix_data = pump_fun_tx.ix_data.as_ref()?;
+
+        let side = match ix_data.instruction.as_slice() {
+            PUMP_FUN_ACTION_BUY => TradeSide::Buy,
+            PUMP_FUN_ACTION_SELL => TradeSide::Sell,
+            _ => return None,
+        };
+
+        // Without a trade event, fall back on the instruction limits
+        let (token_amount, sol_amount) = match &pump_fun_tx.inner_ix_data {
+            Some(inner_ix_data) if inner_ix_data.amount > 0 => {
+                (inner_ix_data.amount, inner_ix_data.sol)
+            }
+            _ => (ix_data.amount, ix_data.sol),
+        };
+
+        Some(ParsedTrade {
+            venue: Self::VENUE,
+            signature: pump_fun_tx.signature.clone(),
+            side,
+            mint: accounts.mint.parse().ok()?,
+            pool_id: accounts.bonding_curve.parse().ok()?,
+            sol_amount,
+            token_amount,
+            compute_unit_limit: pump_fun_tx.compute_unit_limit,
+        })
     }
 }
@@ -0,0 +1,330 @@
+use bytemuck::{Pod, Zeroable};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::constants::{
+    PUMP_FUN_PROGRAM, PUMP_SWAP_CREATOR_VAULT_SEED, PUMP_SWAP_FEE_BASIS_POINTS_DENOMINATOR,
+    PUMP_SWAP_GLOBAL_CONFIG_DISCRIMINATOR, PUMP_SWAP_POOL_AUTHORITY_SEED,
+    PUMP_SWAP_POOL_DISCRIMINATOR, PUMP_SWAP_POOL_SEED, PUMP_SWAP_PROGRAM, WSOL_MINT,
+};
+
+use super::AccountDecoder;
+
+/// PumpSwap constant product pool, where graduated Pump.fun tokens migrate to.
+/// The base side is the token, the quote side WSOL for migrated pools.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PumpSwapPool {
+    pub pool_bump: u8,
+    pub index: u16,
+    pub creator: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub pool_base_token_account: Pubkey,
+    pub pool_quote_token_account: Pubkey,
+    pub lp_supply: u64,
+}
+
+/// PumpSwap global configuration, the fees and the protocol fee recipients
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PumpSwapGlobalConfig {
+    pub admin: Pubkey,
+    pub lp_fee_basis_points: u64,
+    pub protocol_fee_basis_points: u64,
+    /// Bits: create pool, deposit, withdraw, buy, sell
+    pub disable_flags: u8,
+    pub protocol_fee_recipients: [Pubkey; 8],
+}
+
+// Newer versions of the program append the coin creator and its fee
+impl AccountDecoder for PumpSwapPool {
+    const OWNERS: &'static [Pubkey] = &[PUMP_SWAP_PROGRAM];
+    const HEAD: &'static [u8] = PUMP_SWAP_POOL_DISCRIMINATOR;
+    const EXTENDABLE: bool = true;
+}
+
+impl AccountDecoder for PumpSwapGlobalConfig {
+    const OWNERS: &'static [Pubkey] = &[PUMP_SWAP_PROGRAM];
+    const HEAD: &'static [u8] = PUMP_SWAP_GLOBAL_CONFIG_DISCRIMINATOR;
+    const EXTENDABLE: bool = true;
+}
+
+/// `PumpSwapGlobalConfig::disable_flags` bits of the swaps
+const PUMP_SWAP_DISABLE_BUY: u8 = 1 << 3;
+const PUMP_SWAP_DISABLE_SELL: u8 = 1 << 4;
+
+/// Reads the u64 right after the state of an extendable account
+fn appended_u64<T: AccountDecoder>(data: &[u8]) -> Option<u64> {
+    let offset = T::HEAD.len() + std::mem::size_of::<T>();
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+/// Reads the pubkey right after the state of an extendable account
+fn appended_pubkey<T: AccountDecoder>(data: &[u8]) -> Option<Pubkey> {
+    let offset = T::HEAD.len() + std::mem::size_of::<T>();
+    Pubkey::try_from(data.get(offset..offset + 32)?).ok()
+}
+
+impl PumpSwapPool {
+    /// Derives the address of a pool
+    ///
+    /// * `index` - The pool index of the creator
+    /// * `creator` - The pool creator
+    /// * `base_mint` - The base mint
+    /// * `quote_mint` - The quote mint
+    pub fn address(
+        index: u16,
+        creator: &Pubkey,
+        base_mint: &Pubkey,
+        quote_mint: &Pubkey,
+    ) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                PUMP_SWAP_POOL_SEED,
+                &index.to_le_bytes(),
+                creator.as_ref(),
+                base_mint.as_ref(),
+                quote_mint.as_ref(),
+            ],
+            &PUMP_SWAP_PROGRAM,
+        )
+        .0
+    }
+
+    /// Derives the pool a graduated Pump.fun token migrated to: the first pool
+    /// of the Pump.fun pool authority of the mint, paired with WSOL
+    ///
+    /// * `mint` - The graduated token
+    pub fn canonical_address(mint: &Pubkey) -> Pubkey {
+        let pool_authority = Pubkey::find_program_address(
+            &[PUMP_SWAP_POOL_AUTHORITY_SEED, mint.as_ref()],
+            &PUMP_FUN_PROGRAM,
+        )
+        .0;
+
+        Self::address(0, &pool_authority, mint, &WSOL_MINT)
+    }
+
+    /// Reads the coin creator from the raw account data, None for pools
+    /// created before the program stored it or without a creator
+    ///
+    /// * `data` - The raw pool account data
+    pub fn coin_creator(data: &[u8]) -> Option<Pubkey> {
+        appended_pubkey::<Self>(data).filter(|coin_creator| *coin_creator != Pubkey::default())
+    }
+
+    /// Derives the authority of the vault collecting the coin creator fees
+    ///
+    /// * `coin_creator` - The coin creator of the pool
+    pub fn creator_vault_authority(coin_creator: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[PUMP_SWAP_CREATOR_VAULT_SEED, coin_creator.as_ref()],
+            &PUMP_SWAP_PROGRAM,
+        )
+        .0
+    }
+
+    /// Base tokens received for `quote_in`, fees included in `quote_in`
+    ///
+    /// * `quote_in` - The quote amount spent, fees included
+    /// * `base_reserve` - The pool base reserve
+    /// * `quote_reserve` - The pool quote reserve
+    /// * `fee_basis_points` - The total fee charged on the quote side
+    pub fn quote_buy(
+        quote_in: u64,
+        base_reserve: u64,
+        quote_reserve: u64,
+        fee_basis_points: u64,
+    ) -> u64 {
+        // The fees are charged on top of the amount swapped
+        let quote_swapped = quote_in as u128 * PUMP_SWAP_FEE_BASIS_POINTS_DENOMINATOR as u128
+            / (PUMP_SWAP_FEE_BASIS_POINTS_DENOMINATOR + fee_basis_points) as u128;
+
+        let denominator = quote_reserve as u128 + quote_swapped;
+        if denominator == 0 {
+            return 0;
+        }
+
+        (base_reserve as u128 * quote_swapped / denominator) as u64
+    }
+
+    /// Quote received for `base_in`, fees deducted
+    ///
+    /// * `base_in` - The base amount sold
+    /// * `base_reserve` - The pool base reserve
+    /// * `quote_reserve` - The pool quote reserve
+    /// * `fee_basis_points` - The total fee charged on the quote side
+    pub fn quote_sell(
+        base_in: u64,
+        base_reserve: u64,
+        quote_reserve: u64,
+        fee_basis_points: u64,
+    ) -> u64 {
+        let denominator = base_reserve as u128 + base_in as u128;
+        if denominator == 0 {
+            return 0;
+        }
+
+        let quote_out = quote_reserve as u128 * base_in as u128 / denominator;
+        let fees = (quote_out * fee_basis_points as u128)
+            .div_ceil(PUMP_SWAP_FEE_BASIS_POINTS_DENOMINATOR as u128);
+
+        quote_out.saturating_sub(fees) as u64
+    }
+}
+
+impl PumpSwapGlobalConfig {
+    /// Reads the coin creator fee from the raw account data,
+    /// 0 for accounts created before the program charged it
+    ///
+    /// * `data` - The raw global config account data
+    pub fn coin_creator_fee_basis_points(data: &[u8]) -> u64 {
+        appended_u64::<Self>(data).unwrap_or(0)
+    }
+
+    /// Returns the total fee of a swap, the coin creator fee only applies
+    /// to pools with a coin creator
+    ///
+    /// * `coin_creator_fee_basis_points` - The coin creator fee, 0 without a coin creator
+    pub fn fee_basis_points(&self, coin_creator_fee_basis_points: u64) -> u64 {
+        let (lp_fee_basis_points, protocol_fee_basis_points) =
+            (self.lp_fee_basis_points, self.protocol_fee_basis_points);
+
+        lp_fee_basis_points + protocol_fee_basis_points + coin_creator_fee_basis_points
+    }
+
+    /// Returns true if the admin disabled buys, or sells
+    ///
+    /// * `is_buy` - The swap direction
+    pub fn is_swap_disabled(&self, is_buy: bool) -> bool {
+        let flag = if is_buy {
+            PUMP_SWAP_DISABLE_BUY
+        } else {
+            PUMP_SWAP_DISABLE_SELL
+        };
+
+        self.disable_flags & flag != 0
+    }
+
+    /// Returns the first protocol fee recipient set
+    pub fn protocol_fee_recipient(&self) -> Option<Pubkey> {
+        let protocol_fee_recipients = self.protocol_fee_recipients;
+
+        protocol_fee_recipients
+            .into_iter()
+            .find(|recipient| *recipient != Pubkey::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds pool bytes with the given mints and vaults, the rest zeroed
+    fn pool_bytes(base_mint: &Pubkey, pool_base_token_account: &Pubkey) -> Vec<u8> {
+        let mut data = PUMP_SWAP_POOL_DISCRIMINATOR.to_vec();
+        // bump, index, creator
+        data.resize(data.len() + 1 + 2 + 32, 0);
+        data.extend_from_slice(base_mint.as_ref());
+        data.extend_from_slice(WSOL_MINT.as_ref());
+        // lp mint
+        data.resize(data.len() + 32, 0);
+        data.extend_from_slice(pool_base_token_account.as_ref());
+        // pool quote token account, lp supply
+        data.resize(data.len() + 32 + 8, 0);
+        data
+    }
+
+    #[test]
+    fn test_decode_pool() {
+        let base_mint = Pubkey::new_unique();
+        let pool_base_token_account = Pubkey::new_unique();
+        let data = pool_bytes(&base_mint, &pool_base_token_account);
+        assert_eq!(data.len(), 211);
+
+        let pool = PumpSwapPool::decode(&data).unwrap();
+        assert_eq!({ pool.base_mint }, base_mint);
+        assert_eq!({ pool.quote_mint }, WSOL_MINT);
+        assert_eq!({ pool.pool_base_token_account }, pool_base_token_account);
+        assert_eq!(PumpSwapPool::coin_creator(&data), None);
+
+        // Pools created by newer versions carry the coin creator
+        let coin_creator = Pubkey::new_unique();
+        let mut with_creator = data.clone();
+        with_creator.extend_from_slice(coin_creator.as_ref());
+        assert_eq!(PumpSwapPool::decode(&with_creator), Ok(pool));
+        assert_eq!(
+            PumpSwapPool::coin_creator(&with_creator),
+            Some(coin_creator)
+        );
+
+        assert!(PumpSwapPool::decode(&data[..210]).is_err());
+        assert!(PumpSwapGlobalConfig::decode(&data).is_err());
+    }
+
+    #[test]
+    fn test_decode_global_config() {
+        let recipient = Pubkey::new_unique();
+        let mut data = PUMP_SWAP_GLOBAL_CONFIG_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(&20u64.to_le_bytes());
+        data.extend_from_slice(&5u64.to_le_bytes());
+        data.push(PUMP_SWAP_DISABLE_SELL);
+        data.extend_from_slice(recipient.as_ref());
+        data.resize(data.len() + 32 * 7, 0);
+
+        let global_config = PumpSwapGlobalConfig::decode(&data).unwrap();
+        assert_eq!(global_config.protocol_fee_recipient(), Some(recipient));
+        assert!(!global_config.is_swap_disabled(true));
+        assert!(global_config.is_swap_disabled(false));
+        assert_eq!(
+            PumpSwapGlobalConfig::coin_creator_fee_basis_points(&data),
+            0
+        );
+        assert_eq!(global_config.fee_basis_points(0), 25);
+
+        data.extend_from_slice(&5u64.to_le_bytes());
+        assert_eq!(
+            PumpSwapGlobalConfig::coin_creator_fee_basis_points(&data),
+            5
+        );
+    }
+
+    #[test]
+    fn test_quotes() {
+        // 1_000 base against 100 quote, no fee: 10 quote buy 90 base
+        assert_eq!(PumpSwapPool::quote_buy(10, 1_000, 100, 0), 90);
+        // A 25 bps fee is taken out of the quote spent
+        assert_eq!(
+            PumpSwapPool::quote_buy(1_002_500, 1_000_000_000, 100_000_000, 25),
+            9_900_990
+        );
+
+        assert_eq!(PumpSwapPool::quote_sell(100, 900, 110, 0), 11);
+        assert_eq!(
+            PumpSwapPool::quote_sell(10_000_000, 990_000_000, 101_000_000, 25),
+            1_007_475
+        );
+
+        assert_eq!(PumpSwapPool::quote_buy(10, 0, 0, 0), 0);
+        assert_eq!(PumpSwapPool::quote_sell(0, 0, 0, 0), 0);
+    }
+
+    #[test]
+    fn test_canonical_address() {
+        let mint = Pubkey::new_unique();
+        let pool_authority = Pubkey::find_program_address(
+            &[PUMP_SWAP_POOL_AUTHORITY_SEED, mint.as_ref()],
+            &PUMP_FUN_PROGRAM,
+        )
+        .0;
+
+        assert_eq!(
+            PumpSwapPool::canonical_address(&mint),
+            PumpSwapPool::address(0, &pool_authority, &mint, &WSOL_MINT)
+        );
+    }
+}
@@ -0,0 +1,379 @@
+//! Given a transaction having among it's instructions a PumpSwap buy or sell,
+//! this module extracts the swap accounts, amounts and direction.
+//!
+//! PumpSwap is the Pump.fun AMM graduated tokens migrate to. Its buy/sell
+//! instructions mirror the bonding curve ones, so the parsed transaction
+//! reuses the Pump.fun `IxData` / `InnerIxData`: `amount` is the base (token)
+//! side and `sol` the quote (WSOL) side.
+
+use serde::Serialize;
+use serde_json::{from_str, Value};
+
+use crate::bot::tx_common::GetSignature;
+use crate::constants::{
+    PUMP_SWAP_ACCOUNTS_LEN_SWAP, PUMP_SWAP_ACTION_BUY, PUMP_SWAP_ACTION_SELL, PUMP_SWAP_PROGRAM,
+    SPL_TOKEN_2022_PROGRAM, WSOL_MINT,
+};
+
+use super::pump_fun_tx::{InnerIxData, IxData};
+use super::tx_common::{GetComputeData, GetTxCommon};
+
+/// PumpSwap buy/sell instruction accounts, both instructions share the same list
+/// Based on https://github.com/pump-fun/pump-public-docs/blob/main/docs/PUMP_SWAP_README.md
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PumpSwapAccounts {
+    pub pool: String,
+    pub user: String,
+    pub base_mint: String,
+    pub quote_mint: String,
+    pub user_base_token_account: String,
+    pub user_quote_token_account: String,
+    pub pool_base_token_account: String,
+    pub pool_quote_token_account: String,
+    pub protocol_fee_recipient: String,
+    pub protocol_fee_recipient_token_account: String,
+    pub base_token_program: String,
+    pub quote_token_program: String,
+    /// Only listed by pools with a coin creator
+    pub coin_creator_vault_ata: Option<String>,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct PumpSwapTx {
+    pub signature: Option<String>,
+    pub accounts: Option<PumpSwapAccounts>,
+    pub ix_data: Option<IxData>,
+    pub inner_ix_data: Option<InnerIxData>,
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64,
+}
+
+impl GetSignature for PumpSwapTx {}
+
+impl GetComputeData for PumpSwapTx {}
+
+impl GetTxCommon for PumpSwapTx {}
+
+impl PumpSwapTx {
+    /// Creates a new PumpSwapTx instance from a transaction payload.
+    ///
+    /// * `payload` - The transaction payload
+    pub fn new(payload: String) -> Self {
+        let json: Value = match from_str(payload.as_str()) {
+            Ok(json) => json,
+            Err(_) => return Self::default(),
+        };
+
+        let signature = match Self::get_signature(&json) {
+            Some(signature) => signature,
+            None => return Self::default(),
+        };
+
+        let swap_ix = match Self::get_pump_swap_instruction(&json) {
+            Some(swap_ix) => swap_ix,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let accounts = match Self::get_accounts(swap_ix) {
+            Some(accounts) => accounts,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let ix_data = match swap_ix["data"]
+            .as_str()
+            .and_then(Self::get_instruction_data)
+        {
+            Some(ix_data) => ix_data,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    accounts: Some(accounts),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let inner_ix_data = match Self::get_amounts(&json, &accounts) {
+            Some((amount, sol, fee)) => InnerIxData { amount, sol, fee },
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    accounts: Some(accounts),
+                    ix_data: Some(ix_data),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let (compute_unit_limit, compute_unit_price) =
+            Self::get_compute_data(&json).unwrap_or((0, 0));
+
+        Self {
+            signature: Some(signature),
+            accounts: Some(accounts),
+            ix_data: Some(ix_data),
+            inner_ix_data: Some(inner_ix_data),
+            compute_unit_limit,
+            compute_unit_price,
+        }
+    }
+
+    /// Returns true for a buy, false for a sell, None if the swap wasn't parsed
+    pub fn is_buy(&self) -> Option<bool> {
+        let ix_data = self.ix_data.as_ref()?;
+        Some(ix_data.instruction.as_slice() == PUMP_SWAP_ACTION_BUY)
+    }
+
+    /// Returns true if the pool is SOL paired, the only pools the bot trades
+    pub fn is_sol_paired(&self) -> bool {
+        self.accounts
+            .as_ref()
+            .is_some_and(|accounts| accounts.quote_mint == WSOL_MINT.to_string())
+    }
+
+    /// Finds the PumpSwap buy/sell instruction in the transaction,
+    /// either as a main instruction or as an inner instruction.
+    fn get_pump_swap_instruction(json: &Value) -> Option<&Value> {
+        let is_pump_swap = |instruction: &Value| {
+            instruction["programId"]
+                .as_str()
+                .map_or(false, |id| id == PUMP_SWAP_PROGRAM.to_string())
+                && instruction["accounts"]
+                    .as_array()
+                    .map_or(false, |accounts| {
+                        accounts.len() >= PUMP_SWAP_ACCOUNTS_LEN_SWAP
+                    })
+        };
+
+        // Check main instructions
+        let instructions = json["params"]["result"]["transaction"]["transaction"]["message"]
+            ["instructions"]
+            .as_array()?;
+
+        if let Some(instruction) = instructions.iter().find(|&ix| is_pump_swap(ix)) {
+            return Some(instruction);
+        }
+
+        // Check inner instructions
+        json["params"]["result"]["transaction"]["meta"]["innerInstructions"]
+            .as_array()?
+            .iter()
+            .find_map(|inner_ix_group| {
+                inner_ix_group["instructions"]
+                    .as_array()?
+                    .iter()
+                    .find(|&ix| is_pump_swap(ix))
+            })
+    }
+
+    /// Extracts the PumpSwap swap accounts
+    ///
+    /// * `swap_instruction` - The PumpSwap buy/sell instruction
+    fn get_accounts(swap_instruction: &Value) -> Option<PumpSwapAccounts> {
+        let accounts = swap_instruction["accounts"].as_array()?;
+
+        if accounts.len() < PUMP_SWAP_ACCOUNTS_LEN_SWAP {
+            return None;
+        }
+
+        Some(PumpSwapAccounts {
+            pool: Self::pubkey_to_string(&accounts[0]),
+            user: Self::pubkey_to_string(&accounts[1]),
+            // account[2] - Global config
+            base_mint: Self::pubkey_to_string(&accounts[3]),
+            quote_mint: Self::pubkey_to_string(&accounts[4]),
+            user_base_token_account: Self::pubkey_to_string(&accounts[5]),
+            user_quote_token_account: Self::pubkey_to_string(&accounts[6]),
+            pool_base_token_account: Self::pubkey_to_string(&accounts[7]),
+            pool_quote_token_account: Self::pubkey_to_string(&accounts[8]),
+            protocol_fee_recipient: Self::pubkey_to_string(&accounts[9]),
+            protocol_fee_recipient_token_account: Self::pubkey_to_string(&accounts[10]),
+            base_token_program: Self::pubkey_to_string(&accounts[11]),
+            quote_token_program: Self::pubkey_to_string(&accounts[12]),
+            // account[13..17] - System program, ATA program, event authority, PumpSwap program
+            coin_creator_vault_ata: accounts
+                .get(PUMP_SWAP_ACCOUNTS_LEN_SWAP)
+                .map(Self::pubkey_to_string),
+        })
+    }
+
+    /// Extracts the data from the PumpSwap instruction:
+    /// buy(base_amount_out, max_quote_amount_in) or sell(base_amount_in, min_quote_amount_out)
+    ///
+    /// * `input` - The base58 instruction data
+    fn get_instruction_data(input: &str) -> Option<IxData> {
+        let input = bs58::decode(input).into_vec().ok()?;
+
+        if input.len() < 24 {
+            return None;
+        }
+
+        let instruction_name = match &input[..8] {
+            PUMP_SWAP_ACTION_BUY => "buy",
+            PUMP_SWAP_ACTION_SELL => "sell",
+            _ => return None,
+        }
+        .to_string();
+
+        Some(IxData {
+            instruction: input[..8].to_vec(),
+            instruction_name,
+            amount: u64::from_le_bytes(input[8..16].try_into().ok()?),
+            sol: u64::from_le_bytes(input[16..24].try_into().ok()?),
+        })
+    }
+
+    /// Extracts the exact amounts swapped from the inner token transfers:
+    /// the base moved in or out of the user, the quote swapped with the pool
+    /// and the quote fees paid to the protocol and the coin creator.
+    ///
+    /// * `json` - The transaction JSON
+    /// * `accounts` - The PumpSwap swap accounts
+    fn get_amounts(json: &Value, accounts: &PumpSwapAccounts) -> Option<(u64, u64, u64)> {
+        let inner_instructions =
+            json["params"]["result"]["transaction"]["meta"]["innerInstructions"].as_array()?;
+
+        let token_programs = [
+            spl_token::id().to_string(),
+            SPL_TOKEN_2022_PROGRAM.to_string(),
+        ];
+        let fee_accounts = [
+            Some(accounts.protocol_fee_recipient_token_account.as_str()),
+            accounts.coin_creator_vault_ata.as_deref(),
+        ];
+        let user_base = Some(accounts.user_base_token_account.as_str());
+        let user_quote = Some(accounts.user_quote_token_account.as_str());
+        let pool_quote = Some(accounts.pool_quote_token_account.as_str());
+
+        let default_array: Vec<Value> = Vec::new();
+        let amounts = inner_instructions
+            .iter()
+            .flat_map(|inner_ix| {
+                inner_ix["instructions"]
+                    .as_array()
+                    .unwrap_or(&default_array)
+            })
+            .filter(|ix| {
+                ix["programId"].as_str().map_or(false, |id| {
+                    token_programs.iter().any(|program| program == id)
+                })
+            })
+            .filter_map(|ix| {
+                let info = &ix["parsed"]["info"];
+                let amount = info["amount"]
+                    .as_str()
+                    .or_else(|| info["tokenAmount"]["amount"].as_str())?
+                    .parse::<u64>()
+                    .ok()?;
+                let (source, destination) = (info["source"].as_str(), info["destination"].as_str());
+
+                if source == user_base || destination == user_base {
+                    Some((amount, 0, 0))
+                } else if fee_accounts.contains(&destination) {
+                    Some((0, 0, amount))
+                } else if (source == user_quote && destination == pool_quote)
+                    || (source == pool_quote && destination == user_quote)
+                {
+                    Some((0, amount, 0))
+                } else {
+                    None
+                }
+            })
+            .fold((0, 0, 0), |(amount, sol, fee), (a, s, f)| {
+                (amount + a, sol + s, fee + f)
+            });
+
+        Some(amounts)
+    }
+
+    /// Returns a formatted string containing transaction details
+    pub fn format_tx_info(&self) -> String {
+        if let (Some(accounts), Some(is_buy), Some(inner_ix), Some(sig)) = (
+            &self.accounts,
+            self.is_buy(),
+            &self.inner_ix_data,
+            &self.signature,
+        ) {
+            format!(
+                "PumpSwap {} | Mint: {} | Amount: {} | SOL: {} | Fees: {} | TX: https://solscan.io/tx/{}",
+                if is_buy { "Buy" } else { "Sell" },
+                accounts.base_mint,
+                inner_ix.amount,
+                inner_ix.sol,
+                inner_ix.fee,
+                sig
+            )
+        } else {
+            "PumpSwapTx: Error formatting transaction data".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Synthetic PumpSwap buy of a pool with a coin creator:
+    // 3_500_000_000_000 tokens for 0.1 WSOL, plus 0.00005 WSOL to the protocol
+    // and 0.00005 WSOL to the coin creator
+    const TEST_JSON_PUMP_SWAP_BUY: &str = r#"{"jsonrpc":"2.0","method":"transactionNotification","params":{"subscription":1,"result":{"transaction":{"transaction":{"signatures":["3WkZ8yQx1rUbn5VmehgVvQEG5hyEJGCbAACVofCc7YwB5P2MMmoYa1QZn5VmehgVvQEG5hyEJGCbAACVofCc7Yw"],"message":{"instructions":[{"programId":"ComputeBudget111111111111111111111111111111","accounts":[],"data":"JxrTou","stackHeight":null},{"programId":"pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA","accounts":["FxTgR9wajzE9Zwkba9oYesSqZwMX9E7WvgK37DYVASSa","CgSmYZLizTfb7inUbn1pNjn15pE8axadGZ4iaiZk7Dyt","ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt","FWv5hiQqoUahjMyRFzz78q5ajmtwZ9vrn8tytgdFpump","So11111111111111111111111111111111111111112","B5P2MMmoYa1QZn5VmehgVvQEG5hyEJGCbAACVofCc7Yw","98xumqLXVoWTQvL8Jo5a6WVjz7NQerTh2EDpMjkRnJJJ","DTcQJkrBAXubbEXH5fSrcfJ51NpPxbtTmKXBu6rH6Faw","JDEEdzkq2A3xn2kYvuXtE7nJEi7ZVk4PcqraFMEUMvuF","6VsE2AxhLfhTdssEwhsbFzbJB3VCaLdANFiF6jTKgAyx","798dKWn6dDpq332yLia4NcE9KDQbTWQT65CLB2n8XDnz","TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","11111111111111111111111111111111","ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL","GS4CU59F31iL7aR2Q8zVS8DRrcRnXX1yjQ66TqNVQnaR","pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA","EfsmYBKyMYjpcfaToeWfVAhChnZx1bCMQPo3YNPFmbeP","9Bv5nX6XEoXh2oWSYgjq4YTvfA4RTSnK9QzCQvwvVhTD"],"data":"AJTQ2h9DXrBdEK3wbWKawwbuCHNBBGw59","stackHeight":null}]}},"meta":{"err":null,"innerInstructions":[{"index":1,"instructions":[{"program":"spl-token","programId":"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","parsed":{"info":{"authority":"FxTgR9wajzE9Zwkba9oYesSqZwMX9E7WvgK37DYVASSa","destination":"B5P2MMmoYa1QZn5VmehgVvQEG5hyEJGCbAACVofCc7Yw","mint":"FWv5hiQqoUahjMyRFzz78q5ajmtwZ9vrn8tytgdFpump","source":"DTcQJkrBAXubbEXH5fSrcfJ51NpPxbtTmKXBu6rH6Faw","tokenAmount":{"amount":"3500000000000","decimals":6}},"type":"transferChecked"},"stackHeight":2},{"program":"spl-token","programId":"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","parsed":{"info":{"authority":"CgSmYZLizTfb7inUbn1pNjn15pE8axadGZ4iaiZk7Dyt","destination":"JDEEdzkq2A3xn2kYvuXtE7nJEi7ZVk4PcqraFMEUMvuF","mint":"So11111111111111111111111111111111111111112","source":"98xumqLXVoWTQvL8Jo5a6WVjz7NQerTh2EDpMjkRnJJJ","tokenAmount":{"amount":"100000000","decimals":9}},"type":"transferChecked"},"stackHeight":2},{"program":"spl-token","programId":"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","parsed":{"info":{"authority":"CgSmYZLizTfb7inUbn1pNjn15pE8axadGZ4iaiZk7Dyt","destination":"798dKWn6dDpq332yLia4NcE9KDQbTWQT65CLB2n8XDnz","mint":"So11111111111111111111111111111111111111112","source":"98xumqLXVoWTQvL8Jo5a6WVjz7NQerTh2EDpMjkRnJJJ","tokenAmount":{"amount":"50000","decimals":9}},"type":"transferChecked"},"stackHeight":2},{"program":"spl-token","programId":"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","parsed":{"info":{"authority":"CgSmYZLizTfb7inUbn1pNjn15pE8axadGZ4iaiZk7Dyt","destination":"EfsmYBKyMYjpcfaToeWfVAhChnZx1bCMQPo3YNPFmbeP","mint":"So11111111111111111111111111111111111111112","source":"98xumqLXVoWTQvL8Jo5a6WVjz7NQerTh2EDpMjkRnJJJ","tokenAmount":{"amount":"50000","decimals":9}},"type":"transferChecked"},"stackHeight":2}]}]},"version":0},"signature":"3WkZ8yQx1rUbn5VmehgVvQEG5hyEJGCbAACVofCc7YwB5P2MMmoYa1QZn5VmehgVvQEG5hyEJGCbAACVofCc7Yw","slot":1}}}"#;
+
+    #[test]
+    fn test_get_instruction_data() {
+        let ix_data =
+            PumpSwapTx::get_instruction_data("AJTQ2h9DXrBdEK3wbWKawwbuCHNBBGw59").unwrap();
+        assert_eq!(ix_data.instruction, PUMP_SWAP_ACTION_BUY);
+        assert_eq!(ix_data.amount, 3_500_000_000_000);
+        assert_eq!(ix_data.sol, 105_000_000);
+
+        let ix_data =
+            PumpSwapTx::get_instruction_data("5jRcjdixRUDEA7vmJR9TqSBfMd9bdQtWj").unwrap();
+        assert_eq!(ix_data.instruction_name, "sell");
+        assert_eq!(ix_data.sol, 95_000_000);
+
+        assert!(PumpSwapTx::get_instruction_data("JxrTou").is_none());
+    }
+
+    #[test]
+    fn test_new_buy() {
+        let pump_swap_tx = PumpSwapTx::new(TEST_JSON_PUMP_SWAP_BUY.to_string());
+        assert_eq!(pump_swap_tx.is_buy(), Some(true));
+        assert!(pump_swap_tx.is_sol_paired());
+
+        let accounts = pump_swap_tx.accounts.as_ref().unwrap();
+        assert_eq!(
+            accounts.pool,
+            "FxTgR9wajzE9Zwkba9oYesSqZwMX9E7WvgK37DYVASSa"
+        );
+        assert_eq!(
+            accounts.base_mint,
+            "FWv5hiQqoUahjMyRFzz78q5ajmtwZ9vrn8tytgdFpump"
+        );
+        assert_eq!(
+            accounts.coin_creator_vault_ata.as_deref(),
+            Some("EfsmYBKyMYjpcfaToeWfVAhChnZx1bCMQPo3YNPFmbeP")
+        );
+
+        let inner_ix_data = pump_swap_tx.inner_ix_data.as_ref().unwrap();
+        assert_eq!(inner_ix_data.amount, 3_500_000_000_000);
+        assert_eq!(inner_ix_data.sol, 100_000_000);
+        assert_eq!(inner_ix_data.fee, 100_000);
+    }
+
+    #[test]
+    fn test_get_accounts_wrong_len() {
+        let json_str = r#"{"programId":"pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA","accounts":["a","b","c"]}"#;
+        let swap_ix: Value = from_str(json_str).unwrap();
+        assert_eq!(PumpSwapTx::get_accounts(&swap_ix), None);
+    }
+}
@@ -0,0 +1,355 @@
+use helius::Helius;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+use spl_associated_token_account::{
+    get_associated_token_address, get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use std::{error::Error, sync::Arc};
+use tokio::sync::Mutex;
+
+use crate::constants::{
+    PUMP_SWAP_ACTION_BUY, PUMP_SWAP_ACTION_SELL, PUMP_SWAP_COMPUTE_UNIT_LIMIT,
+    PUMP_SWAP_EVENT_AUTHORITY, PUMP_SWAP_GLOBAL_CONFIG, PUMP_SWAP_PROGRAM, WSOL_MINT,
+};
+
+use super::{
+    price_impact::constant_product_price_impact,
+    tx_common::SendSmartTx,
+    wsol::{Wsol, WsolMode},
+    AccountDecoder, ParsedTrade, PumpSwapGlobalConfig, PumpSwapPool, PumpSwapTx, QuoteDetails,
+    SplTokenAccountState, Swap, SwapQuote, TradeSide, Venue, VenueContext,
+};
+
+/// A PumpSwap quote, with everything needed to build the swap
+#[derive(Debug, Clone)]
+pub struct PumpSwapQuote {
+    pub pool_id: Pubkey,
+    pub pool: PumpSwapPool,
+    pub coin_creator: Option<Pubkey>,
+    pub base_token_program: Pubkey,
+    pub protocol_fee_recipient: Pubkey,
+    pub is_buy: bool,
+    pub base_reserve: u64,
+    pub quote_reserve: u64,
+    /// WSOL spent on a buy, tokens sold on a sell
+    pub amount_in: u64,
+    /// Tokens received on a buy, WSOL received on a sell
+    pub amount_out: u64,
+}
+
+impl PumpSwapQuote {
+    /// Returns the instruction arguments once the slippage is applied:
+    /// (base_amount_out, max_quote_amount_in) on a buy,
+    /// (base_amount_in, min_quote_amount_out) on a sell
+    ///
+    /// * `slippage_percent` - The slippage tolerated
+    pub fn limits(&self, slippage_percent: u64) -> (u64, u64) {
+        if self.is_buy {
+            let slippage = (self.amount_in as f64 * slippage_percent as f64 / 100.0).round() as u64;
+            (self.amount_out, self.amount_in + slippage)
+        } else {
+            let min_quote_amount_out =
+                (self.amount_out as f64 * (100.0 - slippage_percent as f64) / 100.0).floor() as u64;
+            (self.amount_in, min_quote_amount_out)
+        }
+    }
+}
+
+pub struct PumpSwapTxSend {}
+
+impl SendSmartTx for PumpSwapTxSend {}
+
+impl PumpSwapTxSend {
+    /// Quotes a buy (WSOL in) or a sell (tokens in) on a SOL paired PumpSwap pool
+    ///
+    /// * `helius` - The Helius client
+    /// * `pool_id` - The PumpSwap pool account
+    /// * `is_buy` - The swap direction
+    /// * `amount_in` - The exact input amount
+    pub async fn quote(
+        helius: Arc<Mutex<Helius>>,
+        pool_id: &Pubkey,
+        is_buy: bool,
+        amount_in: u64,
+    ) -> Result<PumpSwapQuote, Box<dyn Error + Send + Sync>> {
+        let connection = helius.lock().await.async_connection()?;
+
+        let pool_account = connection.get_account(pool_id).await?;
+        let pool = *PumpSwapPool::decode_account(&pool_account)?;
+        if pool.quote_mint != WSOL_MINT {
+            return Err("Only SOL paired PumpSwap pools are traded".into());
+        }
+        let coin_creator = PumpSwapPool::coin_creator(&pool_account.data);
+
+        // Config, both vaults and the base mint (for its token program) in one round trip
+        let accounts = connection
+            .get_multiple_accounts(&[
+                PUMP_SWAP_GLOBAL_CONFIG,
+                pool.pool_base_token_account,
+                pool.pool_quote_token_account,
+                pool.base_mint,
+            ])
+            .await?;
+
+        let global_config_account = accounts[0]
+            .as_ref()
+            .ok_or("PumpSwap global config not found")?;
+        let global_config = PumpSwapGlobalConfig::decode_account(global_config_account)?;
+        if global_config.is_swap_disabled(is_buy) {
+            return Err("Swaps are disabled on PumpSwap".into());
+        }
+        let protocol_fee_recipient = global_config
+            .protocol_fee_recipient()
+            .ok_or("No PumpSwap protocol fee recipient")?;
+        let coin_creator_fee_basis_points = match coin_creator {
+            Some(_) => {
+                PumpSwapGlobalConfig::coin_creator_fee_basis_points(&global_config_account.data)
+            }
+            None => 0,
+        };
+        let fee_basis_points = global_config.fee_basis_points(coin_creator_fee_basis_points);
+
+        let base_reserve = SplTokenAccountState::decode_account(
+            accounts[1]
+                .as_ref()
+                .ok_or("PumpSwap base vault not found")?,
+        )?
+        .amount;
+        let quote_reserve = SplTokenAccountState::decode_account(
+            accounts[2]
+                .as_ref()
+                .ok_or("PumpSwap quote vault not found")?,
+        )?
+        .amount;
+        let base_token_program = accounts[3].as_ref().ok_or("Base mint not found")?.owner;
+
+        let amount_out = if is_buy {
+            PumpSwapPool::quote_buy(amount_in, base_reserve, quote_reserve, fee_basis_points)
+        } else {
+            PumpSwapPool::quote_sell(amount_in, base_reserve, quote_reserve, fee_basis_points)
+        };
+
+        Ok(PumpSwapQuote {
+            pool_id: *pool_id,
+            pool,
+            coin_creator,
+            base_token_program,
+            protocol_fee_recipient,
+            is_buy,
+            base_reserve,
+            quote_reserve,
+            amount_in,
+            amount_out,
+        })
+    }
+
+    /// Builds the PumpSwap buy or sell instruction for the given owner.
+    ///
+    /// * `quote` - The quote to execute
+    /// * `owner` - The wallet swapping
+    /// * `slippage_percent` - The slippage tolerated
+    pub fn swap_instruction(
+        quote: &PumpSwapQuote,
+        owner: &Pubkey,
+        slippage_percent: u64,
+    ) -> Instruction {
+        let pool = quote.pool;
+        let (base_mint, quote_mint) = (pool.base_mint, pool.quote_mint);
+        let (first, second) = quote.limits(slippage_percent);
+
+        let mut data = if quote.is_buy {
+            PUMP_SWAP_ACTION_BUY.to_vec()
+        } else {
+            PUMP_SWAP_ACTION_SELL.to_vec()
+        };
+        data.extend_from_slice(&first.to_le_bytes());
+        data.extend_from_slice(&second.to_le_bytes());
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(quote.pool_id, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(PUMP_SWAP_GLOBAL_CONFIG, false),
+            AccountMeta::new_readonly(base_mint, false),
+            AccountMeta::new_readonly(quote_mint, false),
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(
+                    owner,
+                    &base_mint,
+                    &quote.base_token_program,
+                ),
+                false,
+            ),
+            AccountMeta::new(get_associated_token_address(owner, &quote_mint), false),
+            AccountMeta::new(pool.pool_base_token_account, false),
+            AccountMeta::new(pool.pool_quote_token_account, false),
+            AccountMeta::new_readonly(quote.protocol_fee_recipient, false),
+            AccountMeta::new(
+                get_associated_token_address(&quote.protocol_fee_recipient, &quote_mint),
+                false,
+            ),
+            AccountMeta::new_readonly(quote.base_token_program, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            AccountMeta::new_readonly(PUMP_SWAP_EVENT_AUTHORITY, false),
+            AccountMeta::new_readonly(PUMP_SWAP_PROGRAM, false),
+        ];
+
+        // Pools with a coin creator pay it a fee in WSOL
+        if let Some(coin_creator) = quote.coin_creator {
+            let creator_vault_authority = PumpSwapPool::creator_vault_authority(&coin_creator);
+            accounts.push(AccountMeta::new(
+                get_associated_token_address(&creator_vault_authority, &quote_mint),
+                false,
+            ));
+            accounts.push(AccountMeta::new_readonly(creator_vault_authority, false));
+        }
+
+        Instruction {
+            program_id: PUMP_SWAP_PROGRAM,
+            accounts,
+            data,
+        }
+    }
+
+    /// Builds the instruction list executing a quote for the given owner.
+    ///
+    /// * `helius` - The Helius client
+    /// * `quote` - The quote to execute
+    /// * `owner` - The wallet swapping
+    /// * `slippage_percent` - The slippage tolerated
+    /// * `wsol_mode` - The WSOL funding mode
+    pub async fn build_swap(
+        helius: Arc<Mutex<Helius>>,
+        quote: &PumpSwapQuote,
+        owner: &Pubkey,
+        slippage_percent: u64,
+        wsol_mode: WsolMode,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let mut instructions = vec![];
+
+        let (pre_swap_instructions, post_swap_instructions) = if quote.is_buy {
+            // The buy may spend up to the max quote amount in
+            let (_, max_quote_amount_in) = quote.limits(slippage_percent);
+            let wsol_instructions =
+                Wsol::buy_instructions(helius, owner, wsol_mode, max_quote_amount_in).await?;
+            instructions.extend(wsol_instructions.0);
+            instructions.push(create_associated_token_account_idempotent(
+                owner,
+                owner,
+                &quote.pool.base_mint,
+                &quote.base_token_program,
+            ));
+            (vec![], wsol_instructions.1)
+        } else {
+            Wsol::sell_instructions(owner, wsol_mode)?
+        };
+
+        instructions.extend(pre_swap_instructions);
+        instructions.push(Self::swap_instruction(quote, owner, slippage_percent));
+        instructions.extend(post_swap_instructions);
+
+        Ok(instructions)
+    }
+}
+
+impl Swap for PumpSwapTxSend {
+    const VENUE: Venue = Venue::PumpSwap;
+    const COMPUTE_UNIT_LIMIT: u32 = PUMP_SWAP_COMPUTE_UNIT_LIMIT;
+
+    /// Only the canonical pools, the ones graduated Pump.fun tokens migrate to
+    async fn find_pool(
+        _ctx: &VenueContext,
+        mint: &Pubkey,
+    ) -> Result<Pubkey, Box<dyn Error + Send + Sync>> {
+        Ok(PumpSwapPool::canonical_address(mint))
+    }
+
+    async fn quote_pool(
+        ctx: &VenueContext,
+        pool_id: &Pubkey,
+        mint: &Pubkey,
+        side: TradeSide,
+        amount: u64,
+    ) -> Result<SwapQuote, Box<dyn Error + Send + Sync>> {
+        let quote = Self::quote(
+            Arc::clone(&ctx.helius),
+            pool_id,
+            side == TradeSide::Buy,
+            amount,
+        )
+        .await?;
+        if quote.pool.base_mint != *mint {
+            return Err("Mint is not traded by this PumpSwap pool".into());
+        }
+
+        Ok(SwapQuote {
+            venue: Self::VENUE,
+            pool_id: *pool_id,
+            mint: *mint,
+            side,
+            amount_in: quote.amount_in,
+            amount_out: quote.amount_out,
+            creator: quote.coin_creator,
+            quote_liquidity: quote.quote_reserve,
+            pool_open_time: None,
+            price_impact: constant_product_price_impact(
+                quote.amount_in,
+                match side {
+                    TradeSide::Buy => quote.quote_reserve,
+                    TradeSide::Sell => quote.base_reserve,
+                },
+            ),
+            details: QuoteDetails::PumpSwap(quote),
+        })
+    }
+
+    async fn build_swap(
+        ctx: &VenueContext,
+        quote: &SwapQuote,
+        wallet: &Pubkey,
+        slippage_percent: u64,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let QuoteDetails::PumpSwap(details) = &quote.details else {
+            return Err("Not a PumpSwap quote".into());
+        };
+
+        Self::build_swap(
+            Arc::clone(&ctx.helius),
+            details,
+            wallet,
+            slippage_percent,
+            ctx.wsol_mode,
+        )
+        .await
+    }
+
+    async fn parse_trade(_ctx: &VenueContext, payload: &str) -> Option<ParsedTrade> {
+        let pump_swap_tx = PumpSwapTx::new(payload.to_string());
+        if !pump_swap_tx.is_sol_paired() {
+            return None;
+        }
+        let accounts = pump_swap_tx.accounts.as_ref()?;
+        let inner_ix_data = pump_swap_tx.inner_ix_data.as_ref()?;
+        let side = if pump_swap_tx.is_buy()? {
+            TradeSide::Buy
+        } else {
+            TradeSide::Sell
+        };
+
+        Some(ParsedTrade {
+            venue: Self::VENUE,
+            signature: pump_swap_tx.signature.clone(),
+            side,
+            mint: accounts.base_mint.parse().ok()?,
+            pool_id: accounts.pool.parse().ok()?,
+            sol_amount: inner_ix_data.sol,
+            token_amount: inner_ix_data.amount,
+            compute_unit_limit: pump_swap_tx.compute_unit_limit,
+        })
+    }
+}
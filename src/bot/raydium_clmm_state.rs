@@ -0,0 +1,480 @@
+use bytemuck::{Pod, Zeroable};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::constants::{
+    RAYDIUM_AMM_CONFIG_DISCRIMINATOR, RAYDIUM_CLMM_PROGRAM, RAYDIUM_CLMM_TICK_ARRAY_DISCRIMINATOR,
+    RAYDIUM_CLMM_TICK_ARRAY_SEED, RAYDIUM_CLMM_TICK_ARRAY_SIZE, RAYDIUM_CPMM_FEE_RATE_DENOMINATOR,
+    RAYDIUM_POOL_STATE_DISCRIMINATOR,
+};
+
+use super::AccountDecoder;
+
+/// Raydium CLMM reward information, part of the pool state
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct ClmmRewardInfo {
+    /// Reward state
+    pub reward_state: u8,
+    /// Reward open time
+    pub open_time: u64,
+    /// Reward end time
+    pub end_time: u64,
+    /// Reward last update time
+    pub last_update_time: u64,
+    /// Q64.64 number indicates how many tokens per second are earned per unit of liquidity.
+    pub emissions_per_second_x64: u128,
+    /// The total amount of reward emissioned
+    pub reward_total_emissioned: u64,
+    /// The total amount of claimed reward
+    pub reward_claimed: u64,
+    /// Reward token mint.
+    pub token_mint: Pubkey,
+    /// Reward vault token account.
+    pub token_vault: Pubkey,
+    /// The owner that has permission to set reward param
+    pub authority: Pubkey,
+    /// Q64.64 number that tracks the total tokens earned per unit of liquidity since the reward
+    /// emissions were turned on.
+    pub reward_growth_global_x64: u128,
+}
+
+/// Raydium CLMM pool state.
+/// Based on https://github.com/raydium-io/raydium-clmm/blob/master/programs/amm/src/states/pool.rs
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct ClmmPoolState {
+    /// Bump to identify PDA
+    pub bump: [u8; 1],
+    /// Which config the pool belongs
+    pub amm_config: Pubkey,
+    /// Pool creator
+    pub owner: Pubkey,
+    /// Token pair of the pool, where token_mint_0 address < token_mint_1 address
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    /// Token pair vault
+    pub token_vault_0: Pubkey,
+    pub token_vault_1: Pubkey,
+    /// observation account key
+    pub observation_key: Pubkey,
+    /// mint0 and mint1 decimals
+    pub mint_decimals_0: u8,
+    pub mint_decimals_1: u8,
+    /// The minimum number of ticks between initialized ticks
+    pub tick_spacing: u16,
+    /// The currently in range liquidity available to the pool.
+    pub liquidity: u128,
+    /// The current price of the pool as a sqrt(token_1/token_0) Q64.64 value
+    pub sqrt_price_x64: u128,
+    /// The current tick of the pool, i.e. according to the last tick transition that was run.
+    pub tick_current: i32,
+    pub padding3: u16,
+    pub padding4: u16,
+    /// The fee growth as a Q64.64 number, i.e. fees of token_0 and token_1 collected per
+    /// unit of liquidity for the entire life of the pool.
+    pub fee_growth_global_0_x64: u128,
+    pub fee_growth_global_1_x64: u128,
+    /// The amounts of token_0 and token_1 that are owed to the protocol.
+    pub protocol_fees_token_0: u64,
+    pub protocol_fees_token_1: u64,
+    /// The amounts in and out of swap token_0 and token_1
+    pub swap_in_amount_token_0: u128,
+    pub swap_out_amount_token_1: u128,
+    pub swap_in_amount_token_1: u128,
+    pub swap_out_amount_token_0: u128,
+    /// Bitwise representation of the state of the pool
+    /// bit0, 1: disable open position and increase liquidity, 0: normal
+    /// bit1, 1: disable decrease liquidity, 0: normal
+    /// bit2, 1: disable collect fee, 0: normal
+    /// bit3, 1: disable collect reward, 0: normal
+    /// bit4, 1: disable swap, 0: normal
+    pub status: u8,
+    /// Leave blank for future use
+    pub padding: [u8; 7],
+    pub reward_infos: [ClmmRewardInfo; 3],
+    /// Packed initialized tick array state
+    pub tick_array_bitmap: [u64; 16],
+    /// except protocol_fee and fund_fee
+    pub total_fees_token_0: u64,
+    /// except protocol_fee and fund_fee
+    pub total_fees_claimed_token_0: u64,
+    pub total_fees_token_1: u64,
+    pub total_fees_claimed_token_1: u64,
+    pub fund_fees_token_0: u64,
+    pub fund_fees_token_1: u64,
+    /// The timestamp allowed for swap in the pool.
+    pub open_time: u64,
+    /// account recent update epoch
+    pub recent_epoch: u64,
+    /// Unused bytes for future upgrades.
+    pub padding1: [u64; 24],
+    pub padding2: [u64; 32],
+}
+
+/// Raydium CLMM fee configuration
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct ClmmAmmConfig {
+    /// Bump to identify PDA
+    pub bump: u8,
+    pub index: u16,
+    /// Address of the protocol owner
+    pub owner: Pubkey,
+    /// The protocol fee
+    pub protocol_fee_rate: u32,
+    /// The trade fee, denominated in hundredths of a bip (10^-6)
+    pub trade_fee_rate: u32,
+    /// The tick spacing
+    pub tick_spacing: u16,
+    /// The fund fee, denominated in hundredths of a bip (10^-6)
+    pub fund_fee_rate: u32,
+    // padding space for upgrade
+    pub padding_u32: u32,
+    pub fund_owner: Pubkey,
+    pub padding: [u64; 3],
+}
+
+/// A single tick of a tick array
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct ClmmTickState {
+    pub tick: i32,
+    /// Amount of net liquidity added (subtracted) when tick is crossed from left to right (right to left)
+    pub liquidity_net: i128,
+    /// The total position liquidity that references this tick
+    pub liquidity_gross: u128,
+    /// Fee growth per unit of liquidity on the _other_ side of this tick (relative to the current tick)
+    pub fee_growth_outside_0_x64: u128,
+    pub fee_growth_outside_1_x64: u128,
+    /// Reward growth per unit of liquidity like fee, array of Q64.64
+    pub reward_growths_outside_x64: [u128; 3],
+    /// Unused bytes for future upgrades.
+    pub padding: [u32; 13],
+}
+
+/// A CLMM tick array, holding `RAYDIUM_CLMM_TICK_ARRAY_SIZE` consecutive ticks
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct ClmmTickArrayState {
+    pub pool_id: Pubkey,
+    pub start_tick_index: i32,
+    pub ticks: [ClmmTickState; 60],
+    pub initialized_tick_count: u8,
+    /// account update recent epoch
+    pub recent_epoch: u64,
+    /// Unused bytes for future upgrades.
+    pub padding: [u8; 107],
+}
+
+/// The result of walking the ticks for an exact input swap
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClmmSwapQuote {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    /// Relative difference between the spot price and the execution price, fees excluded
+    pub price_impact: f64,
+    /// Start indexes of the tick arrays the swap goes through, in order
+    pub tick_array_start_indexes: Vec<i32>,
+}
+
+/// Swap disabled bit of `ClmmPoolState::status`
+const CLMM_STATUS_SWAP_DISABLED: u8 = 1 << 4;
+/// Number of tick arrays tracked by `ClmmPoolState::tick_array_bitmap`, half on each side of 0
+const CLMM_TICK_ARRAY_BITMAP_SIZE: i32 = 1024;
+
+// CPMM pools share the discriminator, the owner tells them apart
+impl AccountDecoder for ClmmPoolState {
+    const OWNERS: &'static [Pubkey] = &[RAYDIUM_CLMM_PROGRAM];
+    const HEAD: &'static [u8] = RAYDIUM_POOL_STATE_DISCRIMINATOR;
+}
+
+impl ClmmPoolState {
+    /// Returns true if swaps are currently allowed on the pool
+    pub fn is_swap_enabled(&self) -> bool {
+        self.status & CLMM_STATUS_SWAP_DISABLED == 0
+    }
+
+    /// Number of ticks covered by one tick array
+    pub fn ticks_in_array(&self) -> i32 {
+        self.tick_spacing as i32 * RAYDIUM_CLMM_TICK_ARRAY_SIZE
+    }
+
+    /// Returns the start index of the tick array holding `tick`
+    pub fn tick_array_start_index(&self, tick: i32) -> i32 {
+        tick.div_euclid(self.ticks_in_array()) * self.ticks_in_array()
+    }
+
+    /// Returns true if the tick array starting at `start_index` is flagged as
+    /// initialized in the pool bitmap. Arrays outside the bitmap range (tracked
+    /// by the bitmap extension account) are reported as not initialized.
+    pub fn is_tick_array_initialized(&self, start_index: i32) -> bool {
+        let offset = start_index / self.ticks_in_array() + CLMM_TICK_ARRAY_BITMAP_SIZE / 2;
+        if !(0..CLMM_TICK_ARRAY_BITMAP_SIZE).contains(&offset) {
+            return false;
+        }
+
+        let bitmap = self.tick_array_bitmap;
+        bitmap[(offset / 64) as usize] & (1u64 << (offset % 64)) != 0
+    }
+
+    /// Returns the start indexes of the initialized tick arrays a swap walks
+    /// through, starting with the array holding the current tick.
+    ///
+    /// * `zero_for_one` - true when token 0 is sold to the pool (price goes down)
+    /// * `max` - The maximum number of tick arrays to return
+    pub fn swap_tick_array_start_indexes(&self, zero_for_one: bool, max: usize) -> Vec<i32> {
+        let step = if zero_for_one {
+            -self.ticks_in_array()
+        } else {
+            self.ticks_in_array()
+        };
+        let mut start_index = self.tick_array_start_index(self.tick_current);
+        let mut start_indexes = vec![];
+
+        for _ in 0..CLMM_TICK_ARRAY_BITMAP_SIZE {
+            if start_indexes.len() >= max {
+                break;
+            }
+            if self.is_tick_array_initialized(start_index) {
+                start_indexes.push(start_index);
+            }
+            start_index += step;
+        }
+
+        start_indexes
+    }
+
+    /// Derives the tick array account address
+    ///
+    /// * `pool_id` - The pool state account
+    /// * `start_index` - The tick array start index
+    pub fn tick_array_address(pool_id: &Pubkey, start_index: i32) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                RAYDIUM_CLMM_TICK_ARRAY_SEED,
+                pool_id.as_ref(),
+                &start_index.to_be_bytes(),
+            ],
+            &RAYDIUM_CLMM_PROGRAM,
+        )
+        .0
+    }
+
+    /// Returns the current price as token 1 per token 0, raw amounts
+    pub fn price(&self) -> f64 {
+        let sqrt_price = self.sqrt_price_x64 as f64 / 2f64.powi(64);
+        sqrt_price * sqrt_price
+    }
+
+    /// Quotes an exact input swap by walking the initialized ticks of the
+    /// given tick arrays. The math is done in floating point, which is close
+    /// enough to size the minimum output once the slippage is applied.
+    ///
+    /// * `amount_in` - The exact input amount
+    /// * `zero_for_one` - true when token 0 is sold to the pool
+    /// * `trade_fee_rate` - The `ClmmAmmConfig::trade_fee_rate`
+    /// * `tick_arrays` - The tick arrays returned for `swap_tick_array_start_indexes`
+    pub fn quote_base_input(
+        &self,
+        amount_in: u64,
+        zero_for_one: bool,
+        trade_fee_rate: u32,
+        tick_arrays: &[ClmmTickArrayState],
+    ) -> ClmmSwapQuote {
+        let fee = (amount_in as u128 * trade_fee_rate as u128)
+            .div_ceil(RAYDIUM_CPMM_FEE_RATE_DENOMINATOR as u128) as u64;
+        let amount_in_less_fee = amount_in.saturating_sub(fee) as f64;
+
+        // Initialized ticks in the swap direction, closest first
+        let mut ticks: Vec<(i32, i128, i32)> = tick_arrays
+            .iter()
+            .flat_map(|tick_array| {
+                let start_tick_index = tick_array.start_tick_index;
+                let ticks = tick_array.ticks;
+                ticks
+                    .into_iter()
+                    .filter(|tick| tick.liquidity_gross != 0)
+                    .map(move |tick| (tick.tick, tick.liquidity_net, start_tick_index))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|(tick, _, _)| {
+                if zero_for_one {
+                    *tick <= self.tick_current
+                } else {
+                    *tick > self.tick_current
+                }
+            })
+            .collect();
+        if zero_for_one {
+            ticks.sort_by(|a, b| b.0.cmp(&a.0));
+        } else {
+            ticks.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+
+        let mut sqrt_price = self.sqrt_price_x64 as f64 / 2f64.powi(64);
+        let mut liquidity = self.liquidity as f64;
+        let mut remaining = amount_in_less_fee;
+        let mut amount_out = 0f64;
+        let mut tick_array_start_indexes = vec![self.tick_array_start_index(self.tick_current)];
+
+        for (tick, liquidity_net, start_tick_index) in ticks {
+            if remaining <= 0.0 {
+                break;
+            }
+
+            let target_sqrt_price = Self::tick_to_sqrt_price(tick);
+            if liquidity > 0.0 {
+                // Input needed to move the price to the next initialized tick
+                let step_in = if zero_for_one {
+                    liquidity * (1.0 / target_sqrt_price - 1.0 / sqrt_price)
+                } else {
+                    liquidity * (target_sqrt_price - sqrt_price)
+                };
+
+                if remaining < step_in {
+                    break;
+                }
+
+                amount_out += if zero_for_one {
+                    liquidity * (sqrt_price - target_sqrt_price)
+                } else {
+                    liquidity * (1.0 / sqrt_price - 1.0 / target_sqrt_price)
+                };
+                remaining -= step_in;
+            }
+
+            // Cross the tick
+            sqrt_price = target_sqrt_price;
+            liquidity += if zero_for_one {
+                -(liquidity_net as f64)
+            } else {
+                liquidity_net as f64
+            };
+            if !tick_array_start_indexes.contains(&start_tick_index) {
+                tick_array_start_indexes.push(start_tick_index);
+            }
+        }
+
+        // Whatever is left is swapped within the current range
+        if remaining > 0.0 && liquidity > 0.0 {
+            if zero_for_one {
+                let next_sqrt_price = 1.0 / (1.0 / sqrt_price + remaining / liquidity);
+                amount_out += liquidity * (sqrt_price - next_sqrt_price);
+            } else {
+                let next_sqrt_price = sqrt_price + remaining / liquidity;
+                amount_out += liquidity * (1.0 / sqrt_price - 1.0 / next_sqrt_price);
+            }
+        }
+
+        let spot_price = if zero_for_one {
+            self.price()
+        } else {
+            1.0 / self.price()
+        };
+        let execution_price = if amount_in_less_fee > 0.0 {
+            amount_out / amount_in_less_fee
+        } else {
+            spot_price
+        };
+        let price_impact = if spot_price > 0.0 {
+            (1.0 - execution_price / spot_price).max(0.0)
+        } else {
+            0.0
+        };
+
+        ClmmSwapQuote {
+            amount_in,
+            amount_out: amount_out.floor() as u64,
+            price_impact,
+            tick_array_start_indexes,
+        }
+    }
+
+    /// sqrt(1.0001^tick)
+    fn tick_to_sqrt_price(tick: i32) -> f64 {
+        1.0001f64.powf(tick as f64 / 2.0)
+    }
+}
+
+impl AccountDecoder for ClmmAmmConfig {
+    const OWNERS: &'static [Pubkey] = &[RAYDIUM_CLMM_PROGRAM];
+    const HEAD: &'static [u8] = RAYDIUM_AMM_CONFIG_DISCRIMINATOR;
+}
+
+impl AccountDecoder for ClmmTickArrayState {
+    const OWNERS: &'static [Pubkey] = &[RAYDIUM_CLMM_PROGRAM];
+    const HEAD: &'static [u8] = RAYDIUM_CLMM_TICK_ARRAY_DISCRIMINATOR;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool_state(tick_current: i32, liquidity: u128) -> ClmmPoolState {
+        let mut data = RAYDIUM_POOL_STATE_DISCRIMINATOR.to_vec();
+        data.resize(8 + std::mem::size_of::<ClmmPoolState>(), 0);
+        let mut pool_state = *ClmmPoolState::decode(&data).unwrap();
+        pool_state.tick_spacing = 10;
+        pool_state.tick_current = tick_current;
+        pool_state.liquidity = liquidity;
+        pool_state.sqrt_price_x64 =
+            (ClmmPoolState::tick_to_sqrt_price(tick_current) * 2f64.powi(64)) as u128;
+        pool_state
+    }
+
+    #[test]
+    fn test_account_sizes() {
+        assert_eq!(8 + std::mem::size_of::<ClmmPoolState>(), 1544);
+        assert_eq!(8 + std::mem::size_of::<ClmmAmmConfig>(), 117);
+        assert_eq!(8 + std::mem::size_of::<ClmmTickArrayState>(), 10240);
+    }
+
+    #[test]
+    fn test_tick_array_start_index() {
+        let pool_state = pool_state(0, 0);
+        assert_eq!(pool_state.tick_array_start_index(0), 0);
+        assert_eq!(pool_state.tick_array_start_index(599), 0);
+        assert_eq!(pool_state.tick_array_start_index(600), 600);
+        assert_eq!(pool_state.tick_array_start_index(-1), -600);
+    }
+
+    #[test]
+    fn test_swap_tick_array_start_indexes() {
+        let mut pool_state = pool_state(0, 0);
+        // arrays starting at -1200, 0 and 600 are initialized
+        let mut bitmap = [0u64; 16];
+        for offset in [510, 512, 513] {
+            bitmap[offset / 64] |= 1u64 << (offset % 64);
+        }
+        pool_state.tick_array_bitmap = bitmap;
+
+        assert_eq!(
+            pool_state.swap_tick_array_start_indexes(true, 3),
+            vec![0, -1200]
+        );
+        assert_eq!(pool_state.swap_tick_array_start_indexes(false, 1), vec![0]);
+        assert_eq!(
+            pool_state.swap_tick_array_start_indexes(false, 3),
+            vec![0, 600]
+        );
+    }
+
+    #[test]
+    fn test_quote_within_range() {
+        // Price 1:1, deep liquidity, no fee: the output is close to the input
+        let pool_state = pool_state(0, 1_000_000_000_000_000);
+        let quote = pool_state.quote_base_input(1_000_000, true, 0, &[]);
+        assert!(quote.amount_out <= 1_000_000);
+        assert!(quote.amount_out > 999_990);
+        assert!(quote.price_impact < 0.0001);
+        assert_eq!(quote.tick_array_start_indexes, vec![0]);
+    }
+
+    #[test]
+    fn test_quote_price_impact_grows_with_size() {
+        let pool_state = pool_state(0, 1_000_000_000);
+        let small = pool_state.quote_base_input(1_000, false, 2_500, &[]);
+        let large = pool_state.quote_base_input(100_000_000, false, 2_500, &[]);
+        assert!(large.price_impact > small.price_impact);
+    }
+}
@@ -0,0 +1,423 @@
+//! Given a transaction having among it's instructions a Raydium CLMM swap (`swap` or `swap_v2`),
+//! this module extracts the swap accounts, amounts and direction.
+//!
+//! `swap_v2` lists the vault mints, for `swap` they are looked up in the
+//! transaction token balances. The parsed transaction has the same shape as `RaydiumMemeTx`.
+
+use serde::Serialize;
+use serde_json::{from_str, Value};
+
+use crate::bot::tx_common::GetSignature;
+use crate::constants::{
+    BOT_RAYDIUM_OPERATION_BUY, BOT_RAYDIUM_OPERATION_SELL, RAYDIUM_CLMM_ACCOUNTS_LEN_SWAP,
+    RAYDIUM_CLMM_ACCOUNTS_LEN_SWAP_V2, RAYDIUM_CLMM_ACTION_SWAP, RAYDIUM_CLMM_ACTION_SWAP_V2,
+    RAYDIUM_CLMM_PROGRAM, RAYDIUM_SWAP_BASE_IN_INSTRUCTION, RAYDIUM_SWAP_BASE_OUT_INSTRUCTION,
+    SPL_TOKEN_2022_PROGRAM, WSOL_MINT,
+};
+
+use super::raydium_meme_tx::{
+    InnerIxData, RaydiumMemeTradeData, RaydiumSwapBaseInData, RaydiumSwapBaseOutData,
+    RaydiumSwapData,
+};
+use super::tx_common::{GetComputeData, GetTxCommon};
+
+/// Raydium CLMM swap instruction accounts
+/// Based on https://github.com/raydium-io/raydium-clmm/blob/master/programs/amm/src/instructions/swap.rs
+#[derive(Debug, Serialize, PartialEq)]
+pub struct RaydiumClmmAccounts {
+    pub payer: String,
+    pub amm_config: String,
+    pub pool_state: String,
+    pub input_token_account: String,
+    pub output_token_account: String,
+    pub input_vault: String,
+    pub output_vault: String,
+    pub observation_state: String,
+    pub input_vault_mint: Option<String>,
+    pub output_vault_mint: Option<String>,
+    /// Tick arrays (and the bitmap extension, if any) passed by the swapper
+    pub remaining_accounts: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct RaydiumClmmTx {
+    pub signature: Option<String>,
+    pub accounts: Option<RaydiumClmmAccounts>,
+    pub ix_data: Option<RaydiumSwapData>,
+    pub inner_ix_data: Option<InnerIxData>,
+    pub meme_trade_data: Option<RaydiumMemeTradeData>,
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64,
+}
+
+impl GetSignature for RaydiumClmmTx {}
+
+impl GetComputeData for RaydiumClmmTx {}
+
+impl GetTxCommon for RaydiumClmmTx {}
+
+impl RaydiumClmmTx {
+    /// Creates a new RaydiumClmmTx instance from a transaction payload.
+    ///
+    /// * `payload` - The transaction payload
+    pub fn new(payload: String) -> Self {
+        let json: Value = match from_str(payload.as_str()) {
+            Ok(json) => json,
+            Err(_) => return Self::default(),
+        };
+
+        let signature = match Self::get_signature(&json) {
+            Some(signature) => signature,
+            None => return Self::default(),
+        };
+
+        let swap_ix = match Self::get_clmm_swap_instruction(&json) {
+            Some(swap_ix) => swap_ix,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let (ix_data, is_v2) = match swap_ix["data"]
+            .as_str()
+            .and_then(Self::get_instruction_data)
+        {
+            Some(ix_data) => ix_data,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let accounts = match Self::get_accounts(&json, swap_ix, is_v2) {
+            Some(accounts) => accounts,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    ix_data: Some(ix_data),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let (source_amount, dest_amount) = match Self::get_amounts(&json, &accounts) {
+            Some(amounts) => amounts,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    accounts: Some(accounts),
+                    ix_data: Some(ix_data),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let meme_trade_data = Self::get_meme_trade_data(&accounts);
+
+        let (compute_unit_limit, compute_unit_price) =
+            Self::get_compute_data(&json).unwrap_or((0, 0));
+
+        Self {
+            signature: Some(signature),
+            accounts: Some(accounts),
+            ix_data: Some(ix_data),
+            inner_ix_data: Some(InnerIxData {
+                source_amount,
+                dest_amount,
+            }),
+            meme_trade_data,
+            compute_unit_limit,
+            compute_unit_price,
+        }
+    }
+
+    /// Finds the CLMM swap instruction in the transaction,
+    /// either as a main instruction or as an inner instruction.
+    fn get_clmm_swap_instruction(json: &Value) -> Option<&Value> {
+        let is_clmm_swap = |instruction: &Value| {
+            instruction["programId"]
+                .as_str()
+                .map_or(false, |id| id == RAYDIUM_CLMM_PROGRAM.to_string())
+                && instruction["data"]
+                    .as_str()
+                    .and_then(Self::get_instruction_data)
+                    .is_some()
+        };
+
+        // Check main instructions
+        let instructions = json["params"]["result"]["transaction"]["transaction"]["message"]
+            ["instructions"]
+            .as_array()?;
+
+        if let Some(instruction) = instructions.iter().find(|&ix| is_clmm_swap(ix)) {
+            return Some(instruction);
+        }
+
+        // Check inner instructions
+        json["params"]["result"]["transaction"]["meta"]["innerInstructions"]
+            .as_array()?
+            .iter()
+            .find_map(|inner_ix_group| {
+                inner_ix_group["instructions"]
+                    .as_array()?
+                    .iter()
+                    .find(|&ix| is_clmm_swap(ix))
+            })
+    }
+
+    /// Extracts the data from the CLMM swap instruction,
+    /// returns the swap data and whether it is a `swap_v2`.
+    ///
+    /// * `input` - The base58 instruction data
+    fn get_instruction_data(input: &str) -> Option<(RaydiumSwapData, bool)> {
+        let input = bs58::decode(input).into_vec().ok()?;
+
+        // discriminator, amount, other_amount_threshold, sqrt_price_limit_x64, is_base_input
+        if input.len() < 41 {
+            return None;
+        }
+
+        let is_v2 = match &input[..8] {
+            RAYDIUM_CLMM_ACTION_SWAP => false,
+            RAYDIUM_CLMM_ACTION_SWAP_V2 => true,
+            _ => return None,
+        };
+
+        let amount = u64::from_le_bytes(input[8..16].try_into().ok()?);
+        let other_amount_threshold = u64::from_le_bytes(input[16..24].try_into().ok()?);
+        let is_base_input = input[40] != 0;
+
+        let ix_data = if is_base_input {
+            RaydiumSwapData::BaseIn(RaydiumSwapBaseInData {
+                instruction: RAYDIUM_SWAP_BASE_IN_INSTRUCTION,
+                amount_in: amount,
+                minimum_amount_out: other_amount_threshold,
+            })
+        } else {
+            RaydiumSwapData::BaseOut(RaydiumSwapBaseOutData {
+                instruction: RAYDIUM_SWAP_BASE_OUT_INSTRUCTION,
+                max_amount_in: other_amount_threshold,
+                amount_out: amount,
+            })
+        };
+
+        Some((ix_data, is_v2))
+    }
+
+    /// Extracts the CLMM swap accounts.
+    ///
+    /// * `json` - The transaction JSON, used to resolve the mints of `swap`
+    /// * `swap_instruction` - The CLMM swap instruction
+    /// * `is_v2` - Whether the instruction is a `swap_v2`
+    fn get_accounts(
+        json: &Value,
+        swap_instruction: &Value,
+        is_v2: bool,
+    ) -> Option<RaydiumClmmAccounts> {
+        let accounts = swap_instruction["accounts"].as_array()?;
+
+        let fixed_len = if is_v2 {
+            RAYDIUM_CLMM_ACCOUNTS_LEN_SWAP_V2
+        } else {
+            // `swap` takes the first tick array as a named account
+            RAYDIUM_CLMM_ACCOUNTS_LEN_SWAP - 1
+        };
+
+        if accounts.len() <= fixed_len {
+            return None;
+        }
+
+        let input_token_account = Self::pubkey_to_string(&accounts[3]);
+        let output_token_account = Self::pubkey_to_string(&accounts[4]);
+
+        let (input_vault_mint, output_vault_mint) = if is_v2 {
+            // account[8] - Token Program, account[9] - Token Program 2022, account[10] - Memo Program
+            (
+                Some(Self::pubkey_to_string(&accounts[11])),
+                Some(Self::pubkey_to_string(&accounts[12])),
+            )
+        } else {
+            (
+                Self::get_token_account_mint(json, &input_token_account),
+                Self::get_token_account_mint(json, &output_token_account),
+            )
+        };
+
+        Some(RaydiumClmmAccounts {
+            payer: Self::pubkey_to_string(&accounts[0]),
+            amm_config: Self::pubkey_to_string(&accounts[1]),
+            pool_state: Self::pubkey_to_string(&accounts[2]),
+            input_token_account,
+            output_token_account,
+            input_vault: Self::pubkey_to_string(&accounts[5]),
+            output_vault: Self::pubkey_to_string(&accounts[6]),
+            observation_state: Self::pubkey_to_string(&accounts[7]),
+            input_vault_mint,
+            output_vault_mint,
+            remaining_accounts: accounts[fixed_len..]
+                .iter()
+                .map(Self::pubkey_to_string)
+                .collect(),
+        })
+    }
+
+    /// Extracts the exact amounts swapped from the inner token transfers
+    ///
+    /// * `json` - The transaction JSON
+    /// * `accounts` - The CLMM swap accounts
+    fn get_amounts(json: &Value, accounts: &RaydiumClmmAccounts) -> Option<(u64, u64)> {
+        let inner_instructions =
+            json["params"]["result"]["transaction"]["meta"]["innerInstructions"].as_array()?;
+
+        let token_programs = [
+            spl_token::id().to_string(),
+            SPL_TOKEN_2022_PROGRAM.to_string(),
+        ];
+
+        let default_array: Vec<Value> = Vec::new();
+        let (source_amount, dest_amount) = inner_instructions
+            .iter()
+            .flat_map(|inner_ix| {
+                inner_ix["instructions"]
+                    .as_array()
+                    .unwrap_or(&default_array)
+            })
+            .filter(|ix| {
+                ix["programId"].as_str().map_or(false, |id| {
+                    token_programs.iter().any(|program| program == id)
+                })
+            })
+            .filter_map(|ix| {
+                let info = &ix["parsed"]["info"];
+                let amount = info["amount"]
+                    .as_str()
+                    .or_else(|| info["tokenAmount"]["amount"].as_str())?
+                    .parse::<u64>()
+                    .ok()?;
+
+                if info["source"].as_str() == Some(&accounts.input_token_account)
+                    && info["destination"].as_str() == Some(&accounts.input_vault)
+                {
+                    Some((amount, 0))
+                } else if info["source"].as_str() == Some(&accounts.output_vault)
+                    && info["destination"].as_str() == Some(&accounts.output_token_account)
+                {
+                    Some((0, amount))
+                } else {
+                    None
+                }
+            })
+            .fold((0, 0), |(src, dst), (s, d)| (src + s, dst + d));
+
+        Some((source_amount, dest_amount))
+    }
+
+    /// Detects the meme mint and the trade direction from the vault mints.
+    /// Only SOL paired swaps are considered.
+    ///
+    /// * `accounts` - The CLMM swap accounts
+    fn get_meme_trade_data(accounts: &RaydiumClmmAccounts) -> Option<RaydiumMemeTradeData> {
+        let wsol = WSOL_MINT.to_string();
+        let input_mint = accounts.input_vault_mint.as_ref()?;
+        let output_mint = accounts.output_vault_mint.as_ref()?;
+
+        if *input_mint == wsol && *output_mint != wsol {
+            Some(RaydiumMemeTradeData {
+                meme_mint: output_mint.parse().ok()?,
+                operation: BOT_RAYDIUM_OPERATION_BUY,
+            })
+        } else if *output_mint == wsol && *input_mint != wsol {
+            Some(RaydiumMemeTradeData {
+                meme_mint: input_mint.parse().ok()?,
+                operation: BOT_RAYDIUM_OPERATION_SELL,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a formatted string containing transaction details
+    pub fn format_tx_info(&self) -> String {
+        if let (Some(trade_data), Some(inner_ix), Some(sig)) =
+            (&self.meme_trade_data, &self.inner_ix_data, &self.signature)
+        {
+            let operation = if trade_data.operation == BOT_RAYDIUM_OPERATION_BUY {
+                "Buy"
+            } else {
+                "Sell"
+            };
+
+            format!(
+                "CLMM {} | Mint: {} | Source Amount: {} | Destination Amount: {} | TX: https://solscan.io/tx/{}",
+                operation, trade_data.meme_mint, inner_ix.source_amount, inner_ix.dest_amount, sig
+            )
+        } else {
+            "RaydiumClmmTx: Error formatting transaction data".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Synthetic CLMM `swap` sell: 50_000 meme in, 0.0012 WSOL out
+    const TEST_JSON_CLMM_SELL: &str = r#"{"jsonrpc":"2.0","method":"transactionNotification","params":{"subscription":1,"result":{"transaction":{"transaction":{"signatures":["96BR4LqXrJ6NupqEnz9A1XZVQRtK6W4zUZnTeduzE4DH"],"message":{"accountKeys":[{"pubkey":"3DGpp2jtfoHNf7ZHxYEsBASVqbJZKxB89LrmNLkByt6C","writable":true,"signer":true,"source":"transaction"},{"pubkey":"8U6kfpWNCRrUTqQYwsDwbGixj3UW8wMBMQuz6kXHxNhw","writable":true,"signer":false,"source":"transaction"},{"pubkey":"7haHNgYyFzgeJnHHMZTxugknpNP1cspS6CBRYazReaPi","writable":true,"signer":false,"source":"transaction"}],"instructions":[{"programId":"CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK","accounts":["3DGpp2jtfoHNf7ZHxYEsBASVqbJZKxB89LrmNLkByt6C","CCtCxRVBLqR4YT6JAxL3tUKBXwkhJTJwFzen7HBSuJ9M","7JhqbWvXazPam8zHbSzZejfNQFW8cvW5rBEyG4GrbFj5","8U6kfpWNCRrUTqQYwsDwbGixj3UW8wMBMQuz6kXHxNhw","7haHNgYyFzgeJnHHMZTxugknpNP1cspS6CBRYazReaPi","FD9GiPG18UYxqALopDKojgQxkN9EYuTgqZFiHHYHpH2V","6v1zJ52k7YBLhdDcGaPGeT9SLTSGK7YrmcsnR4TT6rx1","DDtbCN53C37yX61341FiKZfSBpkMnAtre6AikFhxfihQ","TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","8gmsknCjpqw7swpkiTqjTLj1MyXN8QG7GH9KNACSssDd"],"data":"wZRp7wZ3czsV1ePesUNjrwBLSTwcWHQGTZ2bWG227e4CEuDMpY4scfc4","stackHeight":null}]}},"meta":{"err":null,"innerInstructions":[{"index":0,"instructions":[{"program":"spl-token","programId":"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","parsed":{"info":{"amount":"50000000000","authority":"3DGpp2jtfoHNf7ZHxYEsBASVqbJZKxB89LrmNLkByt6C","destination":"FD9GiPG18UYxqALopDKojgQxkN9EYuTgqZFiHHYHpH2V","source":"8U6kfpWNCRrUTqQYwsDwbGixj3UW8wMBMQuz6kXHxNhw"},"type":"transfer"},"stackHeight":2},{"program":"spl-token","programId":"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","parsed":{"info":{"amount":"1200000","authority":"7JhqbWvXazPam8zHbSzZejfNQFW8cvW5rBEyG4GrbFj5","destination":"7haHNgYyFzgeJnHHMZTxugknpNP1cspS6CBRYazReaPi","source":"6v1zJ52k7YBLhdDcGaPGeT9SLTSGK7YrmcsnR4TT6rx1"},"type":"transfer"},"stackHeight":2}]}],"preTokenBalances":[{"accountIndex":1,"mint":"DMoxjTwmmfi7ed5yMPAsXRNHWZgz2pMgPcBvMsHrGRL","uiTokenAmount":{"amount":"50000000000","decimals":6},"owner":"3DGpp2jtfoHNf7ZHxYEsBASVqbJZKxB89LrmNLkByt6C"},{"accountIndex":2,"mint":"So11111111111111111111111111111111111111112","uiTokenAmount":{"amount":"0","decimals":9},"owner":"3DGpp2jtfoHNf7ZHxYEsBASVqbJZKxB89LrmNLkByt6C"}],"postTokenBalances":[]},"version":0},"signature":"96BR4LqXrJ6NupqEnz9A1XZVQRtK6W4zUZnTeduzE4DH","slot":1}}}"#;
+
+    #[test]
+    fn test_get_instruction_data() {
+        let json: Value = from_str(TEST_JSON_CLMM_SELL).unwrap();
+        let swap_ix = RaydiumClmmTx::get_clmm_swap_instruction(&json).unwrap();
+        let (ix_data, is_v2) =
+            RaydiumClmmTx::get_instruction_data(swap_ix["data"].as_str().unwrap()).unwrap();
+        assert!(!is_v2);
+        assert!(matches!(
+            ix_data,
+            RaydiumSwapData::BaseIn(RaydiumSwapBaseInData {
+                amount_in: 50_000_000_000,
+                minimum_amount_out: 1_000_000,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_get_accounts_resolves_mints() {
+        let json: Value = from_str(TEST_JSON_CLMM_SELL).unwrap();
+        let swap_ix = RaydiumClmmTx::get_clmm_swap_instruction(&json).unwrap();
+        let accounts = RaydiumClmmTx::get_accounts(&json, swap_ix, false).unwrap();
+        assert_eq!(
+            accounts.input_vault_mint.as_deref(),
+            Some("DMoxjTwmmfi7ed5yMPAsXRNHWZgz2pMgPcBvMsHrGRL")
+        );
+        assert_eq!(
+            accounts.output_vault_mint.as_deref(),
+            Some("So11111111111111111111111111111111111111112")
+        );
+        assert_eq!(
+            accounts.remaining_accounts,
+            vec!["8gmsknCjpqw7swpkiTqjTLj1MyXN8QG7GH9KNACSssDd".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_new_sell() {
+        let clmm_tx = RaydiumClmmTx::new(TEST_JSON_CLMM_SELL.to_string());
+
+        let inner_ix_data = clmm_tx.inner_ix_data.unwrap();
+        assert_eq!(inner_ix_data.source_amount, 50_000_000_000);
+        assert_eq!(inner_ix_data.dest_amount, 1_200_000);
+
+        let meme_trade_data = clmm_tx.meme_trade_data.unwrap();
+        assert_eq!(meme_trade_data.operation, BOT_RAYDIUM_OPERATION_SELL);
+        assert_eq!(
+            meme_trade_data.meme_mint.to_string(),
+            "DMoxjTwmmfi7ed5yMPAsXRNHWZgz2pMgPcBvMsHrGRL"
+        );
+    }
+}
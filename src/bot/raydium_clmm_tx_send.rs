@@ -0,0 +1,365 @@
+use helius::Helius;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use std::{error::Error, sync::Arc};
+use tokio::sync::Mutex;
+
+use crate::constants::{
+    RAYDIUM_CLMM_ACTION_SWAP_V2, RAYDIUM_CLMM_COMPUTE_UNIT_LIMIT, RAYDIUM_CLMM_MAX_TICK_ARRAYS,
+    RAYDIUM_CLMM_PROGRAM, SPL_MEMO_PROGRAM, SPL_TOKEN_2022_PROGRAM, WSOL_MINT,
+};
+
+use super::{
+    tx_common::SendSmartTx,
+    venue::{deepest_sol_pool, find_sol_paired_pools},
+    wsol::{Wsol, WsolMode},
+    AccountDecoder, ClmmAmmConfig, ClmmPoolState, ClmmSwapQuote, ClmmTickArrayState, ParsedTrade,
+    QuoteDetails, RaydiumClmmTx, SplTokenAccountState, Swap, SwapQuote, TradeSide, Venue,
+    VenueContext,
+};
+
+/// A CLMM exact input quote, with everything needed to build the swap
+#[derive(Debug, Clone)]
+pub struct ClmmQuote {
+    pub pool_id: Pubkey,
+    pub pool_state: ClmmPoolState,
+    pub zero_for_one: bool,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub input_token_program: Pubkey,
+    pub output_token_program: Pubkey,
+    /// Balance of the input vault, the pool liquidity on the sold side
+    pub input_vault_amount: u64,
+    pub swap: ClmmSwapQuote,
+}
+
+impl ClmmQuote {
+    /// Returns (input vault, output vault)
+    fn vaults(&self) -> (Pubkey, Pubkey) {
+        let pool_state = self.pool_state;
+        if self.zero_for_one {
+            (pool_state.token_vault_0, pool_state.token_vault_1)
+        } else {
+            (pool_state.token_vault_1, pool_state.token_vault_0)
+        }
+    }
+}
+
+pub struct RaydiumClmmTxSend {}
+
+impl SendSmartTx for RaydiumClmmTxSend {}
+
+impl RaydiumClmmTxSend {
+    /// Quotes an exact input swap on a CLMM pool by walking the tick arrays
+    /// the swap would cross.
+    ///
+    /// * `helius` - The Helius client
+    /// * `pool_id` - The CLMM pool state account
+    /// * `input_mint` - The mint being sold to the pool
+    /// * `amount_in` - The exact input amount
+    pub async fn quote(
+        helius: Arc<Mutex<Helius>>,
+        pool_id: &Pubkey,
+        input_mint: &Pubkey,
+        amount_in: u64,
+    ) -> Result<ClmmQuote, Box<dyn Error + Send + Sync>> {
+        let connection = helius.lock().await.async_connection()?;
+
+        let pool_state = *ClmmPoolState::decode_account(&connection.get_account(pool_id).await?)?;
+
+        if !pool_state.is_swap_enabled() {
+            return Err("Swaps are disabled on this CLMM pool".into());
+        }
+
+        let (token_mint_0, token_mint_1) = (pool_state.token_mint_0, pool_state.token_mint_1);
+        let zero_for_one = if token_mint_0 == *input_mint {
+            true
+        } else if token_mint_1 == *input_mint {
+            false
+        } else {
+            return Err("Mint is not traded by this CLMM pool".into());
+        };
+        let output_mint = if zero_for_one {
+            token_mint_1
+        } else {
+            token_mint_0
+        };
+
+        let start_indexes =
+            pool_state.swap_tick_array_start_indexes(zero_for_one, RAYDIUM_CLMM_MAX_TICK_ARRAYS);
+        if start_indexes.is_empty() {
+            return Err("No initialized tick array in the swap direction".into());
+        }
+
+        let input_vault = if zero_for_one {
+            pool_state.token_vault_0
+        } else {
+            pool_state.token_vault_1
+        };
+
+        // Config, both mints (for their token programs), the input vault
+        // and the tick arrays in one round trip
+        let mut keys = vec![pool_state.amm_config, *input_mint, output_mint, input_vault];
+        keys.extend(
+            start_indexes
+                .iter()
+                .map(|start_index| ClmmPoolState::tick_array_address(pool_id, *start_index)),
+        );
+        let accounts = connection.get_multiple_accounts(&keys).await?;
+
+        let amm_config = ClmmAmmConfig::decode_account(
+            accounts[0].as_ref().ok_or("CLMM amm config not found")?,
+        )?;
+        let input_token_program = accounts[1].as_ref().ok_or("Input mint not found")?.owner;
+        let output_token_program = accounts[2].as_ref().ok_or("Output mint not found")?.owner;
+        let input_vault_amount = SplTokenAccountState::decode_account(
+            accounts[3].as_ref().ok_or("CLMM input vault not found")?,
+        )?
+        .amount;
+
+        let tick_arrays = accounts[4..]
+            .iter()
+            .map(|account| {
+                let account = account.as_ref().ok_or("CLMM tick array not found")?;
+                Ok(*ClmmTickArrayState::decode_account(account)?)
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+
+        let swap = pool_state.quote_base_input(
+            amount_in,
+            zero_for_one,
+            amm_config.trade_fee_rate,
+            &tick_arrays,
+        );
+
+        Ok(ClmmQuote {
+            pool_id: *pool_id,
+            pool_state,
+            zero_for_one,
+            input_mint: *input_mint,
+            output_mint,
+            input_token_program,
+            output_token_program,
+            input_vault_amount,
+            swap,
+        })
+    }
+
+    /// Builds the CLMM `swap_v2` exact input instruction for the given owner,
+    /// passing the tick arrays picked by the quote as remaining accounts.
+    ///
+    /// * `quote` - The quote to execute
+    /// * `owner` - The wallet swapping
+    /// * `minimum_amount_out` - The minimum output accepted
+    pub fn swap_v2_instruction(
+        quote: &ClmmQuote,
+        owner: &Pubkey,
+        minimum_amount_out: u64,
+    ) -> Instruction {
+        let (input_vault, output_vault) = quote.vaults();
+        let pool_state = quote.pool_state;
+
+        let mut data = RAYDIUM_CLMM_ACTION_SWAP_V2.to_vec();
+        data.extend_from_slice(&quote.swap.amount_in.to_le_bytes());
+        data.extend_from_slice(&minimum_amount_out.to_le_bytes());
+        // No price limit, the minimum output protects the trade
+        data.extend_from_slice(&0u128.to_le_bytes());
+        // is_base_input
+        data.push(1);
+
+        let mut accounts = vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(pool_state.amm_config, false),
+            AccountMeta::new(quote.pool_id, false),
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(
+                    owner,
+                    &quote.input_mint,
+                    &quote.input_token_program,
+                ),
+                false,
+            ),
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(
+                    owner,
+                    &quote.output_mint,
+                    &quote.output_token_program,
+                ),
+                false,
+            ),
+            AccountMeta::new(input_vault, false),
+            AccountMeta::new(output_vault, false),
+            AccountMeta::new(pool_state.observation_key, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(SPL_TOKEN_2022_PROGRAM, false),
+            AccountMeta::new_readonly(SPL_MEMO_PROGRAM, false),
+            AccountMeta::new_readonly(quote.input_mint, false),
+            AccountMeta::new_readonly(quote.output_mint, false),
+        ];
+        accounts.extend(
+            quote
+                .swap
+                .tick_array_start_indexes
+                .iter()
+                .map(|start_index| {
+                    AccountMeta::new(
+                        ClmmPoolState::tick_array_address(&quote.pool_id, *start_index),
+                        false,
+                    )
+                }),
+        );
+
+        Instruction {
+            program_id: RAYDIUM_CLMM_PROGRAM,
+            accounts,
+            data,
+        }
+    }
+
+    /// Builds the instruction list executing a quote for the given owner.
+    ///
+    /// * `helius` - The Helius client
+    /// * `quote` - The quote to execute
+    /// * `owner` - The wallet swapping
+    /// * `slippage_percent` - The slippage applied to the quoted output
+    /// * `wsol_mode` - The WSOL funding mode
+    pub async fn build_swap(
+        helius: Arc<Mutex<Helius>>,
+        quote: &ClmmQuote,
+        owner: &Pubkey,
+        slippage_percent: u64,
+        wsol_mode: WsolMode,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let minimum_amount_out = (quote.swap.amount_out as f64 * (100.0 - slippage_percent as f64)
+            / 100.0)
+            .floor() as u64;
+
+        let (pre_swap_instructions, post_swap_instructions) = if quote.input_mint == WSOL_MINT {
+            Wsol::buy_instructions(helius, owner, wsol_mode, quote.swap.amount_in).await?
+        } else if quote.output_mint == WSOL_MINT {
+            Wsol::sell_instructions(owner, wsol_mode)?
+        } else {
+            (vec![], vec![])
+        };
+
+        let mut instructions = pre_swap_instructions;
+        if quote.output_mint != WSOL_MINT {
+            instructions.push(create_associated_token_account_idempotent(
+                owner,
+                owner,
+                &quote.output_mint,
+                &quote.output_token_program,
+            ));
+        }
+        instructions.push(Self::swap_v2_instruction(quote, owner, minimum_amount_out));
+        instructions.extend(post_swap_instructions);
+
+        Ok(instructions)
+    }
+}
+
+impl Swap for RaydiumClmmTxSend {
+    const VENUE: Venue = Venue::RaydiumClmm;
+    const COMPUTE_UNIT_LIMIT: u32 = RAYDIUM_CLMM_COMPUTE_UNIT_LIMIT;
+
+    async fn find_pool(
+        ctx: &VenueContext,
+        mint: &Pubkey,
+    ) -> Result<Pubkey, Box<dyn Error + Send + Sync>> {
+        let connection = ctx.helius.lock().await.async_connection()?;
+
+        let pools = find_sol_paired_pools::<ClmmPoolState>(
+            &connection,
+            &RAYDIUM_CLMM_PROGRAM,
+            [
+                std::mem::offset_of!(ClmmPoolState, token_mint_0),
+                std::mem::offset_of!(ClmmPoolState, token_mint_1),
+            ],
+            mint,
+        )
+        .await?
+        .into_iter()
+        .filter(|(_, pool)| pool.is_swap_enabled())
+        .map(|(pool_id, pool)| {
+            let wsol_vault = if pool.token_mint_0 == WSOL_MINT {
+                pool.token_vault_0
+            } else {
+                pool.token_vault_1
+            };
+            (pool_id, wsol_vault)
+        })
+        .collect();
+
+        deepest_sol_pool(&connection, pools).await
+    }
+
+    async fn quote_pool(
+        ctx: &VenueContext,
+        pool_id: &Pubkey,
+        mint: &Pubkey,
+        side: TradeSide,
+        amount: u64,
+    ) -> Result<SwapQuote, Box<dyn Error + Send + Sync>> {
+        let (input_mint, output_mint) = side.mints(mint);
+        let quote = Self::quote(Arc::clone(&ctx.helius), pool_id, &input_mint, amount).await?;
+        if quote.output_mint != output_mint {
+            return Err("Only SOL paired CLMM pools are traded".into());
+        }
+
+        Ok(SwapQuote {
+            venue: Self::VENUE,
+            pool_id: *pool_id,
+            mint: *mint,
+            side,
+            amount_in: quote.swap.amount_in,
+            amount_out: quote.swap.amount_out,
+            creator: Some(quote.pool_state.owner),
+            quote_liquidity: match side {
+                TradeSide::Buy => quote.input_vault_amount,
+                TradeSide::Sell => 0,
+            },
+            pool_open_time: Some(quote.pool_state.open_time),
+            price_impact: quote.swap.price_impact,
+            details: QuoteDetails::RaydiumClmm(quote),
+        })
+    }
+
+    async fn build_swap(
+        ctx: &VenueContext,
+        quote: &SwapQuote,
+        wallet: &Pubkey,
+        slippage_percent: u64,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let QuoteDetails::RaydiumClmm(details) = &quote.details else {
+            return Err("Not a Raydium CLMM quote".into());
+        };
+
+        Self::build_swap(
+            Arc::clone(&ctx.helius),
+            details,
+            wallet,
+            slippage_percent,
+            ctx.wsol_mode,
+        )
+        .await
+    }
+
+    async fn parse_trade(_ctx: &VenueContext, payload: &str) -> Option<ParsedTrade> {
+        let tx = RaydiumClmmTx::new(payload.to_string());
+
+        ParsedTrade::from_meme_trade(
+            Self::VENUE,
+            tx.signature.clone(),
+            &tx.accounts.as_ref()?.pool_state,
+            tx.meme_trade_data.as_ref()?,
+            tx.inner_ix_data.as_ref()?,
+            tx.compute_unit_limit,
+        )
+    }
+}
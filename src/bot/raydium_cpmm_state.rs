@@ -0,0 +1,203 @@
+use bytemuck::{Pod, Zeroable};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::constants::{
+    RAYDIUM_AMM_CONFIG_DISCRIMINATOR, RAYDIUM_CPMM_FEE_RATE_DENOMINATOR, RAYDIUM_CPMM_PROGRAM,
+    RAYDIUM_POOL_STATE_DISCRIMINATOR,
+};
+
+use super::AccountDecoder;
+
+/// Raydium CPMM pool state.
+/// Based on https://github.com/raydium-io/raydium-cp-swap/blob/master/programs/cp-swap/src/states/pool.rs
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct CpmmPoolState {
+    /// Which config the pool belongs
+    pub amm_config: Pubkey,
+    /// pool creator
+    pub pool_creator: Pubkey,
+    /// Token A
+    pub token_0_vault: Pubkey,
+    /// Token B
+    pub token_1_vault: Pubkey,
+    /// Pool tokens are issued when A or B tokens are deposited.
+    pub lp_mint: Pubkey,
+    /// Mint information for token A
+    pub token_0_mint: Pubkey,
+    /// Mint information for token B
+    pub token_1_mint: Pubkey,
+    /// token_0 program
+    pub token_0_program: Pubkey,
+    /// token_1 program
+    pub token_1_program: Pubkey,
+    /// observation account to store oracle data
+    pub observation_key: Pubkey,
+    pub auth_bump: u8,
+    /// Bitwise representation of the state of the pool
+    /// bit0, 1: disable deposit(vaule is 1), 0: normal
+    /// bit1, 1: disable withdraw(vaule is 2), 0: normal
+    /// bit2, 1: disable swap(vaule is 4), 0: normal
+    pub status: u8,
+    pub lp_mint_decimals: u8,
+    /// mint0 and mint1 decimals
+    pub mint_0_decimals: u8,
+    pub mint_1_decimals: u8,
+    /// lp mint supply
+    pub lp_supply: u64,
+    /// The amounts of token_0 and token_1 that are owed to the liquidity provider.
+    pub protocol_fees_token_0: u64,
+    pub protocol_fees_token_1: u64,
+    pub fund_fees_token_0: u64,
+    pub fund_fees_token_1: u64,
+    /// The timestamp allowed for swap in the pool.
+    pub open_time: u64,
+    /// recent epoch
+    pub recent_epoch: u64,
+    /// padding for future updates
+    pub padding: [u64; 31],
+}
+
+/// Raydium CPMM fee configuration, shared by all the pools created with it.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct CpmmAmmConfig {
+    /// Bump to identify PDA
+    pub bump: u8,
+    /// Status to control if new pool can be create
+    pub disable_create_pool: u8,
+    /// Config index
+    pub index: u16,
+    /// The trade fee, denominated in hundredths of a bip (10^-6)
+    pub trade_fee_rate: u64,
+    /// The protocol fee
+    pub protocol_fee_rate: u64,
+    /// The fund fee, denominated in hundredths of a bip (10^-6)
+    pub fund_fee_rate: u64,
+    /// Fee for create a new pool
+    pub create_pool_fee: u64,
+    /// Address of the protocol fee owner
+    pub protocol_owner: Pubkey,
+    /// Address of the fund fee owner
+    pub fund_owner: Pubkey,
+    /// padding
+    pub padding: [u64; 16],
+}
+
+// CLMM pools share the discriminator, the owner tells them apart
+impl AccountDecoder for CpmmPoolState {
+    const OWNERS: &'static [Pubkey] = &[RAYDIUM_CPMM_PROGRAM];
+    const HEAD: &'static [u8] = RAYDIUM_POOL_STATE_DISCRIMINATOR;
+}
+
+/// Swap disabled bit of `CpmmPoolState::status`
+const CPMM_STATUS_SWAP_DISABLED: u8 = 1 << 2;
+
+impl CpmmPoolState {
+    /// Returns true if swaps are currently allowed on the pool
+    pub fn is_swap_enabled(&self) -> bool {
+        self.status & CPMM_STATUS_SWAP_DISABLED == 0
+    }
+
+    /// Returns true if `mint` is token 0 of the pool, false if it is token 1
+    /// and None if the pool does not trade it.
+    pub fn is_token_0(&self, mint: &Pubkey) -> Option<bool> {
+        let (token_0_mint, token_1_mint) = (self.token_0_mint, self.token_1_mint);
+
+        if token_0_mint == *mint {
+            Some(true)
+        } else if token_1_mint == *mint {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the swappable reserves given the raw vault balances,
+    /// i.e. without the protocol and fund fees still sitting in the vaults.
+    ///
+    /// * `vault_0_amount` - The token 0 vault balance
+    /// * `vault_1_amount` - The token 1 vault balance
+    pub fn reserves(&self, vault_0_amount: u64, vault_1_amount: u64) -> (u64, u64) {
+        let (protocol_fees_0, fund_fees_0) = (self.protocol_fees_token_0, self.fund_fees_token_0);
+        let (protocol_fees_1, fund_fees_1) = (self.protocol_fees_token_1, self.fund_fees_token_1);
+
+        (
+            vault_0_amount
+                .saturating_sub(protocol_fees_0)
+                .saturating_sub(fund_fees_0),
+            vault_1_amount
+                .saturating_sub(protocol_fees_1)
+                .saturating_sub(fund_fees_1),
+        )
+    }
+
+    /// Constant product quote for an exact input swap, the trade fee is
+    /// taken from the input amount (rounded up) like the on-chain program does.
+    ///
+    /// * `amount_in` - The exact input amount
+    /// * `reserve_in` - The swappable reserve of the input token
+    /// * `reserve_out` - The swappable reserve of the output token
+    /// * `trade_fee_rate` - The `CpmmAmmConfig::trade_fee_rate`
+    pub fn quote_base_input(
+        amount_in: u64,
+        reserve_in: u64,
+        reserve_out: u64,
+        trade_fee_rate: u64,
+    ) -> u64 {
+        let amount_in = amount_in as u128;
+        let fee = (amount_in * trade_fee_rate as u128)
+            .div_ceil(RAYDIUM_CPMM_FEE_RATE_DENOMINATOR as u128);
+        let amount_in_less_fee = amount_in.saturating_sub(fee);
+
+        let denominator = reserve_in as u128 + amount_in_less_fee;
+        if denominator == 0 {
+            return 0;
+        }
+
+        (amount_in_less_fee * reserve_out as u128 / denominator) as u64
+    }
+}
+
+impl AccountDecoder for CpmmAmmConfig {
+    const OWNERS: &'static [Pubkey] = &[RAYDIUM_CPMM_PROGRAM];
+    const HEAD: &'static [u8] = RAYDIUM_AMM_CONFIG_DISCRIMINATOR;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_state_size() {
+        // 637 bytes on chain, discriminator included
+        assert_eq!(8 + std::mem::size_of::<CpmmPoolState>(), 637);
+        assert_eq!(8 + std::mem::size_of::<CpmmAmmConfig>(), 236);
+    }
+
+    #[test]
+    fn test_pool_state_decode() {
+        let token_0_mint = Pubkey::new_unique();
+        let mut data = RAYDIUM_POOL_STATE_DISCRIMINATOR.to_vec();
+        data.resize(637, 0);
+        // token_0_mint is the 6th pubkey after the discriminator
+        data[8 + 5 * 32..8 + 6 * 32].copy_from_slice(token_0_mint.as_ref());
+
+        let pool_state = CpmmPoolState::decode(&data).unwrap();
+        assert_eq!(pool_state.is_token_0(&token_0_mint), Some(true));
+        assert!(pool_state.is_swap_enabled());
+
+        data[0] = 0;
+        assert!(CpmmPoolState::decode(&data).is_err());
+    }
+
+    #[test]
+    fn test_quote_base_input() {
+        // 0.25% fee on 1_000_000 in, 1:1 pool of 1_000_000_000
+        let amount_out =
+            CpmmPoolState::quote_base_input(1_000_000, 1_000_000_000, 1_000_000_000, 2_500);
+        assert_eq!(amount_out, 996_505);
+
+        assert_eq!(CpmmPoolState::quote_base_input(0, 0, 0, 2_500), 0);
+    }
+}
@@ -0,0 +1,378 @@
+//! Given a transaction having among it's instructions a Raydium CPMM (CP-Swap) swap,
+//! this module extracts the swap accounts, amounts and direction.
+//!
+//! Unlike the V4 AMM, the CPMM swap instruction lists both mints, so the meme mint
+//! and the trade direction are known without fetching the pool state.
+//!
+//! The parsed transaction has the same shape as `RaydiumMemeTx`, the swap data
+//! reuses `RaydiumSwapData` with the V4 instruction tags standing for the
+//! base input / base output variants.
+
+use serde::Serialize;
+use serde_json::{from_str, Value};
+
+use crate::bot::tx_common::GetSignature;
+use crate::constants::{
+    BOT_RAYDIUM_OPERATION_BUY, BOT_RAYDIUM_OPERATION_SELL, RAYDIUM_CPMM_ACCOUNTS_LEN_SWAP,
+    RAYDIUM_CPMM_ACTION_SWAP_BASE_INPUT, RAYDIUM_CPMM_ACTION_SWAP_BASE_OUTPUT,
+    RAYDIUM_CPMM_PROGRAM, RAYDIUM_SWAP_BASE_IN_INSTRUCTION, RAYDIUM_SWAP_BASE_OUT_INSTRUCTION,
+    SPL_TOKEN_2022_PROGRAM, WSOL_MINT,
+};
+
+use super::raydium_meme_tx::{
+    InnerIxData, RaydiumMemeTradeData, RaydiumSwapBaseInData, RaydiumSwapBaseOutData,
+    RaydiumSwapData,
+};
+use super::tx_common::{GetComputeData, GetTxCommon};
+
+/// Raydium CPMM swap instruction accounts
+/// Based on https://github.com/raydium-io/raydium-cp-swap/blob/master/programs/cp-swap/src/instructions/swap_base_input.rs
+#[derive(Debug, Serialize, PartialEq)]
+pub struct RaydiumCpmmAccounts {
+    pub payer: String,
+    pub amm_config: String,
+    pub pool_state: String,
+    pub input_token_account: String,
+    pub output_token_account: String,
+    pub input_vault: String,
+    pub output_vault: String,
+    pub input_token_program: String,
+    pub output_token_program: String,
+    pub input_token_mint: String,
+    pub output_token_mint: String,
+    pub observation_state: String,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct RaydiumCpmmTx {
+    pub signature: Option<String>,
+    pub accounts: Option<RaydiumCpmmAccounts>,
+    pub ix_data: Option<RaydiumSwapData>,
+    pub inner_ix_data: Option<InnerIxData>,
+    pub meme_trade_data: Option<RaydiumMemeTradeData>,
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64,
+}
+
+impl GetSignature for RaydiumCpmmTx {}
+
+impl GetComputeData for RaydiumCpmmTx {}
+
+impl GetTxCommon for RaydiumCpmmTx {}
+
+impl RaydiumCpmmTx {
+    /// Creates a new RaydiumCpmmTx instance from a transaction payload.
+    ///
+    /// * `payload` - The transaction payload
+    pub fn new(payload: String) -> Self {
+        let json: Value = match from_str(payload.as_str()) {
+            Ok(json) => json,
+            Err(_) => return Self::default(),
+        };
+
+        let signature = match Self::get_signature(&json) {
+            Some(signature) => signature,
+            None => return Self::default(),
+        };
+
+        let swap_ix = match Self::get_cpmm_swap_instruction(&json) {
+            Some(swap_ix) => swap_ix,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let accounts = match Self::get_accounts(swap_ix) {
+            Some(accounts) => accounts,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let ix_data = match swap_ix["data"]
+            .as_str()
+            .and_then(Self::get_instruction_data)
+        {
+            Some(ix_data) => ix_data,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    accounts: Some(accounts),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let (source_amount, dest_amount) = match Self::get_amounts(&json, &accounts) {
+            Some(amounts) => amounts,
+            None => {
+                return Self {
+                    signature: Some(signature),
+                    accounts: Some(accounts),
+                    ix_data: Some(ix_data),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let meme_trade_data = Self::get_meme_trade_data(&accounts);
+
+        let (compute_unit_limit, compute_unit_price) =
+            Self::get_compute_data(&json).unwrap_or((0, 0));
+
+        Self {
+            signature: Some(signature),
+            accounts: Some(accounts),
+            ix_data: Some(ix_data),
+            inner_ix_data: Some(InnerIxData {
+                source_amount,
+                dest_amount,
+            }),
+            meme_trade_data,
+            compute_unit_limit,
+            compute_unit_price,
+        }
+    }
+
+    /// Finds the CPMM swap instruction in the transaction,
+    /// either as a main instruction or as an inner instruction.
+    fn get_cpmm_swap_instruction(json: &Value) -> Option<&Value> {
+        let is_cpmm_swap = |instruction: &Value| {
+            instruction["programId"]
+                .as_str()
+                .map_or(false, |id| id == RAYDIUM_CPMM_PROGRAM.to_string())
+        };
+
+        // Check main instructions
+        let instructions = json["params"]["result"]["transaction"]["transaction"]["message"]
+            ["instructions"]
+            .as_array()?;
+
+        if let Some(instruction) = instructions.iter().find(|&ix| is_cpmm_swap(ix)) {
+            return Some(instruction);
+        }
+
+        // Check inner instructions
+        json["params"]["result"]["transaction"]["meta"]["innerInstructions"]
+            .as_array()?
+            .iter()
+            .find_map(|inner_ix_group| {
+                inner_ix_group["instructions"]
+                    .as_array()?
+                    .iter()
+                    .find(|&ix| is_cpmm_swap(ix))
+            })
+    }
+
+    /// Extracts the CPMM swap accounts, both swap instructions share the same list.
+    ///
+    /// * `swap_instruction` - The CPMM swap instruction
+    fn get_accounts(swap_instruction: &Value) -> Option<RaydiumCpmmAccounts> {
+        let accounts = swap_instruction["accounts"].as_array()?;
+
+        if accounts.len() != RAYDIUM_CPMM_ACCOUNTS_LEN_SWAP {
+            return None;
+        }
+
+        Some(RaydiumCpmmAccounts {
+            payer: Self::pubkey_to_string(&accounts[0]),
+            // account[1] - CPMM Authority
+            amm_config: Self::pubkey_to_string(&accounts[2]),
+            pool_state: Self::pubkey_to_string(&accounts[3]),
+            input_token_account: Self::pubkey_to_string(&accounts[4]),
+            output_token_account: Self::pubkey_to_string(&accounts[5]),
+            input_vault: Self::pubkey_to_string(&accounts[6]),
+            output_vault: Self::pubkey_to_string(&accounts[7]),
+            input_token_program: Self::pubkey_to_string(&accounts[8]),
+            output_token_program: Self::pubkey_to_string(&accounts[9]),
+            input_token_mint: Self::pubkey_to_string(&accounts[10]),
+            output_token_mint: Self::pubkey_to_string(&accounts[11]),
+            observation_state: Self::pubkey_to_string(&accounts[12]),
+        })
+    }
+
+    /// Extracts the data from the CPMM swap instruction
+    ///
+    /// * `input` - The base58 instruction data
+    fn get_instruction_data(input: &str) -> Option<RaydiumSwapData> {
+        let input = bs58::decode(input).into_vec().ok()?;
+
+        if input.len() < 24 {
+            return None;
+        }
+
+        let first = u64::from_le_bytes(input[8..16].try_into().ok()?);
+        let second = u64::from_le_bytes(input[16..24].try_into().ok()?);
+
+        match &input[..8] {
+            RAYDIUM_CPMM_ACTION_SWAP_BASE_INPUT => {
+                Some(RaydiumSwapData::BaseIn(RaydiumSwapBaseInData {
+                    instruction: RAYDIUM_SWAP_BASE_IN_INSTRUCTION,
+                    amount_in: first,
+                    minimum_amount_out: second,
+                }))
+            }
+            RAYDIUM_CPMM_ACTION_SWAP_BASE_OUTPUT => {
+                Some(RaydiumSwapData::BaseOut(RaydiumSwapBaseOutData {
+                    instruction: RAYDIUM_SWAP_BASE_OUT_INSTRUCTION,
+                    max_amount_in: first,
+                    amount_out: second,
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Extracts the exact amounts swapped from the inner token transfers,
+    /// `transfer` for SPL Token mints and `transferChecked` for Token-2022 mints.
+    ///
+    /// * `json` - The transaction JSON
+    /// * `accounts` - The CPMM swap accounts
+    fn get_amounts(json: &Value, accounts: &RaydiumCpmmAccounts) -> Option<(u64, u64)> {
+        let inner_instructions =
+            json["params"]["result"]["transaction"]["meta"]["innerInstructions"].as_array()?;
+
+        let token_programs = [
+            spl_token::id().to_string(),
+            SPL_TOKEN_2022_PROGRAM.to_string(),
+        ];
+
+        let default_array: Vec<Value> = Vec::new();
+        let (source_amount, dest_amount) = inner_instructions
+            .iter()
+            .flat_map(|inner_ix| {
+                inner_ix["instructions"]
+                    .as_array()
+                    .unwrap_or(&default_array)
+            })
+            .filter(|ix| {
+                ix["programId"].as_str().map_or(false, |id| {
+                    token_programs.iter().any(|program| program == id)
+                })
+            })
+            .filter_map(|ix| {
+                let info = &ix["parsed"]["info"];
+                let amount = info["amount"]
+                    .as_str()
+                    .or_else(|| info["tokenAmount"]["amount"].as_str())?
+                    .parse::<u64>()
+                    .ok()?;
+
+                if info["source"].as_str() == Some(&accounts.input_token_account) {
+                    Some((amount, 0))
+                } else if info["destination"].as_str() == Some(&accounts.output_token_account) {
+                    Some((0, amount))
+                } else {
+                    None
+                }
+            })
+            .fold((0, 0), |(src, dst), (s, d)| (src + s, dst + d));
+
+        Some((source_amount, dest_amount))
+    }
+
+    /// Detects the meme mint and the trade direction from the swap mints.
+    /// Only SOL paired swaps are considered.
+    ///
+    /// * `accounts` - The CPMM swap accounts
+    fn get_meme_trade_data(accounts: &RaydiumCpmmAccounts) -> Option<RaydiumMemeTradeData> {
+        let wsol = WSOL_MINT.to_string();
+
+        if accounts.input_token_mint == wsol && accounts.output_token_mint != wsol {
+            Some(RaydiumMemeTradeData {
+                meme_mint: accounts.output_token_mint.parse().ok()?,
+                operation: BOT_RAYDIUM_OPERATION_BUY,
+            })
+        } else if accounts.output_token_mint == wsol && accounts.input_token_mint != wsol {
+            Some(RaydiumMemeTradeData {
+                meme_mint: accounts.input_token_mint.parse().ok()?,
+                operation: BOT_RAYDIUM_OPERATION_SELL,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a formatted string containing transaction details
+    pub fn format_tx_info(&self) -> String {
+        if let (Some(trade_data), Some(inner_ix), Some(sig)) =
+            (&self.meme_trade_data, &self.inner_ix_data, &self.signature)
+        {
+            let operation = if trade_data.operation == BOT_RAYDIUM_OPERATION_BUY {
+                "Buy"
+            } else {
+                "Sell"
+            };
+
+            format!(
+                "CPMM {} | Mint: {} | Source Amount: {} | Destination Amount: {} | TX: https://solscan.io/tx/{}",
+                operation, trade_data.meme_mint, inner_ix.source_amount, inner_ix.dest_amount, sig
+            )
+        } else {
+            "RaydiumCpmmTx: Error formatting transaction data".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Synthetic CPMM buy: 0.1 WSOL in, 3_510_000_000 meme out
+    const TEST_JSON_CPMM_BUY: &str = r#"{"jsonrpc":"2.0","method":"transactionNotification","params":{"subscription":1,"result":{"transaction":{"transaction":{"signatures":["C884DpRETi7Ryhkou1DY5wwZ8iD44xGy3ddZZzDmUKDJ"],"message":{"instructions":[{"programId":"ComputeBudget111111111111111111111111111111","accounts":[],"data":"JxrTou","stackHeight":null},{"programId":"CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C","accounts":["AWxggjuZRmWULwxwPeM6ZZxRtdDdekVq22mFRx2QbW7U","GpMZbSM2GgvTKHJirzeGfMFoaZ8UR2X7F4v8vHTvxFbL","DMeJ2Ztq2AmTZRGLkjXqzkas8oHuybGvmi6uXYuWvyg3","3gLESRnfLgzAqu6PwGhBwsiBsnQ7BAtyWHhZ5zNcDPMF","DEjME4DXrMqz1hRtpKcZC7VznUa1RKg7DZ3CoTUHzgdV","FsTZPwCVYtEx8fkMf1TUCVqb3EW9N1gEYnbkQGGMEdaD","G6DeLupKfjKJiThG3ZT2rNYyZWLk2CUpuvhJM5GBxbR8","7SEeH1PMDDoyX5Lf1gHo4rjCzMc5JurEQuo93PvMPSth","TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","So11111111111111111111111111111111111111112","BwVdttcP4EnbKEkztKxFiUquPCtdDmVMpBUXSf38Aiuf","FF5WWr1HKNtf7Z4YJfyo19GaLQoNv2gHcDxVyZVMAeEH"],"data":"E73fXHPWvSQzf3eeNeAfhPZ4wNMhnbZbm","stackHeight":null}]}},"meta":{"err":null,"innerInstructions":[{"index":1,"instructions":[{"program":"spl-token","programId":"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","parsed":{"info":{"authority":"AWxggjuZRmWULwxwPeM6ZZxRtdDdekVq22mFRx2QbW7U","destination":"G6DeLupKfjKJiThG3ZT2rNYyZWLk2CUpuvhJM5GBxbR8","mint":"So11111111111111111111111111111111111111112","source":"DEjME4DXrMqz1hRtpKcZC7VznUa1RKg7DZ3CoTUHzgdV","tokenAmount":{"amount":"100000000","decimals":9}},"type":"transferChecked"},"stackHeight":2},{"program":"spl-token","programId":"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","parsed":{"info":{"authority":"GpMZbSM2GgvTKHJirzeGfMFoaZ8UR2X7F4v8vHTvxFbL","destination":"FsTZPwCVYtEx8fkMf1TUCVqb3EW9N1gEYnbkQGGMEdaD","mint":"BwVdttcP4EnbKEkztKxFiUquPCtdDmVMpBUXSf38Aiuf","source":"7SEeH1PMDDoyX5Lf1gHo4rjCzMc5JurEQuo93PvMPSth","tokenAmount":{"amount":"3510000000","decimals":6}},"type":"transferChecked"},"stackHeight":2}]}]},"version":0},"signature":"C884DpRETi7Ryhkou1DY5wwZ8iD44xGy3ddZZzDmUKDJ","slot":1}}}"#;
+
+    #[test]
+    fn test_get_cpmm_swap_instruction() {
+        let json: Value = from_str(TEST_JSON_CPMM_BUY).unwrap();
+        assert!(RaydiumCpmmTx::get_cpmm_swap_instruction(&json).is_some());
+    }
+
+    #[test]
+    fn test_get_instruction_data() {
+        let json: Value = from_str(TEST_JSON_CPMM_BUY).unwrap();
+        let swap_ix = RaydiumCpmmTx::get_cpmm_swap_instruction(&json).unwrap();
+        let ix_data = RaydiumCpmmTx::get_instruction_data(swap_ix["data"].as_str().unwrap());
+        assert!(matches!(
+            ix_data,
+            Some(RaydiumSwapData::BaseIn(RaydiumSwapBaseInData {
+                amount_in: 100_000_000,
+                minimum_amount_out: 3_500_000_000,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_new_buy() {
+        let cpmm_tx = RaydiumCpmmTx::new(TEST_JSON_CPMM_BUY.to_string());
+        let accounts = cpmm_tx.accounts.unwrap();
+        assert_eq!(
+            accounts.pool_state,
+            "3gLESRnfLgzAqu6PwGhBwsiBsnQ7BAtyWHhZ5zNcDPMF"
+        );
+
+        let inner_ix_data = cpmm_tx.inner_ix_data.unwrap();
+        assert_eq!(inner_ix_data.source_amount, 100_000_000);
+        assert_eq!(inner_ix_data.dest_amount, 3_510_000_000);
+
+        let meme_trade_data = cpmm_tx.meme_trade_data.unwrap();
+        assert_eq!(meme_trade_data.operation, BOT_RAYDIUM_OPERATION_BUY);
+        assert_eq!(
+            meme_trade_data.meme_mint.to_string(),
+            "BwVdttcP4EnbKEkztKxFiUquPCtdDmVMpBUXSf38Aiuf"
+        );
+    }
+
+    #[test]
+    fn test_get_accounts_wrong_len() {
+        let json_str = r#"{"programId":"CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C","accounts":["a","b","c"]}"#;
+        let swap_ix: Value = from_str(json_str).unwrap();
+        assert_eq!(RaydiumCpmmTx::get_accounts(&swap_ix), None);
+    }
+}
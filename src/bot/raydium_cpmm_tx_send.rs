@@ -0,0 +1,328 @@
+use helius::Helius;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use std::{error::Error, sync::Arc};
+use tokio::sync::Mutex;
+
+use crate::constants::{
+    RAYDIUM_CPMM_ACTION_SWAP_BASE_INPUT, RAYDIUM_CPMM_AUTHORITY, RAYDIUM_CPMM_COMPUTE_UNIT_LIMIT,
+    RAYDIUM_CPMM_PROGRAM, WSOL_MINT,
+};
+
+use super::{
+    price_impact::constant_product_price_impact,
+    tx_common::SendSmartTx,
+    venue::{deepest_sol_pool, find_sol_paired_pools},
+    wsol::{Wsol, WsolMode},
+    AccountDecoder, CpmmAmmConfig, CpmmPoolState, ParsedTrade, QuoteDetails, RaydiumCpmmTx, Swap,
+    SwapQuote, TradeSide, Venue, VenueContext,
+};
+
+/// A CPMM exact input quote, with everything needed to build the swap
+#[derive(Debug, Clone)]
+pub struct CpmmQuote {
+    pub pool_id: Pubkey,
+    pub pool_state: CpmmPoolState,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    /// Pool reserves of the input and output mints the quote was computed on
+    pub reserve_in: u64,
+    pub reserve_out: u64,
+    pub amount_in: u64,
+    pub amount_out: u64,
+}
+
+impl CpmmQuote {
+    /// Returns (input vault, output vault, input token program, output token program)
+    fn sides(&self) -> (Pubkey, Pubkey, Pubkey, Pubkey) {
+        let pool_state = self.pool_state;
+        if pool_state.token_0_mint == self.input_mint {
+            (
+                pool_state.token_0_vault,
+                pool_state.token_1_vault,
+                pool_state.token_0_program,
+                pool_state.token_1_program,
+            )
+        } else {
+            (
+                pool_state.token_1_vault,
+                pool_state.token_0_vault,
+                pool_state.token_1_program,
+                pool_state.token_0_program,
+            )
+        }
+    }
+}
+
+pub struct RaydiumCpmmTxSend {}
+
+impl SendSmartTx for RaydiumCpmmTxSend {}
+
+impl RaydiumCpmmTxSend {
+    /// Quotes an exact input swap on a CPMM pool using the current vault balances.
+    ///
+    /// * `helius` - The Helius client
+    /// * `pool_id` - The CPMM pool state account
+    /// * `input_mint` - The mint being sold to the pool
+    /// * `amount_in` - The exact input amount
+    pub async fn quote(
+        helius: Arc<Mutex<Helius>>,
+        pool_id: &Pubkey,
+        input_mint: &Pubkey,
+        amount_in: u64,
+    ) -> Result<CpmmQuote, Box<dyn Error + Send + Sync>> {
+        let connection = helius.lock().await.async_connection()?;
+
+        let pool_state = *CpmmPoolState::decode_account(&connection.get_account(pool_id).await?)?;
+
+        if !pool_state.is_swap_enabled() {
+            return Err("Swaps are disabled on this CPMM pool".into());
+        }
+
+        let is_token_0 = pool_state
+            .is_token_0(input_mint)
+            .ok_or("Mint is not traded by this CPMM pool")?;
+
+        let amm_config =
+            *CpmmAmmConfig::decode_account(&connection.get_account(&pool_state.amm_config).await?)?;
+
+        let vault_0_amount = connection
+            .get_token_account_balance(&pool_state.token_0_vault)
+            .await?
+            .amount
+            .parse::<u64>()?;
+        let vault_1_amount = connection
+            .get_token_account_balance(&pool_state.token_1_vault)
+            .await?
+            .amount
+            .parse::<u64>()?;
+
+        let (reserve_0, reserve_1) = pool_state.reserves(vault_0_amount, vault_1_amount);
+        let (reserve_in, reserve_out, output_mint) = if is_token_0 {
+            (reserve_0, reserve_1, pool_state.token_1_mint)
+        } else {
+            (reserve_1, reserve_0, pool_state.token_0_mint)
+        };
+
+        let amount_out = CpmmPoolState::quote_base_input(
+            amount_in,
+            reserve_in,
+            reserve_out,
+            amm_config.trade_fee_rate,
+        );
+
+        Ok(CpmmQuote {
+            pool_id: *pool_id,
+            pool_state,
+            input_mint: *input_mint,
+            output_mint,
+            reserve_in,
+            reserve_out,
+            amount_in,
+            amount_out,
+        })
+    }
+
+    /// Builds the CPMM `swap_base_input` instruction for the given owner.
+    ///
+    /// * `quote` - The quote to execute
+    /// * `owner` - The wallet swapping
+    /// * `minimum_amount_out` - The minimum output accepted
+    pub fn swap_base_input_instruction(
+        quote: &CpmmQuote,
+        owner: &Pubkey,
+        minimum_amount_out: u64,
+    ) -> Instruction {
+        let (input_vault, output_vault, input_token_program, output_token_program) = quote.sides();
+        let pool_state = quote.pool_state;
+
+        let mut data = RAYDIUM_CPMM_ACTION_SWAP_BASE_INPUT.to_vec();
+        data.extend_from_slice(&quote.amount_in.to_le_bytes());
+        data.extend_from_slice(&minimum_amount_out.to_le_bytes());
+
+        Instruction {
+            program_id: RAYDIUM_CPMM_PROGRAM,
+            accounts: vec![
+                AccountMeta::new(*owner, true),
+                AccountMeta::new_readonly(RAYDIUM_CPMM_AUTHORITY, false),
+                AccountMeta::new_readonly(pool_state.amm_config, false),
+                AccountMeta::new(quote.pool_id, false),
+                AccountMeta::new(
+                    get_associated_token_address_with_program_id(
+                        owner,
+                        &quote.input_mint,
+                        &input_token_program,
+                    ),
+                    false,
+                ),
+                AccountMeta::new(
+                    get_associated_token_address_with_program_id(
+                        owner,
+                        &quote.output_mint,
+                        &output_token_program,
+                    ),
+                    false,
+                ),
+                AccountMeta::new(input_vault, false),
+                AccountMeta::new(output_vault, false),
+                AccountMeta::new_readonly(input_token_program, false),
+                AccountMeta::new_readonly(output_token_program, false),
+                AccountMeta::new_readonly(quote.input_mint, false),
+                AccountMeta::new_readonly(quote.output_mint, false),
+                AccountMeta::new(pool_state.observation_key, false),
+            ],
+            data,
+        }
+    }
+
+    /// Builds the instruction list executing a quote for the given owner.
+    ///
+    /// * `helius` - The Helius client
+    /// * `quote` - The quote to execute
+    /// * `owner` - The wallet swapping
+    /// * `slippage_percent` - The slippage applied to the quoted output
+    /// * `wsol_mode` - The WSOL funding mode
+    pub async fn build_swap(
+        helius: Arc<Mutex<Helius>>,
+        quote: &CpmmQuote,
+        owner: &Pubkey,
+        slippage_percent: u64,
+        wsol_mode: WsolMode,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let (_, _, _, output_token_program) = quote.sides();
+        let minimum_amount_out =
+            (quote.amount_out as f64 * (100.0 - slippage_percent as f64) / 100.0).floor() as u64;
+
+        let (pre_swap_instructions, post_swap_instructions) = if quote.input_mint == WSOL_MINT {
+            Wsol::buy_instructions(helius, owner, wsol_mode, quote.amount_in).await?
+        } else if quote.output_mint == WSOL_MINT {
+            Wsol::sell_instructions(owner, wsol_mode)?
+        } else {
+            (vec![], vec![])
+        };
+
+        let mut instructions = pre_swap_instructions;
+        if quote.output_mint != WSOL_MINT {
+            instructions.push(create_associated_token_account_idempotent(
+                owner,
+                owner,
+                &quote.output_mint,
+                &output_token_program,
+            ));
+        }
+        instructions.push(Self::swap_base_input_instruction(
+            quote,
+            owner,
+            minimum_amount_out,
+        ));
+        instructions.extend(post_swap_instructions);
+
+        Ok(instructions)
+    }
+}
+
+impl Swap for RaydiumCpmmTxSend {
+    const VENUE: Venue = Venue::RaydiumCpmm;
+    const COMPUTE_UNIT_LIMIT: u32 = RAYDIUM_CPMM_COMPUTE_UNIT_LIMIT;
+
+    async fn find_pool(
+        ctx: &VenueContext,
+        mint: &Pubkey,
+    ) -> Result<Pubkey, Box<dyn Error + Send + Sync>> {
+        let connection = ctx.helius.lock().await.async_connection()?;
+
+        let pools = find_sol_paired_pools::<CpmmPoolState>(
+            &connection,
+            &RAYDIUM_CPMM_PROGRAM,
+            [
+                std::mem::offset_of!(CpmmPoolState, token_0_mint),
+                std::mem::offset_of!(CpmmPoolState, token_1_mint),
+            ],
+            mint,
+        )
+        .await?
+        .into_iter()
+        .filter(|(_, pool)| pool.is_swap_enabled())
+        .map(|(pool_id, pool)| {
+            let wsol_vault = if pool.token_0_mint == WSOL_MINT {
+                pool.token_0_vault
+            } else {
+                pool.token_1_vault
+            };
+            (pool_id, wsol_vault)
+        })
+        .collect();
+
+        deepest_sol_pool(&connection, pools).await
+    }
+
+    async fn quote_pool(
+        ctx: &VenueContext,
+        pool_id: &Pubkey,
+        mint: &Pubkey,
+        side: TradeSide,
+        amount: u64,
+    ) -> Result<SwapQuote, Box<dyn Error + Send + Sync>> {
+        let (input_mint, output_mint) = side.mints(mint);
+        let quote = Self::quote(Arc::clone(&ctx.helius), pool_id, &input_mint, amount).await?;
+        if quote.output_mint != output_mint {
+            return Err("Only SOL paired CPMM pools are traded".into());
+        }
+
+        Ok(SwapQuote {
+            venue: Self::VENUE,
+            pool_id: *pool_id,
+            mint: *mint,
+            side,
+            amount_in: quote.amount_in,
+            amount_out: quote.amount_out,
+            creator: Some(quote.pool_state.pool_creator),
+            quote_liquidity: match side {
+                TradeSide::Buy => quote.reserve_in,
+                TradeSide::Sell => quote.reserve_out,
+            },
+            pool_open_time: Some(quote.pool_state.open_time),
+            price_impact: constant_product_price_impact(quote.amount_in, quote.reserve_in),
+            details: QuoteDetails::RaydiumCpmm(quote),
+        })
+    }
+
+    async fn build_swap(
+        ctx: &VenueContext,
+        quote: &SwapQuote,
+        wallet: &Pubkey,
+        slippage_percent: u64,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let QuoteDetails::RaydiumCpmm(details) = &quote.details else {
+            return Err("Not a Raydium CPMM quote".into());
+        };
+
+        Self::build_swap(
+            Arc::clone(&ctx.helius),
+            details,
+            wallet,
+            slippage_percent,
+            ctx.wsol_mode,
+        )
+        .await
+    }
+
+    async fn parse_trade(_ctx: &VenueContext, payload: &str) -> Option<ParsedTrade> {
+        let tx = RaydiumCpmmTx::new(payload.to_string());
+
+        ParsedTrade::from_meme_trade(
+            Self::VENUE,
+            tx.signature.clone(),
+            &tx.accounts.as_ref()?.pool_state,
+            tx.meme_trade_data.as_ref()?,
+            tx.inner_ix_data.as_ref()?,
+            tx.compute_unit_limit,
+        )
+    }
+}
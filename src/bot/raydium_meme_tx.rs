@@ -1,18 +1,17 @@
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc};
 
 use helius::Helius;
 use serde::Serialize;
 use serde_json::{from_str, Value};
-use solana_program::program_pack::Pack;
 use solana_sdk::pubkey::Pubkey;
-use spl_token::state::Account as TokenAccount;
+use tokio::sync::Mutex;
 
 use crate::bot::tx_common::GetSignature;
-use crate::bot::AmmInfo;
+use crate::bot::{AccountDecoder, AmmInfo, SplTokenAccountState, TokenFilter};
 use crate::constants::{
     BOT_RAYDIUM_OPERATION_BUY, BOT_RAYDIUM_OPERATION_SELL, RAYDIUM_ACCOUNTS_LEN_SWAP_BASE_IN,
     RAYDIUM_LIQUIDITY_POOL_V4_PROGRAM, RAYDIUM_SWAP_BASE_IN_INSTRUCTION,
-    RAYDIUM_SWAP_BASE_OUT_INSTRUCTION, WSOL_MINT,
+    RAYDIUM_SWAP_BASE_OUT_INSTRUCTION,
 };
 use crate::errors::BotError;
 
@@ -117,8 +116,16 @@ impl RaydiumMemeTx {
     ///
     /// * `payload` - The transaction payload
     /// * `helius` - The Helius client
-    pub async fn new(payload: String, helius: Arc<Helius>) -> Self {
-        let json: Value = from_str(payload.as_str()).expect("Invalid JSON");
+    /// * `token_filter` - The token eligibility policy
+    pub async fn new(
+        payload: String,
+        helius: Arc<Mutex<Helius>>,
+        token_filter: &TokenFilter,
+    ) -> Self {
+        let json: Value = match from_str(payload.as_str()) {
+            Ok(json) => json,
+            Err(_) => return Self::default(),
+        };
         let signature = match Self::get_signature(&json) {
             Some(signature) => signature,
             None => return Self::default(),
@@ -152,20 +159,21 @@ impl RaydiumMemeTx {
                 }
             }
         };
-        let meme_trade_data = match Self::get_and_validate_meme_mint(helius, &accounts).await {
-            Some(meme_trade_data) => meme_trade_data,
-            None => {
-                return Self {
-                    signature: Some(signature),
-                    accounts: Some(accounts),
-                    ix_data: None,
-                    inner_ix_data: None,
-                    meme_trade_data: None,
-                    compute_unit_limit: 0,
-                    compute_unit_price: 0,
+        let meme_trade_data =
+            match Self::get_and_validate_meme_mint(helius, &accounts, token_filter).await {
+                Some(meme_trade_data) => meme_trade_data,
+                None => {
+                    return Self {
+                        signature: Some(signature),
+                        accounts: Some(accounts),
+                        ix_data: None,
+                        inner_ix_data: None,
+                        meme_trade_data: None,
+                        compute_unit_limit: 0,
+                        compute_unit_price: 0,
+                    }
                 }
-            }
-        };
+            };
 
         match raydium_swap_ix["data"].as_str() {
             Some(data) => {
@@ -272,55 +280,53 @@ impl RaydiumMemeTx {
     /// Extracts the meme mint account from the AMM Info account data.
     /// For that it gets the Account Data using the RPC Client.
     ///
-    /// It checks if the Mint is for a Pump.fun meme coin.
-    /// It also detects if it's a Buy or Sell.
+    /// The meme side of the pool is the one that isn't a quote asset of the token filter,
+    /// whether it's the coin or the pc side. It also detects if it's a Buy or Sell.
     ///
     /// * `helius` - The Helius client
     /// * `meme_accounts` - The meme accounts
+    /// * `token_filter` - The token eligibility policy
     async fn get_and_validate_meme_mint(
-        helius: Arc<Helius>,
+        helius: Arc<Mutex<Helius>>,
         accounts: &RaydiumAccounts,
+        token_filter: &TokenFilter,
     ) -> Option<RaydiumMemeTradeData> {
+        let connection = helius.lock().await.async_connection().ok()?;
+
         // Get AMM account data
-        let account_data = match helius
-            .async_connection()
-            .unwrap()
-            .get_account_data(&Pubkey::from_str_const(&accounts.amm_id))
+        let account_data = match connection
+            .get_account_data(&Pubkey::from_str(&accounts.amm_id).ok()?)
             .await
         {
             Ok(account_data) => account_data,
             Err(_) => return None,
         };
 
-        let amm_info = match AmmInfo::try_from_bytes(&account_data) {
-            Ok(amm_info) => amm_info,
+        let amm_info = match AmmInfo::decode(&account_data) {
+            Ok(amm_info) => *amm_info,
             Err(_) => return None,
         };
-        let pc_vault_mint = amm_info.pc_vault_mint;
 
-        // Only process meme tokens ending with "pump"
-        if !pc_vault_mint.to_string().ends_with("pump") {
-            return None;
-        }
+        let (meme_mint, quote_mint) = token_filter
+            .meme_side(&amm_info.coin_vault_mint, &amm_info.pc_vault_mint)
+            .ok()?;
 
         // Default to buy operation
         let mut operation = BOT_RAYDIUM_OPERATION_BUY;
 
-        // Check if source token is WSOL to determine operation type
-        if let Ok(source_data) = helius
-            .async_connection()
-            .unwrap()
-            .get_account_data(&Pubkey::from_str_const(&accounts.user_source_token_account))
+        // Check if source token is the quote asset to determine operation type
+        if let Ok(source_data) = connection
+            .get_account_data(&Pubkey::from_str(&accounts.user_source_token_account).ok()?)
             .await
         {
-            let source_account = TokenAccount::unpack(&source_data).unwrap();
-            if !source_account.mint.to_string().eq(&WSOL_MINT.to_string()) {
+            let source_account = SplTokenAccountState::decode(&source_data).ok()?;
+            if source_account.mint != quote_mint {
                 operation = BOT_RAYDIUM_OPERATION_SELL;
             }
         }
 
         Some(RaydiumMemeTradeData {
-            meme_mint: pc_vault_mint,
+            meme_mint,
             operation,
         })
     }
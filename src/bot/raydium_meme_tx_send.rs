@@ -2,121 +2,110 @@ use helius::Helius;
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
-    signature::{Keypair, Signer},
 };
 use spl_associated_token_account::{
     get_associated_token_address, instruction::create_associated_token_account_idempotent,
 };
 use std::{error::Error, sync::Arc};
+use tokio::sync::Mutex;
 
 use crate::constants::{
-    BOT_RAYDIUM_OPERATION_BUY, BOT_RAYDIUM_OPERATION_SELL, RAYDIUM_AMM_AUTHORITY,
-    RAYDIUM_LIQUIDITY_POOL_V4_PROGRAM, SERUM_PROGRAM, WSOL_MINT,
+    RAYDIUM_AMM_AUTHORITY, RAYDIUM_LIQUIDITY_POOL_V4_PROGRAM, RAYDIUM_SWAP_BASE_IN_INSTRUCTION,
+    RAYDIUM_V4_COMPUTE_UNIT_LIMIT, SERUM_PROGRAM, WSOL_MINT,
 };
 
-use super::{tx_common::SendSmartTx, RaydiumMemeTx, RaydiumSwapData};
+use super::{
+    price_impact::constant_product_price_impact,
+    tx_common::SendSmartTx,
+    wsol::{Wsol, WsolMode},
+    ParsedTrade, QuoteDetails, RaydiumAccounts, RaydiumMemeTx, RaydiumPool, RaydiumPoolDiscovery,
+    Swap, SwapQuote, TradeSide, Venue, VenueContext,
+};
 
 pub struct RaydiumMemeTxSend {}
 
 impl SendSmartTx for RaydiumMemeTxSend {}
 
 impl RaydiumMemeTxSend {
-    pub async fn compose_and_send(
-        helius: Arc<Helius>,
-        signer_prv_key: Arc<String>,
-        max_sol_buy: u64,
-        slippage_percent: u64,
-        payload: String,
-    ) -> Result<String, Box<dyn Error + Send + Sync>> {
-        let raydium_meme_tx = RaydiumMemeTx::new(payload, Arc::clone(&helius)).await;
-        println!("{}", raydium_meme_tx.format_tx_info());
-
-        let meme_trade_data = raydium_meme_tx
-            .meme_trade_data
-            .as_ref()
-            .ok_or("No meme trade data")?;
-
-        if meme_trade_data.operation == BOT_RAYDIUM_OPERATION_SELL {
-            return Err("Sell operation is not supported".into());
-        }
-
-        let mut instructions: Vec<Instruction> = vec![];
-
-        let signer = Keypair::from_base58_string(signer_prv_key.as_str());
-
-        let user_source_owner = signer.try_pubkey().expect("Cannot get signer pubkey");
-
-        let accounts = raydium_meme_tx
-            .accounts
-            .as_ref()
-            .ok_or("No meme accounts")?;
-
-        let ix_data = raydium_meme_tx
-            .ix_data
-            .as_ref()
-            .ok_or("No instruction data")?;
-
-        let inner_ix_data = raydium_meme_tx
-            .inner_ix_data
-            .as_ref()
-            .ok_or("No innner instructions data")?;
-
-        // let source_amount = inner_ix_data.source_amount;
-        // let dest_amount = inner_ix_data.dest_amount;
-
-        // Get user source token account
-        let user_source_token_account = match meme_trade_data.operation {
-            BOT_RAYDIUM_OPERATION_BUY => {
-                get_associated_token_address(&user_source_owner, &WSOL_MINT)
-            }
-            BOT_RAYDIUM_OPERATION_SELL => {
-                get_associated_token_address(&user_source_owner, &meme_trade_data.meme_mint)
-            }
-            _ => {
-                return Err("Unknown operation".into());
-            }
+    /// Builds the instruction list of an exact input swap on a resolved pool.
+    ///
+    /// * `helius` - The Helius client
+    /// * `pool` - The pool with its linked market
+    /// * `owner` - The wallet swapping
+    /// * `input_mint` - The mint sold to the pool
+    /// * `amount_in` - The exact input amount
+    /// * `minimum_amount_out` - The minimum output accepted
+    /// * `wsol_mode` - The WSOL funding mode
+    async fn pool_swap_instructions(
+        helius: Arc<Mutex<Helius>>,
+        pool: &RaydiumPool,
+        owner: &Pubkey,
+        input_mint: &Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        wsol_mode: WsolMode,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let output_mint = if pool.amm_info.coin_vault_mint == *input_mint {
+            pool.amm_info.pc_vault_mint
+        } else {
+            pool.amm_info.coin_vault_mint
         };
+        let user_source_token_account = get_associated_token_address(owner, input_mint);
+        let user_destination_token_account = get_associated_token_address(owner, &output_mint);
 
-        // Get user destination token account
-        let user_destinationm_token_account = match meme_trade_data.operation {
-            BOT_RAYDIUM_OPERATION_BUY => {
-                get_associated_token_address(&user_source_owner, &meme_trade_data.meme_mint)
-            }
-            BOT_RAYDIUM_OPERATION_SELL => {
-                get_associated_token_address(&user_source_owner, &WSOL_MINT)
-            }
-            _ => {
-                return Err("Unknown operation".into());
-            }
+        let (pre_swap_instructions, post_swap_instructions) = if *input_mint == WSOL_MINT {
+            Wsol::buy_instructions(helius, owner, wsol_mode, amount_in).await?
+        } else if output_mint == WSOL_MINT {
+            Wsol::sell_instructions(owner, wsol_mode)?
+        } else {
+            (vec![], vec![])
         };
 
-        // Create associated token account for user source token if needed
-        if meme_trade_data.operation == BOT_RAYDIUM_OPERATION_BUY {
+        let mut instructions = pre_swap_instructions;
+        if output_mint != WSOL_MINT {
             instructions.push(create_associated_token_account_idempotent(
-                &user_source_owner,
-                &user_source_owner,
-                &meme_trade_data.meme_mint,
+                owner,
+                owner,
+                &output_mint,
                 &spl_token::id(),
             ));
-        } else if meme_trade_data.operation == BOT_RAYDIUM_OPERATION_SELL {
-            // TODO: Create associated token account for WSOL
-            // But for now, we decided there is no need to create it
-            // as the user should have it already because
-            // copy trading starts with WSOL.
         }
+        instructions.push(Self::swap_base_in_instruction(
+            &pool.to_raydium_accounts(
+                &user_source_token_account,
+                &user_destination_token_account,
+                owner,
+            ),
+            &user_source_token_account,
+            &user_destination_token_account,
+            owner,
+            amount_in,
+            minimum_amount_out,
+        ));
+        instructions.extend(post_swap_instructions);
 
-        let mut data: Vec<u8> = Vec::new();
-        match ix_data {
-            RaydiumSwapData::BaseIn(swap) => {
-                data.extend(vec![swap.instruction]);
-            }
-            RaydiumSwapData::BaseOut(swap) => {
-                data.extend(vec![swap.instruction]);
-            }
-        }
+        Ok(instructions)
+    }
 
-        data.extend(max_sol_buy.to_le_bytes());
-        data.extend(0u64.to_le_bytes());
+    /// Builds the Raydium V4 `swap_base_in` instruction.
+    ///
+    /// * `accounts` - The pool and market accounts
+    /// * `user_source_token_account` - The token account sold from
+    /// * `user_destination_token_account` - The token account bought to
+    /// * `user_source_owner` - The wallet swapping
+    /// * `amount_in` - The exact input amount
+    /// * `minimum_amount_out` - The minimum output accepted
+    pub fn swap_base_in_instruction(
+        accounts: &RaydiumAccounts,
+        user_source_token_account: &Pubkey,
+        user_destination_token_account: &Pubkey,
+        user_source_owner: &Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> Instruction {
+        let mut data: Vec<u8> = vec![RAYDIUM_SWAP_BASE_IN_INSTRUCTION];
+        data.extend(amount_in.to_le_bytes());
+        data.extend(minimum_amount_out.to_le_bytes());
 
         // Helper function to create AccountMeta
         let writeable = |pubkey: &str| AccountMeta::new(Pubkey::from_str_const(pubkey), false);
@@ -154,29 +143,120 @@ impl RaydiumMemeTxSend {
 
         // User accounts
         let user_accounts = vec![
-            AccountMeta::new(user_source_token_account, false), // #15/16 User Source Token Account
-            AccountMeta::new(user_destinationm_token_account, false), // #16/17 User Destination Token Account
-            AccountMeta::new(user_source_owner, true),                // #17/18 User Source Owner
+            AccountMeta::new(*user_source_token_account, false), // #15/16 User Source Token Account
+            AccountMeta::new(*user_destination_token_account, false), // #16/17 User Destination Token Account
+            AccountMeta::new(*user_source_owner, true),               // #17/18 User Source Owner
         ];
 
         raydium_swap_accounts.extend(pool_and_serum);
         raydium_swap_accounts.extend(user_accounts);
 
-        let raydium_swap_ix = Instruction {
+        Instruction {
             program_id: RAYDIUM_LIQUIDITY_POOL_V4_PROGRAM,
             accounts: raydium_swap_accounts,
             data,
+        }
+    }
+}
+
+impl Swap for RaydiumMemeTxSend {
+    const VENUE: Venue = Venue::RaydiumV4;
+    const COMPUTE_UNIT_LIMIT: u32 = RAYDIUM_V4_COMPUTE_UNIT_LIMIT;
+
+    async fn find_pool(
+        ctx: &VenueContext,
+        mint: &Pubkey,
+    ) -> Result<Pubkey, Box<dyn Error + Send + Sync>> {
+        let pools = RaydiumPoolDiscovery::find_pools(Arc::clone(&ctx.helius), mint)
+            .await?
+            .into_iter()
+            .filter(|pool| pool.is_sol_paired(mint))
+            .collect();
+
+        RaydiumPoolDiscovery::deepest_pool(pools, mint)
+            .map(|pool| pool.amm_id)
+            .ok_or_else(|| "No SOL paired Raydium pool found for mint".into())
+    }
+
+    async fn quote_pool(
+        ctx: &VenueContext,
+        pool_id: &Pubkey,
+        mint: &Pubkey,
+        side: TradeSide,
+        amount: u64,
+    ) -> Result<SwapQuote, Box<dyn Error + Send + Sync>> {
+        let pool = RaydiumPoolDiscovery::load_pool(Arc::clone(&ctx.helius), pool_id).await?;
+        if !pool.is_sol_paired(mint) {
+            return Err("Only SOL paired Raydium pools are traded".into());
+        }
+
+        let input_mint = match side {
+            TradeSide::Buy => WSOL_MINT,
+            TradeSide::Sell => *mint,
+        };
+        let (reserve_in, reserve_out) = pool
+            .reserves_for(&input_mint)
+            .ok_or("Mint is not traded by this Raydium pool")?;
+        let amount_out = pool
+            .amm_info
+            .quote_base_input(amount, reserve_in, reserve_out);
+
+        Ok(SwapQuote {
+            venue: Self::VENUE,
+            pool_id: *pool_id,
+            mint: *mint,
+            side,
+            amount_in: amount,
+            amount_out,
+            creator: None,
+            quote_liquidity: pool.quote_reserve(mint),
+            pool_open_time: Some(pool.amm_info.state_data.pool_open_time),
+            price_impact: constant_product_price_impact(amount, reserve_in),
+            details: QuoteDetails::RaydiumV4(pool),
+        })
+    }
+
+    async fn build_swap(
+        ctx: &VenueContext,
+        quote: &SwapQuote,
+        wallet: &Pubkey,
+        slippage_percent: u64,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let QuoteDetails::RaydiumV4(pool) = &quote.details else {
+            return Err("Not a Raydium V4 quote".into());
         };
-        instructions.push(raydium_swap_ix);
-
-        Self::bfg9000_send_smart_tx(
-            helius,
-            instructions,
-            Some(raydium_meme_tx.compute_unit_limit),
-            None,
-            signer_prv_key.as_str().to_string(),
-            raydium_meme_tx.signature,
+
+        let (input_mint, _) = quote.mints();
+        let minimum_amount_out =
+            (quote.amount_out as f64 * (100.0 - slippage_percent as f64) / 100.0).floor() as u64;
+
+        Self::pool_swap_instructions(
+            Arc::clone(&ctx.helius),
+            pool,
+            wallet,
+            &input_mint,
+            quote.amount_in,
+            minimum_amount_out,
+            ctx.wsol_mode,
         )
         .await
     }
+
+    async fn parse_trade(ctx: &VenueContext, payload: &str) -> Option<ParsedTrade> {
+        let raydium_meme_tx = RaydiumMemeTx::new(
+            payload.to_string(),
+            Arc::clone(&ctx.helius),
+            ctx.token_filter.as_ref(),
+        )
+        .await;
+
+        ParsedTrade::from_meme_trade(
+            Self::VENUE,
+            raydium_meme_tx.signature.clone(),
+            &raydium_meme_tx.accounts.as_ref()?.amm_id,
+            raydium_meme_tx.meme_trade_data.as_ref()?,
+            raydium_meme_tx.inner_ix_data.as_ref()?,
+            raydium_meme_tx.compute_unit_limit,
+        )
+    }
 }
@@ -0,0 +1,353 @@
+use helius::Helius;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::RpcProgramAccountsConfig,
+    rpc_filter::{Memcmp, RpcFilterType},
+    rpc_request::MAX_MULTIPLE_ACCOUNTS,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::{error::Error, sync::Arc};
+use tokio::sync::Mutex;
+
+use crate::constants::{RAYDIUM_LIQUIDITY_POOL_V4_PROGRAM, WSOL_MINT};
+use crate::db::{Database, RaydiumCoinAccounts};
+
+use super::{AccountDecoder, AmmInfo, RaydiumAccounts, SerumMarketState, SplTokenAccountState};
+
+/// A Raydium AMM v4 pool found for a mint, with its linked market decoded
+#[derive(Debug, Clone)]
+pub struct RaydiumPool {
+    pub amm_id: Pubkey,
+    pub amm_info: AmmInfo,
+    pub market_state: SerumMarketState,
+    pub serum_vault_signer: Pubkey,
+    pub coin_reserve: u64,
+    pub pc_reserve: u64,
+}
+
+impl RaydiumPool {
+    /// Returns the reserve of the side paired with `mint`, the liquidity
+    /// available when buying it.
+    ///
+    /// * `mint` - The mint the pool was discovered for
+    pub fn quote_reserve(&self, mint: &Pubkey) -> u64 {
+        let coin_vault_mint = self.amm_info.coin_vault_mint;
+        if coin_vault_mint == *mint {
+            self.pc_reserve
+        } else {
+            self.coin_reserve
+        }
+    }
+
+    /// Returns true if `mint` is paired with SOL in this pool
+    pub fn is_sol_paired(&self, mint: &Pubkey) -> bool {
+        let (coin_vault_mint, pc_vault_mint) =
+            (self.amm_info.coin_vault_mint, self.amm_info.pc_vault_mint);
+
+        (coin_vault_mint == *mint && pc_vault_mint == WSOL_MINT)
+            || (pc_vault_mint == *mint && coin_vault_mint == WSOL_MINT)
+    }
+
+    /// Converts the pool into the accounts persisted for the mint
+    ///
+    /// * `mint` - The mint the pool was discovered for
+    /// * `coin_name` - The name stored alongside the accounts
+    pub fn to_coin_accounts(&self, mint: &Pubkey, coin_name: &str) -> RaydiumCoinAccounts {
+        let amm_info = self.amm_info;
+        let market_state = self.market_state;
+
+        RaydiumCoinAccounts {
+            mint_address: mint.to_string(),
+            coin_name: coin_name.to_string(),
+            amm_id: self.amm_id.to_string(),
+            amm_open_orders: { amm_info.open_orders }.to_string(),
+            amm_target_orders: { amm_info.target_orders }.to_string(),
+            pool_coin_token_account: { amm_info.coin_vault }.to_string(),
+            pool_pc_token_account: { amm_info.pc_vault }.to_string(),
+            serum_market: { amm_info.market }.to_string(),
+            serum_bids: { market_state.bids }.to_string(),
+            serum_asks: { market_state.asks }.to_string(),
+            serum_event_queue: { market_state.event_queue }.to_string(),
+            serum_coin_vault: { market_state.coin_vault }.to_string(),
+            serum_pc_vault: { market_state.pc_vault }.to_string(),
+            serum_vault_signer: self.serum_vault_signer.to_string(),
+        }
+    }
+
+    /// Returns the swap accounts of the pool for the given user accounts
+    ///
+    /// * `user_source_token_account` - The token account sold from
+    /// * `user_destination_token_account` - The token account bought to
+    /// * `user_source_owner` - The wallet swapping
+    pub fn to_raydium_accounts(
+        &self,
+        user_source_token_account: &Pubkey,
+        user_destination_token_account: &Pubkey,
+        user_source_owner: &Pubkey,
+    ) -> RaydiumAccounts {
+        let amm_info = self.amm_info;
+        let market_state = self.market_state;
+
+        RaydiumAccounts {
+            amm_id: self.amm_id.to_string(),
+            amm_open_orders: { amm_info.open_orders }.to_string(),
+            amm_target_orders: { amm_info.target_orders }.to_string(),
+            pool_coin_token_account: { amm_info.coin_vault }.to_string(),
+            pool_pc_token_account: { amm_info.pc_vault }.to_string(),
+            serum_market: { amm_info.market }.to_string(),
+            serum_bids: { market_state.bids }.to_string(),
+            serum_asks: { market_state.asks }.to_string(),
+            serum_event_queue: { market_state.event_queue }.to_string(),
+            serum_coin_vault: { market_state.coin_vault }.to_string(),
+            serum_pc_vault: { market_state.pc_vault }.to_string(),
+            serum_vault_signer: self.serum_vault_signer.to_string(),
+            user_source_token_account: user_source_token_account.to_string(),
+            user_destination_token_account: user_destination_token_account.to_string(),
+            user_source_owner: user_source_owner.to_string(),
+        }
+    }
+
+    /// Returns (reserve in, reserve out) when selling `input_mint` to the pool,
+    /// None if the pool does not trade it.
+    ///
+    /// * `input_mint` - The mint sold to the pool
+    pub fn reserves_for(&self, input_mint: &Pubkey) -> Option<(u64, u64)> {
+        let (coin_vault_mint, pc_vault_mint) =
+            (self.amm_info.coin_vault_mint, self.amm_info.pc_vault_mint);
+
+        if coin_vault_mint == *input_mint {
+            Some((self.coin_reserve, self.pc_reserve))
+        } else if pc_vault_mint == *input_mint {
+            Some((self.pc_reserve, self.coin_reserve))
+        } else {
+            None
+        }
+    }
+}
+
+pub struct RaydiumPoolDiscovery {}
+
+impl RaydiumPoolDiscovery {
+    /// Finds the swappable AMM v4 pools trading `mint`, on either side,
+    /// with their reserves and linked market.
+    ///
+    /// * `helius` - The Helius client
+    /// * `mint` - The mint to look pools up for
+    pub async fn find_pools(
+        helius: Arc<Mutex<Helius>>,
+        mint: &Pubkey,
+    ) -> Result<Vec<RaydiumPool>, Box<dyn Error + Send + Sync>> {
+        let connection = helius.lock().await.async_connection()?;
+
+        let mut amm_accounts = vec![];
+        for offset in [
+            std::mem::offset_of!(AmmInfo, coin_vault_mint),
+            std::mem::offset_of!(AmmInfo, pc_vault_mint),
+        ] {
+            let config = RpcProgramAccountsConfig {
+                filters: Some(vec![
+                    RpcFilterType::DataSize(std::mem::size_of::<AmmInfo>() as u64),
+                    RpcFilterType::Memcmp(Memcmp::new_base58_encoded(offset, mint.as_ref())),
+                ]),
+                ..Default::default()
+            };
+
+            amm_accounts.extend(
+                connection
+                    .get_program_accounts_with_config(&RAYDIUM_LIQUIDITY_POOL_V4_PROGRAM, config)
+                    .await?,
+            );
+        }
+
+        let amm_infos: Vec<(Pubkey, AmmInfo)> = amm_accounts
+            .into_iter()
+            .filter_map(|(amm_id, account)| {
+                let amm_info = *AmmInfo::decode_account(&account).ok()?;
+                amm_info.is_swap_enabled().then_some((amm_id, amm_info))
+            })
+            .collect();
+
+        Self::load_reserves_and_markets(&connection, amm_infos).await
+    }
+
+    /// Loads a single AMM v4 pool with its reserves and linked market,
+    /// to trade a pool without having seen a swap on it first.
+    ///
+    /// * `helius` - The Helius client
+    /// * `amm_id` - The AMM account
+    pub async fn load_pool(
+        helius: Arc<Mutex<Helius>>,
+        amm_id: &Pubkey,
+    ) -> Result<RaydiumPool, Box<dyn Error + Send + Sync>> {
+        let connection = helius.lock().await.async_connection()?;
+
+        let amm_info = *AmmInfo::decode_account(&connection.get_account(amm_id).await?)?;
+        if !amm_info.is_swap_enabled() {
+            return Err("Swaps are disabled on this Raydium pool".into());
+        }
+
+        Self::load_reserves_and_markets(&connection, vec![(*amm_id, amm_info)])
+            .await?
+            .pop()
+            .ok_or_else(|| "Could not load the Raydium pool vaults or market".into())
+    }
+
+    /// Fetches the vaults and markets of the given pools, dropping the pools
+    /// whose accounts can't be decoded.
+    ///
+    /// * `connection` - The RPC client
+    /// * `amm_infos` - The AMM accounts and their decoded state
+    async fn load_reserves_and_markets(
+        connection: &RpcClient,
+        amm_infos: Vec<(Pubkey, AmmInfo)>,
+    ) -> Result<Vec<RaydiumPool>, Box<dyn Error + Send + Sync>> {
+        if amm_infos.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // Vaults and markets of the pools, as many pools per round trip as
+        // the RPC takes keys
+        let mut accounts = Vec::with_capacity(amm_infos.len() * 3);
+        for chunk in amm_infos.chunks(MAX_MULTIPLE_ACCOUNTS / 3) {
+            let keys: Vec<Pubkey> = chunk
+                .iter()
+                .flat_map(|(_, amm_info)| [amm_info.coin_vault, amm_info.pc_vault, amm_info.market])
+                .collect();
+            accounts.extend(connection.get_multiple_accounts(&keys).await?);
+        }
+
+        let pools = amm_infos
+            .into_iter()
+            .zip(accounts.chunks(3))
+            .filter_map(|((amm_id, amm_info), accounts)| {
+                let (market, market_program) = (amm_info.market, amm_info.market_program);
+
+                let coin_vault =
+                    SplTokenAccountState::decode_account(accounts[0].as_ref()?).ok()?;
+                let pc_vault = SplTokenAccountState::decode_account(accounts[1].as_ref()?).ok()?;
+
+                let market_account = accounts[2].as_ref()?;
+                if market_account.owner != market_program {
+                    return None;
+                }
+                let market_state = *SerumMarketState::decode(&market_account.data).ok()?;
+                market_state.validate(&market).ok()?;
+                let serum_vault_signer =
+                    market_state.vault_signer(&market, &market_program).ok()?;
+
+                let (coin_reserve, pc_reserve) =
+                    amm_info.reserves(coin_vault.amount, pc_vault.amount);
+
+                Some(RaydiumPool {
+                    amm_id,
+                    amm_info,
+                    market_state,
+                    serum_vault_signer,
+                    coin_reserve,
+                    pc_reserve,
+                })
+            })
+            .collect();
+
+        Ok(pools)
+    }
+
+    /// Picks the deepest pool for `mint`. SOL paired pools are preferred,
+    /// their depth being comparable, then the largest paired reserve wins.
+    ///
+    /// * `pools` - The pools found for the mint
+    /// * `mint` - The mint the pools were found for
+    pub fn deepest_pool(pools: Vec<RaydiumPool>, mint: &Pubkey) -> Option<RaydiumPool> {
+        pools
+            .into_iter()
+            .max_by_key(|pool| (pool.is_sol_paired(mint), pool.quote_reserve(mint)))
+    }
+
+    /// Discovers the deepest pool for `mint` and persists its accounts.
+    ///
+    /// * `helius` - The Helius client
+    /// * `db` - The database the accounts are stored to
+    /// * `mint` - The mint to discover the pool for
+    /// * `coin_name` - The name stored alongside the accounts
+    pub async fn discover(
+        helius: Arc<Mutex<Helius>>,
+        db: &Database,
+        mint: &Pubkey,
+        coin_name: &str,
+    ) -> Result<RaydiumCoinAccounts, Box<dyn Error + Send + Sync>> {
+        let pools = Self::find_pools(helius, mint).await?;
+        let pool = Self::deepest_pool(pools, mint).ok_or("No Raydium pool found for mint")?;
+
+        let coin_accounts = pool.to_coin_accounts(mint, coin_name);
+        db.add_raydium_coin_accounts(&coin_accounts).await?;
+
+        println!(
+            "Raydium pool discovered | Mint: {} | AMM: {} | Coin reserve: {} | Pc reserve: {}",
+            mint, pool.amm_id, pool.coin_reserve, pool.pc_reserve
+        );
+
+        Ok(coin_accounts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(coin_vault_mint: Pubkey, pc_vault_mint: Pubkey, coin: u64, pc: u64) -> RaydiumPool {
+        RaydiumPool {
+            amm_id: Pubkey::new_unique(),
+            amm_info: AmmInfo {
+                coin_vault_mint,
+                pc_vault_mint,
+                ..Default::default()
+            },
+            market_state: SerumMarketState::default(),
+            serum_vault_signer: Pubkey::new_unique(),
+            coin_reserve: coin,
+            pc_reserve: pc,
+        }
+    }
+
+    #[test]
+    fn test_deepest_pool() {
+        let mint = Pubkey::new_unique();
+        let usdc = Pubkey::new_unique();
+
+        let shallow = pool(mint, WSOL_MINT, 1_000, 10);
+        // SOL on the coin side, the paired reserve is the coin one
+        let deep = pool(WSOL_MINT, mint, 50, 1_000);
+        // Largest reserve but not paired with SOL
+        let usdc_pool = pool(mint, usdc, 1_000, 1_000_000);
+
+        let deep_id = deep.amm_id;
+        let picked = RaydiumPoolDiscovery::deepest_pool(vec![shallow, usdc_pool, deep], &mint);
+        assert_eq!(picked.map(|pool| pool.amm_id), Some(deep_id));
+
+        assert!(RaydiumPoolDiscovery::deepest_pool(vec![], &mint).is_none());
+    }
+
+    #[test]
+    fn test_reserves_for() {
+        let mint = Pubkey::new_unique();
+        let pool = pool(WSOL_MINT, mint, 10, 20);
+
+        assert_eq!(pool.reserves_for(&WSOL_MINT), Some((10, 20)));
+        assert_eq!(pool.reserves_for(&mint), Some((20, 10)));
+        assert_eq!(pool.reserves_for(&Pubkey::new_unique()), None);
+    }
+
+    #[test]
+    fn test_to_coin_accounts() {
+        let mint = Pubkey::new_unique();
+        let pool = pool(mint, WSOL_MINT, 0, 0);
+
+        let accounts = pool.to_coin_accounts(&mint, "TEST");
+        assert_eq!(accounts.mint_address, mint.to_string());
+        assert_eq!(accounts.amm_id, pool.amm_id.to_string());
+        assert_eq!(
+            accounts.serum_vault_signer,
+            pool.serum_vault_signer.to_string()
+        );
+    }
+}
@@ -1,7 +1,12 @@
+use bytemuck::{Pod, Zeroable};
 use solana_sdk::pubkey::Pubkey;
 
+use crate::constants::RAYDIUM_LIQUIDITY_POOL_V4_PROGRAM;
+
+use super::AccountDecoder;
+
 #[repr(C, packed)]
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
 pub struct Fees {
     /// numerator of the min_separate
     pub min_separate_numerator: u64,
@@ -26,7 +31,7 @@ pub struct Fees {
 }
 
 #[repr(C, packed)]
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
 pub struct StateData {
     /// delay to take pnl coin
     pub need_take_pnl_coin: u64,
@@ -59,7 +64,7 @@ pub struct StateData {
 }
 
 #[repr(C, packed)]
-#[derive(Clone, Copy, Default, PartialEq, Debug)]
+#[derive(Clone, Copy, Default, PartialEq, Debug, Pod, Zeroable)]
 pub struct AmmInfo {
     /// Initialized status.
     pub status: u64,
@@ -133,15 +138,101 @@ pub struct AmmInfo {
     pub padding2: u64,
 }
 
+impl AccountDecoder for AmmInfo {
+    const OWNERS: &'static [Pubkey] = &[RAYDIUM_LIQUIDITY_POOL_V4_PROGRAM];
+}
+
+/// `AmmInfo::status` values allowing swaps: Initialized, SwapOnly and WaitingTrade
+const AMM_STATUS_SWAP_ENABLED: &[u64] = &[1, 6, 7];
+
 impl AmmInfo {
-    pub fn try_from_bytes(data: &[u8]) -> Result<Self, &'static str> {
-        if data.len() != std::mem::size_of::<AmmInfo>() {
-            return Err("Invalid data length for AmmInfo");
+    /// Returns true if swaps are currently allowed on the pool
+    pub fn is_swap_enabled(&self) -> bool {
+        AMM_STATUS_SWAP_ENABLED.contains(&{ self.status })
+    }
+
+    /// Returns the swappable reserves given the raw vault balances,
+    /// i.e. without the pnl still owed to the pool owner.
+    ///
+    /// * `coin_vault_amount` - The coin vault balance
+    /// * `pc_vault_amount` - The pc vault balance
+    pub fn reserves(&self, coin_vault_amount: u64, pc_vault_amount: u64) -> (u64, u64) {
+        let (need_take_pnl_coin, need_take_pnl_pc) = (
+            self.state_data.need_take_pnl_coin,
+            self.state_data.need_take_pnl_pc,
+        );
+
+        (
+            coin_vault_amount.saturating_sub(need_take_pnl_coin),
+            pc_vault_amount.saturating_sub(need_take_pnl_pc),
+        )
+    }
+
+    /// Constant product quote for an exact input swap, the swap fee being
+    /// taken from the input amount like the on-chain program does.
+    ///
+    /// * `amount_in` - The exact input amount
+    /// * `reserve_in` - The swappable reserve of the input token
+    /// * `reserve_out` - The swappable reserve of the output token
+    pub fn quote_base_input(&self, amount_in: u64, reserve_in: u64, reserve_out: u64) -> u64 {
+        let (swap_fee_numerator, swap_fee_denominator) =
+            (self.fees.swap_fee_numerator, self.fees.swap_fee_denominator);
+        if swap_fee_denominator == 0 {
+            return 0;
+        }
+
+        let amount_in = amount_in as u128;
+        let fee = (amount_in * swap_fee_numerator as u128).div_ceil(swap_fee_denominator as u128);
+        let amount_in_less_fee = amount_in.saturating_sub(fee);
+
+        let denominator = reserve_in as u128 + amount_in_less_fee;
+        if denominator == 0 {
+            return 0;
         }
 
-        // Since the struct is packed, we can safely transmute the bytes
-        let amm_info = unsafe { std::ptr::read_unaligned(data.as_ptr() as *const AmmInfo) };
+        (amount_in_less_fee * reserve_out as u128 / denominator) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amm_info_layout() {
+        // 752 bytes on chain, the vault mints are the offsets used to find pools by mint
+        assert_eq!(std::mem::size_of::<AmmInfo>(), 752);
+        assert_eq!(std::mem::offset_of!(AmmInfo, coin_vault_mint), 400);
+        assert_eq!(std::mem::offset_of!(AmmInfo, pc_vault_mint), 432);
+    }
+
+    #[test]
+    fn test_reserves() {
+        let mut amm_info = AmmInfo {
+            status: 6,
+            ..Default::default()
+        };
+        amm_info.state_data.need_take_pnl_coin = 10;
+        amm_info.state_data.need_take_pnl_pc = 200;
+
+        assert!(amm_info.is_swap_enabled());
+        assert_eq!(amm_info.reserves(1_000, 100), (990, 0));
+    }
+
+    #[test]
+    fn test_quote_base_input() {
+        let mut amm_info = AmmInfo::default();
+        assert_eq!(
+            amm_info.quote_base_input(1_000_000, 1_000_000_000, 1_000_000_000),
+            0
+        );
 
-        Ok(amm_info)
+        // 0.25% fee on 1_000_000 in, 1:1 pool of 1_000_000_000
+        amm_info.fees.swap_fee_numerator = 25;
+        amm_info.fees.swap_fee_denominator = 10_000;
+        assert_eq!(
+            amm_info.quote_base_input(1_000_000, 1_000_000_000, 1_000_000_000),
+            996_505
+        );
     }
 }
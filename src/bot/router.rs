@@ -0,0 +1,780 @@
+use futures::future::join_all;
+use solana_sdk::pubkey::Pubkey;
+use std::{env, error::Error, fmt, slice, sync::Arc};
+
+use crate::constants::{JITO_TIP_LAMPORTS, SIGNATURE_FEE_LAMPORTS, TOKEN_ACCOUNT_RENT_LAMPORTS};
+
+use super::{
+    split_list,
+    venue::{ParsedTrade, SwapQuote, TradeSide, Venue, VenueContext},
+    OrderTrace,
+};
+
+/// A venue quoted for a route, and how it fared
+#[derive(Debug, Clone)]
+pub struct RouteCandidate {
+    pub venue: Venue,
+    /// The quote for the whole amount, None if the venue could not quote
+    pub quote: Option<SwapQuote>,
+    /// Why the venue could not quote, usually no pool for the mint
+    pub error: Option<String>,
+}
+
+/// The legs an order is sent as, and why they were picked
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub mint: Pubkey,
+    pub side: TradeSide,
+    pub amount_in: u64,
+    /// One quote per venue used, their inputs summing up to `amount_in`
+    pub legs: Vec<SwapQuote>,
+    pub candidates: Vec<RouteCandidate>,
+    pub reason: String,
+}
+
+impl Route {
+    /// Returns the output of all the legs
+    pub fn amount_out(&self) -> u64 {
+        self.legs.iter().map(|leg| leg.amount_out).sum()
+    }
+
+    /// Returns the price impact of the legs, weighted by their input
+    pub fn price_impact(&self) -> f64 {
+        if self.amount_in == 0 {
+            return 0.0;
+        }
+
+        self.legs
+            .iter()
+            .map(|leg| leg.price_impact * leg.amount_in as f64)
+            .sum::<f64>()
+            / self.amount_in as f64
+    }
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Route | {} | Mint: {} | In: {} | Out: {} | Price impact: {:.2}% | {}",
+            self.side,
+            self.mint,
+            self.amount_in,
+            self.amount_out(),
+            self.price_impact() * 100.0,
+            self.reason
+        )?;
+        for candidate in &self.candidates {
+            match (&candidate.quote, &candidate.error) {
+                (Some(quote), _) => write!(f, "\n  {}: {}", candidate.venue, quote.amount_out)?,
+                (None, Some(error)) => write!(f, "\n  {}: {error}", candidate.venue)?,
+                (None, None) => write!(f, "\n  {}: no quote", candidate.venue)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Picks the venue, or venues, an order gets the best output on.
+///
+/// Every venue is quoted for the exact input, the quotes walking the pool
+/// curves, so their outputs are net of the pool fees and of the price impact.
+/// Routes are then ranked on their output net of the lamports they cost to
+/// send: the signature fee, the Jito tip and the priority fee of each leg,
+/// and on a buy the rent of the token account it opens.
+#[derive(Debug, Clone)]
+pub struct Router {
+    /// Venues quoted, in the order ties are broken
+    pub venues: Vec<Venue>,
+    /// Parts an order is split in across venues, 1 to never split
+    pub split_parts: u64,
+    /// Net output a split must add over the best venue, in bps, on top of
+    /// paying for its extra transactions
+    pub min_split_gain_bps: u64,
+    /// Priority fee the transaction costs are estimated at, in micro-lamports
+    /// per compute unit
+    pub compute_unit_price: u64,
+    /// Venue quoted when none of `venues` could
+    pub fallback: Option<Venue>,
+}
+
+impl Default for Router {
+    /// All the venues, no split, Jupiter as the fallback
+    fn default() -> Self {
+        Self {
+            venues: Venue::ALL
+                .into_iter()
+                .filter(|venue| *venue != Venue::Jupiter)
+                .collect(),
+            split_parts: 1,
+            min_split_gain_bps: 50,
+            compute_unit_price: 100_000,
+            fallback: Some(Venue::Jupiter),
+        }
+    }
+}
+
+impl Router {
+    /// Loads the router from the environment, unset variables keep the defaults:
+    ///
+    /// * `router_venues` - Comma separated venues, e.g. "pump_fun, raydium_v4", all but Jupiter by default
+    /// * `router_split_parts` - Parts an order is split in, 1 by default
+    /// * `router_min_split_gain_bps` - Output a split must add, 50 bps by default
+    /// * `router_compute_unit_price_ulamports` - Priority fee the costs are estimated at, 100 000 by default
+    /// * `router_fallback` - Venue quoted when no other could, "jupiter" by default, "none" for none
+    pub fn from_env() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut router = Self::default();
+
+        if let Ok(venues) = env::var("router_venues") {
+            router.venues = split_list(&venues)
+                .map(|venue| {
+                    Venue::from_config(venue).ok_or_else(|| format!("Unknown venue: {venue}"))
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        if let Ok(split_parts) = env::var("router_split_parts") {
+            router.split_parts = split_parts.parse::<u64>()?.max(1);
+        }
+        if let Ok(min_gain) = env::var("router_min_split_gain_bps") {
+            router.min_split_gain_bps = min_gain.parse()?;
+        }
+        if let Ok(compute_unit_price) = env::var("router_compute_unit_price_ulamports") {
+            router.compute_unit_price = compute_unit_price.parse()?;
+        }
+        if let Ok(fallback) = env::var("router_fallback") {
+            router.fallback = match fallback.trim() {
+                "" | "none" => None,
+                venue => Some(
+                    Venue::from_config(venue).ok_or_else(|| format!("Unknown venue: {venue}"))?,
+                ),
+            };
+        }
+
+        Ok(router)
+    }
+
+    /// Quotes every venue in parallel and picks the best net output, split
+    /// across venues when it is worth it. Legs moving the price more than
+    /// the max of the price impact guard are refused or shrunk.
+    ///
+    /// * `ctx` - The venue context
+    /// * `mint` - The token traded against SOL
+    /// * `side` - The swap direction
+    /// * `amount` - Lamports spent on a buy, tokens spent on a sell
+    pub async fn route(
+        &self,
+        ctx: &VenueContext,
+        mint: &Pubkey,
+        side: TradeSide,
+        amount: u64,
+    ) -> Result<Route, Box<dyn Error + Send + Sync>> {
+        if amount == 0 {
+            return Err("Nothing to route".into());
+        }
+
+        let mut candidates = quote_venues(ctx, &self.venues, mint, side, amount).await;
+        let mut fell_back = false;
+        if let Some(fallback) = self.fallback {
+            if best_quote(&candidates, |_| 0).is_none() && !self.venues.contains(&fallback) {
+                candidates.extend(quote_venues(ctx, &[fallback], mint, side, amount).await);
+                fell_back = true;
+            }
+        }
+
+        let best = best_quote(&candidates, |quote| {
+            self.net_amount_out(side, slice::from_ref(quote))
+        })
+        .ok_or_else(|| format!("No venue could quote {mint}"))?
+        .clone();
+        let quoted = candidates
+            .iter()
+            .filter(|candidate| candidate.quote.is_some())
+            .count();
+
+        let mut reason = if fell_back {
+            format!("No venue quoting, {} fallback", best.venue)
+        } else if quoted > 1 {
+            format!(
+                "{} gives the best net output of {quoted} venues",
+                best.venue
+            )
+        } else {
+            format!("{} is the only venue quoting", best.venue)
+        };
+        let mut legs = vec![best.clone()];
+
+        if self.split_parts > 1 && quoted > 1 {
+            let split = self.split(ctx, &candidates, mint, side, amount).await;
+            let gain = gain_bps(
+                self.net_amount_out(side, slice::from_ref(&best)),
+                self.net_amount_out(side, &split),
+            );
+
+            if split.len() > 1 && gain >= self.min_split_gain_bps {
+                reason = format!(
+                    "Split across {} venues adds {gain} bps over {}",
+                    split.len(),
+                    best.venue
+                );
+                legs = split;
+            } else if split.len() > 1 {
+                reason = format!("{reason}, a split would only add {gain} bps");
+            }
+        }
+
+        // Buy legs above the max price impact are refused or shrunk, the
+        // route then sending less than asked
+        let mut guarded = Vec::with_capacity(legs.len());
+        for leg in legs {
+            guarded.push(ctx.price_impact.guard(ctx, leg).await?);
+        }
+        let legs = guarded;
+        let amount_in = legs.iter().map(|leg| leg.amount_in).sum();
+        if amount_in < amount {
+            reason = format!("{reason}, shrunk from {amount} to fit the max price impact");
+        }
+
+        Ok(Route {
+            mint: *mint,
+            side,
+            amount_in,
+            legs,
+            candidates,
+            reason,
+        })
+    }
+
+    /// Splits an order in `split_parts` equal parts, each part going to the
+    /// venue adding the most net output for it, a venue without a leg yet
+    /// paying for its transaction. Empty when a part found no venue.
+    ///
+    /// * `ctx` - The venue context
+    /// * `candidates` - The venues quoted for the whole amount
+    /// * `mint` - The token traded against SOL
+    /// * `side` - The swap direction
+    /// * `amount` - Lamports spent on a buy, tokens spent on a sell
+    async fn split(
+        &self,
+        ctx: &VenueContext,
+        candidates: &[RouteCandidate],
+        mint: &Pubkey,
+        side: TradeSide,
+        amount: u64,
+    ) -> Vec<SwapQuote> {
+        let pools: Vec<(Venue, Pubkey)> = candidates
+            .iter()
+            .filter_map(|candidate| Some((candidate.venue, candidate.quote.as_ref()?.pool_id)))
+            .collect();
+        let part = amount / self.split_parts;
+        if part == 0 {
+            return vec![];
+        }
+
+        let mut legs: Vec<Option<SwapQuote>> = vec![None; pools.len()];
+        for step in 0..self.split_parts {
+            // The last part takes the rounding leftover
+            let size = if step + 1 == self.split_parts {
+                amount - part * (self.split_parts - 1)
+            } else {
+                part
+            };
+
+            let next = join_all(pools.iter().zip(&legs).map(|((venue, pool_id), leg)| {
+                let allocated = leg.as_ref().map_or(0, |leg| leg.amount_in);
+                venue.quote_pool(ctx, pool_id, mint, side, allocated + size)
+            }))
+            .await;
+
+            let current: Vec<u64> = legs
+                .iter()
+                .map(|leg| {
+                    leg.as_ref()
+                        .map_or(0, |leg| self.leg_net_amount_out(side, leg))
+                })
+                .collect();
+            let next: Vec<Option<SwapQuote>> = next.into_iter().map(Result::ok).collect();
+            let next_out: Vec<Option<u64>> = next
+                .iter()
+                .map(|quote| {
+                    quote
+                        .as_ref()
+                        .map(|quote| self.leg_net_amount_out(side, quote))
+                })
+                .collect();
+
+            let Some(index) = best_increment(&current, &next_out) else {
+                return vec![];
+            };
+            legs[index] = next[index].clone();
+        }
+
+        legs.into_iter().flatten().collect()
+    }
+
+    /// Returns the lamports a leg on `venue` is estimated to cost to send:
+    /// the signature fee, the Jito tip and the priority fee of its compute
+    /// unit limit
+    ///
+    /// * `venue` - The venue of the leg
+    pub fn tx_cost(&self, venue: Venue) -> u64 {
+        let priority_fee =
+            venue.compute_unit_limit() as u128 * self.compute_unit_price as u128 / 1_000_000;
+        SIGNATURE_FEE_LAMPORTS + JITO_TIP_LAMPORTS + priority_fee as u64
+    }
+
+    /// Returns the output of legs net of what they cost to send, and on a
+    /// buy of the token account it opens, once whatever the legs
+    ///
+    /// * `side` - The swap direction
+    /// * `legs` - The legs of the route
+    pub fn net_amount_out(&self, side: TradeSide, legs: &[SwapQuote]) -> u64 {
+        let rent = match side {
+            TradeSide::Buy => TOKEN_ACCOUNT_RENT_LAMPORTS,
+            TradeSide::Sell => 0,
+        };
+        let cost = legs.iter().map(|leg| self.tx_cost(leg.venue)).sum::<u64>() + rent;
+
+        net_of_cost(
+            side,
+            legs.iter().map(|leg| leg.amount_in).sum(),
+            legs.iter().map(|leg| leg.amount_out).sum(),
+            cost,
+        )
+    }
+
+    /// Returns the output of a split leg net of its transaction cost, the
+    /// rent being the same whichever venues the parts go to
+    fn leg_net_amount_out(&self, side: TradeSide, leg: &SwapQuote) -> u64 {
+        net_of_cost(side, leg.amount_in, leg.amount_out, self.tx_cost(leg.venue))
+    }
+
+    /// Sends the legs of a route one after the other. Legs failing are
+    /// logged, the route only fails if none went through.
+    ///
+    /// * `ctx` - The venue context
+    /// * `signer_prv_key` - The wallet private key
+    /// * `route` - The route to send
+    /// * `slippage_percent` - The slippage tolerated on each leg
+    /// * `copied_trade` - The leader trade being copied, if any
+    /// * `trace` - The latency trace of the order, if any
+    pub async fn swap(
+        ctx: &VenueContext,
+        signer_prv_key: Arc<String>,
+        route: &Route,
+        slippage_percent: u64,
+        copied_trade: Option<&ParsedTrade>,
+        trace: Option<&OrderTrace>,
+    ) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let mut signatures = vec![];
+        let mut last_error = None;
+
+        for leg in &route.legs {
+            match Venue::swap(
+                ctx,
+                Arc::clone(&signer_prv_key),
+                leg,
+                slippage_percent,
+                copied_trade,
+                trace,
+            )
+            .await
+            {
+                Ok(signature) => signatures.push(signature),
+                Err(e) => {
+                    eprintln!("Error sending {} leg of {}: {e:?}", leg.venue, route.mint);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match last_error {
+            Some(e) if signatures.is_empty() => Err(e),
+            _ => Ok(signatures),
+        }
+    }
+
+    /// Buys `mint` for `lamports` on the best route. Every leg goes through
+    /// the token filter first.
+    ///
+    /// * `ctx` - The venue context
+    /// * `signer_prv_key` - The wallet private key
+    /// * `mint` - The token to buy
+    /// * `lamports` - The SOL spent
+    /// * `slippage_percent` - The slippage tolerated on each leg
+    pub async fn buy(
+        &self,
+        ctx: &VenueContext,
+        signer_prv_key: Arc<String>,
+        mint: &Pubkey,
+        lamports: u64,
+        slippage_percent: u64,
+    ) -> Result<(Route, Vec<String>), Box<dyn Error + Send + Sync>> {
+        let route = self.route(ctx, mint, TradeSide::Buy, lamports).await?;
+        let signatures =
+            Self::execute(ctx, signer_prv_key, &route, slippage_percent, None, None).await?;
+        Ok((route, signatures))
+    }
+
+    /// Sells `amount` tokens of `mint` on the best route
+    ///
+    /// * `ctx` - The venue context
+    /// * `signer_prv_key` - The wallet private key
+    /// * `mint` - The token to sell
+    /// * `amount` - The raw token amount sold
+    /// * `slippage_percent` - The slippage tolerated on each leg
+    pub async fn sell(
+        &self,
+        ctx: &VenueContext,
+        signer_prv_key: Arc<String>,
+        mint: &Pubkey,
+        amount: u64,
+        slippage_percent: u64,
+    ) -> Result<(Route, Vec<String>), Box<dyn Error + Send + Sync>> {
+        let route = self.route(ctx, mint, TradeSide::Sell, amount).await?;
+        let signatures =
+            Self::execute(ctx, signer_prv_key, &route, slippage_percent, None, None).await?;
+        Ok((route, signatures))
+    }
+
+    /// Copies a leader trade on the best route instead of the pool it was
+    /// made on
+    ///
+    /// * `ctx` - The venue context
+    /// * `signer_prv_key` - The wallet private key
+    /// * `trade` - The leader trade
+    /// * `amount_in` - Lamports spent on a buy, tokens spent on a sell
+    /// * `slippage_percent` - The slippage tolerated on each leg
+    /// * `trace` - The latency trace of the order, if any
+    pub async fn copy_trade(
+        &self,
+        ctx: &VenueContext,
+        signer_prv_key: Arc<String>,
+        trade: &ParsedTrade,
+        amount_in: u64,
+        slippage_percent: u64,
+        trace: Option<&OrderTrace>,
+    ) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let route = self.route(ctx, &trade.mint, trade.side, amount_in).await?;
+        Self::execute(
+            ctx,
+            signer_prv_key,
+            &route,
+            slippage_percent,
+            Some(trade),
+            trace,
+        )
+        .await
+    }
+
+    /// Logs a route, checks its buy legs against the token filter and sends it
+    async fn execute(
+        ctx: &VenueContext,
+        signer_prv_key: Arc<String>,
+        route: &Route,
+        slippage_percent: u64,
+        copied_trade: Option<&ParsedTrade>,
+        trace: Option<&OrderTrace>,
+    ) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        println!("{route}");
+
+        if route.side == TradeSide::Buy {
+            for leg in &route.legs {
+                Venue::check_token(ctx, leg).await?;
+            }
+        }
+
+        Self::swap(
+            ctx,
+            signer_prv_key,
+            route,
+            slippage_percent,
+            copied_trade,
+            trace,
+        )
+        .await
+    }
+}
+
+/// Quotes venues in parallel for the whole amount
+///
+/// * `ctx` - The venue context
+/// * `venues` - The venues to quote
+/// * `mint` - The token traded against SOL
+/// * `side` - The swap direction
+/// * `amount` - Lamports spent on a buy, tokens spent on a sell
+async fn quote_venues(
+    ctx: &VenueContext,
+    venues: &[Venue],
+    mint: &Pubkey,
+    side: TradeSide,
+    amount: u64,
+) -> Vec<RouteCandidate> {
+    let quotes = join_all(
+        venues
+            .iter()
+            .map(|venue| venue.quote(ctx, mint, side, amount)),
+    )
+    .await;
+
+    venues
+        .iter()
+        .zip(quotes)
+        .map(|(venue, quote)| match quote {
+            Ok(quote) => RouteCandidate {
+                venue: *venue,
+                quote: Some(quote),
+                error: None,
+            },
+            Err(e) => RouteCandidate {
+                venue: *venue,
+                quote: None,
+                error: Some(e.to_string()),
+            },
+        })
+        .collect()
+}
+
+/// Returns the quote with the best output, the first venue winning ties
+///
+/// * `candidates` - The venues quoted
+/// * `net_out` - The output a quote is ranked on
+fn best_quote(
+    candidates: &[RouteCandidate],
+    net_out: impl Fn(&SwapQuote) -> u64,
+) -> Option<&SwapQuote> {
+    candidates
+        .iter()
+        .filter_map(|candidate| candidate.quote.as_ref())
+        .filter(|quote| quote.amount_out > 0)
+        .map(|quote| (quote, net_out(quote)))
+        .fold(
+            None,
+            |best: Option<(&SwapQuote, u64)>, (quote, out)| match best {
+                Some(best) if best.1 >= out => Some(best),
+                _ => Some((quote, out)),
+            },
+        )
+        .map(|(quote, _)| quote)
+}
+
+/// Returns the output of a swap once `cost` lamports are paid on top of it.
+/// A sell outputs lamports, the cost is taken from them. A buy outputs
+/// tokens, the cost is taken from its input and the output shrinks in
+/// proportion.
+///
+/// * `side` - The swap direction
+/// * `amount_in` - Lamports spent on a buy, tokens spent on a sell
+/// * `amount_out` - Tokens got on a buy, lamports got on a sell
+/// * `cost` - The lamports paid on top
+fn net_of_cost(side: TradeSide, amount_in: u64, amount_out: u64, cost: u64) -> u64 {
+    match side {
+        TradeSide::Sell => amount_out.saturating_sub(cost),
+        TradeSide::Buy if amount_in == 0 => 0,
+        TradeSide::Buy => {
+            (amount_out as u128 * amount_in.saturating_sub(cost) as u128 / amount_in as u128) as u64
+        }
+    }
+}
+
+/// Returns the venue whose next quote adds the most output over its current
+/// one, None if no venue could quote
+///
+/// * `current` - The output of each venue so far
+/// * `next` - The output of each venue with one more part
+fn best_increment(current: &[u64], next: &[Option<u64>]) -> Option<usize> {
+    current
+        .iter()
+        .zip(next)
+        .enumerate()
+        .filter_map(|(index, (current, next))| {
+            Some((index, next.as_ref()?.saturating_sub(*current)))
+        })
+        .filter(|(_, increment)| *increment > 0)
+        .fold(
+            None,
+            |best: Option<(usize, u64)>, (index, increment)| match best {
+                Some(best) if best.1 >= increment => Some(best),
+                _ => Some((index, increment)),
+            },
+        )
+        .map(|(index, _)| index)
+}
+
+/// Returns how much more `other` is than `base`, in bps, 0 if it is not more
+fn gain_bps(base: u64, other: u64) -> u64 {
+    if base == 0 || other <= base {
+        return 0;
+    }
+    ((other - base) as u128 * 10_000 / base as u128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::test_quote;
+
+    #[test]
+    fn test_best_quote() {
+        let candidates = vec![
+            RouteCandidate {
+                venue: Venue::PumpFun,
+                quote: None,
+                error: Some("Bonding curve complete".to_string()),
+            },
+            RouteCandidate {
+                venue: Venue::PumpSwap,
+                quote: Some(test_quote(Venue::PumpSwap, 1_000, 500)),
+                error: None,
+            },
+            RouteCandidate {
+                venue: Venue::RaydiumV4,
+                quote: Some(test_quote(Venue::RaydiumV4, 1_000, 700)),
+                error: None,
+            },
+            RouteCandidate {
+                venue: Venue::RaydiumCpmm,
+                quote: Some(test_quote(Venue::RaydiumCpmm, 1_000, 700)),
+                error: None,
+            },
+        ];
+        let amount_out = |quote: &SwapQuote| quote.amount_out;
+        assert_eq!(
+            best_quote(&candidates, amount_out).unwrap().venue,
+            Venue::RaydiumV4
+        );
+
+        assert!(best_quote(&candidates[..1], amount_out).is_none());
+        assert!(best_quote(
+            &[RouteCandidate {
+                venue: Venue::PumpSwap,
+                quote: Some(test_quote(Venue::PumpSwap, 1_000, 0)),
+                error: None,
+            }],
+            amount_out
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_best_quote_net_of_costs() {
+        let router = Router::default();
+        // CLMM outputs a little more, but its larger compute unit limit
+        // costs more than that
+        let candidates = vec![
+            RouteCandidate {
+                venue: Venue::RaydiumClmm,
+                quote: Some(test_quote(Venue::RaydiumClmm, 5_000_000, 100_010)),
+                error: None,
+            },
+            RouteCandidate {
+                venue: Venue::RaydiumCpmm,
+                quote: Some(test_quote(Venue::RaydiumCpmm, 5_000_000, 100_000)),
+                error: None,
+            },
+        ];
+        let best = best_quote(&candidates, |quote| {
+            router.net_amount_out(TradeSide::Sell, slice::from_ref(quote))
+        });
+        assert_eq!(best.unwrap().venue, Venue::RaydiumCpmm);
+    }
+
+    #[test]
+    fn test_tx_cost() {
+        let router = Router {
+            compute_unit_price: 1_000_000,
+            ..Router::default()
+        };
+        // 1 lamport per compute unit
+        assert_eq!(router.tx_cost(Venue::RaydiumCpmm), 5_000 + 10_000 + 150_000);
+
+        let free = Router {
+            compute_unit_price: 0,
+            ..Router::default()
+        };
+        assert_eq!(free.tx_cost(Venue::Jupiter), 15_000);
+    }
+
+    #[test]
+    fn test_net_amount_out() {
+        let router = Router {
+            compute_unit_price: 0,
+            ..Router::default()
+        };
+        // A split pays one more transaction
+        let legs = [
+            test_quote(Venue::PumpSwap, 1_000_000, 500_000),
+            test_quote(Venue::RaydiumV4, 1_000_000, 500_000),
+        ];
+        assert_eq!(router.net_amount_out(TradeSide::Sell, &legs[..1]), 485_000);
+        assert_eq!(router.net_amount_out(TradeSide::Sell, &legs), 970_000);
+
+        // The fees and the token account rent take 2 054 280 of the
+        // 4 054 280 lamports of a buy, the tokens shrinking in proportion
+        let buy = [test_quote(Venue::PumpSwap, 4_054_280, 1_000_000)];
+        assert_eq!(router.net_amount_out(TradeSide::Buy, &buy), 493_305);
+    }
+
+    #[test]
+    fn test_net_of_cost() {
+        assert_eq!(net_of_cost(TradeSide::Sell, 10, 1_000, 100), 900);
+        assert_eq!(net_of_cost(TradeSide::Sell, 10, 1_000, 2_000), 0);
+        assert_eq!(net_of_cost(TradeSide::Buy, 1_000, 500, 100), 450);
+        assert_eq!(net_of_cost(TradeSide::Buy, 1_000, 500, 2_000), 0);
+        assert_eq!(net_of_cost(TradeSide::Buy, 0, 500, 100), 0);
+    }
+
+    #[test]
+    fn test_best_increment() {
+        assert_eq!(best_increment(&[0, 0], &[Some(100), Some(120)]), Some(1));
+        // Venue 1 went deeper into its curve, venue 0 adds more now
+        assert_eq!(best_increment(&[0, 120], &[Some(100), Some(210)]), Some(0));
+        assert_eq!(best_increment(&[100, 120], &[None, Some(130)]), Some(1));
+        assert_eq!(best_increment(&[100, 120], &[None, None]), None);
+        assert_eq!(best_increment(&[100], &[Some(100)]), None);
+    }
+
+    #[test]
+    fn test_gain_bps() {
+        assert_eq!(gain_bps(10_000, 10_050), 50);
+        assert_eq!(gain_bps(10_000, 9_000), 0);
+        assert_eq!(gain_bps(0, 100), 0);
+        assert_eq!(gain_bps(u64::MAX / 2, u64::MAX), 10_000);
+    }
+
+    #[test]
+    fn test_route_amount_out() {
+        let route = Route {
+            mint: Pubkey::new_unique(),
+            side: TradeSide::Buy,
+            amount_in: 2_000,
+            legs: vec![
+                test_quote(Venue::PumpSwap, 1_000, 500),
+                test_quote(Venue::RaydiumV4, 1_000, 700),
+            ],
+            candidates: vec![],
+            reason: String::new(),
+        };
+        assert_eq!(route.amount_out(), 1_200);
+    }
+
+    #[test]
+    fn test_route_price_impact() {
+        let mut small = test_quote(Venue::PumpSwap, 1_000, 500);
+        small.price_impact = 0.01;
+        let mut large = test_quote(Venue::RaydiumV4, 1_000, 700);
+        large.amount_in = 3_000;
+        large.price_impact = 0.05;
+        let mut route = Route {
+            mint: Pubkey::new_unique(),
+            side: TradeSide::Buy,
+            amount_in: 4_000,
+            legs: vec![small, large],
+            candidates: vec![],
+            reason: String::new(),
+        };
+        assert!((route.price_impact() - 0.04).abs() < 1e-12);
+
+        route.amount_in = 0;
+        assert_eq!(route.price_impact(), 0.0);
+    }
+}
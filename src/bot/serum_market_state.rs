@@ -0,0 +1,163 @@
+use bytemuck::{Pod, Zeroable};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::constants::SERUM_PROGRAM;
+
+use super::AccountDecoder;
+
+/// Serum/OpenBook v1 market state, the order book Raydium AMM v4 pools are linked to.
+/// Based on https://github.com/openbook-dex/program/blob/master/dex/src/state.rs
+///
+/// The account is wrapped by the `serum` head and `padding` tail blobs,
+/// this struct only covers the bytes in between.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct SerumMarketState {
+    pub account_flags: u64,
+    pub own_address: Pubkey,
+    /// Nonce used with the market address to derive the vault signer
+    pub vault_signer_nonce: u64,
+    pub coin_mint: Pubkey,
+    pub pc_mint: Pubkey,
+    pub coin_vault: Pubkey,
+    pub coin_deposits_total: u64,
+    pub coin_fees_accrued: u64,
+    pub pc_vault: Pubkey,
+    pub pc_deposits_total: u64,
+    pub pc_fees_accrued: u64,
+    pub pc_dust_threshold: u64,
+    pub request_queue: Pubkey,
+    pub event_queue: Pubkey,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub coin_lot_size: u64,
+    pub pc_lot_size: u64,
+    pub fee_rate_bps: u64,
+    pub referrer_rebates_accrued: u64,
+}
+
+/// Blob in front of every Serum account
+const SERUM_ACCOUNT_HEAD: &[u8] = b"serum";
+/// Blob at the end of every Serum account
+const SERUM_ACCOUNT_TAIL: &[u8] = b"padding";
+/// `SerumMarketState::account_flags` bits of a live market
+const SERUM_ACCOUNT_FLAG_INITIALIZED: u64 = 1 << 0;
+const SERUM_ACCOUNT_FLAG_MARKET: u64 = 1 << 1;
+
+impl AccountDecoder for SerumMarketState {
+    const OWNERS: &'static [Pubkey] = &[SERUM_PROGRAM];
+    const HEAD: &'static [u8] = SERUM_ACCOUNT_HEAD;
+    const TAIL: &'static [u8] = SERUM_ACCOUNT_TAIL;
+}
+
+impl SerumMarketState {
+    /// Checks the account is an initialized market living at `market_id`
+    ///
+    /// * `market_id` - The market account the state was read from
+    pub fn validate(&self, market_id: &Pubkey) -> Result<(), &'static str> {
+        let (account_flags, own_address) = (self.account_flags, self.own_address);
+        let market_flags = SERUM_ACCOUNT_FLAG_INITIALIZED | SERUM_ACCOUNT_FLAG_MARKET;
+
+        if account_flags & market_flags != market_flags {
+            return Err("Serum account is not an initialized market");
+        }
+
+        if own_address != *market_id {
+            return Err("Serum market address mismatch");
+        }
+
+        Ok(())
+    }
+
+    /// Derives the vault signer, the authority of the market coin and pc vaults
+    ///
+    /// * `market_id` - The market account
+    /// * `market_program` - The program owning the market
+    pub fn vault_signer(
+        &self,
+        market_id: &Pubkey,
+        market_program: &Pubkey,
+    ) -> Result<Pubkey, &'static str> {
+        let nonce = self.vault_signer_nonce;
+
+        Pubkey::create_program_address(&[market_id.as_ref(), &nonce.to_le_bytes()], market_program)
+            .map_err(|_| "Invalid vault signer nonce")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds market bytes with the given fields set, the rest zeroed
+    fn market_bytes(own_address: &Pubkey, nonce: u64, bids: &Pubkey) -> Vec<u8> {
+        let mut data = SERUM_ACCOUNT_HEAD.to_vec();
+        data.extend_from_slice(
+            &(SERUM_ACCOUNT_FLAG_INITIALIZED | SERUM_ACCOUNT_FLAG_MARKET).to_le_bytes(),
+        );
+        data.extend_from_slice(own_address.as_ref());
+        data.extend_from_slice(&nonce.to_le_bytes());
+        // coin/pc mint, coin vault, deposits, fees, pc vault, deposits, fees, dust,
+        // request queue, event queue
+        data.resize(data.len() + 32 * 3 + 16 + 32 + 24 + 32 * 2, 0);
+        data.extend_from_slice(bids.as_ref());
+        data.resize(data.len() + 32 + 8 * 4, 0);
+        data.extend_from_slice(SERUM_ACCOUNT_TAIL);
+        data
+    }
+
+    #[test]
+    fn test_market_state_size() {
+        // 388 bytes on chain, head and tail included
+        assert_eq!(5 + std::mem::size_of::<SerumMarketState>() + 7, 388);
+    }
+
+    #[test]
+    fn test_market_state_decode() {
+        let market_id = Pubkey::new_unique();
+        let bids = Pubkey::new_unique();
+        let data = market_bytes(&market_id, 3, &bids);
+
+        let market_state = SerumMarketState::decode(&data).unwrap();
+        assert_eq!({ market_state.own_address }, market_id);
+        assert_eq!({ market_state.vault_signer_nonce }, 3);
+        assert_eq!({ market_state.bids }, bids);
+        assert!(market_state.validate(&market_id).is_ok());
+        assert!(market_state.validate(&bids).is_err());
+
+        assert!(SerumMarketState::decode(&data[1..]).is_err());
+        let mut bad_head = data.clone();
+        bad_head[0] = b'x';
+        assert!(SerumMarketState::decode(&bad_head).is_err());
+
+        // Not a market, e.g. an open orders account
+        let mut not_market = data.clone();
+        not_market[5] = SERUM_ACCOUNT_FLAG_INITIALIZED as u8;
+        let not_market = SerumMarketState::decode(&not_market).unwrap();
+        assert!(not_market.validate(&market_id).is_err());
+    }
+
+    #[test]
+    fn test_vault_signer() {
+        let market_id = Pubkey::new_unique();
+
+        // The market creator picks the first nonce giving a valid program address
+        let (nonce, expected) = (0u64..)
+            .find_map(|nonce| {
+                Pubkey::create_program_address(
+                    &[market_id.as_ref(), &nonce.to_le_bytes()],
+                    &SERUM_PROGRAM,
+                )
+                .ok()
+                .map(|address| (nonce, address))
+            })
+            .unwrap();
+
+        let data = market_bytes(&market_id, nonce, &market_id);
+        let market_state = SerumMarketState::decode(&data).unwrap();
+        assert_eq!(
+            market_state.vault_signer(&market_id, &SERUM_PROGRAM),
+            Ok(expected)
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use bytemuck::{Pod, Zeroable};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::constants::SPL_TOKEN_2022_PROGRAM;
+
+use super::AccountDecoder;
+
+/// SPL Token mint, same layout for Token and Token-2022 (whose extensions follow)
+/// Based on https://github.com/solana-labs/solana-program-library/blob/master/token/program/src/state.rs
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct SplMintState {
+    /// 1 if `mint_authority` is set, 0 otherwise
+    pub mint_authority_option: u32,
+    pub mint_authority: Pubkey,
+    pub supply: u64,
+    pub decimals: u8,
+    pub is_initialized: u8,
+    /// 1 if `freeze_authority` is set, 0 otherwise
+    pub freeze_authority_option: u32,
+    pub freeze_authority: Pubkey,
+}
+
+/// SPL Token account, same layout for Token and Token-2022 (whose extensions follow)
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct SplTokenAccountState {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub delegate_option: u32,
+    pub delegate: Pubkey,
+    /// 0 uninitialized, 1 initialized, 2 frozen
+    pub state: u8,
+    pub is_native_option: u32,
+    /// Rent exempt reserve of a native (WSOL) account
+    pub is_native: u64,
+    pub delegated_amount: u64,
+    pub close_authority_option: u32,
+    pub close_authority: Pubkey,
+}
+
+impl AccountDecoder for SplMintState {
+    const OWNERS: &'static [Pubkey] = &[spl_token::ID, SPL_TOKEN_2022_PROGRAM];
+    const EXTENDABLE: bool = true;
+}
+
+impl AccountDecoder for SplTokenAccountState {
+    const OWNERS: &'static [Pubkey] = &[spl_token::ID, SPL_TOKEN_2022_PROGRAM];
+    const EXTENDABLE: bool = true;
+}
+
+/// `SplTokenAccountState::state` of a frozen account
+const SPL_TOKEN_ACCOUNT_STATE_FROZEN: u8 = 2;
+
+impl SplMintState {
+    /// Returns the mint authority, None once renounced
+    pub fn mint_authority(&self) -> Option<Pubkey> {
+        (self.mint_authority_option != 0).then_some(self.mint_authority)
+    }
+
+    /// Returns the freeze authority, None once renounced
+    pub fn freeze_authority(&self) -> Option<Pubkey> {
+        (self.freeze_authority_option != 0).then_some(self.freeze_authority)
+    }
+}
+
+impl SplTokenAccountState {
+    /// Returns true if the account is frozen by the mint freeze authority
+    pub fn is_frozen(&self) -> bool {
+        self.state == SPL_TOKEN_ACCOUNT_STATE_FROZEN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use solana_program::{program_option::COption, program_pack::Pack};
+    use spl_token::state::{Account, AccountState, Mint};
+
+    // The reference encoder of the token program produces the golden bytes
+
+    #[test]
+    fn test_decode_mint() {
+        let freeze_authority = Pubkey::new_unique();
+        let mint = Mint {
+            mint_authority: COption::None,
+            supply: 1_000_000_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::Some(freeze_authority),
+        };
+        let mut data = vec![0; Mint::LEN];
+        Mint::pack(mint, &mut data).unwrap();
+
+        let state = SplMintState::decode(&data).unwrap();
+        assert_eq!(state.mint_authority(), None);
+        assert_eq!(state.freeze_authority(), Some(freeze_authority));
+        assert_eq!({ state.supply }, 1_000_000_000_000_000);
+        assert_eq!(state.decimals, 6);
+        assert_eq!(state.is_initialized, 1);
+
+        assert!(SplMintState::decode(&data[..Mint::LEN - 1]).is_err());
+    }
+
+    // USDC mint on mainnet, dumped with
+    // `solana account EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v`
+    const USDC_MINT: &str = "AQAAABzjWe1aAS4E+hQrnHUaHF6Hz9CgFhuchf/TG3jN/Nj2gIZEdqQNAAAGAQEAAAAqnl7btTwEZ5CY/3sSZRcUQ0/AjFYqmjuGEQXmctQicw==";
+
+    #[test]
+    fn test_decode_mainnet_mint() {
+        let data = STANDARD.decode(USDC_MINT).unwrap();
+
+        let state = SplMintState::decode(&data).unwrap();
+        assert_eq!(
+            state.mint_authority(),
+            Some(Pubkey::from_str_const(
+                "2wmVCSfPxGPjrnMMn7rchp4uaeoTqN39mXFC2zhPdri9"
+            ))
+        );
+        assert_eq!(
+            state.freeze_authority(),
+            Some(Pubkey::from_str_const(
+                "3sNBr7kMccME5D55xNgsmYpZnzPgP2g12CixAajXypn6"
+            ))
+        );
+        assert_eq!({ state.supply }, 15_000_010_000_000);
+        assert_eq!(state.decimals, 6);
+        assert_eq!(state.is_initialized, 1);
+    }
+
+    #[test]
+    fn test_decode_token_account() {
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let account = Account {
+            mint,
+            owner,
+            amount: 42,
+            state: AccountState::Frozen,
+            ..Default::default()
+        };
+        let mut data = vec![0; Account::LEN];
+        Account::pack(account, &mut data).unwrap();
+
+        let state = *SplTokenAccountState::decode(&data).unwrap();
+        assert_eq!({ state.mint }, mint);
+        assert_eq!({ state.owner }, owner);
+        assert_eq!({ state.amount }, 42);
+        assert!(state.is_frozen());
+
+        // Token-2022 accounts carry their extensions after the base layout
+        data.extend_from_slice(&[2, 0, 0, 0]);
+        assert_eq!(SplTokenAccountState::decode(&data), Ok(&state));
+    }
+}
@@ -0,0 +1,473 @@
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::HashSet,
+    env,
+    error::Error,
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::constants::{PUMP_FUN_PROGRAM, SOL_DECIMALS, WSOL_MINT};
+use crate::errors::TokenFilterError;
+
+use super::PumpFunBondingCurve;
+
+/// Platform a token was launched on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Launchpad {
+    PumpFun,
+    /// Launched anywhere else, or directly on an AMM
+    Unknown,
+}
+
+impl Launchpad {
+    /// Parses a launchpad from the config, e.g. "pump_fun"
+    ///
+    /// * `launchpad` - The launchpad name
+    pub fn from_config(launchpad: &str) -> Option<Self> {
+        match launchpad.trim().to_lowercase().as_str() {
+            "pump_fun" | "pumpfun" => Some(Self::PumpFun),
+            "unknown" => Some(Self::Unknown),
+            _ => None,
+        }
+    }
+
+    /// Finds where a mint was launched. Pump.fun tokens keep their
+    /// bonding curve account after migrating to an AMM.
+    ///
+    /// * `connection` - The RPC client
+    /// * `mint` - The token mint
+    pub async fn detect(
+        connection: &RpcClient,
+        mint: &Pubkey,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let bonding_curve = PumpFunBondingCurve::address(mint);
+        let account = connection
+            .get_multiple_accounts(&[bonding_curve])
+            .await?
+            .pop()
+            .flatten();
+
+        match account {
+            Some(account) if account.owner == PUMP_FUN_PROGRAM => Ok(Self::PumpFun),
+            _ => Ok(Self::Unknown),
+        }
+    }
+}
+
+impl fmt::Display for Launchpad {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PumpFun => write!(f, "Pump.fun"),
+            Self::Unknown => write!(f, "an unknown launchpad"),
+        }
+    }
+}
+
+/// What a venue knows about a token it is about to buy
+#[derive(Debug, Clone)]
+pub struct TokenCandidate {
+    pub mint: Pubkey,
+    /// The token or pool creator, None when the venue does not record it
+    pub creator: Option<Pubkey>,
+    pub launchpad: Launchpad,
+    /// Quote asset held by the pool or bonding curve, in raw units
+    pub quote_liquidity: u64,
+    /// Unix timestamp the pool opened at, None when the venue does not record it
+    pub pool_open_time: Option<u64>,
+}
+
+/// Token eligibility policy shared by all the venues, so Pump.fun and
+/// Raydium copy the same tokens.
+///
+/// Applied to buys through `Venue::check_token`. Empty allowlists allow
+/// everything.
+#[derive(Debug, Clone)]
+pub struct TokenFilter {
+    /// Mints a pool can be priced in. Only WSOL, the venues sizing and
+    /// quoting every trade in lamports
+    pub quote_mints: HashSet<Pubkey>,
+    /// The token mint has to end with one of them, e.g. "pump"
+    pub mint_suffixes: Vec<String>,
+    pub allowed_creators: HashSet<Pubkey>,
+    pub blocked_creators: HashSet<Pubkey>,
+    pub launchpads: HashSet<Launchpad>,
+    pub blocked_mints: HashSet<Pubkey>,
+    /// Minimum quote liquidity, in raw units of the quote mint
+    pub min_quote_liquidity: u64,
+    pub min_pool_age_secs: Option<u64>,
+    pub max_pool_age_secs: Option<u64>,
+}
+
+impl Default for TokenFilter {
+    /// SOL paired tokens ending with "pump", the historical behavior
+    fn default() -> Self {
+        Self {
+            quote_mints: HashSet::from([WSOL_MINT]),
+            mint_suffixes: vec!["pump".to_string()],
+            allowed_creators: HashSet::new(),
+            blocked_creators: HashSet::new(),
+            launchpads: HashSet::new(),
+            blocked_mints: HashSet::new(),
+            min_quote_liquidity: 0,
+            min_pool_age_secs: None,
+            max_pool_age_secs: None,
+        }
+    }
+}
+
+impl TokenFilter {
+    /// Loads the filter from the environment, unset variables keep the defaults:
+    ///
+    /// * `token_quote_mints` - Comma separated quote mints, WSOL by default and the only one supported
+    /// * `token_mint_suffixes` - Comma separated suffixes, "pump" by default, empty for any
+    /// * `token_allowed_creators` / `token_blocked_creators` - Comma separated wallets
+    /// * `token_launchpads` - Comma separated launchpads: pump_fun, unknown
+    /// * `token_blocked_mints` - Comma separated mints
+    /// * `token_min_liquidity_sol` - Minimum quote liquidity in SOL
+    /// * `token_min_pool_age_secs` / `token_max_pool_age_secs` - Pool age bounds
+    pub fn from_env() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut token_filter = Self::default();
+
+        if let Ok(quote_mints) = env::var("token_quote_mints") {
+            token_filter.quote_mints = parse_quote_mints(&quote_mints)?;
+        }
+        if let Ok(mint_suffixes) = env::var("token_mint_suffixes") {
+            token_filter.mint_suffixes = split_list(&mint_suffixes).map(String::from).collect();
+        }
+        if let Ok(creators) = env::var("token_allowed_creators") {
+            token_filter.allowed_creators = parse_pubkeys(&creators)?;
+        }
+        if let Ok(creators) = env::var("token_blocked_creators") {
+            token_filter.blocked_creators = parse_pubkeys(&creators)?;
+        }
+        if let Ok(launchpads) = env::var("token_launchpads") {
+            token_filter.launchpads = split_list(&launchpads)
+                .map(|launchpad| {
+                    Launchpad::from_config(launchpad)
+                        .ok_or_else(|| format!("Unknown launchpad: {launchpad}"))
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        if let Ok(mints) = env::var("token_blocked_mints") {
+            token_filter.blocked_mints = parse_pubkeys(&mints)?;
+        }
+        if let Ok(min_liquidity) = env::var("token_min_liquidity_sol") {
+            token_filter.min_quote_liquidity =
+                (min_liquidity.parse::<f64>()? * SOL_DECIMALS as f64) as u64;
+        }
+        if let Ok(min_age) = env::var("token_min_pool_age_secs") {
+            token_filter.min_pool_age_secs = Some(min_age.parse()?);
+        }
+        if let Ok(max_age) = env::var("token_max_pool_age_secs") {
+            token_filter.max_pool_age_secs = Some(max_age.parse()?);
+        }
+
+        Ok(token_filter)
+    }
+
+    /// Returns true if `mint` is one of the quote assets
+    pub fn is_quote_mint(&self, mint: &Pubkey) -> bool {
+        self.quote_mints.contains(mint)
+    }
+
+    /// Resolves which side of a pool is the traded token, instead of
+    /// assuming a fixed side. Returns (token mint, quote mint).
+    ///
+    /// * `mint_a` - The first mint of the pool
+    /// * `mint_b` - The second mint of the pool
+    pub fn meme_side(
+        &self,
+        mint_a: &Pubkey,
+        mint_b: &Pubkey,
+    ) -> Result<(Pubkey, Pubkey), TokenFilterError> {
+        match (self.is_quote_mint(mint_a), self.is_quote_mint(mint_b)) {
+            (false, true) => Ok((*mint_a, *mint_b)),
+            (true, false) => Ok((*mint_b, *mint_a)),
+            _ => Err(TokenFilterError::NoQuoteSide),
+        }
+    }
+
+    /// Finds the launchpad of a mint, only when a launchpad rule needs it
+    ///
+    /// * `connection` - The RPC client
+    /// * `mint` - The token mint
+    pub async fn launchpad_of(
+        &self,
+        connection: &RpcClient,
+        mint: &Pubkey,
+    ) -> Result<Launchpad, Box<dyn Error + Send + Sync>> {
+        if self.launchpads.is_empty() {
+            return Ok(Launchpad::Unknown);
+        }
+
+        Launchpad::detect(connection, mint).await
+    }
+
+    /// Checks a token against every rule, at the current time
+    ///
+    /// * `candidate` - The token about to be bought
+    pub fn check_now(&self, candidate: &TokenCandidate) -> Result<(), TokenFilterError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        self.check(candidate, now)
+    }
+
+    /// Checks a token against every rule
+    ///
+    /// * `candidate` - The token about to be bought
+    /// * `now` - The current unix timestamp
+    pub fn check(&self, candidate: &TokenCandidate, now: u64) -> Result<(), TokenFilterError> {
+        let mint = candidate.mint;
+
+        if self.blocked_mints.contains(&mint) {
+            return Err(TokenFilterError::BlockedMint(mint));
+        }
+
+        if !self.mint_suffixes.is_empty() {
+            let mint_str = mint.to_string();
+            if !self
+                .mint_suffixes
+                .iter()
+                .any(|suffix| mint_str.ends_with(suffix.as_str()))
+            {
+                return Err(TokenFilterError::SuffixNotAllowed(mint));
+            }
+        }
+
+        if !self.allowed_creators.is_empty() || !self.blocked_creators.is_empty() {
+            let creator = candidate.creator.ok_or(TokenFilterError::UnknownCreator)?;
+            if self.blocked_creators.contains(&creator)
+                || (!self.allowed_creators.is_empty() && !self.allowed_creators.contains(&creator))
+            {
+                return Err(TokenFilterError::CreatorNotAllowed(creator));
+            }
+        }
+
+        if !self.launchpads.is_empty() && !self.launchpads.contains(&candidate.launchpad) {
+            return Err(TokenFilterError::LaunchpadNotAllowed(candidate.launchpad));
+        }
+
+        if candidate.quote_liquidity < self.min_quote_liquidity {
+            return Err(TokenFilterError::LowLiquidity {
+                min: self.min_quote_liquidity,
+                actual: candidate.quote_liquidity,
+            });
+        }
+
+        // Bonding curves have no pool to age
+        if let Some(pool_open_time) = candidate.pool_open_time {
+            let age = now.saturating_sub(pool_open_time);
+
+            if let Some(min) = self.min_pool_age_secs {
+                if age < min {
+                    return Err(TokenFilterError::PoolTooYoung { min, age });
+                }
+            }
+
+            if let Some(max) = self.max_pool_age_secs {
+                if age > max {
+                    return Err(TokenFilterError::PoolTooOld { max, age });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits a comma separated config value, skipping blanks
+pub(crate) fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// Parses a comma separated list of addresses
+fn parse_pubkeys(list: &str) -> Result<HashSet<Pubkey>, Box<dyn Error + Send + Sync>> {
+    split_list(list)
+        .map(|pubkey| {
+            pubkey
+                .parse::<Pubkey>()
+                .map_err(|_| format!("Invalid address in token filter: {pubkey}").into())
+        })
+        .collect()
+}
+
+/// Parses the quote mints, refusing anything but WSOL
+fn parse_quote_mints(list: &str) -> Result<HashSet<Pubkey>, Box<dyn Error + Send + Sync>> {
+    let quote_mints = parse_pubkeys(list)?;
+    if let Some(mint) = quote_mints.iter().find(|mint| **mint != WSOL_MINT) {
+        return Err(format!("Only WSOL is supported as a quote mint, got: {mint}").into());
+    }
+    Ok(quote_mints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUMP_MINT: Pubkey =
+        Pubkey::from_str_const("3WZNphshQpxWGAN9EgTgAQPj4kHkTxmSYmmH91Dupump");
+
+    fn candidate() -> TokenCandidate {
+        TokenCandidate {
+            mint: PUMP_MINT,
+            creator: None,
+            launchpad: Launchpad::PumpFun,
+            quote_liquidity: 10 * SOL_DECIMALS,
+            pool_open_time: None,
+        }
+    }
+
+    #[test]
+    fn test_meme_side() {
+        let token_filter = TokenFilter::default();
+
+        assert_eq!(
+            token_filter.meme_side(&PUMP_MINT, &WSOL_MINT),
+            Ok((PUMP_MINT, WSOL_MINT))
+        );
+        assert_eq!(
+            token_filter.meme_side(&WSOL_MINT, &PUMP_MINT),
+            Ok((PUMP_MINT, WSOL_MINT))
+        );
+        assert_eq!(
+            token_filter.meme_side(&PUMP_MINT, &Pubkey::new_unique()),
+            Err(TokenFilterError::NoQuoteSide)
+        );
+        assert_eq!(
+            token_filter.meme_side(&WSOL_MINT, &WSOL_MINT),
+            Err(TokenFilterError::NoQuoteSide)
+        );
+    }
+
+    #[test]
+    fn test_mint_rules() {
+        let mut token_filter = TokenFilter::default();
+        assert!(token_filter.check(&candidate(), 0).is_ok());
+
+        let other = TokenCandidate {
+            mint: WSOL_MINT,
+            ..candidate()
+        };
+        assert_eq!(
+            token_filter.check(&other, 0),
+            Err(TokenFilterError::SuffixNotAllowed(WSOL_MINT))
+        );
+
+        token_filter.mint_suffixes.clear();
+        assert!(token_filter.check(&other, 0).is_ok());
+
+        token_filter.blocked_mints.insert(PUMP_MINT);
+        assert_eq!(
+            token_filter.check(&candidate(), 0),
+            Err(TokenFilterError::BlockedMint(PUMP_MINT))
+        );
+    }
+
+    #[test]
+    fn test_creator_and_launchpad_rules() {
+        let creator = Pubkey::new_unique();
+        let mut token_filter = TokenFilter {
+            allowed_creators: HashSet::from([creator]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            token_filter.check(&candidate(), 0),
+            Err(TokenFilterError::UnknownCreator)
+        );
+        let created = TokenCandidate {
+            creator: Some(creator),
+            ..candidate()
+        };
+        assert!(token_filter.check(&created, 0).is_ok());
+
+        token_filter.blocked_creators.insert(creator);
+        assert_eq!(
+            token_filter.check(&created, 0),
+            Err(TokenFilterError::CreatorNotAllowed(creator))
+        );
+
+        let token_filter = TokenFilter {
+            launchpads: HashSet::from([Launchpad::PumpFun]),
+            ..Default::default()
+        };
+        assert!(token_filter.check(&candidate(), 0).is_ok());
+        let unknown = TokenCandidate {
+            launchpad: Launchpad::Unknown,
+            ..candidate()
+        };
+        assert_eq!(
+            token_filter.check(&unknown, 0),
+            Err(TokenFilterError::LaunchpadNotAllowed(Launchpad::Unknown))
+        );
+    }
+
+    #[test]
+    fn test_liquidity_and_age_rules() {
+        let token_filter = TokenFilter {
+            min_quote_liquidity: 20 * SOL_DECIMALS,
+            ..Default::default()
+        };
+        assert_eq!(
+            token_filter.check(&candidate(), 0),
+            Err(TokenFilterError::LowLiquidity {
+                min: 20 * SOL_DECIMALS,
+                actual: 10 * SOL_DECIMALS
+            })
+        );
+
+        let token_filter = TokenFilter {
+            min_pool_age_secs: Some(60),
+            max_pool_age_secs: Some(3_600),
+            ..Default::default()
+        };
+        // Bonding curves are not aged
+        assert!(token_filter.check(&candidate(), 1_000).is_ok());
+
+        let pool = TokenCandidate {
+            pool_open_time: Some(1_000),
+            ..candidate()
+        };
+        assert_eq!(
+            token_filter.check(&pool, 1_030),
+            Err(TokenFilterError::PoolTooYoung { min: 60, age: 30 })
+        );
+        assert!(token_filter.check(&pool, 1_060).is_ok());
+        assert_eq!(
+            token_filter.check(&pool, 5_000),
+            Err(TokenFilterError::PoolTooOld {
+                max: 3_600,
+                age: 4_000
+            })
+        );
+        // Not opened yet
+        assert!(token_filter.check(&pool, 900).is_err());
+    }
+
+    #[test]
+    fn test_from_config() {
+        assert_eq!(
+            Launchpad::from_config(" Pump_Fun"),
+            Some(Launchpad::PumpFun)
+        );
+        assert_eq!(Launchpad::from_config("unknown"), Some(Launchpad::Unknown));
+        assert_eq!(Launchpad::from_config("moonshot"), None);
+
+        let pubkeys = parse_pubkeys(&format!("{PUMP_MINT}, ,{WSOL_MINT}")).unwrap();
+        assert_eq!(pubkeys, HashSet::from([PUMP_MINT, WSOL_MINT]));
+        assert!(parse_pubkeys("not-a-pubkey").is_err());
+
+        assert_eq!(
+            parse_quote_mints(&WSOL_MINT.to_string()).unwrap(),
+            HashSet::from([WSOL_MINT])
+        );
+        assert!(parse_quote_mints(&format!("{WSOL_MINT},{PUMP_MINT}")).is_err());
+    }
+}
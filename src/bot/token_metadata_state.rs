@@ -0,0 +1,165 @@
+use solana_sdk::pubkey::Pubkey;
+
+use crate::constants::{TOKEN_METADATA_PROGRAM, TOKEN_METADATA_SEED};
+
+/// `key` of a Metaplex metadata account
+const METADATA_V1_KEY: u8 = 4;
+/// Bytes of a `Creator` entry: address, verified flag and share
+const CREATOR_LEN: usize = 34;
+
+/// The part of the Metaplex metadata of a mint the safety checks read.
+/// The account is Borsh encoded with variable length strings, so it is
+/// read field by field instead of cast.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenMetadata {
+    /// Can change the metadata as long as it is mutable
+    pub update_authority: Pubkey,
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub creators: Vec<Pubkey>,
+    /// False once the name, symbol and URI are frozen
+    pub is_mutable: bool,
+}
+
+impl TokenMetadata {
+    /// Derives the metadata address of a mint
+    ///
+    /// * `mint` - The token mint
+    pub fn address(mint: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                TOKEN_METADATA_SEED,
+                TOKEN_METADATA_PROGRAM.as_ref(),
+                mint.as_ref(),
+            ],
+            &TOKEN_METADATA_PROGRAM,
+        )
+        .0
+    }
+
+    /// Parses the raw metadata account data, None when it is not a
+    /// metadata account or is cut short
+    ///
+    /// * `data` - The raw metadata account data
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let mut reader = Reader { data, offset: 0 };
+
+        if reader.u8()? != METADATA_V1_KEY {
+            return None;
+        }
+        let update_authority = reader.pubkey()?;
+        let mint = reader.pubkey()?;
+        // Padded with null bytes up to their max length
+        let name = reader.string()?.trim_end_matches('\0').to_string();
+        let symbol = reader.string()?.trim_end_matches('\0').to_string();
+        let _uri = reader.string()?;
+        let _seller_fee_basis_points = reader.bytes(2)?;
+
+        let mut creators = Vec::new();
+        if reader.u8()? == 1 {
+            let count = reader.u32()? as usize;
+            for _ in 0..count {
+                let creator = reader.bytes(CREATOR_LEN)?;
+                creators.push(Pubkey::try_from(&creator[..32]).ok()?);
+            }
+        }
+        let _primary_sale_happened = reader.u8()?;
+        let is_mutable = reader.u8()? != 0;
+
+        Some(Self {
+            update_authority,
+            mint,
+            name,
+            symbol,
+            creators,
+            is_mutable,
+        })
+    }
+}
+
+/// Reads Borsh fields one after the other
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.offset..self.offset.checked_add(len)?)?;
+        self.offset += len;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    fn pubkey(&mut self) -> Option<Pubkey> {
+        Pubkey::try_from(self.bytes(32)?).ok()
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.bytes(len)?.to_vec()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(value: &str, padded_len: usize) -> Vec<u8> {
+        let mut bytes = (padded_len as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(value.as_bytes());
+        bytes.resize(4 + padded_len, 0);
+        bytes
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let (update_authority, mint, creator) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut data = vec![METADATA_V1_KEY];
+        data.extend_from_slice(update_authority.as_ref());
+        data.extend_from_slice(mint.as_ref());
+        data.extend(string("Test Coin", 32));
+        data.extend(string("TEST", 10));
+        data.extend(string("https://example.com/test.json", 200));
+        data.extend_from_slice(&500u16.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(creator.as_ref());
+        data.extend_from_slice(&[1, 100]);
+        data.push(0);
+        data.push(1);
+        // Newer fields follow
+        data.extend_from_slice(&[0; 16]);
+
+        let metadata = TokenMetadata::parse(&data).unwrap();
+        assert_eq!(metadata.update_authority, update_authority);
+        assert_eq!(metadata.mint, mint);
+        assert_eq!(metadata.name, "Test Coin");
+        assert_eq!(metadata.symbol, "TEST");
+        assert_eq!(metadata.creators, vec![creator]);
+        assert!(metadata.is_mutable);
+
+        // Frozen metadata
+        let mut frozen = data.clone();
+        let is_mutable = data.len() - 17;
+        frozen[is_mutable] = 0;
+        assert!(!TokenMetadata::parse(&frozen).unwrap().is_mutable);
+
+        // Cut short, or not a metadata account
+        assert_eq!(TokenMetadata::parse(&data[..100]), None);
+        data[0] = 0;
+        assert_eq!(TokenMetadata::parse(&data), None);
+    }
+}
@@ -26,6 +26,9 @@ use solana_sdk::{
 // Serialize the transaction
 use bincode::{serialize, ErrorKind};
 use tokio::sync::Mutex;
+
+use super::{LatencyStage, OrderTrace};
+use crate::constants::JITO_TIP_LAMPORTS;
 pub trait GetSignature {
     // Returns the signature of the transaction
     fn get_signature(json: &Value) -> Option<String> {
@@ -73,10 +76,38 @@ pub trait GetTxCommon {
         json.as_str()
             .map_or("not found".to_string(), ToString::to_string)
     }
+
+    /// Returns the mint of a token account touched by the transaction,
+    /// looked up in the pre/post token balances of the transaction meta.
+    ///
+    /// * `json` - The transaction JSON
+    /// * `token_account` - The token account address
+    fn get_token_account_mint(json: &Value, token_account: &str) -> Option<String> {
+        let account_keys = json["params"]["result"]["transaction"]["transaction"]["message"]
+            ["accountKeys"]
+            .as_array()?;
+
+        let account_index = account_keys.iter().position(|account_key| {
+            account_key["pubkey"]
+                .as_str()
+                .or_else(|| account_key.as_str())
+                .map_or(false, |pubkey| pubkey == token_account)
+        })?;
+
+        let meta = &json["params"]["result"]["transaction"]["meta"];
+        ["preTokenBalances", "postTokenBalances"]
+            .iter()
+            .filter_map(|balances| meta[*balances].as_array())
+            .flatten()
+            .find(|balance| balance["accountIndex"].as_u64() == Some(account_index as u64))
+            .and_then(|balance| balance["mint"].as_str())
+            .map(ToString::to_string)
+    }
 }
 
 pub trait SendSmartTx {
-    /// Sends a smart transaction with a tip Tokio async compatible
+    /// Sends a smart transaction with a tip Tokio async compatible,
+    /// stamping the signature, submission and confirmation on `trace`
     async fn bfg9000_send_smart_tx(
         helius: Arc<Mutex<Helius>>,
         mut instructions: Vec<Instruction>,
@@ -84,8 +115,9 @@ pub trait SendSmartTx {
         lookup_tables: Option<Vec<AddressLookupTableAccount>>,
         signer_prv_key: String,
         copied_tx_id: Option<String>,
+        trace: Option<&OrderTrace>,
     ) -> Result<String, Box<dyn Error + Send + Sync>> {
-        let helius = helius.lock().await;
+        let helius = unlocked(&helius).await;
         // check if this needs to be a V0 tx
         let is_versioned: bool = lookup_tables.is_some();
         // instantiate signer
@@ -95,7 +127,7 @@ pub trait SendSmartTx {
         // determine tip for Jito
         // determine tip for Jito
         // TODO: Optimize tip
-        let tip_amount = 10000; // 10k lamports;
+        let tip_amount = JITO_TIP_LAMPORTS;
 
         // select region for Jito
         let region = "Frankfurt";
@@ -260,6 +292,9 @@ pub trait SendSmartTx {
                 .map_err(|e: Box<ErrorKind>| HeliusError::InvalidInput(e.to_string()))?,
         };
         let transaction_base58: String = encode(&serialized_transaction).into_string();
+        if let Some(trace) = trace {
+            trace.stamp(LatencyStage::Signed);
+        }
 
         // println!("Stop here!");
         // return Ok("Stop here".to_string());
@@ -269,6 +304,9 @@ pub trait SendSmartTx {
             .await?;
 
         println!("Bundle sent: {}", bundle_id);
+        if let Some(trace) = trace {
+            trace.stamp(LatencyStage::Submitted);
+        }
 
         // Poll for confirmation status
         let timeout: Duration = Duration::from_secs(60);
@@ -292,6 +330,13 @@ pub trait SendSmartTx {
                     if let Some(status) = values[0]["confirmation_status"].as_str() {
                         if status == "confirmed" {
                             let tx_id = values[0]["transactions"][0].as_str().unwrap().to_string();
+                            if let Some(trace) = trace {
+                                trace.stamp(LatencyStage::Confirmed);
+                                if let Some(slot) = values[0]["slot"].as_u64() {
+                                    trace.set_landed_slot(slot);
+                                }
+                                tracing::debug!("| 0:: latency: {trace}");
+                            }
 
                             if copied_tx_id.is_some() {
                                 println!(
@@ -378,12 +423,12 @@ pub trait SendSmartTx {
         instructions: &[Instruction],
         signer_prv_key: &str,
     ) -> Result<String, Box<dyn Error>> {
-        let helius = helius.lock().await;
+        let connection = helius.lock().await.connection();
 
         let signer = Keypair::from_base58_string(signer_prv_key);
         let signer_pubkey = signer.pubkey();
         let signers: Vec<Arc<dyn Signer>> = vec![Arc::new(signer)];
-        let recent_blockhash = helius.connection().get_latest_blockhash()?;
+        let recent_blockhash = connection.get_latest_blockhash()?;
         let transaction = Transaction::new_signed_with_payer(
             instructions,
             Some(&signer_pubkey),
@@ -391,7 +436,7 @@ pub trait SendSmartTx {
             recent_blockhash,
         );
 
-        match helius.connection().send_transaction_with_config(
+        match connection.send_transaction_with_config(
             &transaction,
             RpcSendTransactionConfig {
                 skip_preflight: false,
@@ -412,3 +457,16 @@ pub trait SendSmartTx {
         }
     }
 }
+
+/// Copies the clients out of the shared Helius, they are reference counted,
+/// so the lock is not held across the send and its confirmation polling
+async fn unlocked(helius: &Mutex<Helius>) -> Helius {
+    let helius = helius.lock().await;
+    Helius {
+        config: Arc::clone(&helius.config),
+        client: helius.client.clone(),
+        rpc_client: Arc::clone(&helius.rpc_client),
+        async_rpc_client: helius.async_rpc_client.clone(),
+        ws_client: helius.ws_client.clone(),
+    }
+}
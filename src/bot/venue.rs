@@ -0,0 +1,943 @@
+use helius::Helius;
+use serde_json::Value;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::RpcProgramAccountsConfig,
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{
+    address_lookup_table::AddressLookupTableAccount,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use std::{error::Error, fmt, sync::Arc};
+use tokio::sync::Mutex;
+
+use crate::constants::{
+    BOT_RAYDIUM_OPERATION_BUY, METEORA_DLMM_PROGRAM, ORCA_WHIRLPOOL_PROGRAM, PUMP_FUN_PROGRAM,
+    PUMP_SWAP_PROGRAM, RAYDIUM_CLMM_PROGRAM, RAYDIUM_CPMM_PROGRAM,
+    RAYDIUM_LIQUIDITY_POOL_V4_PROGRAM, WSOL_MINT,
+};
+
+use super::{
+    raydium_meme_tx::{InnerIxData, RaydiumMemeTradeData},
+    tx_common::SendSmartTx,
+    wsol::WsolMode,
+    AccountDecoder, ClmmQuote, CpmmQuote, DlmmQuote, JupiterClient, JupiterQuote, JupiterTxSend,
+    LatencyStage, MeteoraDlmmTxSend, OrcaWhirlpoolTxSend, OrderTrace, PriceImpactGuard,
+    PumpFunQuote, PumpFunTxSend, PumpSwapQuote, PumpSwapTxSend, RaydiumClmmTxSend,
+    RaydiumCpmmTxSend, RaydiumMemeTxSend, RaydiumPool, SplTokenAccountState, TokenCandidate,
+    TokenFilter, WhirlpoolQuote,
+};
+
+/// Direction of a trade against SOL: buys spend SOL, sells spend the token
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+impl TradeSide {
+    /// Returns (input mint, output mint) of a swap of `mint` against SOL
+    ///
+    /// * `mint` - The token traded
+    pub fn mints(self, mint: &Pubkey) -> (Pubkey, Pubkey) {
+        match self {
+            Self::Buy => (WSOL_MINT, *mint),
+            Self::Sell => (*mint, WSOL_MINT),
+        }
+    }
+}
+
+impl fmt::Display for TradeSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Buy => write!(f, "Buy"),
+            Self::Sell => write!(f, "Sell"),
+        }
+    }
+}
+
+/// Venues a token can be swapped against SOL on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Venue {
+    PumpFun,
+    PumpSwap,
+    RaydiumV4,
+    RaydiumCpmm,
+    RaydiumClmm,
+    MeteoraDlmm,
+    OrcaWhirlpool,
+    /// Jupiter aggregator routes, the fallback for tokens no other venue trades
+    Jupiter,
+}
+
+impl fmt::Display for Venue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PumpFun => write!(f, "Pump.fun"),
+            Self::PumpSwap => write!(f, "PumpSwap"),
+            Self::RaydiumV4 => write!(f, "Raydium V4"),
+            Self::RaydiumCpmm => write!(f, "Raydium CPMM"),
+            Self::RaydiumClmm => write!(f, "Raydium CLMM"),
+            Self::MeteoraDlmm => write!(f, "Meteora DLMM"),
+            Self::OrcaWhirlpool => write!(f, "Orca Whirlpool"),
+            Self::Jupiter => write!(f, "Jupiter"),
+        }
+    }
+}
+
+/// What every venue needs to quote, build and parse swaps
+#[derive(Clone)]
+pub struct VenueContext {
+    pub helius: Arc<Mutex<Helius>>,
+    pub token_filter: Arc<TokenFilter>,
+    pub wsol_mode: WsolMode,
+    pub jupiter: JupiterClient,
+    /// Bounds the price impact of the buys sent
+    pub price_impact: PriceImpactGuard,
+}
+
+/// A swap against SOL decoded from a transaction, whatever the venue
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedTrade {
+    pub venue: Venue,
+    pub signature: Option<String>,
+    pub side: TradeSide,
+    pub mint: Pubkey,
+    /// The pool, pair or bonding curve traded on
+    pub pool_id: Pubkey,
+    /// Lamports spent on a buy, received on a sell
+    pub sol_amount: u64,
+    /// Tokens received on a buy, spent on a sell
+    pub token_amount: u64,
+    pub compute_unit_limit: u32,
+}
+
+impl ParsedTrade {
+    /// Builds a trade out of the data the AMM parsers share
+    ///
+    /// * `venue` - The venue the transaction traded on
+    /// * `signature` - The transaction signature
+    /// * `pool_id` - The pool traded on
+    /// * `meme_trade_data` - The traded mint and the direction
+    /// * `inner_ix_data` - The amounts transferred in and out of the pool
+    /// * `compute_unit_limit` - The compute unit limit of the transaction
+    pub(crate) fn from_meme_trade(
+        venue: Venue,
+        signature: Option<String>,
+        pool_id: &str,
+        meme_trade_data: &RaydiumMemeTradeData,
+        inner_ix_data: &InnerIxData,
+        compute_unit_limit: u32,
+    ) -> Option<Self> {
+        let (side, sol_amount, token_amount) =
+            if meme_trade_data.operation == BOT_RAYDIUM_OPERATION_BUY {
+                (
+                    TradeSide::Buy,
+                    inner_ix_data.source_amount,
+                    inner_ix_data.dest_amount,
+                )
+            } else {
+                (
+                    TradeSide::Sell,
+                    inner_ix_data.dest_amount,
+                    inner_ix_data.source_amount,
+                )
+            };
+
+        Some(Self {
+            venue,
+            signature,
+            side,
+            mint: meme_trade_data.meme_mint,
+            pool_id: pool_id.parse().ok()?,
+            sol_amount,
+            token_amount,
+            compute_unit_limit,
+        })
+    }
+}
+
+/// The venue specific part of a quote, what its swap is built from
+#[derive(Debug, Clone)]
+pub enum QuoteDetails {
+    PumpFun(PumpFunQuote),
+    PumpSwap(PumpSwapQuote),
+    RaydiumV4(RaydiumPool),
+    RaydiumCpmm(CpmmQuote),
+    RaydiumClmm(ClmmQuote),
+    MeteoraDlmm(DlmmQuote),
+    OrcaWhirlpool(WhirlpoolQuote),
+    Jupiter(JupiterQuote),
+}
+
+/// An exact input quote for a swap against SOL on a venue
+#[derive(Debug, Clone)]
+pub struct SwapQuote {
+    pub venue: Venue,
+    pub pool_id: Pubkey,
+    pub mint: Pubkey,
+    pub side: TradeSide,
+    /// Lamports spent on a buy, tokens spent on a sell
+    pub amount_in: u64,
+    /// Tokens received on a buy, lamports received on a sell
+    pub amount_out: u64,
+    /// The token or pool creator, None when the venue does not record it
+    pub creator: Option<Pubkey>,
+    /// SOL held by the pool. Venues reading only the input side of the pool
+    /// report it on buys alone, the only side the token filter checks.
+    pub quote_liquidity: u64,
+    /// Unix timestamp the pool opened at, None when the venue does not record it
+    pub pool_open_time: Option<u64>,
+    /// Relative difference between the spot price and the execution price,
+    /// fees excluded
+    pub price_impact: f64,
+    pub details: QuoteDetails,
+}
+
+impl SwapQuote {
+    /// Returns (input mint, output mint) of the swap
+    pub fn mints(&self) -> (Pubkey, Pubkey) {
+        self.side.mints(&self.mint)
+    }
+}
+
+/// The instructions of a swap, with the lookup tables of a V0 transaction
+pub type SwapTransaction = (Vec<Instruction>, Option<Vec<AddressLookupTableAccount>>);
+
+/// One API over the venues: find where a mint trades, quote it, build the
+/// swap and decode the trades made there.
+///
+/// Venues only trade tokens against SOL. Implementors keep their own
+/// inherent `quote` / `build_swap`, working on their own quote type, so
+/// dispatching code calls the trait ones as `<T as Swap>::quote`.
+pub trait Swap: SendSmartTx {
+    const VENUE: Venue;
+    /// Compute units a swap needs, WSOL and ATA instructions included
+    const COMPUTE_UNIT_LIMIT: u32;
+
+    /// Finds the pool `mint` trades against SOL on, the deepest if there are several
+    ///
+    /// * `ctx` - The venue context
+    /// * `mint` - The token to trade
+    async fn find_pool(
+        ctx: &VenueContext,
+        mint: &Pubkey,
+    ) -> Result<Pubkey, Box<dyn Error + Send + Sync>>;
+
+    /// Quotes an exact input swap on a known pool
+    ///
+    /// * `ctx` - The venue context
+    /// * `pool_id` - The pool to swap on
+    /// * `mint` - The token traded against SOL
+    /// * `side` - The swap direction
+    /// * `amount` - Lamports spent on a buy, tokens spent on a sell
+    async fn quote_pool(
+        ctx: &VenueContext,
+        pool_id: &Pubkey,
+        mint: &Pubkey,
+        side: TradeSide,
+        amount: u64,
+    ) -> Result<SwapQuote, Box<dyn Error + Send + Sync>>;
+
+    /// Builds the whole instruction list of a quote for a wallet: WSOL
+    /// lifecycle, output ATA creation and the swap itself
+    ///
+    /// * `ctx` - The venue context
+    /// * `quote` - A quote of this venue
+    /// * `wallet` - The wallet swapping
+    /// * `slippage_percent` - The slippage tolerated on the quote
+    async fn build_swap(
+        ctx: &VenueContext,
+        quote: &SwapQuote,
+        wallet: &Pubkey,
+        slippage_percent: u64,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>>;
+
+    /// Builds the swap of a quote with the lookup tables its transaction is
+    /// compiled against, None for a legacy transaction
+    ///
+    /// * `ctx` - The venue context
+    /// * `quote` - A quote of this venue
+    /// * `wallet` - The wallet swapping
+    /// * `slippage_percent` - The slippage tolerated on the quote
+    async fn build_swap_transaction(
+        ctx: &VenueContext,
+        quote: &SwapQuote,
+        wallet: &Pubkey,
+        slippage_percent: u64,
+    ) -> Result<SwapTransaction, Box<dyn Error + Send + Sync>> {
+        let instructions = Self::build_swap(ctx, quote, wallet, slippage_percent).await?;
+        Ok((instructions, None))
+    }
+
+    /// Decodes a swap of this venue out of a transaction notification,
+    /// None if the transaction did not trade on it
+    ///
+    /// * `ctx` - The venue context
+    /// * `payload` - The transaction notification JSON
+    async fn parse_trade(ctx: &VenueContext, payload: &str) -> Option<ParsedTrade>;
+
+    /// Quotes an exact input swap on the pool found for `mint`
+    ///
+    /// * `ctx` - The venue context
+    /// * `mint` - The token traded against SOL
+    /// * `side` - The swap direction
+    /// * `amount` - Lamports spent on a buy, tokens spent on a sell
+    async fn quote(
+        ctx: &VenueContext,
+        mint: &Pubkey,
+        side: TradeSide,
+        amount: u64,
+    ) -> Result<SwapQuote, Box<dyn Error + Send + Sync>> {
+        let pool_id = Self::find_pool(ctx, mint).await?;
+        Self::quote_pool(ctx, &pool_id, mint, side, amount).await
+    }
+
+    /// Builds and sends the swap of a quote
+    ///
+    /// * `ctx` - The venue context
+    /// * `signer_prv_key` - The wallet private key
+    /// * `quote` - A quote of this venue
+    /// * `slippage_percent` - The slippage tolerated on the quote
+    /// * `copied_trade` - The leader trade being copied, if any
+    /// * `trace` - The latency trace of the order, if any
+    async fn swap(
+        ctx: &VenueContext,
+        signer_prv_key: Arc<String>,
+        quote: &SwapQuote,
+        slippage_percent: u64,
+        copied_trade: Option<&ParsedTrade>,
+        trace: Option<&OrderTrace>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let signer = Keypair::from_base58_string(signer_prv_key.as_str());
+        let wallet = signer.try_pubkey()?;
+
+        let (instructions, lookup_tables) =
+            Self::build_swap_transaction(ctx, quote, &wallet, slippage_percent).await?;
+        if let Some(trace) = trace {
+            trace.stamp(LatencyStage::Built);
+        }
+
+        Self::bfg9000_send_smart_tx(
+            Arc::clone(&ctx.helius),
+            instructions,
+            Some(
+                copied_trade
+                    .map_or(0, |trade| trade.compute_unit_limit)
+                    .max(Self::COMPUTE_UNIT_LIMIT),
+            ),
+            lookup_tables,
+            signer_prv_key.as_str().to_string(),
+            copied_trade.and_then(|trade| trade.signature.clone()),
+            trace,
+        )
+        .await
+    }
+}
+
+/// Calls a `Swap` method on the implementor of a venue
+macro_rules! dispatch {
+    ($venue:expr, $method:ident($($arg:expr),*)) => {
+        match $venue {
+            Venue::PumpFun => <PumpFunTxSend as Swap>::$method($($arg),*).await,
+            Venue::PumpSwap => <PumpSwapTxSend as Swap>::$method($($arg),*).await,
+            Venue::RaydiumV4 => <RaydiumMemeTxSend as Swap>::$method($($arg),*).await,
+            Venue::RaydiumCpmm => <RaydiumCpmmTxSend as Swap>::$method($($arg),*).await,
+            Venue::RaydiumClmm => <RaydiumClmmTxSend as Swap>::$method($($arg),*).await,
+            Venue::MeteoraDlmm => <MeteoraDlmmTxSend as Swap>::$method($($arg),*).await,
+            Venue::OrcaWhirlpool => <OrcaWhirlpoolTxSend as Swap>::$method($($arg),*).await,
+            Venue::Jupiter => <JupiterTxSend as Swap>::$method($($arg),*).await,
+        }
+    };
+}
+
+impl Venue {
+    pub const ALL: [Venue; 8] = [
+        Venue::PumpFun,
+        Venue::PumpSwap,
+        Venue::RaydiumV4,
+        Venue::RaydiumCpmm,
+        Venue::RaydiumClmm,
+        Venue::MeteoraDlmm,
+        Venue::OrcaWhirlpool,
+        Venue::Jupiter,
+    ];
+
+    /// Parses a venue from the config, e.g. "raydium_cpmm"
+    ///
+    /// * `venue` - The venue name
+    pub fn from_config(venue: &str) -> Option<Self> {
+        // "raydium_cpmm" and "RaydiumCpmm" alike
+        match venue.trim().to_lowercase().replace('_', "").as_str() {
+            "pumpfun" => Some(Self::PumpFun),
+            "pumpswap" => Some(Self::PumpSwap),
+            "raydiumv4" | "raydium" => Some(Self::RaydiumV4),
+            "raydiumcpmm" => Some(Self::RaydiumCpmm),
+            "raydiumclmm" => Some(Self::RaydiumClmm),
+            "meteoradlmm" | "meteora" => Some(Self::MeteoraDlmm),
+            "orcawhirlpool" | "orca" => Some(Self::OrcaWhirlpool),
+            "jupiter" => Some(Self::Jupiter),
+            _ => None,
+        }
+    }
+
+    /// Returns the program trades on the venue invoke, None for Jupiter
+    /// whose routes trade on the other venues
+    pub fn program(self) -> Option<Pubkey> {
+        match self {
+            Self::PumpFun => Some(PUMP_FUN_PROGRAM),
+            Self::PumpSwap => Some(PUMP_SWAP_PROGRAM),
+            Self::RaydiumV4 => Some(RAYDIUM_LIQUIDITY_POOL_V4_PROGRAM),
+            Self::RaydiumCpmm => Some(RAYDIUM_CPMM_PROGRAM),
+            Self::RaydiumClmm => Some(RAYDIUM_CLMM_PROGRAM),
+            Self::MeteoraDlmm => Some(METEORA_DLMM_PROGRAM),
+            Self::OrcaWhirlpool => Some(ORCA_WHIRLPOOL_PROGRAM),
+            Self::Jupiter => None,
+        }
+    }
+
+    /// See `Swap::COMPUTE_UNIT_LIMIT`
+    pub fn compute_unit_limit(self) -> u32 {
+        match self {
+            Self::PumpFun => <PumpFunTxSend as Swap>::COMPUTE_UNIT_LIMIT,
+            Self::PumpSwap => <PumpSwapTxSend as Swap>::COMPUTE_UNIT_LIMIT,
+            Self::RaydiumV4 => <RaydiumMemeTxSend as Swap>::COMPUTE_UNIT_LIMIT,
+            Self::RaydiumCpmm => <RaydiumCpmmTxSend as Swap>::COMPUTE_UNIT_LIMIT,
+            Self::RaydiumClmm => <RaydiumClmmTxSend as Swap>::COMPUTE_UNIT_LIMIT,
+            Self::MeteoraDlmm => <MeteoraDlmmTxSend as Swap>::COMPUTE_UNIT_LIMIT,
+            Self::OrcaWhirlpool => <OrcaWhirlpoolTxSend as Swap>::COMPUTE_UNIT_LIMIT,
+            Self::Jupiter => <JupiterTxSend as Swap>::COMPUTE_UNIT_LIMIT,
+        }
+    }
+
+    /// Returns the venues whose program a transaction notification
+    /// invokes. Programs are always static account keys, even in V0
+    /// transactions, so the account keys are enough.
+    ///
+    /// * `json` - The transaction notification JSON
+    pub fn venues_of(json: &Value) -> Vec<Self> {
+        let Some(account_keys) = json["params"]["result"]["transaction"]["transaction"]["message"]
+            ["accountKeys"]
+            .as_array()
+        else {
+            return Vec::new();
+        };
+        let programs: Vec<&str> = account_keys
+            .iter()
+            .filter_map(|account_key| account_key["pubkey"].as_str().or(account_key.as_str()))
+            .collect();
+
+        Self::ALL
+            .into_iter()
+            .filter(|venue| {
+                venue
+                    .program()
+                    .is_some_and(|program| programs.contains(&program.to_string().as_str()))
+            })
+            .collect()
+    }
+
+    /// See `Swap::find_pool`
+    pub async fn find_pool(
+        self,
+        ctx: &VenueContext,
+        mint: &Pubkey,
+    ) -> Result<Pubkey, Box<dyn Error + Send + Sync>> {
+        dispatch!(self, find_pool(ctx, mint))
+    }
+
+    /// See `Swap::quote`
+    pub async fn quote(
+        self,
+        ctx: &VenueContext,
+        mint: &Pubkey,
+        side: TradeSide,
+        amount: u64,
+    ) -> Result<SwapQuote, Box<dyn Error + Send + Sync>> {
+        dispatch!(self, quote(ctx, mint, side, amount))
+    }
+
+    /// See `Swap::quote_pool`
+    pub async fn quote_pool(
+        self,
+        ctx: &VenueContext,
+        pool_id: &Pubkey,
+        mint: &Pubkey,
+        side: TradeSide,
+        amount: u64,
+    ) -> Result<SwapQuote, Box<dyn Error + Send + Sync>> {
+        dispatch!(self, quote_pool(ctx, pool_id, mint, side, amount))
+    }
+
+    /// See `Swap::build_swap`, on the venue of the quote
+    pub async fn build_swap(
+        ctx: &VenueContext,
+        quote: &SwapQuote,
+        wallet: &Pubkey,
+        slippage_percent: u64,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        dispatch!(
+            quote.venue,
+            build_swap(ctx, quote, wallet, slippage_percent)
+        )
+    }
+
+    /// See `Swap::swap`, on the venue of the quote
+    pub async fn swap(
+        ctx: &VenueContext,
+        signer_prv_key: Arc<String>,
+        quote: &SwapQuote,
+        slippage_percent: u64,
+        copied_trade: Option<&ParsedTrade>,
+        trace: Option<&OrderTrace>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        dispatch!(
+            quote.venue,
+            swap(
+                ctx,
+                signer_prv_key,
+                quote,
+                slippage_percent,
+                copied_trade,
+                trace
+            )
+        )
+    }
+
+    /// Decodes the swap of a transaction notification, only with the
+    /// decoders of the venues whose program it invokes. None if it did not
+    /// trade on one.
+    ///
+    /// * `ctx` - The venue context
+    /// * `payload` - The transaction notification JSON
+    pub async fn parse_trade(ctx: &VenueContext, payload: &str) -> Option<ParsedTrade> {
+        let json = serde_json::from_str::<Value>(payload).ok()?;
+
+        for venue in Self::venues_of(&json) {
+            if let Some(trade) = dispatch!(venue, parse_trade(ctx, payload)) {
+                return Some(trade);
+            }
+        }
+        None
+    }
+
+    /// Checks the token of a buy quote against the token filter. Only buys
+    /// are filtered, a held token can always be sold
+    ///
+    /// * `ctx` - The venue context
+    /// * `quote` - The buy quote
+    pub async fn check_token(
+        ctx: &VenueContext,
+        quote: &SwapQuote,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let launchpad = {
+            let connection = ctx.helius.lock().await.async_connection()?;
+            ctx.token_filter
+                .launchpad_of(&connection, &quote.mint)
+                .await?
+        };
+
+        ctx.token_filter.check_now(&TokenCandidate {
+            mint: quote.mint,
+            creator: quote.creator,
+            launchpad,
+            quote_liquidity: quote.quote_liquidity,
+            pool_open_time: quote.pool_open_time,
+        })?;
+
+        Ok(())
+    }
+
+    /// Copies a leader trade on the pool it was made on, buys going
+    /// through the token filter first. Buys above the max price impact are
+    /// refused or shrunk.
+    ///
+    /// * `ctx` - The venue context
+    /// * `signer_prv_key` - The wallet private key
+    /// * `trade` - The leader trade
+    /// * `amount_in` - Lamports spent on a buy, tokens spent on a sell
+    /// * `slippage_percent` - The slippage tolerated on the quote
+    /// * `trace` - The latency trace of the order, if any
+    pub async fn copy_trade(
+        ctx: &VenueContext,
+        signer_prv_key: Arc<String>,
+        trade: &ParsedTrade,
+        amount_in: u64,
+        slippage_percent: u64,
+        trace: Option<&OrderTrace>,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        if amount_in == 0 {
+            return Err("Nothing to swap".into());
+        }
+
+        let quote = trade
+            .venue
+            .quote_pool(ctx, &trade.pool_id, &trade.mint, trade.side, amount_in)
+            .await?;
+        println!(
+            "{} quote | {} | Mint: {} | In: {} | Out: {} | Price impact: {:.2}%",
+            quote.venue,
+            quote.side,
+            quote.mint,
+            quote.amount_in,
+            quote.amount_out,
+            quote.price_impact * 100.0
+        );
+        let quote = ctx.price_impact.guard(ctx, quote).await?;
+
+        if trade.side == TradeSide::Buy {
+            Self::check_token(ctx, &quote).await?;
+        }
+
+        Self::swap(
+            ctx,
+            signer_prv_key,
+            &quote,
+            slippage_percent,
+            Some(trade),
+            trace,
+        )
+        .await
+    }
+}
+
+/// Returns the raw token balance of a wallet, 0 if its ATA does not exist
+///
+/// * `ctx` - The venue context
+/// * `owner` - The wallet
+/// * `mint` - The token mint
+pub async fn token_balance(
+    ctx: &VenueContext,
+    owner: &Pubkey,
+    mint: &Pubkey,
+) -> Result<u64, Box<dyn Error + Send + Sync>> {
+    let connection = ctx.helius.lock().await.async_connection()?;
+
+    let token_program = connection.get_account(mint).await?.owner;
+    let user_ata = get_associated_token_address_with_program_id(owner, mint, &token_program);
+
+    match connection.get_token_account_balance(&user_ata).await {
+        Ok(balance) => Ok(balance.amount.parse::<u64>()?),
+        Err(_) => Ok(0),
+    }
+}
+
+/// Finds the pools of `program` pairing `mint` with WSOL, the mint being on
+/// either side of the pool.
+///
+/// * `connection` - The RPC client
+/// * `program` - The program owning the pools
+/// * `mint_offsets` - Offsets of the two mint fields in the pool state
+/// * `mint` - The token to find pools for
+pub(crate) async fn find_sol_paired_pools<T: AccountDecoder>(
+    connection: &RpcClient,
+    program: &Pubkey,
+    mint_offsets: [usize; 2],
+    mint: &Pubkey,
+) -> Result<Vec<(Pubkey, T)>, Box<dyn Error + Send + Sync>> {
+    let mut pools = vec![];
+    for (mint_offset, wsol_offset) in [
+        (mint_offsets[0], mint_offsets[1]),
+        (mint_offsets[1], mint_offsets[0]),
+    ] {
+        let mut filters = vec![
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                T::HEAD.len() + mint_offset,
+                mint.as_ref(),
+            )),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                T::HEAD.len() + wsol_offset,
+                WSOL_MINT.as_ref(),
+            )),
+        ];
+        if !T::EXTENDABLE {
+            filters.push(RpcFilterType::DataSize(
+                (T::HEAD.len() + std::mem::size_of::<T>() + T::TAIL.len()) as u64,
+            ));
+        }
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            ..Default::default()
+        };
+
+        pools.extend(
+            connection
+                .get_program_accounts_with_config(program, config)
+                .await?
+                .into_iter()
+                .filter_map(|(pool_id, account)| {
+                    Some((pool_id, *T::decode_account(&account).ok()?))
+                }),
+        );
+    }
+
+    Ok(pools)
+}
+
+/// Picks the pool holding the most WSOL
+///
+/// * `connection` - The RPC client
+/// * `pools` - The pools with their WSOL vault
+pub(crate) async fn deepest_sol_pool(
+    connection: &RpcClient,
+    pools: Vec<(Pubkey, Pubkey)>,
+) -> Result<Pubkey, Box<dyn Error + Send + Sync>> {
+    if pools.is_empty() {
+        return Err("No SOL paired pool found for mint".into());
+    }
+
+    let vaults: Vec<Pubkey> = pools.iter().map(|(_, vault)| *vault).collect();
+    let accounts = connection.get_multiple_accounts(&vaults).await?;
+
+    pools
+        .into_iter()
+        .zip(accounts)
+        .filter_map(|((pool_id, _), account)| {
+            let vault = SplTokenAccountState::decode_account(account.as_ref()?).ok()?;
+            Some((pool_id, vault.amount))
+        })
+        .max_by_key(|(_, amount)| *amount)
+        .map(|(pool_id, _)| pool_id)
+        .ok_or_else(|| "No SOL paired pool found for mint".into())
+}
+
+/// A buy quote on fresh accounts, its details matching the venue, for the
+/// tests of the quote consumers
+#[cfg(test)]
+pub(crate) fn test_quote(venue: Venue, amount_in: u64, amount_out: u64) -> SwapQuote {
+    use super::{ClmmSwapQuote, DlmmSwapQuote, WhirlpoolSwapQuote};
+    use bytemuck::Zeroable;
+
+    let (pool_id, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let details = match venue {
+        Venue::PumpFun => QuoteDetails::PumpFun(PumpFunQuote {
+            associated_bonding_curve: Pubkey::new_unique(),
+            curve: Default::default(),
+        }),
+        Venue::PumpSwap => QuoteDetails::PumpSwap(PumpSwapQuote {
+            pool_id,
+            pool: Default::default(),
+            coin_creator: None,
+            base_token_program: spl_token::ID,
+            protocol_fee_recipient: Pubkey::new_unique(),
+            is_buy: true,
+            base_reserve: 0,
+            quote_reserve: 0,
+            amount_in,
+            amount_out,
+        }),
+        Venue::RaydiumV4 => QuoteDetails::RaydiumV4(RaydiumPool {
+            amm_id: pool_id,
+            amm_info: Default::default(),
+            market_state: Default::default(),
+            serum_vault_signer: Pubkey::new_unique(),
+            coin_reserve: 0,
+            pc_reserve: 0,
+        }),
+        Venue::RaydiumCpmm => QuoteDetails::RaydiumCpmm(CpmmQuote {
+            pool_id,
+            pool_state: Default::default(),
+            input_mint: WSOL_MINT,
+            output_mint: mint,
+            reserve_in: 0,
+            reserve_out: 0,
+            amount_in,
+            amount_out,
+        }),
+        Venue::RaydiumClmm => QuoteDetails::RaydiumClmm(ClmmQuote {
+            pool_id,
+            pool_state: Zeroable::zeroed(),
+            zero_for_one: true,
+            input_mint: WSOL_MINT,
+            output_mint: mint,
+            input_token_program: spl_token::ID,
+            output_token_program: spl_token::ID,
+            input_vault_amount: 0,
+            swap: ClmmSwapQuote {
+                amount_in,
+                amount_out,
+                price_impact: 0.0,
+                tick_array_start_indexes: vec![],
+            },
+        }),
+        Venue::MeteoraDlmm => QuoteDetails::MeteoraDlmm(DlmmQuote {
+            pool_id,
+            lb_pair: Zeroable::zeroed(),
+            swap_for_y: true,
+            input_mint: WSOL_MINT,
+            output_mint: mint,
+            input_token_program: spl_token::ID,
+            output_token_program: spl_token::ID,
+            input_reserve_amount: 0,
+            swap: DlmmSwapQuote {
+                amount_in,
+                amount_out,
+                price_impact: 0.0,
+                bin_array_indexes: vec![],
+            },
+        }),
+        Venue::OrcaWhirlpool => QuoteDetails::OrcaWhirlpool(WhirlpoolQuote {
+            pool_id,
+            whirlpool: Default::default(),
+            a_to_b: true,
+            input_mint: WSOL_MINT,
+            output_mint: mint,
+            input_token_program: spl_token::ID,
+            output_token_program: spl_token::ID,
+            input_vault_amount: 0,
+            tick_array_start_indexes: vec![],
+            swap: WhirlpoolSwapQuote {
+                amount_in,
+                amount_out,
+                price_impact: 0.0,
+            },
+        }),
+        Venue::Jupiter => QuoteDetails::Jupiter(JupiterQuote {
+            input_mint: WSOL_MINT,
+            output_mint: mint,
+            in_amount: amount_in,
+            out_amount: amount_out,
+            other_amount_threshold: amount_out,
+            slippage_bps: 0,
+            price_impact_pct: 0.0,
+            route_labels: vec![],
+            response: Value::Null,
+        }),
+    };
+
+    SwapQuote {
+        venue,
+        pool_id,
+        mint,
+        side: TradeSide::Buy,
+        amount_in,
+        amount_out,
+        creator: None,
+        quote_liquidity: 0,
+        pool_open_time: None,
+        price_impact: 0.0,
+        details,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_venues_of() {
+        let notification = |account_keys: Value| {
+            json!({ "params": { "result": { "transaction": {
+                "transaction": { "message": { "accountKeys": account_keys } }
+            }}}})
+        };
+
+        // jsonParsed account keys
+        let pump_fun = notification(json!([
+            { "pubkey": Pubkey::new_unique().to_string(), "signer": true },
+            { "pubkey": PUMP_FUN_PROGRAM.to_string(), "signer": false },
+        ]));
+        assert_eq!(Venue::venues_of(&pump_fun), vec![Venue::PumpFun]);
+
+        // Plain account keys, a route through two venues
+        let route = notification(json!([
+            Pubkey::new_unique().to_string(),
+            RAYDIUM_CPMM_PROGRAM.to_string(),
+            ORCA_WHIRLPOOL_PROGRAM.to_string(),
+        ]));
+        assert_eq!(
+            Venue::venues_of(&route),
+            vec![Venue::RaydiumCpmm, Venue::OrcaWhirlpool]
+        );
+
+        assert!(Venue::venues_of(&notification(json!([]))).is_empty());
+        assert!(Venue::venues_of(&json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_from_meme_trade() {
+        let mint = Pubkey::new_unique();
+        let pool_id = Pubkey::new_unique();
+        let inner_ix_data = InnerIxData {
+            source_amount: 100,
+            dest_amount: 5_000,
+        };
+
+        let buy = ParsedTrade::from_meme_trade(
+            Venue::RaydiumCpmm,
+            None,
+            &pool_id.to_string(),
+            &RaydiumMemeTradeData {
+                meme_mint: mint,
+                operation: BOT_RAYDIUM_OPERATION_BUY,
+            },
+            &inner_ix_data,
+            0,
+        )
+        .unwrap();
+        assert_eq!(buy.side, TradeSide::Buy);
+        assert_eq!(buy.pool_id, pool_id);
+        assert_eq!((buy.sol_amount, buy.token_amount), (100, 5_000));
+
+        let sell = ParsedTrade::from_meme_trade(
+            Venue::RaydiumCpmm,
+            None,
+            &pool_id.to_string(),
+            &RaydiumMemeTradeData {
+                meme_mint: mint,
+                operation: crate::constants::BOT_RAYDIUM_OPERATION_SELL,
+            },
+            &inner_ix_data,
+            0,
+        )
+        .unwrap();
+        assert_eq!(sell.side, TradeSide::Sell);
+        assert_eq!((sell.sol_amount, sell.token_amount), (5_000, 100));
+
+        assert!(ParsedTrade::from_meme_trade(
+            Venue::RaydiumCpmm,
+            None,
+            "not a pubkey",
+            &RaydiumMemeTradeData {
+                meme_mint: mint,
+                operation: BOT_RAYDIUM_OPERATION_BUY,
+            },
+            &inner_ix_data,
+            0,
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_quote_mints() {
+        let mint = Pubkey::new_unique();
+        let mut quote = SwapQuote {
+            mint,
+            ..test_quote(Venue::PumpSwap, 0, 0)
+        };
+        assert_eq!(quote.mints(), (WSOL_MINT, mint));
+
+        quote.side = TradeSide::Sell;
+        assert_eq!(quote.mints(), (mint, WSOL_MINT));
+    }
+
+    #[test]
+    fn test_venue_from_config() {
+        for venue in Venue::ALL {
+            let name = format!("{venue:?}");
+            assert_eq!(Venue::from_config(&name), Some(venue));
+        }
+        assert_eq!(
+            Venue::from_config(" Raydium_CPMM "),
+            Some(Venue::RaydiumCpmm)
+        );
+        assert_eq!(Venue::from_config("orca"), Some(Venue::OrcaWhirlpool));
+        assert_eq!(Venue::from_config("jupiter"), Some(Venue::Jupiter));
+        assert_eq!(Venue::from_config("phoenix"), None);
+    }
+}
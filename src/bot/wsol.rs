@@ -0,0 +1,241 @@
+use std::{env, error::Error, sync::Arc};
+
+use helius::Helius;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, system_instruction};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account_idempotent,
+};
+use tokio::sync::Mutex;
+
+use crate::constants::{SOL_DECIMALS, WSOL_MINT};
+
+use super::{AccountDecoder, SplTokenAccountState};
+
+/// How the WSOL side of an AMM trade is funded.
+///
+/// The Raydium pools trade against WSOL, not native SOL, so every buy needs a funded
+/// WSOL token account and every sell leaves its proceeds in one.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum WsolMode {
+    /// Keep a WSOL ATA alive between trades, topped up to `float_lamports`
+    /// whenever a buy would not fit in it. Sell proceeds stay wrapped.
+    Persistent { float_lamports: u64 },
+    /// Wrap exactly what the buy needs and close the WSOL ATA once the swap
+    /// is done, so both the leftovers and the rent go back to the wallet as SOL.
+    #[default]
+    PerTrade,
+}
+
+impl WsolMode {
+    /// Builds the mode from the `wsol_mode` / `wsol_float_sol` settings.
+    ///
+    /// * `mode` - "persistent" or "per_trade"
+    /// * `float_lamports` - The WSOL float to keep around in persistent mode
+    pub fn from_config(mode: &str, float_lamports: u64) -> Option<Self> {
+        match mode.to_ascii_lowercase().as_str() {
+            "persistent" => Some(WsolMode::Persistent { float_lamports }),
+            "per_trade" | "per-trade" => Some(WsolMode::PerTrade),
+            _ => None,
+        }
+    }
+
+    /// Loads the mode from the environment, per trade when unset:
+    ///
+    /// * `wsol_mode` - "per_trade" (default) or "persistent"
+    /// * `wsol_float_sol` - The WSOL float kept in persistent mode, 0 by default
+    pub fn from_env() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let float_lamports = match env::var("wsol_float_sol") {
+            Ok(float) => (float.parse::<f64>()? * SOL_DECIMALS as f64) as u64,
+            Err(_) => 0,
+        };
+
+        match env::var("wsol_mode") {
+            Ok(mode) => Self::from_config(&mode, float_lamports)
+                .ok_or_else(|| format!("Unknown WSOL mode: {mode}").into()),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+}
+
+pub struct Wsol {}
+
+impl Wsol {
+    /// Returns the WSOL associated token account of the owner
+    pub fn ata(owner: &Pubkey) -> Pubkey {
+        get_associated_token_address(owner, &WSOL_MINT)
+    }
+
+    /// Creates (if needed) the WSOL ATA, moves `lamports` into it and syncs
+    /// the token amount with the lamports balance.
+    ///
+    /// * `owner` - The wallet paying for and owning the WSOL account
+    /// * `lamports` - The lamports to wrap
+    pub fn wrap_instructions(
+        owner: &Pubkey,
+        lamports: u64,
+    ) -> Result<Vec<Instruction>, Box<dyn Error + Send + Sync>> {
+        let wsol_ata = Self::ata(owner);
+
+        let mut instructions = vec![create_associated_token_account_idempotent(
+            owner,
+            owner,
+            &WSOL_MINT,
+            &spl_token::id(),
+        )];
+
+        if lamports > 0 {
+            instructions.push(system_instruction::transfer(owner, &wsol_ata, lamports));
+            instructions.push(spl_token::instruction::sync_native(
+                &spl_token::id(),
+                &wsol_ata,
+            )?);
+        }
+
+        Ok(instructions)
+    }
+
+    /// Closes the WSOL ATA, unwrapping its whole balance (and rent) back to SOL
+    ///
+    /// * `owner` - The wallet owning the WSOL account
+    pub fn unwrap_instruction(owner: &Pubkey) -> Result<Instruction, Box<dyn Error + Send + Sync>> {
+        Ok(spl_token::instruction::close_account(
+            &spl_token::id(),
+            &Self::ata(owner),
+            owner,
+            owner,
+            &[],
+        )?)
+    }
+
+    /// Returns the WSOL balance of the owner, 0 if the ATA does not exist yet
+    pub async fn balance(
+        helius: Arc<Mutex<Helius>>,
+        owner: &Pubkey,
+    ) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        let connection = helius.lock().await.async_connection()?;
+
+        let account = connection
+            .get_multiple_accounts(&[Self::ata(owner)])
+            .await?
+            .pop()
+            .flatten();
+
+        match account {
+            Some(account) => Ok(SplTokenAccountState::decode_account(&account)?.amount),
+            None => Ok(0),
+        }
+    }
+
+    /// Returns the instructions to run before and after a swap spending
+    /// `amount_in` lamports of WSOL.
+    ///
+    /// * `helius` - The Helius client, used for the persistent float balance
+    /// * `owner` - The wallet
+    /// * `mode` - The WSOL funding mode
+    /// * `amount_in` - The WSOL amount the swap will spend
+    pub async fn buy_instructions(
+        helius: Arc<Mutex<Helius>>,
+        owner: &Pubkey,
+        mode: WsolMode,
+        amount_in: u64,
+    ) -> Result<(Vec<Instruction>, Vec<Instruction>), Box<dyn Error + Send + Sync>> {
+        match mode {
+            WsolMode::PerTrade => Ok((
+                Self::wrap_instructions(owner, amount_in)?,
+                vec![Self::unwrap_instruction(owner)?],
+            )),
+            WsolMode::Persistent { float_lamports } => {
+                let balance = Self::balance(helius, owner).await?;
+                let top_up = Self::persistent_top_up(balance, float_lamports, amount_in);
+
+                Ok((Self::wrap_instructions(owner, top_up)?, vec![]))
+            }
+        }
+    }
+
+    /// Returns the instructions to run before and after a swap that
+    /// receives WSOL.
+    ///
+    /// * `owner` - The wallet
+    /// * `mode` - The WSOL funding mode
+    pub fn sell_instructions(
+        owner: &Pubkey,
+        mode: WsolMode,
+    ) -> Result<(Vec<Instruction>, Vec<Instruction>), Box<dyn Error + Send + Sync>> {
+        let pre = Self::wrap_instructions(owner, 0)?;
+        let post = match mode {
+            WsolMode::PerTrade => vec![Self::unwrap_instruction(owner)?],
+            WsolMode::Persistent { .. } => vec![],
+        };
+
+        Ok((pre, post))
+    }
+
+    /// Computes how many lamports must be wrapped so that the persistent float
+    /// covers `amount_in`. The float is refilled to `float_lamports` (or to
+    /// `amount_in` if the trade is larger than the float).
+    fn persistent_top_up(balance: u64, float_lamports: u64, amount_in: u64) -> u64 {
+        if balance >= amount_in {
+            return 0;
+        }
+
+        float_lamports.max(amount_in).saturating_sub(balance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config() {
+        assert_eq!(
+            WsolMode::from_config("persistent", 5),
+            Some(WsolMode::Persistent { float_lamports: 5 })
+        );
+        assert_eq!(
+            WsolMode::from_config("PER_TRADE", 5),
+            Some(WsolMode::PerTrade)
+        );
+        assert_eq!(WsolMode::from_config("unknown", 5), None);
+    }
+
+    #[test]
+    fn test_persistent_top_up() {
+        // Enough float, nothing to wrap
+        assert_eq!(Wsol::persistent_top_up(100, 1_000, 50), 0);
+        // Refill the float
+        assert_eq!(Wsol::persistent_top_up(100, 1_000, 200), 900);
+        // Trade larger than the float
+        assert_eq!(Wsol::persistent_top_up(100, 1_000, 5_000), 4_900);
+    }
+
+    #[test]
+    fn test_wrap_instructions() {
+        let owner = Pubkey::new_unique();
+        let instructions = Wsol::wrap_instructions(&owner, 1_000).unwrap();
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(
+            instructions[0].program_id,
+            spl_associated_token_account::id()
+        );
+        assert_eq!(instructions[2].program_id, spl_token::id());
+
+        // Nothing to transfer, only the idempotent ATA creation
+        let instructions = Wsol::wrap_instructions(&owner, 0).unwrap();
+        assert_eq!(instructions.len(), 1);
+    }
+
+    #[test]
+    fn test_per_trade_sell_closes_account() {
+        let owner = Pubkey::new_unique();
+        let (pre, post) = Wsol::sell_instructions(&owner, WsolMode::PerTrade).unwrap();
+        assert_eq!(pre.len(), 1);
+        assert_eq!(post.len(), 1);
+        assert_eq!(post[0].accounts[0].pubkey, Wsol::ata(&owner));
+
+        let (_, post) =
+            Wsol::sell_instructions(&owner, WsolMode::Persistent { float_lamports: 1 }).unwrap();
+        assert!(post.is_empty());
+    }
+}
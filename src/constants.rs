@@ -13,6 +13,35 @@ pub const PUMP_EVENT_AUTHORITY: Pubkey =
 pub const PUMP_FUN_ACTION_BUY: &[u8] = &[102, 6, 61, 18, 1, 218, 235, 234];
 pub const PUMP_FUN_ACTION_SELL: &[u8] = &[51, 230, 133, 164, 1, 127, 131, 173];
 pub const PUMP_FUN_FEES: f64 = 0.01; // 1%
+/// Tokens a new curve sells before it completes
+pub const PUMP_FUN_INITIAL_REAL_TOKEN_RESERVES: u64 = 793_100_000_000_000;
+pub const PUMP_FUN_INITIAL_VIRTUAL_TOKEN_RESERVES: u64 = 1_073_000_000_000_000;
+pub const PUMP_FUN_INITIAL_VIRTUAL_SOL_RESERVES: u64 = 30_000_000_000;
+pub const PUMP_FUN_TOKEN_TOTAL_SUPPLY: u64 = 1_000_000_000_000_000;
+
+// Pump.fun Anchor accounts
+pub const PUMP_FUN_BONDING_CURVE_DISCRIMINATOR: &[u8] = &[23, 183, 248, 55, 96, 216, 172, 96];
+pub const PUMP_FUN_GLOBAL_DISCRIMINATOR: &[u8] = &[167, 232, 232, 177, 200, 108, 114, 127];
+pub const PUMP_FUN_BONDING_CURVE_SEED: &[u8] = b"bonding-curve";
+
+// PumpSwap (Pump.fun AMM) constants
+pub const PUMP_SWAP_PROGRAM: Pubkey =
+    Pubkey::from_str_const("pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA");
+pub const PUMP_SWAP_GLOBAL_CONFIG: Pubkey =
+    Pubkey::from_str_const("ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt");
+pub const PUMP_SWAP_EVENT_AUTHORITY: Pubkey =
+    Pubkey::from_str_const("GS4CU59F31iL7aR2Q8zVS8DRrcRnXX1yjQ66TqNVQnaR");
+pub const PUMP_SWAP_ACTION_BUY: &[u8] = &[102, 6, 61, 18, 1, 218, 235, 234];
+pub const PUMP_SWAP_ACTION_SELL: &[u8] = &[51, 230, 133, 164, 1, 127, 131, 173];
+pub const PUMP_SWAP_POOL_DISCRIMINATOR: &[u8] = &[241, 154, 109, 4, 17, 177, 109, 188];
+pub const PUMP_SWAP_GLOBAL_CONFIG_DISCRIMINATOR: &[u8] = &[149, 8, 156, 202, 160, 252, 176, 217];
+pub const PUMP_SWAP_POOL_SEED: &[u8] = b"pool";
+pub const PUMP_SWAP_POOL_AUTHORITY_SEED: &[u8] = b"pool-authority";
+pub const PUMP_SWAP_CREATOR_VAULT_SEED: &[u8] = b"creator_vault";
+/// Accounts of a buy/sell before the coin creator accounts were appended
+pub const PUMP_SWAP_ACCOUNTS_LEN_SWAP: usize = 17;
+pub const PUMP_SWAP_FEE_BASIS_POINTS_DENOMINATOR: u64 = 10_000;
+pub const PUMP_SWAP_COMPUTE_UNIT_LIMIT: u32 = 150_000;
 
 // Raydium constants
 
@@ -26,10 +55,100 @@ pub const SERUM_PROGRAM: Pubkey =
 pub const RAYDIUM_SWAP_BASE_IN_INSTRUCTION: u8 = 9;
 pub const RAYDIUM_SWAP_BASE_OUT_INSTRUCTION: u8 = 11;
 pub const RAYDIUM_ACCOUNTS_LEN_SWAP_BASE_IN: usize = 17;
+pub const RAYDIUM_V4_COMPUTE_UNIT_LIMIT: u32 = 150_000;
 pub const BOT_RAYDIUM_OPERATION_BUY: u8 = 1;
 pub const BOT_RAYDIUM_OPERATION_SELL: u8 = 2;
 
+// Raydium CPMM (CP-Swap) constants
+pub const RAYDIUM_CPMM_PROGRAM: Pubkey =
+    Pubkey::from_str_const("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C");
+pub const RAYDIUM_CPMM_AUTHORITY: Pubkey =
+    Pubkey::from_str_const("GpMZbSM2GgvTKHJirzeGfMFoaZ8UR2X7F4v8vHTvxFbL");
+pub const RAYDIUM_CPMM_ACTION_SWAP_BASE_INPUT: &[u8] = &[143, 190, 90, 218, 196, 30, 51, 222];
+pub const RAYDIUM_CPMM_ACTION_SWAP_BASE_OUTPUT: &[u8] = &[55, 217, 98, 86, 163, 74, 180, 173];
+pub const RAYDIUM_CPMM_ACCOUNTS_LEN_SWAP: usize = 13;
+pub const RAYDIUM_CPMM_FEE_RATE_DENOMINATOR: u64 = 1_000_000;
+pub const RAYDIUM_CPMM_COMPUTE_UNIT_LIMIT: u32 = 150_000;
+
+// Raydium CLMM (concentrated liquidity) constants
+pub const RAYDIUM_CLMM_PROGRAM: Pubkey =
+    Pubkey::from_str_const("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
+pub const RAYDIUM_CLMM_ACTION_SWAP: &[u8] = &[248, 198, 158, 145, 225, 117, 135, 200];
+pub const RAYDIUM_CLMM_ACTION_SWAP_V2: &[u8] = &[43, 4, 237, 11, 26, 201, 30, 98];
+pub const RAYDIUM_CLMM_ACCOUNTS_LEN_SWAP: usize = 10;
+pub const RAYDIUM_CLMM_ACCOUNTS_LEN_SWAP_V2: usize = 13;
+pub const RAYDIUM_CLMM_TICK_ARRAY_SIZE: i32 = 60;
+pub const RAYDIUM_CLMM_TICK_ARRAY_SEED: &[u8] = b"tick_array";
+pub const RAYDIUM_CLMM_TICK_ARRAY_DISCRIMINATOR: &[u8] = &[192, 155, 85, 205, 49, 249, 129, 42];
+pub const RAYDIUM_CLMM_MAX_TICK_ARRAYS: usize = 3;
+pub const RAYDIUM_CLMM_COMPUTE_UNIT_LIMIT: u32 = 250_000;
+pub const SPL_MEMO_PROGRAM: Pubkey =
+    Pubkey::from_str_const("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+// Raydium Anchor accounts shared by CPMM and CLMM
+pub const RAYDIUM_POOL_STATE_DISCRIMINATOR: &[u8] = &[247, 237, 227, 245, 215, 195, 222, 70];
+pub const RAYDIUM_AMM_CONFIG_DISCRIMINATOR: &[u8] = &[218, 244, 33, 104, 203, 203, 43, 111];
+
+// Meteora DLMM (liquidity book) constants
+pub const METEORA_DLMM_PROGRAM: Pubkey =
+    Pubkey::from_str_const("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo");
+pub const METEORA_DLMM_EVENT_AUTHORITY: Pubkey =
+    Pubkey::from_str_const("D1ZN9Wj1fRSUQfCjhvnu1hqDMT7hzjzBBpi12nVniYD6");
+pub const METEORA_DLMM_ACTION_SWAP: &[u8] = &[248, 198, 158, 145, 225, 117, 135, 200];
+pub const METEORA_DLMM_ACTION_SWAP_EXACT_OUT: &[u8] = &[250, 73, 101, 33, 38, 207, 75, 184];
+pub const METEORA_DLMM_ACTION_SWAP_WITH_PRICE_IMPACT: &[u8] =
+    &[56, 173, 230, 208, 173, 228, 156, 205];
+pub const METEORA_DLMM_ACTION_SWAP2: &[u8] = &[65, 75, 63, 76, 235, 91, 91, 136];
+pub const METEORA_DLMM_ACTION_SWAP_EXACT_OUT2: &[u8] = &[43, 215, 247, 132, 137, 60, 243, 81];
+pub const METEORA_DLMM_ACTION_SWAP_WITH_PRICE_IMPACT2: &[u8] = &[74, 98, 192, 214, 177, 51, 75, 51];
+pub const METEORA_DLMM_ACCOUNTS_LEN_SWAP: usize = 15;
+pub const METEORA_DLMM_ACCOUNTS_LEN_SWAP2: usize = 16;
+pub const METEORA_DLMM_LB_PAIR_DISCRIMINATOR: &[u8] = &[33, 11, 49, 98, 181, 101, 177, 13];
+pub const METEORA_DLMM_BIN_ARRAY_DISCRIMINATOR: &[u8] = &[92, 142, 92, 220, 5, 148, 70, 181];
+pub const METEORA_DLMM_BIN_ARRAY_SEED: &[u8] = b"bin_array";
+pub const METEORA_DLMM_BINS_PER_ARRAY: i32 = 70;
+pub const METEORA_DLMM_BASIS_POINT_MAX: u64 = 10_000;
+pub const METEORA_DLMM_FEE_PRECISION: u64 = 1_000_000_000;
+pub const METEORA_DLMM_MAX_FEE_RATE: u64 = 100_000_000;
+pub const METEORA_DLMM_MAX_BIN_ARRAYS: usize = 3;
+pub const METEORA_DLMM_COMPUTE_UNIT_LIMIT: u32 = 300_000;
+
+// Orca Whirlpool constants
+pub const ORCA_WHIRLPOOL_PROGRAM: Pubkey =
+    Pubkey::from_str_const("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+pub const ORCA_WHIRLPOOL_ACTION_SWAP: &[u8] = &[248, 198, 158, 145, 225, 117, 135, 200];
+pub const ORCA_WHIRLPOOL_ACTION_SWAP_V2: &[u8] = &[43, 4, 237, 11, 26, 201, 30, 98];
+pub const ORCA_WHIRLPOOL_ACCOUNTS_LEN_SWAP: usize = 11;
+pub const ORCA_WHIRLPOOL_ACCOUNTS_LEN_SWAP_V2: usize = 15;
+pub const ORCA_WHIRLPOOL_DISCRIMINATOR: &[u8] = &[63, 149, 209, 12, 225, 128, 99, 9];
+pub const ORCA_WHIRLPOOL_TICK_ARRAY_DISCRIMINATOR: &[u8] = &[69, 97, 189, 190, 110, 7, 66, 187];
+pub const ORCA_WHIRLPOOL_TICK_ARRAY_SEED: &[u8] = b"tick_array";
+pub const ORCA_WHIRLPOOL_ORACLE_SEED: &[u8] = b"oracle";
+pub const ORCA_WHIRLPOOL_TICK_ARRAY_SIZE: i32 = 88;
+/// A swap always takes three consecutive tick arrays, initialized or not
+pub const ORCA_WHIRLPOOL_SWAP_TICK_ARRAYS: usize = 3;
+pub const ORCA_WHIRLPOOL_MIN_SQRT_PRICE_X64: u128 = 4_295_048_016;
+pub const ORCA_WHIRLPOOL_MAX_SQRT_PRICE_X64: u128 = 79_226_673_515_401_279_992_447_579_055;
+pub const ORCA_WHIRLPOOL_FEE_RATE_DENOMINATOR: u64 = 1_000_000;
+pub const ORCA_WHIRLPOOL_COMPUTE_UNIT_LIMIT: u32 = 250_000;
+
+// Jupiter constants
+pub const JUPITER_API_URL: &str = "https://lite-api.jup.ag/swap/v1";
+/// Routes can hop through several pools
+pub const JUPITER_COMPUTE_UNIT_LIMIT: u32 = 600_000;
+
 // Solana constants
 pub const SOL_DECIMALS: u64 = 10u64.pow(spl_token::native_mint::DECIMALS as u32);
 pub const WSOL_MINT: Pubkey = Pubkey::from_str_const("So11111111111111111111111111111111111111112");
+pub const SPL_TOKEN_2022_PROGRAM: Pubkey =
+    Pubkey::from_str_const("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+pub const TOKEN_METADATA_PROGRAM: Pubkey =
+    Pubkey::from_str_const("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+pub const TOKEN_METADATA_SEED: &[u8] = b"metadata";
 pub const DEFAULT_COMPUTE_UNIT_LIMIT: u32 = 100_000;
+/// Base fee of a transaction, per signature
+pub const SIGNATURE_FEE_LAMPORTS: u64 = 5_000;
+/// Jito tip added to every transaction sent
+pub const JITO_TIP_LAMPORTS: u64 = 10_000;
+/// Rent exempt minimum of a 165 byte token account
+pub const TOKEN_ACCOUNT_RENT_LAMPORTS: u64 = 2_039_280;
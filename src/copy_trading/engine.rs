@@ -0,0 +1,444 @@
+use serde_json::Value;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use std::{error::Error, sync::Arc, time::Instant};
+
+use crate::{
+    bot::{
+        token_balance, LatencyHistograms, LatencyStage, OrderTrace, ParsedTrade, Router, TradeSide,
+        Venue, VenueContext,
+    },
+    db::{CopyOrder, CopyOrderStatus, Database},
+    errors::SignalFilterError,
+    trading::{RiskEngine, TokenSafety},
+};
+
+use super::{
+    mirrored_sell_amount, unix_now, BuyCooldown, CopyTradingConfig, LeaderBalances, LeaderConfig,
+    LeaderHoldings, SignalFilter, SignatureDedup,
+};
+
+/// A leader trade decoded out of a transaction notification
+#[derive(Debug, Clone)]
+pub struct LeaderSignal {
+    /// The signature of the leader transaction
+    pub source_signature: String,
+    pub leader: LeaderConfig,
+    pub trade: ParsedTrade,
+    pub balances: LeaderBalances,
+    /// The fraction of its position the leader sold, 0 on buys
+    pub sold_fraction: f64,
+    /// Timestamps of the copy, from the websocket frame on
+    pub trace: Arc<OrderTrace>,
+}
+
+/// A leader trade that passed the filters, with the amount its copy spends
+#[derive(Debug, Clone)]
+pub struct PendingCopy {
+    pub signal: LeaderSignal,
+    /// Lamports spent on a buy, tokens spent on a sell
+    pub amount_in: u64,
+}
+
+/// Copies the trades of the configured leaders, each with its own settings
+pub struct CopyEngine {
+    ctx: VenueContext,
+    /// Routes copies to the best venue, None to copy on the leader pool
+    router: Option<Arc<Router>>,
+    signer_prv_key: Arc<String>,
+    wallet: Pubkey,
+    config: CopyTradingConfig,
+    holdings: LeaderHoldings,
+    /// Leader buys failing it are not copied
+    signal_filter: SignalFilter,
+    cooldown: BuyCooldown,
+    /// Drops the notifications already handled
+    dedup: SignatureDedup,
+    /// Holds an order per copied signal, across restarts
+    db: Arc<Database>,
+    latency: LatencyHistograms,
+    /// Approves the buys, and tracks their positions until they are sold
+    risk: Arc<RiskEngine>,
+    /// Blocks the buys of the tokens scoring too risky
+    safety: Arc<TokenSafety>,
+}
+
+impl CopyEngine {
+    pub fn new(
+        ctx: VenueContext,
+        router: Option<Arc<Router>>,
+        signer_prv_key: Arc<String>,
+        config: CopyTradingConfig,
+        signal_filter: SignalFilter,
+        db: Arc<Database>,
+        risk: Arc<RiskEngine>,
+        safety: Arc<TokenSafety>,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let wallet = Keypair::from_base58_string(signer_prv_key.as_str()).try_pubkey()?;
+
+        Ok(Self {
+            ctx,
+            router,
+            signer_prv_key,
+            wallet,
+            config,
+            holdings: LeaderHoldings::default(),
+            signal_filter,
+            cooldown: BuyCooldown::default(),
+            dedup: SignatureDedup::default(),
+            db,
+            latency: LatencyHistograms::default(),
+            risk,
+            safety,
+        })
+    }
+
+    pub fn config(&self) -> &CopyTradingConfig {
+        &self.config
+    }
+
+    /// Returns the per stage latency histograms of the copies sent
+    pub fn latency(&self) -> &LatencyHistograms {
+        &self.latency
+    }
+
+    /// Copies the trade of a transaction notification with the settings of
+    /// the leader it matched, running the pipeline stages in a row.
+    /// Ok(None) when there is nothing to copy.
+    ///
+    /// * `payload` - The transaction notification JSON
+    pub async fn handle(
+        &self,
+        payload: &str,
+    ) -> Result<Option<Vec<String>>, Box<dyn Error + Send + Sync>> {
+        let Some(signal) = self.decode(payload, Instant::now()).await else {
+            return Ok(None);
+        };
+        let Some(copy) = self.filter(signal).await? else {
+            return Ok(None);
+        };
+        self.execute(&copy).await
+    }
+
+    /// Decodes the leader trade of a transaction notification, only with
+    /// the decoder of the venue it invoked. Every leader trade is tracked,
+    /// copied or not. None when there is no new leader trade.
+    ///
+    /// * `payload` - The transaction notification JSON
+    /// * `received_at` - When its websocket frame was received
+    pub async fn decode(&self, payload: &str, received_at: Instant) -> Option<LeaderSignal> {
+        let json = serde_json::from_str::<Value>(payload).ok()?;
+        let source_signature = json["params"]["result"]["signature"].as_str()?;
+        if !self.dedup.first_seen(source_signature, Instant::now()) {
+            return None;
+        }
+        let leader = self.config.leader_of(&json)?;
+        let trade = Venue::parse_trade(&self.ctx, payload).await?;
+
+        let balances = LeaderBalances::from_notification(&json, &leader.wallet, &trade.mint);
+        let sold_fraction = self.holdings.observe(&leader.wallet, &trade, &balances);
+
+        let trace = Arc::new(OrderTrace::new(received_at));
+        trace.stamp(LatencyStage::Decoded);
+        if let Some(slot) = json["params"]["result"]["slot"].as_u64() {
+            trace.set_leader_slot(slot);
+        }
+
+        Some(LeaderSignal {
+            source_signature: source_signature.to_string(),
+            leader: leader.clone(),
+            trade,
+            balances,
+            sold_fraction,
+            trace,
+        })
+    }
+
+    /// Runs a leader trade through the leader settings and the signal
+    /// filter, then sizes its copy. Ok(None) when it is not copied, the
+    /// reason being logged.
+    ///
+    /// * `signal` - The decoded leader trade
+    pub async fn filter(
+        &self,
+        signal: LeaderSignal,
+    ) -> Result<Option<PendingCopy>, Box<dyn Error + Send + Sync>> {
+        let LeaderSignal {
+            leader,
+            trade,
+            balances,
+            sold_fraction,
+            ..
+        } = &signal;
+
+        if !leader.allows(trade.venue) {
+            println!("{} traded on {}, not copied", leader.name(), trade.venue);
+            return Ok(None);
+        }
+        if trade.side == TradeSide::Sell && !leader.copy_sells {
+            println!("{} sold {}, not copied", leader.name(), trade.mint);
+            return Ok(None);
+        }
+        if trade.side == TradeSide::Buy {
+            if let Err(reason) = self.check_signal(trade).await? {
+                println!("{} buy of {} skipped: {reason}", leader.name(), trade.mint);
+                return Ok(None);
+            }
+            if let Err(reason) = self.safety.check_buy(&trade.mint).await? {
+                println!("{} buy of {} blocked: {reason}", leader.name(), trade.mint);
+                return Ok(None);
+            }
+        }
+
+        // Sells mirror the share of its position the leader sold
+        let amount_in = match trade.side {
+            TradeSide::Buy => self.buy_lamports(leader, trade, balances).await?,
+            TradeSide::Sell => mirrored_sell_amount(
+                token_balance(&self.ctx, &self.wallet, &trade.mint).await?,
+                *sold_fraction,
+            ),
+        };
+        if amount_in == 0 {
+            println!(
+                "{} {} of {} sized to 0, not copied",
+                leader.name(),
+                trade.side,
+                trade.mint
+            );
+            return Ok(None);
+        }
+        if trade.side == TradeSide::Buy {
+            // Last, so that a buy skipped for any other reason does not count
+            let cooldown =
+                self.signal_filter
+                    .check_cooldown(&self.cooldown, &trade.mint, unix_now());
+            if let Err(reason) = cooldown {
+                println!("{} buy of {} skipped: {reason}", leader.name(), trade.mint);
+                return Ok(None);
+            }
+        }
+
+        signal.trace.stamp(LatencyStage::Filtered);
+        Ok(Some(PendingCopy { signal, amount_in }))
+    }
+
+    /// Sends a sized copy. A leader transaction produces at most one order:
+    /// the order is claimed in the database under the idempotency key of
+    /// the signal before it is sent. Ok(None) when it already has one.
+    ///
+    /// * `copy` - The copy to send
+    pub async fn execute(
+        &self,
+        copy: &PendingCopy,
+    ) -> Result<Option<Vec<String>>, Box<dyn Error + Send + Sync>> {
+        let PendingCopy { signal, amount_in } = copy;
+        let LeaderSignal {
+            source_signature,
+            leader,
+            trade,
+            trace,
+            ..
+        } = signal;
+        println!(
+            "Copying {} | {} | {} | Mint: {} | In: {amount_in}",
+            leader.name(),
+            trade.venue,
+            trade.side,
+            trade.mint
+        );
+
+        // Kept until the position of the buy is recorded
+        let _permit = match trade.side {
+            TradeSide::Buy => match self.risk.approve_buy(&trade.mint, *amount_in).await? {
+                Ok(permit) => Some(permit),
+                Err(reason) => {
+                    println!("{} buy of {} rejected: {reason}", leader.name(), trade.mint);
+                    return Ok(None);
+                }
+            },
+            TradeSide::Sell => None,
+        };
+
+        let idempotency_key = CopyOrder::key(&leader.wallet.to_string(), source_signature);
+        let order = CopyOrder {
+            idempotency_key: idempotency_key.clone(),
+            leader: leader.wallet.to_string(),
+            source_signature: source_signature.clone(),
+            mint: trade.mint.to_string(),
+            side: trade.side.to_string(),
+            status: CopyOrderStatus::Pending,
+            signatures: String::new(),
+            error: String::new(),
+            created_at: unix_now(),
+            leader_slot: trace.leader_slot(),
+            landed_slot: None,
+            latency: String::new(),
+        };
+        if !self.db.claim_copy_order(&order).await? {
+            println!("{source_signature} already has an order, not copied");
+            return Ok(None);
+        }
+
+        let result = self.send(leader, trade, *amount_in, trace).await;
+        self.risk.record_send(result.is_ok());
+        self.latency.record(trace);
+        self.db
+            .set_copy_order_latency(
+                &idempotency_key,
+                trace.leader_slot(),
+                trace.landed_slot(),
+                &trace.to_json().to_string(),
+            )
+            .await?;
+        match &result {
+            Ok(signatures) => {
+                self.db
+                    .set_copy_order_status(
+                        &idempotency_key,
+                        CopyOrderStatus::Sent,
+                        &signatures.join(","),
+                        "",
+                    )
+                    .await?
+            }
+            Err(e) => {
+                self.db
+                    .set_copy_order_status(
+                        &idempotency_key,
+                        CopyOrderStatus::Failed,
+                        "",
+                        &e.to_string(),
+                    )
+                    .await?
+            }
+        }
+
+        let signatures = result?;
+        let positions = self.risk.positions();
+        let (tokens, net_lamports) = positions
+            .ledger()
+            .book_all(&signatures, &trade.mint, Some(&leader.wallet), "copy")
+            .await;
+
+        // At the confirmed amounts, the intended ones when they cannot be read
+        let position = match trade.side {
+            TradeSide::Buy => {
+                let lamports = if tokens > 0 {
+                    net_lamports.unsigned_abs()
+                } else {
+                    *amount_in
+                };
+                positions
+                    .record_buy(trade.venue, trade.pool_id, trade.mint, lamports)
+                    .await
+                    .map(|_| ())
+            }
+            TradeSide::Sell => {
+                let price = if tokens > 0 {
+                    Some(net_lamports as f64 / tokens as f64)
+                } else {
+                    (trade.token_amount > 0)
+                        .then(|| trade.sol_amount as f64 / trade.token_amount as f64)
+                };
+                positions.sync(&trade.mint, price).await
+            }
+        };
+        if let Err(e) = position {
+            eprintln!("Error updating the position of {}: {e:?}", trade.mint);
+        }
+
+        Ok(Some(signatures))
+    }
+
+    /// Sends the copy of a leader trade, on the best route or the leader pool
+    async fn send(
+        &self,
+        leader: &LeaderConfig,
+        trade: &ParsedTrade,
+        amount_in: u64,
+        trace: &OrderTrace,
+    ) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        match &self.router {
+            Some(router) => {
+                leader_router(router, leader)
+                    .copy_trade(
+                        &self.ctx,
+                        Arc::clone(&self.signer_prv_key),
+                        trade,
+                        amount_in,
+                        leader.slippage_percent,
+                        Some(trace),
+                    )
+                    .await
+            }
+            None => Ok(vec![
+                Venue::copy_trade(
+                    &self.ctx,
+                    Arc::clone(&self.signer_prv_key),
+                    trade,
+                    amount_in,
+                    leader.slippage_percent,
+                    Some(trace),
+                )
+                .await?,
+            ]),
+        }
+    }
+
+    /// Runs a leader buy through the signal filter, fetching only the
+    /// market data its rules need. Ok(Err) holds the reason to skip it.
+    ///
+    /// * `trade` - The leader buy
+    async fn check_signal(
+        &self,
+        trade: &ParsedTrade,
+    ) -> Result<Result<(), SignalFilterError>, Box<dyn Error + Send + Sync>> {
+        if let Err(reason) = self.signal_filter.check_leader_trade(trade) {
+            return Ok(Err(reason));
+        }
+
+        let snapshot = self
+            .signal_filter
+            .snapshot(&self.ctx, trade, unix_now())
+            .await?;
+
+        Ok(self.signal_filter.check(&snapshot))
+    }
+
+    /// Sizes the copy of a leader buy
+    async fn buy_lamports(
+        &self,
+        leader: &LeaderConfig,
+        trade: &ParsedTrade,
+        balances: &LeaderBalances,
+    ) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        let balance = if leader.sizing.needs_balance() {
+            let connection = self.ctx.helius.lock().await.async_connection()?;
+            connection.get_balance(&self.wallet).await?
+        } else {
+            0
+        };
+
+        Ok(leader.sizing.buy_lamports(
+            trade.sol_amount,
+            balances.sol_pre.unwrap_or_default(),
+            balance,
+        ))
+    }
+}
+
+/// Returns the router restricted to the venues a leader is copied on
+fn leader_router(router: &Router, leader: &LeaderConfig) -> Router {
+    Router {
+        venues: router
+            .venues
+            .iter()
+            .copied()
+            .filter(|venue| leader.allows(*venue))
+            .collect(),
+        fallback: router.fallback.filter(|venue| leader.allows(*venue)),
+        ..router.clone()
+    }
+}
@@ -0,0 +1,394 @@
+use serde::{de, Deserialize, Deserializer};
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashSet, env, error::Error, fs, str::FromStr};
+
+use crate::{bot::Venue, constants::SOL_DECIMALS};
+
+/// How much SOL a copied buy spends
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SizingMode {
+    /// The same amount on every buy
+    FixedSol { sol: f64 },
+    /// A multiple of what the leader spent, capped at `max_sol`
+    LeaderProportional { ratio: f64, max_sol: Option<f64> },
+    /// The share of our balance matching the share of its balance the
+    /// leader spent, capped at `max_sol`
+    MirrorBalance { max_sol: Option<f64> },
+    /// A percent of our SOL balance
+    PercentOfBalance { percent: f64 },
+}
+
+impl SizingMode {
+    /// Returns the lamports a copied buy spends
+    ///
+    /// * `leader_lamports` - The lamports the leader spent
+    /// * `leader_balance` - The leader SOL balance before the buy, 0 if unknown
+    /// * `balance` - Our SOL balance, only read when sizing on it
+    pub fn buy_lamports(&self, leader_lamports: u64, leader_balance: u64, balance: u64) -> u64 {
+        match self {
+            Self::FixedSol { sol } => to_lamports(*sol),
+            Self::LeaderProportional { ratio, max_sol } => {
+                cap((leader_lamports as f64 * ratio) as u64, *max_sol)
+            }
+            Self::MirrorBalance { max_sol } => {
+                if leader_balance == 0 {
+                    return 0;
+                }
+                let share = (leader_lamports as f64 / leader_balance as f64).min(1.0);
+                cap((balance as f64 * share) as u64, *max_sol)
+            }
+            Self::PercentOfBalance { percent } => (balance as f64 * percent / 100.0) as u64,
+        }
+    }
+
+    /// Returns true if the sizing reads our SOL balance
+    pub fn needs_balance(&self) -> bool {
+        matches!(
+            self,
+            Self::MirrorBalance { .. } | Self::PercentOfBalance { .. }
+        )
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let valid = match self {
+            Self::FixedSol { sol } => *sol > 0.0,
+            Self::LeaderProportional { ratio, max_sol } => {
+                *ratio > 0.0 && max_sol.iter().all(|max_sol| *max_sol > 0.0)
+            }
+            Self::MirrorBalance { max_sol } => max_sol.iter().all(|max_sol| *max_sol > 0.0),
+            Self::PercentOfBalance { percent } => *percent > 0.0 && *percent <= 100.0,
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("Invalid sizing: {self:?}"))
+        }
+    }
+}
+
+/// How a leader wallet is copied
+#[derive(Debug, Clone, Deserialize)]
+pub struct LeaderConfig {
+    #[serde(deserialize_with = "deserialize_pubkey")]
+    pub wallet: Pubkey,
+    /// A name for the logs
+    #[serde(default)]
+    pub label: Option<String>,
+    pub sizing: SizingMode,
+    pub slippage_percent: u64,
+    /// Venues the leader is copied on, all when empty
+    #[serde(default, deserialize_with = "deserialize_venues")]
+    pub venues: HashSet<Venue>,
+    /// Copies the sells too, not only the buys
+    #[serde(default = "default_true")]
+    pub copy_sells: bool,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+impl LeaderConfig {
+    /// Returns true if trades on `venue` are copied
+    pub fn allows(&self, venue: Venue) -> bool {
+        self.venues.is_empty() || self.venues.contains(&venue)
+    }
+
+    /// Returns the label of the leader, its wallet if it has none
+    pub fn name(&self) -> String {
+        self.label
+            .clone()
+            .unwrap_or_else(|| self.wallet.to_string())
+    }
+}
+
+/// The leaders copied, e.g.
+///
+/// ```json
+/// { "leaders": [{
+///     "wallet": "6pURJRF6meemMHSdkuypCMrzUfDk1YKYa8MersBrpump",
+///     "label": "whale",
+///     "sizing": { "mode": "leader_proportional", "ratio": 0.1, "max_sol": 0.5 },
+///     "slippage_percent": 10,
+///     "venues": ["pump_fun", "pump_swap"],
+///     "copy_sells": true,
+///     "enabled": true
+/// }] }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CopyTradingConfig {
+    pub leaders: Vec<LeaderConfig>,
+}
+
+impl CopyTradingConfig {
+    /// Parses and validates a JSON config
+    ///
+    /// * `json` - The config JSON
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let config: Self = serde_json::from_str(json)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks every leader is configured once, with a valid slippage and
+    /// sizing, and that one of them is enabled
+    pub fn validate(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut wallets = HashSet::new();
+        for leader in &self.leaders {
+            if !wallets.insert(leader.wallet) {
+                return Err(format!("Leader {} is configured twice", leader.wallet).into());
+            }
+            if leader.slippage_percent > 100 {
+                return Err(format!("Invalid slippage for leader {}", leader.name()).into());
+            }
+            leader.sizing.validate()?;
+        }
+        if !self.leaders.iter().any(|leader| leader.enabled) {
+            return Err("No enabled leader to copy".into());
+        }
+
+        Ok(())
+    }
+
+    /// Loads the config file at `copy_leaders_config`. Without one, the
+    /// single leader of `copy_wallet` is copied with a fixed `max_sol_buy`
+    /// and `slippage_percent`.
+    pub fn from_env() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        if let Ok(path) = env::var("copy_leaders_config") {
+            return Self::from_json(&fs::read_to_string(path)?);
+        }
+
+        Ok(Self {
+            leaders: vec![LeaderConfig {
+                wallet: Pubkey::from_str(env::var("copy_wallet")?.trim())?,
+                label: None,
+                sizing: SizingMode::FixedSol {
+                    sol: env::var("max_sol_buy")?.parse()?,
+                },
+                slippage_percent: env::var("slippage_percent")?.parse::<f64>()? as u64,
+                venues: HashSet::new(),
+                copy_sells: true,
+                enabled: true,
+            }],
+        })
+    }
+
+    /// Returns the wallets of the enabled leaders
+    pub fn wallets(&self) -> Vec<String> {
+        self.leaders
+            .iter()
+            .filter(|leader| leader.enabled)
+            .map(|leader| leader.wallet.to_string())
+            .collect()
+    }
+
+    /// Finds the enabled leader a transaction notification matched on: the
+    /// leader signing it, else the first leader among its accounts
+    ///
+    /// * `json` - The transaction notification JSON
+    pub fn leader_of(&self, json: &Value) -> Option<&LeaderConfig> {
+        let account_keys = json["params"]["result"]["transaction"]["transaction"]["message"]
+            ["accountKeys"]
+            .as_array()?;
+
+        let leader_at = |signers_only: bool| {
+            account_keys.iter().find_map(|account_key| {
+                let pubkey = account_key["pubkey"]
+                    .as_str()
+                    .or_else(|| account_key.as_str())?;
+                if signers_only && account_key["signer"].as_bool() != Some(true) {
+                    return None;
+                }
+                self.leaders
+                    .iter()
+                    .find(|leader| leader.enabled && leader.wallet.to_string() == pubkey)
+            })
+        };
+
+        leader_at(true).or_else(|| leader_at(false))
+    }
+}
+
+fn to_lamports(sol: f64) -> u64 {
+    (sol * SOL_DECIMALS as f64) as u64
+}
+
+fn cap(lamports: u64, max_sol: Option<f64>) -> u64 {
+    max_sol.map_or(lamports, |max_sol| lamports.min(to_lamports(max_sol)))
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn deserialize_pubkey<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+    let pubkey = String::deserialize(deserializer)?;
+    Pubkey::from_str(&pubkey).map_err(de::Error::custom)
+}
+
+fn deserialize_venues<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashSet<Venue>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|venue| {
+            Venue::from_config(venue)
+                .ok_or_else(|| de::Error::custom(format!("Unknown venue: {venue}")))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const LEADER_A: &str = "6pURJRF6meemMHSdkuypCMrzUfDk1YKYa8MersBrpump";
+    const LEADER_B: &str = "5p8mtXXa5rGNwwuHZ7bDH6QKCkPNDqmHT2YTyM6wZbZ5";
+
+    fn config() -> CopyTradingConfig {
+        CopyTradingConfig::from_json(
+            &json!({
+                "leaders": [
+                    {
+                        "wallet": LEADER_A,
+                        "label": "whale",
+                        "sizing": { "mode": "leader_proportional", "ratio": 0.1, "max_sol": 0.5 },
+                        "slippage_percent": 10,
+                        "venues": ["pump_fun", "raydium_v4"],
+                        "copy_sells": false
+                    },
+                    {
+                        "wallet": LEADER_B,
+                        "sizing": { "mode": "fixed_sol", "sol": 0.05 },
+                        "slippage_percent": 5
+                    }
+                ]
+            })
+            .to_string(),
+        )
+        .unwrap()
+    }
+
+    fn notification(account_keys: Value) -> Value {
+        json!({
+            "params": { "result": { "transaction": { "transaction": {
+                "message": { "accountKeys": account_keys }
+            }}}}
+        })
+    }
+
+    #[test]
+    fn test_from_json() {
+        let config = config();
+        let whale = &config.leaders[0];
+        assert_eq!(whale.name(), "whale");
+        assert!(whale.allows(Venue::PumpFun));
+        assert!(!whale.allows(Venue::PumpSwap));
+        assert!(!whale.copy_sells);
+        assert!(whale.enabled);
+
+        let other = &config.leaders[1];
+        assert_eq!(other.name(), LEADER_B);
+        assert!(other.allows(Venue::OrcaWhirlpool));
+        assert!(other.copy_sells);
+        assert_eq!(config.wallets(), vec![LEADER_A, LEADER_B]);
+    }
+
+    #[test]
+    fn test_from_json_rejects() {
+        let leader = |wallet: &str, sizing: Value, venues: Value| {
+            json!({
+                "wallet": wallet,
+                "sizing": sizing,
+                "slippage_percent": 10,
+                "venues": venues,
+            })
+        };
+        let fixed = json!({ "mode": "fixed_sol", "sol": 0.1 });
+
+        for leaders in [
+            json!([
+                leader(LEADER_A, fixed.clone(), json!([])),
+                leader(LEADER_A, fixed.clone(), json!([]))
+            ]),
+            json!([leader(LEADER_A, fixed.clone(), json!(["uniswap"]))]),
+            json!([leader("not a wallet", fixed.clone(), json!([]))]),
+            json!([leader(
+                LEADER_A,
+                json!({ "mode": "fixed_sol", "sol": 0.0 }),
+                json!([])
+            )]),
+            json!([leader(
+                LEADER_A,
+                json!({ "mode": "percent_of_balance", "percent": 150.0 }),
+                json!([])
+            )]),
+            json!([]),
+        ] {
+            let json = json!({ "leaders": leaders }).to_string();
+            assert!(CopyTradingConfig::from_json(&json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn test_buy_lamports() {
+        let fixed = SizingMode::FixedSol { sol: 0.05 };
+        assert_eq!(fixed.buy_lamports(10 * SOL_DECIMALS, 0, 0), 50_000_000);
+
+        let proportional = SizingMode::LeaderProportional {
+            ratio: 0.1,
+            max_sol: Some(0.5),
+        };
+        assert_eq!(proportional.buy_lamports(SOL_DECIMALS, 0, 0), 100_000_000);
+        assert_eq!(
+            proportional.buy_lamports(10 * SOL_DECIMALS, 0, 0),
+            500_000_000
+        );
+
+        // The leader spent 10% of its balance, so do we
+        let mirror = SizingMode::MirrorBalance { max_sol: Some(1.0) };
+        assert!(mirror.needs_balance());
+        assert_eq!(
+            mirror.buy_lamports(SOL_DECIMALS, 10 * SOL_DECIMALS, 2 * SOL_DECIMALS),
+            200_000_000
+        );
+        assert_eq!(
+            mirror.buy_lamports(SOL_DECIMALS, 2 * SOL_DECIMALS, 40 * SOL_DECIMALS),
+            SOL_DECIMALS
+        );
+        assert_eq!(mirror.buy_lamports(SOL_DECIMALS, 0, SOL_DECIMALS), 0);
+
+        let percent = SizingMode::PercentOfBalance { percent: 2.5 };
+        assert!(percent.needs_balance());
+        assert_eq!(
+            percent.buy_lamports(SOL_DECIMALS, 0, 4 * SOL_DECIMALS),
+            100_000_000
+        );
+    }
+
+    #[test]
+    fn test_leader_of() {
+        let config = config();
+        let signer = |pubkey: &str, signer: bool| json!({ "pubkey": pubkey, "signer": signer });
+
+        // The signing leader wins over a leader only touched by the transaction
+        let json = notification(json!([signer(LEADER_A, false), signer(LEADER_B, true)]));
+        assert_eq!(config.leader_of(&json).unwrap().name(), LEADER_B);
+
+        let json = notification(json!([
+            signer(&Pubkey::new_unique().to_string(), true),
+            signer(LEADER_A, false)
+        ]));
+        assert_eq!(config.leader_of(&json).unwrap().name(), "whale");
+
+        let json = notification(json!([signer(&Pubkey::new_unique().to_string(), true)]));
+        assert!(config.leader_of(&json).is_none());
+
+        let mut config = config;
+        config.leaders[1].enabled = false;
+        let json = notification(json!([signer(LEADER_B, true)]));
+        assert!(config.leader_of(&json).is_none());
+    }
+}
@@ -0,0 +1,266 @@
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::bot::{ParsedTrade, TradeSide};
+
+/// The leader balances a transaction notification shows, None when the
+/// transaction does not record them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LeaderBalances {
+    /// Lamports held before the transaction
+    pub sol_pre: Option<u64>,
+    /// Raw tokens of the traded mint held before the transaction
+    pub token_pre: Option<u64>,
+    /// Raw tokens of the traded mint held after the transaction
+    pub token_post: Option<u64>,
+}
+
+impl LeaderBalances {
+    /// Reads the leader balances out of the transaction meta
+    ///
+    /// * `json` - The transaction notification JSON
+    /// * `leader` - The leader wallet
+    /// * `mint` - The traded mint
+    pub fn from_notification(json: &Value, leader: &Pubkey, mint: &Pubkey) -> Self {
+        let leader = leader.to_string();
+        let mint = mint.to_string();
+        let meta = &json["params"]["result"]["transaction"]["meta"];
+
+        let sol_pre = json["params"]["result"]["transaction"]["transaction"]["message"]
+            ["accountKeys"]
+            .as_array()
+            .and_then(|account_keys| {
+                account_keys.iter().position(|account_key| {
+                    account_key["pubkey"]
+                        .as_str()
+                        .or_else(|| account_key.as_str())
+                        == Some(leader.as_str())
+                })
+            })
+            .and_then(|index| meta["preBalances"][index].as_u64());
+
+        // A wallet can hold the mint in several token accounts
+        let token_balance = |balances: &str| {
+            let balances = meta[balances].as_array()?;
+            Some(
+                balances
+                    .iter()
+                    .filter(|balance| {
+                        balance["owner"].as_str() == Some(leader.as_str())
+                            && balance["mint"].as_str() == Some(mint.as_str())
+                    })
+                    .filter_map(|balance| {
+                        balance["uiTokenAmount"]["amount"]
+                            .as_str()?
+                            .parse::<u64>()
+                            .ok()
+                    })
+                    .sum(),
+            )
+        };
+
+        Self {
+            sol_pre,
+            token_pre: token_balance("preTokenBalances"),
+            token_post: token_balance("postTokenBalances"),
+        }
+    }
+}
+
+/// The token holdings of the leaders, per mint, as seen in their trades
+#[derive(Debug, Default)]
+pub struct LeaderHoldings {
+    holdings: Mutex<HashMap<(Pubkey, Pubkey), u64>>,
+}
+
+impl LeaderHoldings {
+    /// Returns the tokens of `mint` a leader is known to hold
+    pub fn get(&self, leader: &Pubkey, mint: &Pubkey) -> Option<u64> {
+        self.holdings.lock().ok()?.get(&(*leader, *mint)).copied()
+    }
+
+    /// Records a leader trade and returns the fraction of the position it
+    /// sold, 1 when the position is unknown. Buys return 0.
+    ///
+    /// The transaction balances win over the tracked holdings, which only
+    /// fill in when the transaction does not record them.
+    ///
+    /// * `leader` - The leader wallet
+    /// * `trade` - The leader trade
+    /// * `balances` - The leader balances of the transaction
+    pub fn observe(&self, leader: &Pubkey, trade: &ParsedTrade, balances: &LeaderBalances) -> f64 {
+        let Ok(mut holdings) = self.holdings.lock() else {
+            return 1.0;
+        };
+        let key = (*leader, trade.mint);
+
+        let pre = balances.token_pre.or_else(|| holdings.get(&key).copied());
+        let post = balances.token_post.or_else(|| {
+            let pre = pre?;
+            Some(match trade.side {
+                TradeSide::Buy => pre.saturating_add(trade.token_amount),
+                TradeSide::Sell => pre.saturating_sub(trade.token_amount),
+            })
+        });
+
+        match post {
+            Some(0) => holdings.remove(&key),
+            Some(post) => holdings.insert(key, post),
+            None if trade.side == TradeSide::Buy => holdings.insert(key, trade.token_amount),
+            None => holdings.remove(&key),
+        };
+
+        match (trade.side, pre) {
+            (TradeSide::Buy, _) => 0.0,
+            (TradeSide::Sell, Some(pre)) if pre > 0 => {
+                let sold = pre.saturating_sub(post.unwrap_or(0));
+                (sold as f64 / pre as f64).min(1.0)
+            }
+            (TradeSide::Sell, _) => 1.0,
+        }
+    }
+}
+
+/// Returns the tokens a mirrored sell spends, the whole balance once the
+/// fraction is close enough to 1 to leave dust otherwise
+///
+/// * `balance` - Our raw token balance
+/// * `sold_fraction` - The fraction of the position the leader sold
+pub fn mirrored_sell_amount(balance: u64, sold_fraction: f64) -> u64 {
+    if sold_fraction >= 0.99 {
+        return balance;
+    }
+    (balance as f64 * sold_fraction.max(0.0)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Venue;
+    use serde_json::json;
+
+    fn trade(mint: Pubkey, side: TradeSide, token_amount: u64) -> ParsedTrade {
+        ParsedTrade {
+            venue: Venue::PumpFun,
+            signature: None,
+            side,
+            mint,
+            pool_id: Pubkey::new_unique(),
+            sol_amount: 1_000_000,
+            token_amount,
+            compute_unit_limit: 0,
+        }
+    }
+
+    #[test]
+    fn test_from_notification() {
+        let leader = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_balance = |index: u64, owner: &Pubkey, mint: &Pubkey, amount: &str| {
+            json!({
+                "accountIndex": index,
+                "owner": owner.to_string(),
+                "mint": mint.to_string(),
+                "uiTokenAmount": { "amount": amount },
+            })
+        };
+        let json = json!({
+            "params": { "result": { "transaction": {
+                "transaction": { "message": { "accountKeys": [
+                    { "pubkey": Pubkey::new_unique().to_string() },
+                    { "pubkey": leader.to_string() },
+                ]}},
+                "meta": {
+                    "preBalances": [5, 2_000_000_000u64],
+                    "preTokenBalances": [
+                        token_balance(2, &leader, &mint, "600"),
+                        token_balance(3, &leader, &mint, "400"),
+                        token_balance(4, &Pubkey::new_unique(), &mint, "9999"),
+                    ],
+                    "postTokenBalances": [
+                        token_balance(2, &leader, &mint, "0"),
+                        token_balance(3, &leader, &mint, "400"),
+                        token_balance(5, &leader, &Pubkey::new_unique(), "77"),
+                    ],
+                }
+            }}}
+        });
+
+        assert_eq!(
+            LeaderBalances::from_notification(&json, &leader, &mint),
+            LeaderBalances {
+                sol_pre: Some(2_000_000_000),
+                token_pre: Some(1_000),
+                token_post: Some(400),
+            }
+        );
+        assert_eq!(
+            LeaderBalances::from_notification(&json!({}), &leader, &mint),
+            LeaderBalances::default()
+        );
+    }
+
+    #[test]
+    fn test_observe_with_balances() {
+        let holdings = LeaderHoldings::default();
+        let leader = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        let buy = LeaderBalances {
+            sol_pre: Some(1_000_000_000),
+            token_pre: Some(0),
+            token_post: Some(1_000),
+        };
+        assert_eq!(
+            holdings.observe(&leader, &trade(mint, TradeSide::Buy, 1_000), &buy),
+            0.0
+        );
+        assert_eq!(holdings.get(&leader, &mint), Some(1_000));
+
+        let sell = LeaderBalances {
+            sol_pre: None,
+            token_pre: Some(1_000),
+            token_post: Some(600),
+        };
+        let fraction = holdings.observe(&leader, &trade(mint, TradeSide::Sell, 400), &sell);
+        assert!((fraction - 0.4).abs() < 1e-9);
+        assert_eq!(holdings.get(&leader, &mint), Some(600));
+    }
+
+    #[test]
+    fn test_observe_tracked() {
+        let holdings = LeaderHoldings::default();
+        let leader = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let none = LeaderBalances::default();
+
+        // Unknown position, a sell is a full exit
+        assert_eq!(
+            holdings.observe(&leader, &trade(mint, TradeSide::Sell, 10), &none),
+            1.0
+        );
+
+        holdings.observe(&leader, &trade(mint, TradeSide::Buy, 800), &none);
+        holdings.observe(&leader, &trade(mint, TradeSide::Buy, 200), &none);
+        assert_eq!(holdings.get(&leader, &mint), Some(1_000));
+
+        let fraction = holdings.observe(&leader, &trade(mint, TradeSide::Sell, 250), &none);
+        assert!((fraction - 0.25).abs() < 1e-9);
+        assert_eq!(holdings.get(&leader, &mint), Some(750));
+
+        assert_eq!(
+            holdings.observe(&leader, &trade(mint, TradeSide::Sell, 750), &none),
+            1.0
+        );
+        assert_eq!(holdings.get(&leader, &mint), None);
+    }
+
+    #[test]
+    fn test_mirrored_sell_amount() {
+        assert_eq!(mirrored_sell_amount(1_000, 0.4), 400);
+        assert_eq!(mirrored_sell_amount(1_000, 0.995), 1_000);
+        assert_eq!(mirrored_sell_amount(1_000, 1.0), 1_000);
+        assert_eq!(mirrored_sell_amount(1_000, -1.0), 0);
+    }
+}
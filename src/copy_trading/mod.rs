@@ -0,0 +1,13 @@
+mod engine;
+mod leader_config;
+mod leader_holdings;
+mod pipeline;
+mod signal_filter;
+mod signature_dedup;
+
+pub use engine::*;
+pub use leader_config::*;
+pub use leader_holdings::*;
+pub use pipeline::*;
+pub use signal_filter::*;
+pub use signature_dedup::*;
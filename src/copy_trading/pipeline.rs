@@ -0,0 +1,363 @@
+use std::{
+    collections::VecDeque,
+    env,
+    error::Error,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+use tokio::{
+    sync::{Notify, Semaphore},
+    task::JoinSet,
+};
+
+use super::{CopyEngine, LeaderSignal, PendingCopy};
+
+/// What a full queue does with a new item
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverloadPolicy {
+    /// Drops the oldest item to make room, stale signals are worth the least
+    #[default]
+    DropOldest,
+    /// Drops the new item
+    Reject,
+}
+
+impl OverloadPolicy {
+    /// Parses a policy from the config, e.g. "drop_oldest"
+    ///
+    /// * `policy` - The policy name
+    pub fn from_config(policy: &str) -> Option<Self> {
+        match policy.trim().to_lowercase().as_str() {
+            "drop_oldest" => Some(Self::DropOldest),
+            "reject" => Some(Self::Reject),
+            _ => None,
+        }
+    }
+}
+
+/// A bounded multi-consumer queue between two pipeline stages, applying
+/// the overload policy once full
+#[derive(Debug)]
+pub struct BoundedQueue<T> {
+    capacity: usize,
+    policy: OverloadPolicy,
+    items: Mutex<VecDeque<T>>,
+    ready: Notify,
+    dropped: AtomicU64,
+}
+
+impl<T> BoundedQueue<T> {
+    pub fn new(capacity: usize, policy: OverloadPolicy) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            policy,
+            items: Mutex::new(VecDeque::with_capacity(capacity)),
+            ready: Notify::new(),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Queues an item, returns false when the item or an older one was
+    /// dropped to respect the capacity
+    ///
+    /// * `item` - The item to queue
+    pub fn push(&self, item: T) -> bool {
+        let Ok(mut items) = self.items.lock() else {
+            return false;
+        };
+
+        let accepted = if items.len() < self.capacity {
+            items.push_back(item);
+            true
+        } else {
+            if self.policy == OverloadPolicy::DropOldest {
+                items.pop_front();
+                items.push_back(item);
+            }
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            false
+        };
+        drop(items);
+
+        self.ready.notify_one();
+        accepted
+    }
+
+    /// Waits for the next item
+    pub async fn pop(&self) -> T {
+        loop {
+            if let Some(item) = self.try_pop() {
+                return item;
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    /// Takes the next item, None when the queue is empty
+    pub fn try_pop(&self) -> Option<T> {
+        let mut items = self.items.lock().ok()?;
+        let item = items.pop_front();
+
+        // Passes the wake up on while items are left for other consumers
+        if !items.is_empty() {
+            self.ready.notify_one();
+        }
+        item
+    }
+
+    pub fn len(&self) -> usize {
+        self.items
+            .lock()
+            .map(|items| items.len())
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of items dropped by the overload policy
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Sizes of the copy pipeline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PipelineConfig {
+    /// Capacity of each queue between two stages
+    pub queue_capacity: usize,
+    /// Notifications decoded in parallel at most
+    pub decode_concurrency: usize,
+    /// Tasks filtering signals in parallel
+    pub filter_workers: usize,
+    /// Copies sent in parallel at most
+    pub execute_concurrency: usize,
+    pub overload_policy: OverloadPolicy,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 256,
+            decode_concurrency: 8,
+            filter_workers: 4,
+            execute_concurrency: 4,
+            overload_policy: OverloadPolicy::DropOldest,
+        }
+    }
+}
+
+impl PipelineConfig {
+    /// Loads the config from the environment, unset variables keep the defaults:
+    ///
+    /// * `pipeline_queue_capacity` - Capacity of each queue, 256 by default
+    /// * `pipeline_decode_concurrency` - Notifications decoded in parallel, 8 by default
+    /// * `pipeline_filter_workers` - Signals filtered in parallel, 4 by default
+    /// * `pipeline_execute_concurrency` - Copies sent in parallel, 4 by default
+    /// * `pipeline_overload_policy` - "drop_oldest" (default) or "reject"
+    pub fn from_env() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut config = Self::default();
+
+        if let Ok(capacity) = env::var("pipeline_queue_capacity") {
+            config.queue_capacity = capacity.parse()?;
+        }
+        if let Ok(concurrency) = env::var("pipeline_decode_concurrency") {
+            config.decode_concurrency = concurrency.parse()?;
+        }
+        if let Ok(workers) = env::var("pipeline_filter_workers") {
+            config.filter_workers = workers.parse()?;
+        }
+        if let Ok(concurrency) = env::var("pipeline_execute_concurrency") {
+            config.execute_concurrency = concurrency.parse()?;
+        }
+        if let Ok(policy) = env::var("pipeline_overload_policy") {
+            config.overload_policy = OverloadPolicy::from_config(&policy)
+                .ok_or_else(|| format!("Unknown overload policy: {policy}"))?;
+        }
+
+        Ok(config)
+    }
+}
+
+/// Copies leader trades in stages connected by bounded queues:
+/// ingest, decode, filter, then execute.
+///
+/// Every notification is decoded on its own task, up to the decode
+/// concurrency, so a slow RPC call or a panicking decoder only holds up its
+/// own notification. Filtering and execution run on a fixed number of
+/// tasks, execution being the concurrency limit on sent copies. A full queue
+/// applies the overload policy instead of growing.
+pub struct CopyPipeline {
+    config: PipelineConfig,
+    /// Notifications with the instant their frame was received
+    ingested: BoundedQueue<(String, Instant)>,
+    decoded: BoundedQueue<LeaderSignal>,
+    sized: BoundedQueue<PendingCopy>,
+}
+
+impl fmt::Debug for CopyPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CopyPipeline")
+            .field("config", &self.config)
+            .field("ingested", &self.ingested.len())
+            .field("decoded", &self.decoded.len())
+            .field("sized", &self.sized.len())
+            .finish()
+    }
+}
+
+impl CopyPipeline {
+    /// Starts the stage tasks of the pipeline
+    ///
+    /// * `engine` - The copy engine running the stages
+    /// * `config` - The pipeline sizes
+    pub fn start(engine: Arc<CopyEngine>, config: PipelineConfig) -> Arc<Self> {
+        let pipeline = Arc::new(Self {
+            config,
+            ingested: BoundedQueue::new(config.queue_capacity, config.overload_policy),
+            decoded: BoundedQueue::new(config.queue_capacity, config.overload_policy),
+            sized: BoundedQueue::new(config.queue_capacity, config.overload_policy),
+        });
+
+        {
+            let (pipeline, engine) = (Arc::clone(&pipeline), Arc::clone(&engine));
+            let slots = Arc::new(Semaphore::new(config.decode_concurrency.max(1)));
+            tokio::spawn(async move {
+                let mut decoding = JoinSet::new();
+                loop {
+                    // Waits for a free slot before taking the next notification
+                    let Ok(slot) = Arc::clone(&slots).acquire_owned().await else {
+                        return;
+                    };
+                    let (payload, received_at) = pipeline.ingested.pop().await;
+
+                    let (stage, engine) = (Arc::clone(&pipeline), Arc::clone(&engine));
+                    decoding.spawn(async move {
+                        let _slot = slot;
+                        if let Some(signal) = engine.decode(&payload, received_at).await {
+                            if !stage.decoded.push(signal) {
+                                eprintln!("Pipeline overloaded, a decoded signal was dropped");
+                            }
+                        }
+                    });
+
+                    while let Some(result) = decoding.try_join_next() {
+                        if let Err(e) = result {
+                            eprintln!("Error decoding a notification: {e:?}");
+                        }
+                    }
+                }
+            });
+        }
+
+        for _ in 0..config.filter_workers.max(1) {
+            let (pipeline, engine) = (Arc::clone(&pipeline), Arc::clone(&engine));
+            tokio::spawn(async move {
+                loop {
+                    let signal = pipeline.decoded.pop().await;
+                    match engine.filter(signal).await {
+                        Ok(Some(copy)) => {
+                            if !pipeline.sized.push(copy) {
+                                eprintln!("Pipeline overloaded, a sized copy was dropped");
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => eprintln!("Error filtering copy signal: {e:?}"),
+                    }
+                }
+            });
+        }
+
+        for _ in 0..config.execute_concurrency.max(1) {
+            let (pipeline, engine) = (Arc::clone(&pipeline), Arc::clone(&engine));
+            tokio::spawn(async move {
+                loop {
+                    let copy = pipeline.sized.pop().await;
+                    if let Err(e) = engine.execute(&copy).await {
+                        eprintln!("Error sending copy Tx: {e:?}");
+                    }
+                }
+            });
+        }
+
+        pipeline
+    }
+
+    /// Queues a transaction notification, without waiting for room
+    ///
+    /// * `payload` - The transaction notification JSON
+    /// * `received_at` - When its websocket frame was received
+    pub fn ingest(&self, payload: String, received_at: Instant) {
+        if !self.ingested.push((payload, received_at)) {
+            eprintln!(
+                "Pipeline overloaded, {} notifications dropped so far",
+                self.ingested.dropped()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drop_oldest() {
+        let queue = BoundedQueue::new(2, OverloadPolicy::DropOldest);
+
+        assert!(queue.push(1));
+        assert!(queue.push(2));
+        assert!(!queue.push(3));
+        assert_eq!(queue.dropped(), 1);
+        assert_eq!(queue.try_pop(), Some(2));
+        assert_eq!(queue.try_pop(), Some(3));
+        assert_eq!(queue.try_pop(), None);
+    }
+
+    #[test]
+    fn test_reject() {
+        let queue = BoundedQueue::new(2, OverloadPolicy::Reject);
+
+        assert!(queue.push(1));
+        assert!(queue.push(2));
+        assert!(!queue.push(3));
+        assert_eq!(queue.dropped(), 1);
+        assert_eq!(queue.try_pop(), Some(1));
+        assert_eq!(queue.try_pop(), Some(2));
+        assert!(queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pop_waits() {
+        let queue = Arc::new(BoundedQueue::new(4, OverloadPolicy::Reject));
+
+        let consumer = {
+            let queue = Arc::clone(&queue);
+            tokio::spawn(async move { (queue.pop().await, queue.pop().await) })
+        };
+        tokio::task::yield_now().await;
+        queue.push("a");
+        queue.push("b");
+
+        assert_eq!(consumer.await.unwrap(), ("a", "b"));
+    }
+
+    #[test]
+    fn test_overload_policy_from_config() {
+        assert_eq!(
+            OverloadPolicy::from_config("drop_oldest"),
+            Some(OverloadPolicy::DropOldest)
+        );
+        assert_eq!(
+            OverloadPolicy::from_config(" Reject "),
+            Some(OverloadPolicy::Reject)
+        );
+        assert_eq!(OverloadPolicy::from_config("block"), None);
+    }
+}
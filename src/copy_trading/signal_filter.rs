@@ -0,0 +1,554 @@
+use serde::Deserialize;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    str::FromStr,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    bot::{AccountDecoder, ParsedTrade, QuoteDetails, SplMintState, TradeSide, VenueContext},
+    constants::SOL_DECIMALS,
+    errors::SignalFilterError,
+};
+
+/// Signatures fetched per page when dating a mint
+const MINT_AGE_PAGE_SIZE: usize = 1_000;
+/// Pages scanned at most when dating a mint, busier mints get a lower bound
+const MINT_AGE_MAX_PAGES: usize = 5;
+
+/// What is known of the market of a leader buy, only the parts a rule needs
+/// are fetched
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SignalSnapshot {
+    /// Seconds since the mint was created, a lower bound for busy mints
+    pub token_age_secs: Option<u64>,
+    /// Percent of the bonding curve sold, None when the trade is not on a curve
+    pub curve_progress: Option<f64>,
+    /// Lamports held by the leader pool
+    pub liquidity: Option<u64>,
+    /// Supply valued at the leader price, in lamports
+    pub market_cap: Option<u64>,
+}
+
+/// Rules a leader buy has to pass to be copied, checked before sizing.
+///
+/// Unset rules are not checked and their data is not fetched. Deserialized
+/// with the amounts in lamports, unset fields disabling their rule.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SignalFilter {
+    /// Lamports the leader spent
+    pub min_leader_lamports: u64,
+    pub max_leader_lamports: Option<u64>,
+    pub min_token_age_secs: Option<u64>,
+    pub max_token_age_secs: Option<u64>,
+    /// Percent of the bonding curve sold, only checked on Pump.fun curve trades
+    pub min_curve_progress: Option<f64>,
+    pub max_curve_progress: Option<f64>,
+    /// Lamports held by the leader pool
+    pub min_liquidity: Option<u64>,
+    /// Market cap bounds in lamports
+    pub min_market_cap: Option<u64>,
+    pub max_market_cap: Option<u64>,
+    /// Seconds before the same mint is bought again
+    pub buy_cooldown_secs: Option<u64>,
+}
+
+impl SignalFilter {
+    /// Loads the filter from the environment, unset variables disable their rule:
+    ///
+    /// * `signal_min_leader_sol` / `signal_max_leader_sol` - Leader buy size bounds in SOL
+    /// * `signal_min_token_age_secs` / `signal_max_token_age_secs` - Token age bounds
+    /// * `signal_min_curve_progress` / `signal_max_curve_progress` - Bonding curve percent sold bounds
+    /// * `signal_min_liquidity_sol` - Minimum SOL held by the leader pool
+    /// * `signal_min_market_cap_sol` / `signal_max_market_cap_sol` - Market cap bounds in SOL
+    /// * `signal_buy_cooldown_secs` - Seconds before the same mint is bought again
+    pub fn from_env() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let sol = |name: &str| -> Result<Option<u64>, Box<dyn Error + Send + Sync>> {
+            match env::var(name) {
+                Ok(sol) => Ok(Some((sol.parse::<f64>()? * SOL_DECIMALS as f64) as u64)),
+                Err(_) => Ok(None),
+            }
+        };
+        let secs = |name: &str| -> Result<Option<u64>, Box<dyn Error + Send + Sync>> {
+            match env::var(name) {
+                Ok(secs) => Ok(Some(secs.parse()?)),
+                Err(_) => Ok(None),
+            }
+        };
+        let percent = |name: &str| -> Result<Option<f64>, Box<dyn Error + Send + Sync>> {
+            match env::var(name) {
+                Ok(percent) => Ok(Some(percent.parse()?)),
+                Err(_) => Ok(None),
+            }
+        };
+
+        Ok(Self {
+            min_leader_lamports: sol("signal_min_leader_sol")?.unwrap_or_default(),
+            max_leader_lamports: sol("signal_max_leader_sol")?,
+            min_token_age_secs: secs("signal_min_token_age_secs")?,
+            max_token_age_secs: secs("signal_max_token_age_secs")?,
+            min_curve_progress: percent("signal_min_curve_progress")?,
+            max_curve_progress: percent("signal_max_curve_progress")?,
+            min_liquidity: sol("signal_min_liquidity_sol")?,
+            min_market_cap: sol("signal_min_market_cap_sol")?,
+            max_market_cap: sol("signal_max_market_cap_sol")?,
+            buy_cooldown_secs: secs("signal_buy_cooldown_secs")?,
+        })
+    }
+
+    /// Checks the size of the leader buy, before anything is fetched
+    ///
+    /// * `trade` - The leader buy
+    pub fn check_leader_trade(&self, trade: &ParsedTrade) -> Result<(), SignalFilterError> {
+        let actual = trade.sol_amount;
+
+        if actual < self.min_leader_lamports {
+            return Err(SignalFilterError::LeaderTradeTooSmall {
+                min: self.min_leader_lamports,
+                actual,
+            });
+        }
+        if let Some(max) = self.max_leader_lamports {
+            if actual > max {
+                return Err(SignalFilterError::LeaderTradeTooLarge { max, actual });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetches the market data the rules set need
+    ///
+    /// * `ctx` - The venue context
+    /// * `trade` - The leader buy
+    /// * `now` - The current unix timestamp
+    pub async fn snapshot(
+        &self,
+        ctx: &VenueContext,
+        trade: &ParsedTrade,
+        now: u64,
+    ) -> Result<SignalSnapshot, Box<dyn Error + Send + Sync>> {
+        let mut snapshot = SignalSnapshot::default();
+
+        let needs_pool = self.min_liquidity.is_some()
+            || self.min_curve_progress.is_some()
+            || self.max_curve_progress.is_some();
+        if needs_pool {
+            let quote = trade
+                .venue
+                .quote_pool(
+                    ctx,
+                    &trade.pool_id,
+                    &trade.mint,
+                    TradeSide::Buy,
+                    trade.sol_amount.max(1),
+                )
+                .await?;
+            snapshot.liquidity = Some(quote.quote_liquidity);
+            if let QuoteDetails::PumpFun(details) = &quote.details {
+                snapshot.curve_progress = Some(details.curve.progress());
+            }
+        }
+
+        let needs_market_cap = self.min_market_cap.is_some() || self.max_market_cap.is_some();
+        let needs_age = self.min_token_age_secs.is_some() || self.max_token_age_secs.is_some();
+        if needs_market_cap || needs_age {
+            let connection = ctx.helius.lock().await.async_connection()?;
+
+            if needs_market_cap {
+                let account = connection.get_account(&trade.mint).await?;
+                let supply = SplMintState::decode_account(&account)?.supply;
+                snapshot.market_cap = market_cap(trade, supply);
+            }
+            if needs_age {
+                snapshot.token_age_secs = mint_age_secs(&connection, &trade.mint, now).await?;
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Checks the market of a leader buy against every rule set
+    ///
+    /// * `snapshot` - The market data of the leader buy
+    pub fn check(&self, snapshot: &SignalSnapshot) -> Result<(), SignalFilterError> {
+        if self.min_token_age_secs.is_some() || self.max_token_age_secs.is_some() {
+            let age = snapshot
+                .token_age_secs
+                .ok_or(SignalFilterError::Unknown("token age"))?;
+            if let Some(min) = self.min_token_age_secs {
+                if age < min {
+                    return Err(SignalFilterError::TokenTooYoung { min, age });
+                }
+            }
+            if let Some(max) = self.max_token_age_secs {
+                if age > max {
+                    return Err(SignalFilterError::TokenTooOld { max, age });
+                }
+            }
+        }
+
+        // Tokens off a bonding curve have no progress to check
+        if let Some(actual) = snapshot.curve_progress {
+            if let Some(min) = self.min_curve_progress {
+                if actual < min {
+                    return Err(SignalFilterError::CurveProgressTooLow { min, actual });
+                }
+            }
+            if let Some(max) = self.max_curve_progress {
+                if actual > max {
+                    return Err(SignalFilterError::CurveProgressTooHigh { max, actual });
+                }
+            }
+        }
+
+        if let Some(min) = self.min_liquidity {
+            let actual = snapshot
+                .liquidity
+                .ok_or(SignalFilterError::Unknown("pool liquidity"))?;
+            if actual < min {
+                return Err(SignalFilterError::LowLiquidity { min, actual });
+            }
+        }
+
+        if self.min_market_cap.is_some() || self.max_market_cap.is_some() {
+            let actual = snapshot
+                .market_cap
+                .ok_or(SignalFilterError::Unknown("market cap"))?;
+            if let Some(min) = self.min_market_cap {
+                if actual < min {
+                    return Err(SignalFilterError::MarketCapTooLow { min, actual });
+                }
+            }
+            if let Some(max) = self.max_market_cap {
+                if actual > max {
+                    return Err(SignalFilterError::MarketCapTooHigh { max, actual });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records a buy of `mint`, unless it was bought within the cooldown
+    ///
+    /// * `cooldown` - The last buy of every mint
+    /// * `mint` - The token about to be bought
+    /// * `now` - The current unix timestamp
+    pub fn check_cooldown(
+        &self,
+        cooldown: &BuyCooldown,
+        mint: &Pubkey,
+        now: u64,
+    ) -> Result<(), SignalFilterError> {
+        match self.buy_cooldown_secs {
+            Some(cooldown_secs) => cooldown.acquire(mint, cooldown_secs, now),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The last time every mint was bought, shared by the leaders
+#[derive(Debug, Default)]
+pub struct BuyCooldown {
+    last_buys: Mutex<HashMap<Pubkey, u64>>,
+}
+
+impl BuyCooldown {
+    /// Records a buy of `mint` at `now`, unless the last one is less than
+    /// `cooldown_secs` old
+    ///
+    /// * `mint` - The token about to be bought
+    /// * `cooldown_secs` - Seconds between two buys of a mint
+    /// * `now` - The current unix timestamp
+    pub fn acquire(
+        &self,
+        mint: &Pubkey,
+        cooldown_secs: u64,
+        now: u64,
+    ) -> Result<(), SignalFilterError> {
+        let Ok(mut last_buys) = self.last_buys.lock() else {
+            return Ok(());
+        };
+
+        if let Some(last_buy) = last_buys.get(mint) {
+            let elapsed = now.saturating_sub(*last_buy);
+            if elapsed < cooldown_secs {
+                return Err(SignalFilterError::Cooldown {
+                    mint: *mint,
+                    elapsed,
+                    cooldown: cooldown_secs,
+                });
+            }
+        }
+        last_buys.insert(*mint, now);
+
+        Ok(())
+    }
+}
+
+/// Returns the current unix timestamp
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Values the supply at the price the leader paid, in lamports
+///
+/// * `trade` - The leader buy
+/// * `supply` - The raw token supply
+pub fn market_cap(trade: &ParsedTrade, supply: u64) -> Option<u64> {
+    if trade.token_amount == 0 {
+        return None;
+    }
+    let price = trade.sol_amount as f64 / trade.token_amount as f64;
+    Some((price * supply as f64) as u64)
+}
+
+/// Dates a mint from its oldest signature. Mints with more signatures than
+/// scanned get a lower bound. None when the RPC returns no block time.
+///
+/// * `connection` - The RPC client
+/// * `mint` - The token mint
+/// * `now` - The current unix timestamp
+async fn mint_age_secs(
+    connection: &RpcClient,
+    mint: &Pubkey,
+    now: u64,
+) -> Result<Option<u64>, Box<dyn Error + Send + Sync>> {
+    let mut before = None;
+    let mut oldest = None;
+
+    for _ in 0..MINT_AGE_MAX_PAGES {
+        let signatures = connection
+            .get_signatures_for_address_with_config(
+                mint,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    limit: Some(MINT_AGE_PAGE_SIZE),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        let Some(last) = signatures.last() else {
+            break;
+        };
+        oldest = last.block_time.or(oldest);
+        if signatures.len() < MINT_AGE_PAGE_SIZE {
+            break;
+        }
+        before = Some(Signature::from_str(&last.signature)?);
+    }
+
+    Ok(oldest.map(|block_time| now.saturating_sub(block_time.max(0) as u64)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Venue;
+
+    fn buy(sol_amount: u64, token_amount: u64) -> ParsedTrade {
+        ParsedTrade {
+            venue: Venue::PumpFun,
+            signature: None,
+            side: TradeSide::Buy,
+            mint: Pubkey::new_unique(),
+            pool_id: Pubkey::new_unique(),
+            sol_amount,
+            token_amount,
+            compute_unit_limit: 0,
+        }
+    }
+
+    #[test]
+    fn test_check_leader_trade() {
+        let filter = SignalFilter {
+            min_leader_lamports: 100_000_000,
+            max_leader_lamports: Some(5_000_000_000),
+            ..Default::default()
+        };
+
+        assert!(filter.check_leader_trade(&buy(1_000_000_000, 1)).is_ok());
+        assert_eq!(
+            filter.check_leader_trade(&buy(10_000_000, 1)),
+            Err(SignalFilterError::LeaderTradeTooSmall {
+                min: 100_000_000,
+                actual: 10_000_000
+            })
+        );
+        assert_eq!(
+            filter.check_leader_trade(&buy(6_000_000_000, 1)),
+            Err(SignalFilterError::LeaderTradeTooLarge {
+                max: 5_000_000_000,
+                actual: 6_000_000_000
+            })
+        );
+        assert!(SignalFilter::default()
+            .check_leader_trade(&buy(0, 0))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_check() {
+        let filter = SignalFilter {
+            min_token_age_secs: Some(60),
+            max_token_age_secs: Some(3_600),
+            min_curve_progress: Some(10.0),
+            max_curve_progress: Some(80.0),
+            min_liquidity: Some(5_000_000_000),
+            min_market_cap: Some(20_000_000_000),
+            max_market_cap: Some(500_000_000_000),
+            ..Default::default()
+        };
+        let snapshot = SignalSnapshot {
+            token_age_secs: Some(600),
+            curve_progress: Some(40.0),
+            liquidity: Some(10_000_000_000),
+            market_cap: Some(50_000_000_000),
+        };
+        assert!(filter.check(&snapshot).is_ok());
+
+        let cases = [
+            (
+                SignalSnapshot {
+                    token_age_secs: Some(30),
+                    ..snapshot.clone()
+                },
+                SignalFilterError::TokenTooYoung { min: 60, age: 30 },
+            ),
+            (
+                SignalSnapshot {
+                    token_age_secs: Some(7_200),
+                    ..snapshot.clone()
+                },
+                SignalFilterError::TokenTooOld {
+                    max: 3_600,
+                    age: 7_200,
+                },
+            ),
+            (
+                SignalSnapshot {
+                    token_age_secs: None,
+                    ..snapshot.clone()
+                },
+                SignalFilterError::Unknown("token age"),
+            ),
+            (
+                SignalSnapshot {
+                    curve_progress: Some(5.0),
+                    ..snapshot.clone()
+                },
+                SignalFilterError::CurveProgressTooLow {
+                    min: 10.0,
+                    actual: 5.0,
+                },
+            ),
+            (
+                SignalSnapshot {
+                    curve_progress: Some(95.0),
+                    ..snapshot.clone()
+                },
+                SignalFilterError::CurveProgressTooHigh {
+                    max: 80.0,
+                    actual: 95.0,
+                },
+            ),
+            (
+                SignalSnapshot {
+                    liquidity: Some(1_000_000_000),
+                    ..snapshot.clone()
+                },
+                SignalFilterError::LowLiquidity {
+                    min: 5_000_000_000,
+                    actual: 1_000_000_000,
+                },
+            ),
+            (
+                SignalSnapshot {
+                    market_cap: Some(10_000_000_000),
+                    ..snapshot.clone()
+                },
+                SignalFilterError::MarketCapTooLow {
+                    min: 20_000_000_000,
+                    actual: 10_000_000_000,
+                },
+            ),
+            (
+                SignalSnapshot {
+                    market_cap: Some(900_000_000_000),
+                    ..snapshot.clone()
+                },
+                SignalFilterError::MarketCapTooHigh {
+                    max: 500_000_000_000,
+                    actual: 900_000_000_000,
+                },
+            ),
+        ];
+        for (snapshot, reason) in cases {
+            assert_eq!(filter.check(&snapshot), Err(reason));
+        }
+
+        // Off a bonding curve the progress rules do not apply
+        let amm = SignalSnapshot {
+            curve_progress: None,
+            ..snapshot
+        };
+        assert!(filter.check(&amm).is_ok());
+
+        // Nothing set, nothing checked
+        assert!(SignalFilter::default()
+            .check(&SignalSnapshot::default())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let filter = SignalFilter {
+            buy_cooldown_secs: Some(300),
+            ..Default::default()
+        };
+        let cooldown = BuyCooldown::default();
+        let mint = Pubkey::new_unique();
+
+        assert!(filter.check_cooldown(&cooldown, &mint, 1_000).is_ok());
+        assert_eq!(
+            filter.check_cooldown(&cooldown, &mint, 1_100),
+            Err(SignalFilterError::Cooldown {
+                mint,
+                elapsed: 100,
+                cooldown: 300
+            })
+        );
+        // Other mints have their own cooldown
+        assert!(filter
+            .check_cooldown(&cooldown, &Pubkey::new_unique(), 1_100)
+            .is_ok());
+        assert!(filter.check_cooldown(&cooldown, &mint, 1_300).is_ok());
+
+        // Without a cooldown every buy passes
+        let no_cooldown = SignalFilter::default();
+        assert!(no_cooldown.check_cooldown(&cooldown, &mint, 1_301).is_ok());
+    }
+
+    #[test]
+    fn test_market_cap() {
+        // 1 SOL for 10M tokens of a 1B supply, 6 decimals: 100 SOL
+        let trade = buy(1_000_000_000, 10_000_000_000_000);
+        assert_eq!(
+            market_cap(&trade, 1_000_000_000_000_000),
+            Some(100_000_000_000)
+        );
+        assert_eq!(market_cap(&buy(1_000_000_000, 0), 1_000), None);
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Signatures remembered by default
+pub const SIGNATURE_DEDUP_CAPACITY: usize = 10_000;
+/// How long a signature is remembered by default
+pub const SIGNATURE_DEDUP_TTL: Duration = Duration::from_secs(600);
+
+/// Drops the notifications of a signature already seen. Subscriptions at
+/// `processed` commitment, and resubscriptions after a reconnect, can
+/// deliver the same transaction more than once.
+///
+/// Signatures are remembered for `ttl`, and at most `capacity` of them,
+/// the oldest being forgotten first.
+#[derive(Debug)]
+pub struct SignatureDedup {
+    ttl: Duration,
+    capacity: usize,
+    seen: Mutex<SeenSignatures>,
+}
+
+#[derive(Debug, Default)]
+struct SeenSignatures {
+    by_signature: HashMap<String, Instant>,
+    /// Signatures in the order they were first seen
+    order: VecDeque<(String, Instant)>,
+}
+
+impl Default for SignatureDedup {
+    fn default() -> Self {
+        Self::new(SIGNATURE_DEDUP_TTL, SIGNATURE_DEDUP_CAPACITY)
+    }
+}
+
+impl SignatureDedup {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity: capacity.max(1),
+            seen: Mutex::new(SeenSignatures::default()),
+        }
+    }
+
+    /// Returns true the first time a signature is seen within the TTL
+    ///
+    /// * `signature` - The transaction signature
+    /// * `now` - The current instant
+    pub fn first_seen(&self, signature: &str, now: Instant) -> bool {
+        let Ok(mut seen) = self.seen.lock() else {
+            return true;
+        };
+
+        while let Some((expired, _)) = seen
+            .order
+            .front()
+            .filter(|(_, seen_at)| now.saturating_duration_since(*seen_at) >= self.ttl)
+            .cloned()
+        {
+            seen.order.pop_front();
+            seen.by_signature.remove(&expired);
+        }
+
+        if seen.by_signature.contains_key(signature) {
+            return false;
+        }
+
+        // Makes room by forgetting the oldest signature
+        if seen.order.len() >= self.capacity {
+            if let Some((oldest, _)) = seen.order.pop_front() {
+                seen.by_signature.remove(&oldest);
+            }
+        }
+        seen.by_signature.insert(signature.to_string(), now);
+        seen.order.push_back((signature.to_string(), now));
+
+        true
+    }
+
+    /// Returns the number of signatures remembered
+    pub fn remembered(&self) -> usize {
+        self.seen
+            .lock()
+            .map(|seen| seen.by_signature.len())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_seen() {
+        let dedup = SignatureDedup::default();
+        let now = Instant::now();
+
+        assert!(dedup.first_seen("sig_a", now));
+        assert!(!dedup.first_seen("sig_a", now + Duration::from_secs(1)));
+        assert!(dedup.first_seen("sig_b", now + Duration::from_secs(1)));
+        assert_eq!(dedup.remembered(), 2);
+    }
+
+    #[test]
+    fn test_ttl() {
+        let dedup = SignatureDedup::new(Duration::from_secs(60), 100);
+        let now = Instant::now();
+
+        assert!(dedup.first_seen("sig_a", now));
+        assert!(!dedup.first_seen("sig_a", now + Duration::from_secs(59)));
+        assert!(dedup.first_seen("sig_a", now + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_capacity() {
+        let dedup = SignatureDedup::new(Duration::from_secs(60), 2);
+        let now = Instant::now();
+
+        assert!(dedup.first_seen("sig_a", now));
+        assert!(dedup.first_seen("sig_b", now));
+        assert!(dedup.first_seen("sig_c", now));
+        assert_eq!(dedup.remembered(), 2);
+
+        // The oldest was forgotten to make room
+        assert!(!dedup.first_seen("sig_c", now));
+        assert!(dedup.first_seen("sig_a", now));
+    }
+}
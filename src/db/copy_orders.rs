@@ -0,0 +1,209 @@
+use rusqlite::OptionalExtension;
+use rusqlite::{params, Connection, Result};
+use tokio::sync::MutexGuard;
+
+/// What became of a copied signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyOrderStatus {
+    /// Claimed, the order may or may not have been sent
+    Pending,
+    Sent,
+    Failed,
+}
+
+impl CopyOrderStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Sent => "sent",
+            Self::Failed => "failed",
+        }
+    }
+
+    fn parse(status: &str) -> Self {
+        match status {
+            "sent" => Self::Sent,
+            "failed" => Self::Failed,
+            _ => Self::Pending,
+        }
+    }
+}
+
+/// The order placed for a copied signal, keyed on the idempotency key of
+/// the signal so that a leader trade produces at most one order
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyOrder {
+    pub idempotency_key: String,
+    pub leader: String,
+    pub source_signature: String,
+    pub mint: String,
+    pub side: String,
+    pub status: CopyOrderStatus,
+    /// Signatures of the transactions sent, comma separated
+    pub signatures: String,
+    /// Why the order failed, empty otherwise
+    pub error: String,
+    /// Unix timestamp of the claim
+    pub created_at: u64,
+    /// Slot of the leader transaction
+    pub leader_slot: Option<u64>,
+    /// Slot our transaction landed in
+    pub landed_slot: Option<u64>,
+    /// Stage offsets from the frame receipt, as JSON
+    pub latency: String,
+}
+
+impl CopyOrder {
+    pub const TABLE_NAME: &'static str = "copy_orders";
+
+    /// Returns the idempotency key of the copy of a leader trade
+    ///
+    /// * `leader` - The leader wallet
+    /// * `source_signature` - The signature of the leader transaction
+    pub fn key(leader: &str, source_signature: &str) -> String {
+        format!("{leader}:{source_signature}")
+    }
+
+    pub(crate) fn init_table(conn: &MutexGuard<'_, Connection>) -> Result<()> {
+        conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                idempotency_key TEXT PRIMARY KEY,
+                leader TEXT NOT NULL,
+                source_signature TEXT NOT NULL,
+                mint TEXT NOT NULL,
+                side TEXT NOT NULL,
+                status TEXT NOT NULL,
+                signatures TEXT NOT NULL,
+                error TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                leader_slot INTEGER,
+                landed_slot INTEGER,
+                latency TEXT NOT NULL DEFAULT ''
+            )",
+                Self::TABLE_NAME
+            ),
+            [],
+        )?;
+
+        // Tables created before the latency columns
+        for (column, definition) in [
+            ("leader_slot", "INTEGER"),
+            ("landed_slot", "INTEGER"),
+            ("latency", "TEXT NOT NULL DEFAULT ''"),
+        ] {
+            let exists = conn
+                .prepare(&format!(
+                    "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1",
+                    Self::TABLE_NAME
+                ))?
+                .exists(params![column])?;
+            if !exists {
+                conn.execute(
+                    &format!(
+                        "ALTER TABLE {} ADD COLUMN {column} {definition}",
+                        Self::TABLE_NAME
+                    ),
+                    [],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Inserts the order unless its key is already taken, returns true if
+    /// it was inserted
+    pub(crate) fn claim(conn: &MutexGuard<'_, Connection>, order: &Self) -> Result<bool> {
+        let inserted = conn.execute(
+            &format!(
+                "INSERT OR IGNORE INTO {} (
+                idempotency_key, leader, source_signature, mint, side, status, signatures, error, created_at,
+                leader_slot, landed_slot, latency
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                Self::TABLE_NAME
+            ),
+            params![
+                order.idempotency_key,
+                order.leader,
+                order.source_signature,
+                order.mint,
+                order.side,
+                order.status.as_str(),
+                order.signatures,
+                order.error,
+                order.created_at,
+                order.leader_slot,
+                order.landed_slot,
+                order.latency
+            ],
+        )?;
+        Ok(inserted == 1)
+    }
+
+    pub(crate) fn set_status(
+        conn: &MutexGuard<'_, Connection>,
+        idempotency_key: &str,
+        status: CopyOrderStatus,
+        signatures: &str,
+        error: &str,
+    ) -> Result<()> {
+        conn.execute(
+            &format!(
+                "UPDATE {} SET status = ?2, signatures = ?3, error = ?4 WHERE idempotency_key = ?1",
+                Self::TABLE_NAME
+            ),
+            params![idempotency_key, status.as_str(), signatures, error],
+        )?;
+        Ok(())
+    }
+
+    pub(crate) fn set_latency(
+        conn: &MutexGuard<'_, Connection>,
+        idempotency_key: &str,
+        leader_slot: Option<u64>,
+        landed_slot: Option<u64>,
+        latency: &str,
+    ) -> Result<()> {
+        conn.execute(
+            &format!(
+                "UPDATE {} SET leader_slot = ?2, landed_slot = ?3, latency = ?4 WHERE idempotency_key = ?1",
+                Self::TABLE_NAME
+            ),
+            params![idempotency_key, leader_slot, landed_slot, latency],
+        )?;
+        Ok(())
+    }
+
+    pub(crate) fn get(
+        conn: &MutexGuard<'_, Connection>,
+        idempotency_key: &str,
+    ) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT idempotency_key, leader, source_signature, mint, side, status, signatures, error, created_at,
+                leader_slot, landed_slot, latency
+            FROM {} WHERE idempotency_key = ?1",
+            Self::TABLE_NAME
+        ))?;
+
+        let order = stmt
+            .query_row(params![idempotency_key], |row| {
+                Ok(CopyOrder {
+                    idempotency_key: row.get(0)?,
+                    leader: row.get(1)?,
+                    source_signature: row.get(2)?,
+                    mint: row.get(3)?,
+                    side: row.get(4)?,
+                    status: CopyOrderStatus::parse(&row.get::<_, String>(5)?),
+                    signatures: row.get(6)?,
+                    error: row.get(7)?,
+                    created_at: row.get(8)?,
+                    leader_slot: row.get(9)?,
+                    landed_slot: row.get(10)?,
+                    latency: row.get(11)?,
+                })
+            })
+            .optional()?;
+
+        Ok(order)
+    }
+}
@@ -0,0 +1,142 @@
+use rusqlite::{params, Connection, Result};
+use tokio::sync::MutexGuard;
+
+/// A confirmed swap of our wallet, with the amounts read from the
+/// confirmed transaction
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fill {
+    pub signature: String,
+    pub mint: String,
+    /// "buy" or "sell"
+    pub side: String,
+    /// Raw tokens received on a buy, spent on a sell
+    pub tokens: u64,
+    /// Lamports spent on a buy, received on a sell, before the costs below
+    pub sol_amount: u64,
+    /// Lamports paid to the Pump.fun fee recipient
+    pub pump_fun_fee: u64,
+    /// Base and priority fees in lamports
+    pub network_fee: u64,
+    /// Lamports tipped to Jito
+    pub jito_tip: u64,
+    /// Rent of the token accounts opened, less the rent of those closed
+    pub ata_rent: i64,
+    /// The copied leader, empty when the fill is not a copy
+    pub leader: String,
+    /// What sent the order, e.g. "copy", "agent" or "exit"
+    pub strategy: String,
+    pub slot: u64,
+    /// Unix timestamp of the block
+    pub block_time: u64,
+}
+
+impl Fill {
+    pub const TABLE_NAME: &'static str = "fills";
+
+    pub fn is_buy(&self) -> bool {
+        self.side == "buy"
+    }
+
+    /// Returns every cost of the fill in lamports
+    pub fn costs(&self) -> i64 {
+        (self.pump_fun_fee + self.network_fee + self.jito_tip) as i64 + self.ata_rent
+    }
+
+    /// Returns the lamports the fill moved in and out of the wallet, costs
+    /// included: negative on a buy, positive on a profitable sell
+    pub fn net_lamports(&self) -> i64 {
+        if self.is_buy() {
+            -(self.sol_amount as i64) - self.costs()
+        } else {
+            self.sol_amount as i64 - self.costs()
+        }
+    }
+
+    pub(crate) fn init_table(conn: &MutexGuard<'_, Connection>) -> Result<()> {
+        conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                signature TEXT NOT NULL,
+                mint TEXT NOT NULL,
+                side TEXT NOT NULL,
+                tokens INTEGER NOT NULL,
+                sol_amount INTEGER NOT NULL,
+                pump_fun_fee INTEGER NOT NULL,
+                network_fee INTEGER NOT NULL,
+                jito_tip INTEGER NOT NULL,
+                ata_rent INTEGER NOT NULL,
+                leader TEXT NOT NULL,
+                strategy TEXT NOT NULL,
+                slot INTEGER NOT NULL,
+                block_time INTEGER NOT NULL,
+                PRIMARY KEY (signature, mint)
+            )",
+                Self::TABLE_NAME
+            ),
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Inserts the fill unless it is already booked, returns true if it
+    /// was inserted
+    pub(crate) fn insert(conn: &MutexGuard<'_, Connection>, fill: &Self) -> Result<bool> {
+        let inserted = conn.execute(
+            &format!(
+                "INSERT OR IGNORE INTO {} (
+                signature, mint, side, tokens, sol_amount, pump_fun_fee, network_fee, jito_tip,
+                ata_rent, leader, strategy, slot, block_time
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                Self::TABLE_NAME
+            ),
+            params![
+                fill.signature,
+                fill.mint,
+                fill.side,
+                fill.tokens,
+                fill.sol_amount,
+                fill.pump_fun_fee,
+                fill.network_fee,
+                fill.jito_tip,
+                fill.ata_rent,
+                fill.leader,
+                fill.strategy,
+                fill.slot,
+                fill.block_time
+            ],
+        )?;
+        Ok(inserted == 1)
+    }
+
+    /// Returns the fills in the order they landed
+    pub(crate) fn all(conn: &MutexGuard<'_, Connection>) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT signature, mint, side, tokens, sol_amount, pump_fun_fee, network_fee, jito_tip,
+                ata_rent, leader, strategy, slot, block_time
+            FROM {} ORDER BY slot, block_time",
+            Self::TABLE_NAME
+        ))?;
+
+        let fills = stmt
+            .query_map([], |row| {
+                Ok(Fill {
+                    signature: row.get(0)?,
+                    mint: row.get(1)?,
+                    side: row.get(2)?,
+                    tokens: row.get(3)?,
+                    sol_amount: row.get(4)?,
+                    pump_fun_fee: row.get(5)?,
+                    network_fee: row.get(6)?,
+                    jito_tip: row.get(7)?,
+                    ata_rent: row.get(8)?,
+                    leader: row.get(9)?,
+                    strategy: row.get(10)?,
+                    slot: row.get(11)?,
+                    block_time: row.get(12)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(fills)
+    }
+}
@@ -1,5 +1,9 @@
+mod copy_orders;
+mod fills;
+mod positions;
 mod pump_fun;
 mod raydium;
+mod scheduled_orders;
 
 use rusqlite::Connection;
 use rusqlite::Result;
@@ -8,8 +12,12 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::sync::MutexGuard;
 
+pub use copy_orders::{CopyOrder, CopyOrderStatus};
+pub use fills::Fill;
+pub use positions::PositionRecord;
 pub use pump_fun::PumpFunCoinAccounts;
 pub use raydium::RaydiumCoinAccounts;
+pub use scheduled_orders::ScheduledOrderRecord;
 
 pub struct Database {
     conn: Arc<Mutex<Connection>>,
@@ -41,6 +49,10 @@ impl Database {
         // Initialize both tables
         RaydiumCoinAccounts::init_table(&conn.lock().await)?;
         PumpFunCoinAccounts::init_table(&conn.lock().await)?;
+        CopyOrder::init_table(&conn.lock().await)?;
+        PositionRecord::init_table(&conn.lock().await)?;
+        Fill::init_table(&conn.lock().await)?;
+        ScheduledOrderRecord::init_table(&conn.lock().await)?;
 
         Ok(Database { conn })
     }
@@ -80,4 +92,225 @@ impl Database {
     ) -> Result<Option<PumpFunCoinAccounts>> {
         PumpFunCoinAccounts::get_coin_accounts_by_coin_name(&self.conn.lock().await, coin_name)
     }
+
+    /// Claims the idempotency key of a copied signal, false when an order
+    /// already exists for it, even from a previous run
+    pub async fn claim_copy_order(&self, order: &CopyOrder) -> Result<bool> {
+        CopyOrder::claim(&self.conn.lock().await, order)
+    }
+
+    /// Records what became of a claimed order
+    pub async fn set_copy_order_status(
+        &self,
+        idempotency_key: &str,
+        status: CopyOrderStatus,
+        signatures: &str,
+        error: &str,
+    ) -> Result<()> {
+        CopyOrder::set_status(
+            &self.conn.lock().await,
+            idempotency_key,
+            status,
+            signatures,
+            error,
+        )
+    }
+
+    /// Attaches the slots and stage latencies of an order
+    pub async fn set_copy_order_latency(
+        &self,
+        idempotency_key: &str,
+        leader_slot: Option<u64>,
+        landed_slot: Option<u64>,
+        latency: &str,
+    ) -> Result<()> {
+        CopyOrder::set_latency(
+            &self.conn.lock().await,
+            idempotency_key,
+            leader_slot,
+            landed_slot,
+            latency,
+        )
+    }
+
+    pub async fn get_copy_order(&self, idempotency_key: &str) -> Result<Option<CopyOrder>> {
+        CopyOrder::get(&self.conn.lock().await, idempotency_key)
+    }
+
+    /// Stores an open position, replacing the previous state of its mint
+    pub async fn upsert_position(&self, position: &PositionRecord) -> Result<()> {
+        PositionRecord::upsert(&self.conn.lock().await, position)
+    }
+
+    pub async fn delete_position(&self, mint: &str) -> Result<()> {
+        PositionRecord::delete(&self.conn.lock().await, mint)
+    }
+
+    pub async fn get_positions(&self) -> Result<Vec<PositionRecord>> {
+        PositionRecord::all(&self.conn.lock().await)
+    }
+
+    /// Books a fill once, returns false when it was already booked
+    pub async fn add_fill(&self, fill: &Fill) -> Result<bool> {
+        Fill::insert(&self.conn.lock().await, fill)
+    }
+
+    pub async fn get_fills(&self) -> Result<Vec<Fill>> {
+        Fill::all(&self.conn.lock().await)
+    }
+
+    /// Stores a new scheduled order, returns its id
+    pub async fn add_scheduled_order(&self, order: &ScheduledOrderRecord) -> Result<i64> {
+        ScheduledOrderRecord::insert(&self.conn.lock().await, order)
+    }
+
+    /// Stores the progress of a scheduled order
+    pub async fn update_scheduled_order(&self, order: &ScheduledOrderRecord) -> Result<()> {
+        ScheduledOrderRecord::update(&self.conn.lock().await, order)
+    }
+
+    /// Returns the scheduled orders of a status, or all of them, oldest first
+    pub async fn get_scheduled_orders(
+        &self,
+        status: Option<&str>,
+    ) -> Result<Vec<ScheduledOrderRecord>> {
+        ScheduledOrderRecord::all(&self.conn.lock().await, status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(key: &str) -> CopyOrder {
+        CopyOrder {
+            idempotency_key: key.to_string(),
+            leader: "leader".to_string(),
+            source_signature: "source".to_string(),
+            mint: "mint".to_string(),
+            side: "Buy".to_string(),
+            status: CopyOrderStatus::Pending,
+            signatures: String::new(),
+            error: String::new(),
+            created_at: 1_700_000_000,
+            leader_slot: Some(300_000_000),
+            landed_slot: None,
+            latency: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_claim_copy_order() {
+        let db = Database::new(Path::new(":memory:")).await.unwrap();
+        let key = CopyOrder::key("leader", "source");
+
+        assert!(db.claim_copy_order(&order(&key)).await.unwrap());
+        // The same signal never gets a second order
+        assert!(!db.claim_copy_order(&order(&key)).await.unwrap());
+        assert!(db.claim_copy_order(&order("other")).await.unwrap());
+
+        db.set_copy_order_status(&key, CopyOrderStatus::Sent, "sig_a,sig_b", "")
+            .await
+            .unwrap();
+        let stored = db.get_copy_order(&key).await.unwrap().unwrap();
+        assert_eq!(stored.status, CopyOrderStatus::Sent);
+        assert_eq!(stored.signatures, "sig_a,sig_b");
+
+        db.set_copy_order_latency(&key, Some(300_000_000), Some(300_000_002), "{}")
+            .await
+            .unwrap();
+        let stored = db.get_copy_order(&key).await.unwrap().unwrap();
+        assert_eq!(stored.landed_slot, Some(300_000_002));
+        assert_eq!(stored.latency, "{}");
+        assert_eq!(db.get_copy_order("missing").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_positions() {
+        let db = Database::new(Path::new(":memory:")).await.unwrap();
+        let mut position = PositionRecord {
+            mint: "mint".to_string(),
+            venue: "PumpFun".to_string(),
+            pool_id: "curve".to_string(),
+            size: 1_000,
+            cost_basis: 50_000_000,
+            peak_price: 50_000.0,
+            opened_at: 1_700_000_000,
+        };
+
+        db.upsert_position(&position).await.unwrap();
+        position.size = 400;
+        db.upsert_position(&position).await.unwrap();
+        assert_eq!(db.get_positions().await.unwrap(), vec![position]);
+
+        db.delete_position("mint").await.unwrap();
+        assert!(db.get_positions().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fills() {
+        let db = Database::new(Path::new(":memory:")).await.unwrap();
+        let fill = |signature: &str, slot: u64| Fill {
+            signature: signature.to_string(),
+            mint: "mint".to_string(),
+            side: "buy".to_string(),
+            tokens: 1_000,
+            sol_amount: 10_000_000,
+            slot,
+            ..Fill::default()
+        };
+
+        assert!(db.add_fill(&fill("second", 20)).await.unwrap());
+        assert!(db.add_fill(&fill("first", 10)).await.unwrap());
+        // Booked once
+        assert!(!db.add_fill(&fill("first", 10)).await.unwrap());
+        assert_eq!(
+            db.get_fills().await.unwrap(),
+            vec![fill("first", 10), fill("second", 20)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_orders() {
+        let db = Database::new(Path::new(":memory:")).await.unwrap();
+        let order = ScheduledOrderRecord {
+            mint: "mint".to_string(),
+            side: "buy".to_string(),
+            kind: "twap".to_string(),
+            interval_secs: 60,
+            slices: 5,
+            total: 1_000_000_000,
+            next_at: 1_700_000_000,
+            slippage_percent: 10,
+            status: "active".to_string(),
+            created_at: 1_700_000_000,
+            ..ScheduledOrderRecord::default()
+        };
+
+        let id = db.add_scheduled_order(&order).await.unwrap();
+        let other = db.add_scheduled_order(&order).await.unwrap();
+        assert_ne!(id, other);
+
+        let mut progressed = ScheduledOrderRecord {
+            id,
+            ..order.clone()
+        };
+        progressed.filled = 200_000_000;
+        progressed.children = 1;
+        progressed.next_at = 1_700_000_060;
+        progressed.signatures = "sig_a".to_string();
+        progressed.in_flight_amount = 200_000_000;
+        progressed.in_flight_at = 1_700_000_030;
+        progressed.status = "done".to_string();
+        db.update_scheduled_order(&progressed).await.unwrap();
+
+        assert_eq!(
+            db.get_scheduled_orders(Some("done")).await.unwrap(),
+            vec![progressed.clone()]
+        );
+        let all = db.get_scheduled_orders(None).await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0], progressed);
+        assert_eq!(all[1], ScheduledOrderRecord { id: other, ..order });
+    }
 }
@@ -0,0 +1,94 @@
+use rusqlite::{params, Connection, Result};
+use tokio::sync::MutexGuard;
+
+/// An open position as stored, one per mint
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionRecord {
+    pub mint: String,
+    /// The venue the position is priced and exited on
+    pub venue: String,
+    pub pool_id: String,
+    /// Raw tokens held
+    pub size: u64,
+    /// Lamports spent on the tokens held
+    pub cost_basis: u64,
+    /// Highest price seen since the entry, in lamports per raw token
+    pub peak_price: f64,
+    /// Unix timestamp of the first buy
+    pub opened_at: u64,
+}
+
+impl PositionRecord {
+    pub const TABLE_NAME: &'static str = "positions";
+
+    pub(crate) fn init_table(conn: &MutexGuard<'_, Connection>) -> Result<()> {
+        conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                mint TEXT PRIMARY KEY,
+                venue TEXT NOT NULL,
+                pool_id TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                cost_basis INTEGER NOT NULL,
+                peak_price FLOAT NOT NULL,
+                opened_at INTEGER NOT NULL
+            )",
+                Self::TABLE_NAME
+            ),
+            [],
+        )?;
+        Ok(())
+    }
+
+    pub(crate) fn upsert(conn: &MutexGuard<'_, Connection>, position: &Self) -> Result<()> {
+        conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO {} (
+                mint, venue, pool_id, size, cost_basis, peak_price, opened_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                Self::TABLE_NAME
+            ),
+            params![
+                position.mint,
+                position.venue,
+                position.pool_id,
+                position.size,
+                position.cost_basis,
+                position.peak_price,
+                position.opened_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub(crate) fn delete(conn: &MutexGuard<'_, Connection>, mint: &str) -> Result<()> {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE mint = ?1", Self::TABLE_NAME),
+            params![mint],
+        )?;
+        Ok(())
+    }
+
+    pub(crate) fn all(conn: &MutexGuard<'_, Connection>) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT mint, venue, pool_id, size, cost_basis, peak_price, opened_at FROM {}",
+            Self::TABLE_NAME
+        ))?;
+
+        let positions = stmt
+            .query_map([], |row| {
+                Ok(PositionRecord {
+                    mint: row.get(0)?,
+                    venue: row.get(1)?,
+                    pool_id: row.get(2)?,
+                    size: row.get(3)?,
+                    cost_basis: row.get(4)?,
+                    peak_price: row.get(5)?,
+                    opened_at: row.get(6)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(positions)
+    }
+}
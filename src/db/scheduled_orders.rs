@@ -0,0 +1,212 @@
+use rusqlite::{params, Connection, Result};
+use tokio::sync::MutexGuard;
+
+/// A parent order as stored, split into child swaps over time
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ScheduledOrderRecord {
+    /// Assigned on insert
+    pub id: i64,
+    pub mint: String,
+    /// "buy" or "sell"
+    pub side: String,
+    /// "twap", "dca" or "scale_out"
+    pub kind: String,
+    /// Seconds between two DCA children, the whole duration for a TWAP
+    pub interval_secs: u64,
+    /// Children the total is split in, 0 for scale outs
+    pub slices: u64,
+    /// Rise above the reference price triggering a scale out sell, in percent
+    pub trigger_percent: f64,
+    /// Part of the total sold on each scale out sell, in percent
+    pub sell_percent: f64,
+    /// Lamports spent on a buy, raw tokens sold on a sell
+    pub total: u64,
+    /// Part of the total sent so far
+    pub filled: u64,
+    /// Children sent so far
+    pub children: u64,
+    /// Unix timestamp the next child is due at
+    pub next_at: u64,
+    /// Price of the last scale out sell, in lamports per raw token
+    pub reference_price: f64,
+    pub slippage_percent: u64,
+    /// "active", "done", "cancelled" or "failed"
+    pub status: String,
+    /// Children failing in a row
+    pub failures: u64,
+    /// Why the last child failed, empty otherwise
+    pub error: String,
+    /// Signatures of the children sent, comma separated
+    pub signatures: String,
+    pub created_at: u64,
+    /// Input of the child being sent, 0 when none is
+    pub in_flight_amount: u64,
+    /// Unix timestamp the child in flight was sent at
+    pub in_flight_at: u64,
+}
+
+impl ScheduledOrderRecord {
+    pub const TABLE_NAME: &'static str = "scheduled_orders";
+
+    pub(crate) fn init_table(conn: &MutexGuard<'_, Connection>) -> Result<()> {
+        conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                mint TEXT NOT NULL,
+                side TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                interval_secs INTEGER NOT NULL,
+                slices INTEGER NOT NULL,
+                trigger_percent FLOAT NOT NULL,
+                sell_percent FLOAT NOT NULL,
+                total INTEGER NOT NULL,
+                filled INTEGER NOT NULL,
+                children INTEGER NOT NULL,
+                next_at INTEGER NOT NULL,
+                reference_price FLOAT NOT NULL,
+                slippage_percent INTEGER NOT NULL,
+                status TEXT NOT NULL,
+                failures INTEGER NOT NULL,
+                error TEXT NOT NULL,
+                signatures TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                in_flight_amount INTEGER NOT NULL DEFAULT 0,
+                in_flight_at INTEGER NOT NULL DEFAULT 0
+            )",
+                Self::TABLE_NAME
+            ),
+            [],
+        )?;
+
+        // Tables created before the child in flight columns
+        for column in ["in_flight_amount", "in_flight_at"] {
+            let exists = conn
+                .prepare(&format!(
+                    "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1",
+                    Self::TABLE_NAME
+                ))?
+                .exists(params![column])?;
+            if !exists {
+                conn.execute(
+                    &format!(
+                        "ALTER TABLE {} ADD COLUMN {column} INTEGER NOT NULL DEFAULT 0",
+                        Self::TABLE_NAME
+                    ),
+                    [],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Inserts a new order, returns its id
+    pub(crate) fn insert(conn: &MutexGuard<'_, Connection>, order: &Self) -> Result<i64> {
+        conn.execute(
+            &format!(
+                "INSERT INTO {} (
+                mint, side, kind, interval_secs, slices, trigger_percent, sell_percent, total,
+                filled, children, next_at, reference_price, slippage_percent, status, failures,
+                error, signatures, created_at, in_flight_amount, in_flight_at
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18,
+                ?19, ?20
+            )",
+                Self::TABLE_NAME
+            ),
+            params![
+                order.mint,
+                order.side,
+                order.kind,
+                order.interval_secs,
+                order.slices,
+                order.trigger_percent,
+                order.sell_percent,
+                order.total,
+                order.filled,
+                order.children,
+                order.next_at,
+                order.reference_price,
+                order.slippage_percent,
+                order.status,
+                order.failures,
+                order.error,
+                order.signatures,
+                order.created_at,
+                order.in_flight_amount,
+                order.in_flight_at
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Stores the progress of an order
+    pub(crate) fn update(conn: &MutexGuard<'_, Connection>, order: &Self) -> Result<()> {
+        conn.execute(
+            &format!(
+                "UPDATE {} SET filled = ?2, children = ?3, next_at = ?4, reference_price = ?5,
+                status = ?6, failures = ?7, error = ?8, signatures = ?9, in_flight_amount = ?10,
+                in_flight_at = ?11 WHERE id = ?1",
+                Self::TABLE_NAME
+            ),
+            params![
+                order.id,
+                order.filled,
+                order.children,
+                order.next_at,
+                order.reference_price,
+                order.status,
+                order.failures,
+                order.error,
+                order.signatures,
+                order.in_flight_amount,
+                order.in_flight_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the orders of a status, or all of them, oldest first
+    pub(crate) fn all(
+        conn: &MutexGuard<'_, Connection>,
+        status: Option<&str>,
+    ) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, mint, side, kind, interval_secs, slices, trigger_percent, sell_percent,
+                total, filled, children, next_at, reference_price, slippage_percent, status,
+                failures, error, signatures, created_at, in_flight_amount, in_flight_at
+            FROM {} WHERE ?1 IS NULL OR status = ?1 ORDER BY id",
+            Self::TABLE_NAME
+        ))?;
+
+        let orders = stmt
+            .query_map(params![status], |row| {
+                Ok(ScheduledOrderRecord {
+                    id: row.get(0)?,
+                    mint: row.get(1)?,
+                    side: row.get(2)?,
+                    kind: row.get(3)?,
+                    interval_secs: row.get(4)?,
+                    slices: row.get(5)?,
+                    trigger_percent: row.get(6)?,
+                    sell_percent: row.get(7)?,
+                    total: row.get(8)?,
+                    filled: row.get(9)?,
+                    children: row.get(10)?,
+                    next_at: row.get(11)?,
+                    reference_price: row.get(12)?,
+                    slippage_percent: row.get(13)?,
+                    status: row.get(14)?,
+                    failures: row.get(15)?,
+                    error: row.get(16)?,
+                    signatures: row.get(17)?,
+                    created_at: row.get(18)?,
+                    in_flight_amount: row.get(19)?,
+                    in_flight_at: row.get(20)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(orders)
+    }
+}
@@ -1,7 +1,128 @@
+use solana_sdk::pubkey::Pubkey;
 use thiserror::Error;
 
+use crate::bot::{Launchpad, TradeSide, Venue};
+
 #[derive(Error, Debug)]
 pub enum BotError {
     #[error("An instruction's data contents was invalid")]
     InvalidInstructionData,
 }
+
+#[derive(Error, Debug, PartialEq)]
+pub enum AccountDecodeError {
+    #[error("The account is owned by {0}, not by the expected program")]
+    InvalidOwner(Pubkey),
+    #[error("The account data length is {actual}, expected {expected}")]
+    InvalidLength { expected: usize, actual: usize },
+    #[error("The account discriminator does not match the expected account type")]
+    InvalidDiscriminator,
+    #[error("The account padding does not match the expected account type")]
+    InvalidPadding,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TokenFilterError {
+    #[error("The pool does not pair exactly one quote asset")]
+    NoQuoteSide,
+    #[error("The mint {0} is blocklisted")]
+    BlockedMint(Pubkey),
+    #[error("The mint {0} does not end with an allowed suffix")]
+    SuffixNotAllowed(Pubkey),
+    #[error("The creator {0} is not allowed")]
+    CreatorNotAllowed(Pubkey),
+    #[error("The token creator is unknown")]
+    UnknownCreator,
+    #[error("The token launched on {0}, which is not allowed")]
+    LaunchpadNotAllowed(Launchpad),
+    #[error("The quote liquidity is {actual}, below the {min} minimum")]
+    LowLiquidity { min: u64, actual: u64 },
+    #[error("The pool is {age}s old, younger than the {min}s minimum")]
+    PoolTooYoung { min: u64, age: u64 },
+    #[error("The pool is {age}s old, older than the {max}s maximum")]
+    PoolTooOld { max: u64, age: u64 },
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SignalFilterError {
+    #[error("The leader spent {actual} lamports, below the {min} minimum")]
+    LeaderTradeTooSmall { min: u64, actual: u64 },
+    #[error("The leader spent {actual} lamports, above the {max} maximum")]
+    LeaderTradeTooLarge { max: u64, actual: u64 },
+    #[error("The token is {age}s old, younger than the {min}s minimum")]
+    TokenTooYoung { min: u64, age: u64 },
+    #[error("The token is {age}s old, older than the {max}s maximum")]
+    TokenTooOld { max: u64, age: u64 },
+    #[error("The bonding curve is {actual:.1}% sold, below the {min}% minimum")]
+    CurveProgressTooLow { min: f64, actual: f64 },
+    #[error("The bonding curve is {actual:.1}% sold, above the {max}% maximum")]
+    CurveProgressTooHigh { max: f64, actual: f64 },
+    #[error("The pool holds {actual} lamports, below the {min} minimum")]
+    LowLiquidity { min: u64, actual: u64 },
+    #[error("The market cap is {actual} lamports, below the {min} minimum")]
+    MarketCapTooLow { min: u64, actual: u64 },
+    #[error("The market cap is {actual} lamports, above the {max} maximum")]
+    MarketCapTooHigh { max: u64, actual: u64 },
+    #[error("The mint {mint} was bought {elapsed}s ago, within the {cooldown}s cooldown")]
+    Cooldown {
+        mint: Pubkey,
+        elapsed: u64,
+        cooldown: u64,
+    },
+    #[error("The {0} is unknown")]
+    Unknown(&'static str),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RiskError {
+    #[error("New buys are halted: {0}")]
+    Halted(String),
+    #[error("{open} positions are open, the maximum is {max}")]
+    TooManyPositions { max: usize, open: usize },
+    #[error("The exposure would be {exposure} lamports, above the {max} maximum")]
+    MaxExposure { max: u64, exposure: u64 },
+    #[error("The exposure on {mint} would be {exposure} lamports, above the {max} maximum")]
+    MaxMintExposure {
+        mint: Pubkey,
+        max: u64,
+        exposure: u64,
+    },
+    #[error("{loss} lamports were lost today, the cap is {max}")]
+    DailyLossCap { max: u64, loss: u64 },
+    #[error("The buy would leave {remaining} lamports, below the {min} reserve")]
+    LowReserve { min: u64, remaining: u64 },
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SafetyError {
+    #[error(
+        "The token {mint} scores {score} on the safety checks, above the {max} maximum: {findings}"
+    )]
+    TooRisky {
+        mint: Pubkey,
+        score: u32,
+        max: u32,
+        findings: String,
+    },
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PriceImpactError {
+    #[error("The {side} of {mint} on {venue} moves the price {impact_bps} bps, above the {max_bps} bps maximum")]
+    TooHigh {
+        venue: Venue,
+        side: TradeSide,
+        mint: Pubkey,
+        impact_bps: u64,
+        max_bps: u64,
+    },
+    #[error(
+        "The {side} of {mint} on {venue} could not be shrunk under {max_bps} bps of price impact"
+    )]
+    CannotShrink {
+        venue: Venue,
+        side: TradeSide,
+        mint: Pubkey,
+        max_bps: u64,
+    },
+}
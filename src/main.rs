@@ -5,20 +5,31 @@
 mod agentic_tools;
 mod bot;
 mod constants;
+mod copy_trading;
 mod db;
 mod errors;
 mod fast_websocket_client;
+mod trading;
 use anyhow::Result;
 use dotenv::dotenv;
 use helius::{types::Cluster, Helius};
 use rig_mongodb::{MongoDbVectorIndex, SearchParams};
-use std::{env, path::Path, sync::Arc};
+use solana_sdk::pubkey::Pubkey;
+use std::{env, path::Path, str::FromStr, sync::Arc};
 use tokio::sync::Mutex;
 
+use bot::{
+    JupiterClient, PriceImpactGuard, RaydiumPoolDiscovery, Router, TokenFilter, VenueContext,
+    WsolMode,
+};
 use db::{Database, PumpFunCoinAccounts};
 use fast_websocket_client::OpCode;
+use trading::{
+    ExitRules, OrderScheduler, PnlLedger, PositionManager, RiskEngine, RiskLimits, SafetyPolicy,
+    TokenSafety,
+};
 
-use agentic_tools::ToolPumpFunBuy;
+use agentic_tools::{ToolKillSwitch, ToolPumpFunBuy, ToolScheduledOrders, ToolTradeHistory};
 use rig::{
     cli_chatbot::cli_chatbot,
     providers::{
@@ -56,8 +67,17 @@ async fn main() -> Result<(), anyhow::Error> {
         .unwrap(),
     ));
 
+    // Venues the tools trade on, WSOL funded as set by wsol_mode
+    let venue_context = VenueContext {
+        helius,
+        token_filter: Arc::new(TokenFilter::from_env().map_err(anyhow::Error::from_boxed)?),
+        wsol_mode: WsolMode::from_env().map_err(anyhow::Error::from_boxed)?,
+        jupiter: JupiterClient::from_env(),
+        price_impact: PriceImpactGuard::from_env().map_err(anyhow::Error::from_boxed)?,
+    };
+
     // Initialize database
-    let db = Database::new(Path::new("src/db/meme_coins.db")).await?;
+    let db = Arc::new(Database::new(Path::new("src/db/meme_coins.db")).await?);
 
     // TODO: remove this
     // TODO: provision information through another process
@@ -80,12 +100,110 @@ async fn main() -> Result<(), anyhow::Error> {
         db.add_pump_fun_coin_accounts(&accounts).await?;
     }
 
+    // Raydium pools of the listed mints, discovered and persisted on every start,
+    // e.g. RAYDIUM_POOL_MINTS=<mint>,<mint>:<coin name>
+    if let Ok(mints) = env::var("RAYDIUM_POOL_MINTS") {
+        for entry in mints
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (mint, coin_name) = entry.split_once(':').unwrap_or((entry, entry));
+            let mint = Pubkey::from_str(mint.trim())?;
+            if let Err(e) = RaydiumPoolDiscovery::discover(
+                Arc::clone(&venue_context.helius),
+                &db,
+                &mint,
+                coin_name.trim(),
+            )
+            .await
+            {
+                eprintln!("Error discovering the Raydium pool of {mint}: {e:?}");
+            }
+        }
+    }
+
     // OpenAI client
     let openai_client =
         openai::Client::new(&env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY not set"));
 
+    // Fills read from the confirmed transactions, costs included
+    let pnl_ledger = Arc::new(
+        PnlLedger::new(venue_context.clone(), &singer_prv_key, Arc::clone(&db))
+            .map_err(anyhow::Error::from_boxed)?,
+    );
+
+    // Positions of the tool buys, sold on the exit rules
+    let position_manager = Arc::new(
+        PositionManager::new(
+            venue_context.clone(),
+            Arc::clone(&singer_prv_key),
+            ExitRules::from_env().map_err(anyhow::Error::from_boxed)?,
+            Arc::clone(&db),
+            pnl_ledger,
+        )
+        .map_err(anyhow::Error::from_boxed)?,
+    );
+    position_manager
+        .load()
+        .await
+        .map_err(anyhow::Error::from_boxed)?;
+    tokio::spawn(Arc::clone(&position_manager).run());
+
+    // Limits every buy, and halts them on the kill switch
+    let risk_engine = Arc::new(
+        RiskEngine::new(
+            venue_context.clone(),
+            &singer_prv_key,
+            RiskLimits::from_env().map_err(anyhow::Error::from_boxed)?,
+            Arc::clone(&position_manager),
+        )
+        .map_err(anyhow::Error::from_boxed)?,
+    );
+
+    // Blocks the buys of the tokens scoring too risky
+    let safety_policy = SafetyPolicy::from_env().map_err(anyhow::Error::from_boxed)?;
+    println!("Safety policy: {safety_policy:?}");
+    let token_safety = Arc::new(TokenSafety::new(venue_context.clone(), safety_policy));
+
+    let router = Arc::new(Router::from_env().map_err(anyhow::Error::from_boxed)?);
+
+    // TWAP, DCA and scale out orders, resumed after a restart
+    let order_scheduler = Arc::new(
+        OrderScheduler::new(
+            venue_context.clone(),
+            Arc::clone(&router),
+            Arc::clone(&singer_prv_key),
+            Arc::clone(&db),
+            Arc::clone(&risk_engine),
+            Arc::clone(&token_safety),
+        )
+        .map_err(anyhow::Error::from_boxed)?,
+    );
+    order_scheduler
+        .load()
+        .await
+        .map_err(anyhow::Error::from_boxed)?;
+    tokio::spawn(Arc::clone(&order_scheduler).run());
+
+    // Tool for the scheduled orders
+    let tool_scheduled_orders = ToolScheduledOrders::new(order_scheduler, Arc::clone(&db));
+
+    // Tool halting and resuming the buys
+    let tool_kill_switch = ToolKillSwitch::new(Arc::clone(&risk_engine));
+
     // Tool for buying meme coins
-    let tool_pump_fun_buy = ToolPumpFunBuy::new(helius, singer_prv_key, db.into());
+    let tool_pump_fun_buy = ToolPumpFunBuy::new(
+        venue_context,
+        router,
+        singer_prv_key,
+        db,
+        risk_engine,
+        token_safety,
+    );
+
+    // Tool for the trade history and the PnL
+    let tool_trade_history = ToolTradeHistory::new(position_manager);
 
     // Add the embeddings
     // Create the embedding model using OpenAI's text-embedding-ada-002
@@ -116,10 +234,13 @@ async fn main() -> Result<(), anyhow::Error> {
     // Create agent with a single context prompt and a single tool
     let agent = openai_client
         .agent(providers::openai::GPT_4O)
-        .preamble("You are a Pump.fun trading assistant. Help users buy meme coins safely by using the provided tool. Always warn users about the risks of trading meme coins.")
+        .preamble("You are a Pump.fun trading assistant. Help users buy meme coins safely and review their trades by using the provided tools. Always warn users about the risks of trading meme coins. Before buying, quote the buy and report its expected price impact to the user. For larger orders, offer to split them over time with a scheduled order.")
         .dynamic_context(10, index)
         .max_tokens(8192)
         .tool(tool_pump_fun_buy)
+        .tool(tool_trade_history)
+        .tool(tool_scheduled_orders)
+        .tool(tool_kill_switch)
         .build();
 
     println!(
@@ -15,6 +15,7 @@ use pdf_extract::extract_text;
 use std::{env, path::Path, sync::Arc};
 use tokio::sync::Mutex;
 
+use bot::{JupiterClient, PriceImpactGuard, Router, TokenFilter, VenueContext, WsolMode};
 use db::{Database, PumpFunCoinAccounts};
 use fast_websocket_client::OpCode;
 
@@ -58,6 +59,15 @@ async fn main() -> Result<(), anyhow::Error> {
         .unwrap(),
     ));
 
+    // Venues the tools trade on, WSOL funded per trade
+    let venue_context = VenueContext {
+        helius,
+        token_filter: Arc::new(TokenFilter::from_env().unwrap()),
+        wsol_mode: WsolMode::default(),
+        jupiter: JupiterClient::from_env(),
+        price_impact: PriceImpactGuard::from_env().unwrap(),
+    };
+
     // Initialize database
     let db = Database::new(Path::new("src/db/meme_coins.db")).await?;
 
@@ -87,7 +97,12 @@ async fn main() -> Result<(), anyhow::Error> {
         openai::Client::new(&env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY not set"));
 
     // Tool for buying meme coins
-    let tool_pump_fun_buy = ToolPumpFunBuy::new(helius, singer_prv_key, db.into());
+    let tool_pump_fun_buy = ToolPumpFunBuy::new(
+        venue_context,
+        Arc::new(Router::from_env().unwrap()),
+        singer_prv_key,
+        db.into(),
+    );
 
     // Add the embeddings
     // Create the embedding model using OpenAI's text-embedding-ada-002
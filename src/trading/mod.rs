@@ -0,0 +1,15 @@
+mod order_scheduler;
+mod pnl;
+mod position;
+mod position_manager;
+mod risk;
+mod safety;
+mod scheduled_order;
+
+pub use order_scheduler::*;
+pub use pnl::*;
+pub use position::*;
+pub use position_manager::*;
+pub use risk::*;
+pub use safety::*;
+pub use scheduled_order::*;
@@ -0,0 +1,415 @@
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use std::{collections::HashMap, env, error::Error, sync::Arc, sync::Mutex, time::Duration};
+
+use crate::{
+    bot::{token_balance, Route, Router, TradeSide, VenueContext},
+    copy_trading::unix_now,
+    db::Database,
+};
+
+use super::{
+    OrderSchedule, RiskEngine, ScheduledOrder, ScheduledOrderStatus, TokenSafety,
+    SCHEDULED_ORDER_IN_FLIGHT_SECS,
+};
+
+/// Sends the children of the scheduled orders when they are due, on the
+/// best route.
+///
+/// Buy children go through the safety checks and the risk engine like any
+/// other buy, and every child is booked in the PnL ledger and the positions.
+/// The orders are stored in the database before and after every child and
+/// reloaded on restart, a TWAP or DCA resuming where it stopped. A child
+/// still in flight when the process stopped is reconciled against the
+/// wallet fills before anything is sent again.
+pub struct OrderScheduler {
+    ctx: VenueContext,
+    router: Arc<Router>,
+    signer_prv_key: Arc<String>,
+    wallet: Pubkey,
+    db: Arc<Database>,
+    risk: Arc<RiskEngine>,
+    safety: Arc<TokenSafety>,
+    /// Time between two checks of the orders due
+    check_interval: Duration,
+    /// The active orders, keyed on their id
+    orders: Mutex<HashMap<i64, ScheduledOrder>>,
+}
+
+impl OrderScheduler {
+    /// * `scheduled_order_check_interval_ms` - Milliseconds between two checks of the orders due, 5000 by default
+    pub fn new(
+        ctx: VenueContext,
+        router: Arc<Router>,
+        signer_prv_key: Arc<String>,
+        db: Arc<Database>,
+        risk: Arc<RiskEngine>,
+        safety: Arc<TokenSafety>,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let wallet = Keypair::from_base58_string(signer_prv_key.as_str()).try_pubkey()?;
+        let check_interval = match env::var("scheduled_order_check_interval_ms") {
+            Ok(ms) => Duration::from_millis(ms.parse()?),
+            Err(_) => Duration::from_millis(5_000),
+        };
+
+        Ok(Self {
+            ctx,
+            router,
+            signer_prv_key,
+            wallet,
+            db,
+            risk,
+            safety,
+            check_interval,
+            orders: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Reloads the active orders stored and reconciles their children in
+    /// flight, returns how many orders there are
+    pub async fn load(&self) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let records = self
+            .db
+            .get_scheduled_orders(Some(ScheduledOrderStatus::Active.as_str()))
+            .await?;
+        let mut in_flight = vec![];
+        {
+            let mut orders = self.orders.lock().map_err(|e| e.to_string())?;
+            for record in &records {
+                let order = ScheduledOrder::from_record(record)?;
+                if order.in_flight_amount > 0 {
+                    in_flight.push(order.clone());
+                }
+                orders.insert(order.id, order);
+            }
+        }
+
+        for order in in_flight {
+            let id = order.id;
+            if let Err(e) = self.reconcile(order).await {
+                eprintln!("Error reconciling scheduled order #{id}: {e:?}");
+            }
+        }
+        Ok(self.orders.lock().map_err(|e| e.to_string())?.len())
+    }
+
+    /// Returns the raw token balance of the wallet
+    ///
+    /// * `mint` - The token held
+    pub async fn held(&self, mint: &Pubkey) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        token_balance(&self.ctx, &self.wallet, mint).await
+    }
+
+    /// Stores a new order, its first child being sent on the next check
+    ///
+    /// * `mint` - The token traded against SOL
+    /// * `side` - The swap direction
+    /// * `schedule` - How the total is split
+    /// * `total` - Lamports spent on a buy, raw tokens sold on a sell
+    /// * `slippage_percent` - The slippage tolerated on each child
+    pub async fn create(
+        &self,
+        mint: Pubkey,
+        side: TradeSide,
+        schedule: OrderSchedule,
+        total: u64,
+        slippage_percent: u64,
+    ) -> Result<ScheduledOrder, Box<dyn Error + Send + Sync>> {
+        let mut order =
+            ScheduledOrder::new(mint, side, schedule, total, slippage_percent, unix_now())?;
+        order.id = self.db.add_scheduled_order(&order.to_record()).await?;
+
+        self.orders
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(order.id, order.clone());
+        println!("Scheduled order {order}");
+        Ok(order)
+    }
+
+    /// Cancels an active order, its children already sent being kept.
+    /// None when no active order has this id.
+    ///
+    /// * `id` - The order id
+    pub async fn cancel(
+        &self,
+        id: i64,
+    ) -> Result<Option<ScheduledOrder>, Box<dyn Error + Send + Sync>> {
+        let Some(mut order) = self.orders.lock().map_err(|e| e.to_string())?.remove(&id) else {
+            return Ok(None);
+        };
+        order.status = ScheduledOrderStatus::Cancelled;
+
+        self.db.update_scheduled_order(&order.to_record()).await?;
+        println!("Cancelled scheduled order {order}");
+        Ok(Some(order))
+    }
+
+    /// Returns every order stored, the finished ones included, oldest first
+    pub async fn orders(&self) -> Result<Vec<ScheduledOrder>, Box<dyn Error + Send + Sync>> {
+        self.db
+            .get_scheduled_orders(None)
+            .await?
+            .iter()
+            .map(ScheduledOrder::from_record)
+            .collect()
+    }
+
+    /// Sends the children of the active orders as they come due, forever
+    pub async fn run(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(self.check_interval);
+        loop {
+            interval.tick().await;
+
+            let mut ids: Vec<i64> = self
+                .orders
+                .lock()
+                .map(|orders| orders.keys().copied().collect())
+                .unwrap_or_default();
+            ids.sort_unstable();
+            for id in ids {
+                if let Err(e) = self.check(id).await {
+                    eprintln!("Error checking scheduled order #{id}: {e:?}");
+                }
+            }
+        }
+    }
+
+    /// Sends the child of an order if one is due. Children failing are
+    /// retried on the next interval, or the next check for a scale out.
+    ///
+    /// * `id` - The order id
+    pub async fn check(&self, id: i64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(mut order) = self
+            .orders
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get(&id)
+            .cloned()
+        else {
+            return Ok(());
+        };
+        if order.in_flight_amount > 0 {
+            return self.reconcile(order).await;
+        }
+
+        // Scale outs are priced on a sell of their next child
+        let mut price = None;
+        if let OrderSchedule::ScaleOut { .. } = order.schedule {
+            let route = self
+                .router
+                .route(
+                    &self.ctx,
+                    &order.mint,
+                    TradeSide::Sell,
+                    order.child_amount(),
+                )
+                .await?;
+            let current = route.amount_out() as f64 / route.amount_in as f64;
+            if order.reference_price <= 0.0 {
+                order.reference_price = current;
+                println!(
+                    "Scheduled order #{id} starts at {current:.4} lamports/token, {}",
+                    order.schedule
+                );
+                return self.store(order).await;
+            }
+            price = Some(current);
+        }
+
+        let now = unix_now();
+        let Some(amount) = order.due(now, price) else {
+            return Ok(());
+        };
+
+        // Stored first, a restart during the send reconciles the child
+        order.start_child(amount, now);
+        self.db.update_scheduled_order(&order.to_record()).await?;
+
+        match self.send_child(&order, amount).await {
+            Ok((route, signatures)) => {
+                let price = match order.side {
+                    TradeSide::Buy => route.amount_in as f64 / route.amount_out().max(1) as f64,
+                    TradeSide::Sell => route.amount_out() as f64 / route.amount_in.max(1) as f64,
+                };
+                order.record_child(route.amount_in, &signatures, price, now);
+                println!("Scheduled order {order}");
+            }
+            Err(e) => {
+                eprintln!("Error sending a child of scheduled order #{id}: {e}");
+                order.record_failure(&e.to_string(), now);
+            }
+        }
+        self.store(order).await
+    }
+
+    /// Sends a child on the best route and books it, returns the route and
+    /// its transactions
+    ///
+    /// * `order` - The parent order
+    /// * `amount` - Lamports spent on a buy, raw tokens sold on a sell
+    async fn send_child(
+        &self,
+        order: &ScheduledOrder,
+        amount: u64,
+    ) -> Result<(Route, Vec<String>), Box<dyn Error + Send + Sync>> {
+        let mint = order.mint;
+        let strategy = order.strategy();
+
+        match order.side {
+            TradeSide::Buy => {
+                self.safety.check_buy(&mint).await??;
+                // Kept until the position of the child is recorded
+                let _permit = self.risk.approve_buy(&mint, amount).await??;
+
+                let result = self
+                    .router
+                    .buy(
+                        &self.ctx,
+                        Arc::clone(&self.signer_prv_key),
+                        &mint,
+                        amount,
+                        order.slippage_percent,
+                    )
+                    .await;
+                self.risk.record_send(result.is_ok());
+                let (route, signatures) = result?;
+
+                let positions = self.risk.positions();
+                let (tokens, net_lamports) = positions
+                    .ledger()
+                    .book_all(&signatures, &mint, None, &strategy)
+                    .await;
+                let lamports = if tokens > 0 {
+                    net_lamports.unsigned_abs()
+                } else {
+                    route.amount_in
+                };
+                if let Err(e) = positions.record_route_buy(&route, lamports).await {
+                    eprintln!("Error recording the position of {mint}: {e:?}");
+                }
+                Ok((route, signatures))
+            }
+            TradeSide::Sell => {
+                // The tokens may have been sold meanwhile, by an exit rule
+                let amount = amount.min(self.held(&mint).await?);
+                if amount == 0 {
+                    return Err(format!("No tokens of {mint} held").into());
+                }
+
+                let (route, signatures) = self
+                    .router
+                    .sell(
+                        &self.ctx,
+                        Arc::clone(&self.signer_prv_key),
+                        &mint,
+                        amount,
+                        order.slippage_percent,
+                    )
+                    .await?;
+
+                let positions = self.risk.positions();
+                positions
+                    .ledger()
+                    .book_all(&signatures, &mint, None, &strategy)
+                    .await;
+                let exit_price = route.amount_out() as f64 / route.amount_in as f64;
+                positions.sync(&mint, Some(exit_price)).await?;
+                Ok((route, signatures))
+            }
+        }
+    }
+
+    /// Settles the child an order had in flight when the process stopped.
+    /// The wallet transactions sent since are booked, and their fills
+    /// record the child. Without fills, the child is waited for until its
+    /// transactions can no longer land, then failed and sent again when due.
+    ///
+    /// * `order` - The order with a child in flight
+    async fn reconcile(
+        &self,
+        mut order: ScheduledOrder,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let positions = self.risk.positions();
+        let fills: Vec<_> = positions
+            .ledger()
+            .book_since(&order.mint, order.in_flight_at, &order.strategy())
+            .await?
+            .into_iter()
+            .filter(|fill| !order.signatures.contains(&fill.signature))
+            .collect();
+        let now = unix_now();
+
+        if fills.is_empty() {
+            if now < order.in_flight_at + SCHEDULED_ORDER_IN_FLIGHT_SECS {
+                return Ok(());
+            }
+            println!(
+                "Scheduled order #{} child of {} never landed",
+                order.id, order.in_flight_amount
+            );
+            order.record_failure("The child in flight never landed", now);
+            return self.store(order).await;
+        }
+
+        let tokens: u64 = fills.iter().map(|fill| fill.tokens).sum();
+        let lamports: u64 = fills.iter().map(|fill| fill.sol_amount).sum();
+        let signatures: Vec<String> = fills.iter().map(|fill| fill.signature.clone()).collect();
+        let price = lamports as f64 / tokens.max(1) as f64;
+        let amount_in = match order.side {
+            TradeSide::Buy => lamports,
+            TradeSide::Sell => tokens,
+        };
+
+        match order.side {
+            // Priced and sold where a sell of the tokens routes best
+            TradeSide::Buy => {
+                let route = self
+                    .router
+                    .route(&self.ctx, &order.mint, TradeSide::Sell, tokens)
+                    .await;
+                let recorded = match route.as_ref().map(|route| route.legs.first()) {
+                    Ok(Some(leg)) => {
+                        positions
+                            .record_buy(leg.venue, leg.pool_id, order.mint, lamports)
+                            .await
+                    }
+                    Ok(None) => Err("The route has no leg".into()),
+                    Err(e) => Err(e.to_string().into()),
+                };
+                if let Err(e) = recorded {
+                    eprintln!("Error recording the position of {}: {e:?}", order.mint);
+                }
+            }
+            TradeSide::Sell => positions.sync(&order.mint, Some(price)).await?,
+        }
+
+        order.record_child(amount_in, &signatures, price, now);
+        println!("Scheduled order child in flight landed, {order}");
+        self.store(order).await
+    }
+
+    /// Stores the progress of an order, dropping it from the active ones
+    /// once it is finished
+    ///
+    /// * `order` - The order checked
+    async fn store(&self, mut order: ScheduledOrder) -> Result<(), Box<dyn Error + Send + Sync>> {
+        {
+            let mut orders = self.orders.lock().map_err(|e| e.to_string())?;
+            // Cancelled while its child was being sent
+            if !orders.contains_key(&order.id) && order.status == ScheduledOrderStatus::Active {
+                order.status = ScheduledOrderStatus::Cancelled;
+            }
+            if order.status == ScheduledOrderStatus::Active {
+                orders.insert(order.id, order.clone());
+            } else {
+                orders.remove(&order.id);
+            }
+        }
+
+        self.db.update_scheduled_order(&order.to_record()).await?;
+        Ok(())
+    }
+}
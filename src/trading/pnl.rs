@@ -0,0 +1,676 @@
+use helius::jito::JITO_TIP_ACCOUNTS;
+use serde_json::{json, Value};
+use solana_client::{rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_request::RpcRequest};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt,
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    bot::VenueContext,
+    constants::{PUMP_FUN_FEE_RECIPIENT, WSOL_MINT},
+    db::{Database, Fill},
+};
+
+/// Transactions of the wallet read back when booking the fills since a time
+const FILL_LOOKBACK: usize = 100;
+
+/// Attempts at reading a confirmed transaction, RPC nodes may lag behind
+const FILL_FETCH_ATTEMPTS: usize = 5;
+const FILL_FETCH_DELAY: Duration = Duration::from_secs(1);
+
+/// Reads the fill of `mint` out of a confirmed transaction of `wallet`,
+/// None when the transaction did not move `mint` in or out of the wallet.
+///
+/// The amounts are the balance changes of the wallet, WSOL included, and
+/// the costs are read from the accounts they were paid to.
+///
+/// * `transaction` - The `getTransaction` result, JSON encoded
+/// * `wallet` - The wallet that sent the transaction
+/// * `mint` - The token swapped
+pub fn fill_from_transaction(transaction: &Value, wallet: &Pubkey, mint: &Pubkey) -> Option<Fill> {
+    let meta = &transaction["meta"];
+    if !meta["err"].is_null() {
+        return None;
+    }
+
+    // Loaded addresses follow the static keys in the balances
+    let message = &transaction["transaction"]["message"];
+    let keys: Vec<&str> = message["accountKeys"]
+        .as_array()?
+        .iter()
+        .chain(
+            meta["loadedAddresses"]["writable"]
+                .as_array()
+                .into_iter()
+                .flatten(),
+        )
+        .chain(
+            meta["loadedAddresses"]["readonly"]
+                .as_array()
+                .into_iter()
+                .flatten(),
+        )
+        .filter_map(|key| key.as_str())
+        .collect();
+    let pre_balances = meta["preBalances"].as_array()?;
+    let post_balances = meta["postBalances"].as_array()?;
+    let lamports_delta = |index: usize| -> i64 {
+        let pre = pre_balances
+            .get(index)
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        let post = post_balances
+            .get(index)
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        post as i64 - pre as i64
+    };
+
+    let wallet_str = wallet.to_string();
+    let wallet_index = keys.iter().position(|key| *key == wallet_str)?;
+
+    // Token balances of the wallet per account index: (mint, pre, post)
+    let mut token_balances: BTreeMap<usize, (String, u64, u64)> = BTreeMap::new();
+    for (field, is_post) in [("preTokenBalances", false), ("postTokenBalances", true)] {
+        for balance in meta[field].as_array().into_iter().flatten() {
+            if balance["owner"].as_str() != Some(wallet_str.as_str()) {
+                continue;
+            }
+            let (Some(index), Some(balance_mint)) =
+                (balance["accountIndex"].as_u64(), balance["mint"].as_str())
+            else {
+                continue;
+            };
+            let amount = balance["uiTokenAmount"]["amount"]
+                .as_str()
+                .and_then(|amount| amount.parse::<u64>().ok())
+                .unwrap_or_default();
+            let entry = token_balances
+                .entry(index as usize)
+                .or_insert_with(|| (balance_mint.to_string(), 0, 0));
+            if is_post {
+                entry.2 = amount;
+            } else {
+                entry.1 = amount;
+            }
+        }
+    }
+
+    let token_delta = |token_mint: &str| -> i64 {
+        token_balances
+            .values()
+            .filter(|(balance_mint, _, _)| balance_mint == token_mint)
+            .map(|(_, pre, post)| *post as i64 - *pre as i64)
+            .sum()
+    };
+    let tokens = token_delta(&mint.to_string());
+    if tokens == 0 {
+        return None;
+    }
+
+    // Token accounts of the wallet opened or closed by the transaction
+    let ata_rent: i64 = token_balances
+        .keys()
+        .filter_map(|index| {
+            let pre = pre_balances.get(*index)?.as_u64()?;
+            let post = post_balances.get(*index)?.as_u64()?;
+            match (pre, post) {
+                (0, post) if post > 0 => Some(post as i64),
+                (pre, 0) if pre > 0 => Some(-(pre as i64)),
+                _ => None,
+            }
+        })
+        .sum();
+
+    let received_by = |accounts: &[&str]| -> u64 {
+        keys.iter()
+            .enumerate()
+            .filter(|(_, key)| accounts.contains(*key))
+            .map(|(index, _)| lamports_delta(index).max(0) as u64)
+            .sum()
+    };
+    let jito_tip = received_by(&JITO_TIP_ACCOUNTS);
+    let pump_fun_fee = received_by(&[PUMP_FUN_FEE_RECIPIENT.to_string().as_str()]);
+    let network_fee = meta["fee"].as_u64().unwrap_or_default();
+
+    let mut fill = Fill {
+        signature: transaction["transaction"]["signatures"][0]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        mint: mint.to_string(),
+        side: if tokens > 0 { "buy" } else { "sell" }.to_string(),
+        tokens: tokens.unsigned_abs(),
+        sol_amount: 0,
+        pump_fun_fee,
+        network_fee,
+        jito_tip,
+        ata_rent,
+        slot: transaction["slot"].as_u64().unwrap_or_default(),
+        block_time: transaction["blockTime"].as_u64().unwrap_or_default(),
+        ..Fill::default()
+    };
+
+    // What the wallet paid or received, the costs taken out
+    let sol_delta = lamports_delta(wallet_index) + token_delta(&WSOL_MINT.to_string());
+    fill.sol_amount = if fill.is_buy() {
+        (-sol_delta - fill.costs()).max(0) as u64
+    } else {
+        (sol_delta + fill.costs()).max(0) as u64
+    };
+
+    Some(fill)
+}
+
+/// Realized and mark-to-market PnL in lamports, costs included
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PnlLine {
+    pub realized: i64,
+    /// Open tokens valued at their mark price, less their cost
+    pub unrealized: i64,
+    /// Fees, tips and rent paid
+    pub costs: i64,
+    pub fills: usize,
+    /// Raw tokens still held
+    pub open_tokens: u64,
+    /// Lamports spent on the tokens still held
+    pub open_cost: u64,
+    /// Whether tokens are held with no mark price
+    pub unmarked: bool,
+}
+
+impl PnlLine {
+    pub fn total(&self) -> i64 {
+        self.realized + self.unrealized
+    }
+}
+
+impl fmt::Display for PnlLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "realized {} | unrealized {}{} | costs {} | fills {} | open {} tokens for {}",
+            self.realized,
+            self.unrealized,
+            if self.unmarked { " (unmarked)" } else { "" },
+            self.costs,
+            self.fills,
+            self.open_tokens,
+            self.open_cost
+        )
+    }
+}
+
+/// Who the tokens of a lot were bought for
+type Attribution = (String, String);
+
+/// Tokens bought by a leader and strategy, at their average cost
+#[derive(Debug, Clone, Copy, Default)]
+struct Lot {
+    tokens: u64,
+    cost: u64,
+}
+
+/// PnL of the fills booked, in total and per mint, copied leader and
+/// strategy
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PnlReport {
+    pub total: PnlLine,
+    pub by_mint: BTreeMap<String, PnlLine>,
+    /// Keyed on the leader wallet, "none" for the fills that are not copies
+    pub by_leader: BTreeMap<String, PnlLine>,
+    pub by_strategy: BTreeMap<String, PnlLine>,
+}
+
+impl PnlReport {
+    /// Accounts fills at their average cost per mint.
+    ///
+    /// A sell realizes against the lots of every leader and strategy that
+    /// bought the mint, in proportion to the tokens of each lot, so the PnL
+    /// goes to what opened the position whatever closed it. Open tokens are
+    /// valued at their mark price.
+    ///
+    /// * `fills` - The fills, in the order they landed
+    /// * `marks` - Lamports per raw token, per mint
+    pub fn from_fills(fills: &[Fill], marks: &HashMap<String, f64>) -> Self {
+        let mut report = Self::default();
+        let mut books: BTreeMap<String, BTreeMap<Attribution, Lot>> = BTreeMap::new();
+
+        for fill in fills {
+            let attribution = attribution_of(fill);
+            report.apply(&fill.mint, &attribution, |line| {
+                line.costs += fill.costs();
+                line.fills += 1;
+            });
+
+            let lots = books.entry(fill.mint.clone()).or_default();
+            if fill.is_buy() {
+                let lot = lots.entry(attribution).or_default();
+                lot.tokens += fill.tokens;
+                lot.cost += fill.net_lamports().unsigned_abs();
+                continue;
+            }
+
+            let proceeds = fill.net_lamports();
+            let held: u64 = lots.values().map(|lot| lot.tokens).sum();
+            if held == 0 {
+                // Tokens we have no cost for, all proceeds
+                report.apply(&fill.mint, &attribution, |line| line.realized += proceeds);
+                continue;
+            }
+
+            let sold = fill.tokens.min(held);
+            for (lot_attribution, lot) in lots.iter_mut() {
+                if lot.tokens == 0 {
+                    continue;
+                }
+                let share = |value: i128| (value * lot.tokens as i128 / held as i128) as i64;
+                let lot_sold = share(sold as i128) as u64;
+                let lot_proceeds = share(proceeds as i128);
+                let lot_cost = (lot.cost as u128 * lot_sold as u128 / lot.tokens as u128) as u64;
+                lot.tokens -= lot_sold;
+                lot.cost -= lot_cost;
+
+                report.apply(&fill.mint, lot_attribution, |line| {
+                    line.realized += lot_proceeds - lot_cost as i64
+                });
+            }
+        }
+
+        for (mint, lots) in &books {
+            for (attribution, lot) in lots.iter().filter(|(_, lot)| lot.tokens > 0) {
+                let mark = marks.get(mint);
+                report.apply(mint, attribution, |line| {
+                    line.open_tokens += lot.tokens;
+                    line.open_cost += lot.cost;
+                    match mark {
+                        Some(mark) => {
+                            line.unrealized += (lot.tokens as f64 * mark) as i64 - lot.cost as i64
+                        }
+                        None => line.unmarked = true,
+                    }
+                });
+            }
+        }
+
+        report
+    }
+
+    /// Returns the lamports realized by the sells landed from `since`, at
+    /// the average costs of `from_fills`
+    ///
+    /// * `fills` - The fills, in the order they landed
+    /// * `since` - Unix timestamp the sells are counted from
+    pub fn realized_since(fills: &[Fill], since: u64) -> i64 {
+        let marks = HashMap::new();
+        let before: Vec<Fill> = fills
+            .iter()
+            .filter(|fill| fill.block_time < since)
+            .cloned()
+            .collect();
+        Self::from_fills(fills, &marks).total.realized
+            - Self::from_fills(&before, &marks).total.realized
+    }
+
+    /// Applies `update` to the total and to the lines of the mint, the
+    /// leader and the strategy
+    fn apply(&mut self, mint: &str, attribution: &Attribution, update: impl Fn(&mut PnlLine)) {
+        let (leader, strategy) = attribution;
+        update(&mut self.total);
+        update(self.by_mint.entry(mint.to_string()).or_default());
+        update(self.by_leader.entry(leader.clone()).or_default());
+        update(self.by_strategy.entry(strategy.clone()).or_default());
+    }
+}
+
+impl fmt::Display for PnlReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Total: {}", self.total)?;
+        for (title, lines) in [
+            ("Mint", &self.by_mint),
+            ("Leader", &self.by_leader),
+            ("Strategy", &self.by_strategy),
+        ] {
+            for (key, line) in lines {
+                writeln!(f, "{title} {key}: {line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn attribution_of(fill: &Fill) -> Attribution {
+    let leader = if fill.leader.is_empty() {
+        "none".to_string()
+    } else {
+        fill.leader.clone()
+    };
+    (leader, fill.strategy.clone())
+}
+
+/// Books the confirmed fills of our wallet and accounts their PnL
+pub struct PnlLedger {
+    ctx: VenueContext,
+    wallet: Pubkey,
+    db: Arc<Database>,
+}
+
+impl PnlLedger {
+    pub fn new(
+        ctx: VenueContext,
+        signer_prv_key: &str,
+        db: Arc<Database>,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let wallet = Keypair::from_base58_string(signer_prv_key).try_pubkey()?;
+        Ok(Self { ctx, wallet, db })
+    }
+
+    /// Reads the fill of `mint` out of a confirmed transaction and books it
+    /// once. None when the transaction did not swap `mint`.
+    ///
+    /// * `signature` - The confirmed transaction
+    /// * `mint` - The token swapped
+    /// * `leader` - The copied leader, None when the order is not a copy
+    /// * `strategy` - What sent the order, e.g. "copy"
+    pub async fn book(
+        &self,
+        signature: &str,
+        mint: &Pubkey,
+        leader: Option<&Pubkey>,
+        strategy: &str,
+    ) -> Result<Option<Fill>, Box<dyn Error + Send + Sync>> {
+        let transaction = self.fetch_transaction(signature).await?;
+        let Some(mut fill) = fill_from_transaction(&transaction, &self.wallet, mint) else {
+            return Ok(None);
+        };
+        fill.leader = leader.map(|leader| leader.to_string()).unwrap_or_default();
+        fill.strategy = strategy.to_string();
+
+        if self.db.add_fill(&fill).await? {
+            println!(
+                "Booked {} {} of {} for {} lamports, costs {}",
+                fill.side,
+                fill.tokens,
+                fill.mint,
+                fill.sol_amount,
+                fill.costs()
+            );
+        }
+        Ok(Some(fill))
+    }
+
+    /// Books every fill of `mint` in the transactions sent for an order,
+    /// returns (tokens, net lamports) summed over them
+    ///
+    /// * `signatures` - The transactions of the order
+    /// * `mint` - The token swapped
+    /// * `leader` - The copied leader, None when the order is not a copy
+    /// * `strategy` - What sent the order
+    pub async fn book_all(
+        &self,
+        signatures: &[String],
+        mint: &Pubkey,
+        leader: Option<&Pubkey>,
+        strategy: &str,
+    ) -> (u64, i64) {
+        let mut total = (0, 0);
+        for signature in signatures {
+            match self.book(signature, mint, leader, strategy).await {
+                Ok(Some(fill)) => {
+                    total.0 += fill.tokens;
+                    total.1 += fill.net_lamports();
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("Error booking {signature}: {e:?}"),
+            }
+        }
+        total
+    }
+
+    /// Books the transactions of the wallet landed from `since` that swapped
+    /// `mint` and were not booked yet, e.g. an order sent right before a
+    /// restart. Returns the fills of `mint` booked under `strategy` from
+    /// `since`, in the order they landed.
+    ///
+    /// * `mint` - The token swapped
+    /// * `since` - Unix timestamp the transactions are read from
+    /// * `strategy` - What sent the order
+    pub async fn book_since(
+        &self,
+        mint: &Pubkey,
+        since: u64,
+        strategy: &str,
+    ) -> Result<Vec<Fill>, Box<dyn Error + Send + Sync>> {
+        let statuses = {
+            let connection = self.ctx.helius.lock().await.async_connection()?;
+            connection
+                .get_signatures_for_address_with_config(
+                    &self.wallet,
+                    GetConfirmedSignaturesForAddress2Config {
+                        limit: Some(FILL_LOOKBACK),
+                        ..Default::default()
+                    },
+                )
+                .await?
+        };
+
+        // Newest first, booked oldest first
+        for status in statuses.iter().rev().filter(|status| {
+            status.err.is_none() && status.block_time.unwrap_or_default() >= since as i64
+        }) {
+            if let Err(e) = self.book(&status.signature, mint, None, strategy).await {
+                eprintln!("Error booking {}: {e:?}", status.signature);
+            }
+        }
+
+        let mint = mint.to_string();
+        Ok(self
+            .history()
+            .await?
+            .into_iter()
+            .filter(|fill| {
+                fill.mint == mint && fill.strategy == strategy && fill.block_time >= since
+            })
+            .collect())
+    }
+
+    /// Returns the fills booked, in the order they landed
+    pub async fn history(&self) -> Result<Vec<Fill>, Box<dyn Error + Send + Sync>> {
+        Ok(self.db.get_fills().await?)
+    }
+
+    /// Returns the lamports realized by the sells booked on the UTC day of
+    /// `now`, negative on a loss
+    ///
+    /// * `now` - The current unix timestamp
+    pub async fn realized_today(&self, now: u64) -> Result<i64, Box<dyn Error + Send + Sync>> {
+        let day_start = now / 86_400 * 86_400;
+        Ok(PnlReport::realized_since(&self.history().await?, day_start))
+    }
+
+    /// Returns the PnL of the fills booked
+    ///
+    /// * `marks` - Lamports per raw token, per mint, to value the open tokens
+    pub async fn report(
+        &self,
+        marks: &HashMap<String, f64>,
+    ) -> Result<PnlReport, Box<dyn Error + Send + Sync>> {
+        Ok(PnlReport::from_fills(&self.history().await?, marks))
+    }
+
+    async fn fetch_transaction(
+        &self,
+        signature: &str,
+    ) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let mut last_error = None;
+
+        for attempt in 0..FILL_FETCH_ATTEMPTS {
+            if attempt > 0 {
+                tokio::time::sleep(FILL_FETCH_DELAY).await;
+            }
+            let result = {
+                let connection = self.ctx.helius.lock().await.async_connection()?;
+                connection
+                    .send::<Value>(
+                        RpcRequest::GetTransaction,
+                        json!([signature, {
+                            "encoding": "json",
+                            "commitment": "confirmed",
+                            "maxSupportedTransactionVersion": 0,
+                        }]),
+                    )
+                    .await
+            };
+            match result {
+                Ok(transaction) if !transaction.is_null() => return Ok(transaction),
+                Ok(_) => last_error = Some(format!("{signature} is not confirmed yet")),
+                Err(e) => last_error = Some(e.to_string()),
+            }
+        }
+
+        Err(last_error.unwrap_or_default().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(side: &str, tokens: u64, sol_amount: u64, leader: &str, strategy: &str) -> Fill {
+        Fill {
+            mint: "mint".to_string(),
+            side: side.to_string(),
+            tokens,
+            sol_amount,
+            leader: leader.to_string(),
+            strategy: strategy.to_string(),
+            ..Fill::default()
+        }
+    }
+
+    #[test]
+    fn test_fill_from_transaction() {
+        let wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let ata = Pubkey::new_unique();
+        let curve = Pubkey::new_unique();
+        let tip = JITO_TIP_ACCOUNTS[0];
+
+        // A buy of 1_000_000 tokens for 0.1 SOL, opening the ATA
+        let transaction = json!({
+            "slot": 42,
+            "blockTime": 1_700_000_000,
+            "transaction": {
+                "signatures": ["sig"],
+                "message": {
+                    "accountKeys": [
+                        wallet.to_string(),
+                        ata.to_string(),
+                        curve.to_string(),
+                        PUMP_FUN_FEE_RECIPIENT.to_string(),
+                    ],
+                },
+            },
+            "meta": {
+                "err": null,
+                "fee": 15_000,
+                "preBalances": [1_000_000_000, 0, 5_000_000_000u64, 1, 1],
+                "postBalances": [
+                    1_000_000_000u64 - 100_000_000 - 1_000_000 - 15_000 - 10_000 - 2_039_280,
+                    2_039_280,
+                    5_100_000_000u64,
+                    1_000_001,
+                    10_001,
+                ],
+                "preTokenBalances": [],
+                "postTokenBalances": [{
+                    "accountIndex": 1,
+                    "mint": mint.to_string(),
+                    "owner": wallet.to_string(),
+                    "uiTokenAmount": { "amount": "1000000" },
+                }],
+                "loadedAddresses": { "writable": [tip], "readonly": [] },
+            },
+        });
+
+        let fill = fill_from_transaction(&transaction, &wallet, &mint).unwrap();
+        assert_eq!(
+            fill,
+            Fill {
+                signature: "sig".to_string(),
+                mint: mint.to_string(),
+                side: "buy".to_string(),
+                tokens: 1_000_000,
+                sol_amount: 100_000_000,
+                pump_fun_fee: 1_000_000,
+                network_fee: 15_000,
+                jito_tip: 10_000,
+                ata_rent: 2_039_280,
+                slot: 42,
+                block_time: 1_700_000_000,
+                ..Fill::default()
+            }
+        );
+        assert_eq!(fill.net_lamports(), -103_064_280);
+        assert_eq!(
+            fill_from_transaction(&transaction, &wallet, &Pubkey::new_unique()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let fills = [
+            fill("buy", 1_000, 1_000_000, "leader", "copy"),
+            fill("buy", 1_000, 3_000_000, "", "agent"),
+            fill("sell", 1_000, 2_500_000, "", "exit"),
+        ];
+        let marks = HashMap::from([("mint".to_string(), 2_000.0)]);
+
+        let report = PnlReport::from_fills(&fills, &marks);
+        let leader = report.by_leader["leader"];
+        assert_eq!(leader.realized, 1_250_000 - 500_000);
+        assert_eq!(leader.unrealized, 1_000_000 - 500_000);
+        assert_eq!(report.by_strategy["agent"].realized, 1_250_000 - 1_500_000);
+        assert_eq!(
+            report.by_strategy["agent"].unrealized,
+            1_000_000 - 1_500_000
+        );
+        // Exits close positions, the PnL goes to what opened them
+        assert_eq!(report.by_strategy["exit"].realized, 0);
+        assert_eq!(report.by_strategy["exit"].fills, 1);
+        assert_eq!(report.total.realized, 500_000);
+        assert_eq!(report.total.total(), 500_000);
+        assert_eq!(report.by_mint["mint"].open_tokens, 1_000);
+
+        let unmarked = PnlReport::from_fills(&fills, &HashMap::new());
+        assert!(unmarked.total.unmarked);
+        assert_eq!(unmarked.total.unrealized, 0);
+    }
+
+    #[test]
+    fn test_realized_since() {
+        let at = |mut fill: Fill, block_time: u64| {
+            fill.block_time = block_time;
+            fill
+        };
+        let fills = [
+            at(fill("buy", 2_000, 2_000_000, "", "copy"), 100),
+            at(fill("sell", 1_000, 1_500_000, "", "copy"), 200),
+            at(fill("sell", 1_000, 400_000, "", "exit"), 300),
+        ];
+
+        // Yesterday's sell is left out, today's realizes at the average cost
+        assert_eq!(PnlReport::realized_since(&fills, 250), 400_000 - 1_000_000);
+        assert_eq!(PnlReport::realized_since(&fills, 0), 1_900_000 - 2_000_000);
+        assert_eq!(PnlReport::realized_since(&fills, 400), 0);
+    }
+}
@@ -0,0 +1,390 @@
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::{env, error::Error, fmt, str::FromStr};
+
+use crate::{bot::Venue, db::PositionRecord};
+
+/// Tokens bought on a mint and what they cost
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub mint: Pubkey,
+    /// The venue the position is priced and exited on
+    pub venue: Venue,
+    /// The pool, pair or bonding curve the position is priced and exited on
+    pub pool_id: Pubkey,
+    /// Raw tokens held
+    pub size: u64,
+    /// Lamports spent on the tokens held
+    pub cost_basis: u64,
+    /// Highest price seen since the entry, in lamports per raw token
+    pub peak_price: f64,
+    /// Unix timestamp of the first buy
+    pub opened_at: u64,
+}
+
+impl Position {
+    /// Opens a position out of a confirmed buy
+    ///
+    /// * `venue` - The venue bought on
+    /// * `pool_id` - The pool bought on
+    /// * `mint` - The token bought
+    /// * `tokens` - Raw tokens received
+    /// * `lamports` - Lamports spent
+    /// * `now` - The current unix timestamp
+    pub fn open(
+        venue: Venue,
+        pool_id: Pubkey,
+        mint: Pubkey,
+        tokens: u64,
+        lamports: u64,
+        now: u64,
+    ) -> Self {
+        let mut position = Self {
+            mint,
+            venue,
+            pool_id,
+            size: tokens,
+            cost_basis: lamports,
+            peak_price: 0.0,
+            opened_at: now,
+        };
+        position.peak_price = position.entry_price();
+        position
+    }
+
+    /// Returns the average price paid, in lamports per raw token
+    pub fn entry_price(&self) -> f64 {
+        if self.size == 0 {
+            return 0.0;
+        }
+        self.cost_basis as f64 / self.size as f64
+    }
+
+    /// Adds a confirmed buy to the position, the peak restarts at the new
+    /// entry price when it was below it
+    ///
+    /// * `tokens` - Raw tokens received
+    /// * `lamports` - Lamports spent
+    pub fn add(&mut self, tokens: u64, lamports: u64) {
+        self.size = self.size.saturating_add(tokens);
+        self.cost_basis = self.cost_basis.saturating_add(lamports);
+        self.peak_price = self.peak_price.max(self.entry_price());
+    }
+
+    /// Shrinks the position to the tokens still held, the cost basis
+    /// shrinking in proportion. Returns the cost basis of the tokens sold.
+    ///
+    /// * `size` - Raw tokens still held
+    pub fn reduce(&mut self, size: u64) -> u64 {
+        if size >= self.size {
+            return 0;
+        }
+        let cost_basis = (self.cost_basis as u128 * size as u128 / self.size as u128) as u64;
+        let sold_cost = self.cost_basis - cost_basis;
+        self.cost_basis = cost_basis;
+        self.size = size;
+        sold_cost
+    }
+
+    /// Records a price, returns true when it is a new peak
+    ///
+    /// * `price` - Lamports per raw token
+    pub fn observe(&mut self, price: f64) -> bool {
+        if price <= self.peak_price {
+            return false;
+        }
+        self.peak_price = price;
+        true
+    }
+
+    /// Returns the change of `price` from the entry price, in percent
+    pub fn pnl_percent(&self, price: f64) -> f64 {
+        let entry_price = self.entry_price();
+        if entry_price == 0.0 {
+            return 0.0;
+        }
+        (price / entry_price - 1.0) * 100.0
+    }
+
+    pub fn to_record(&self) -> PositionRecord {
+        PositionRecord {
+            mint: self.mint.to_string(),
+            venue: format!("{:?}", self.venue),
+            pool_id: self.pool_id.to_string(),
+            size: self.size,
+            cost_basis: self.cost_basis,
+            peak_price: self.peak_price,
+            opened_at: self.opened_at,
+        }
+    }
+
+    pub fn from_record(record: &PositionRecord) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Ok(Self {
+            mint: Pubkey::from_str(&record.mint)?,
+            venue: Venue::from_config(&record.venue)
+                .ok_or_else(|| format!("Unknown venue: {}", record.venue))?,
+            pool_id: Pubkey::from_str(&record.pool_id)?,
+            size: record.size,
+            cost_basis: record.cost_basis,
+            peak_price: record.peak_price,
+            opened_at: record.opened_at,
+        })
+    }
+}
+
+/// Lamports realized on the sells of the current UTC day
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DailyPnl {
+    /// Days since the unix epoch
+    day: u64,
+    realized: i64,
+}
+
+impl DailyPnl {
+    /// Adds a realized profit, or loss when negative, restarting from 0 on
+    /// a new day
+    ///
+    /// * `pnl` - Lamports realized
+    /// * `now` - The current unix timestamp
+    pub fn record(&mut self, pnl: i64, now: u64) {
+        self.roll(now);
+        self.realized = self.realized.saturating_add(pnl);
+    }
+
+    /// Returns the lamports realized today
+    ///
+    /// * `now` - The current unix timestamp
+    pub fn today(&self, now: u64) -> i64 {
+        if now / 86_400 == self.day {
+            self.realized
+        } else {
+            0
+        }
+    }
+
+    fn roll(&mut self, now: u64) {
+        let day = now / 86_400;
+        if day != self.day {
+            self.day = day;
+            self.realized = 0;
+        }
+    }
+}
+
+/// Why a position is sold
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitReason {
+    /// The price rose the take-profit percent above the entry
+    TakeProfit { pnl_percent: f64 },
+    /// The price fell the stop-loss percent below the entry
+    StopLoss { pnl_percent: f64 },
+    /// The price fell the trailing-stop percent below the peak
+    TrailingStop { drawdown_percent: f64 },
+    /// The position was held for the max hold time
+    MaxHoldTime { held_secs: u64 },
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TakeProfit { pnl_percent } => write!(f, "take profit at {pnl_percent:+.2}%"),
+            Self::StopLoss { pnl_percent } => write!(f, "stop loss at {pnl_percent:+.2}%"),
+            Self::TrailingStop { drawdown_percent } => {
+                write!(f, "trailing stop {drawdown_percent:.2}% below the peak")
+            }
+            Self::MaxHoldTime { held_secs } => write!(f, "held for {held_secs}s"),
+        }
+    }
+}
+
+/// When an open position is sold in full. Unset rules never fire.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ExitRules {
+    /// Percent above the entry price
+    pub take_profit_percent: Option<f64>,
+    /// Percent below the entry price
+    pub stop_loss_percent: Option<f64>,
+    /// Percent below the peak price since the entry
+    pub trailing_stop_percent: Option<f64>,
+    pub max_hold_secs: Option<u64>,
+    /// Slippage of the exit sells
+    #[serde(default = "default_exit_slippage_percent")]
+    pub slippage_percent: u64,
+}
+
+fn default_exit_slippage_percent() -> u64 {
+    10
+}
+
+impl ExitRules {
+    /// Loads the rules from the environment, unset variables disable their rule:
+    ///
+    /// * `position_take_profit_percent` - Percent above the entry price
+    /// * `position_stop_loss_percent` - Percent below the entry price
+    /// * `position_trailing_stop_percent` - Percent below the peak price
+    /// * `position_max_hold_secs` - Seconds a position is held at most
+    /// * `position_slippage_percent` - Slippage of the exit sells, 10 by default
+    pub fn from_env() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let percent = |name: &str| -> Result<Option<f64>, Box<dyn Error + Send + Sync>> {
+            match env::var(name) {
+                Ok(percent) => Ok(Some(percent.parse()?)),
+                Err(_) => Ok(None),
+            }
+        };
+
+        Ok(Self {
+            take_profit_percent: percent("position_take_profit_percent")?,
+            stop_loss_percent: percent("position_stop_loss_percent")?,
+            trailing_stop_percent: percent("position_trailing_stop_percent")?,
+            max_hold_secs: match env::var("position_max_hold_secs") {
+                Ok(secs) => Some(secs.parse()?),
+                Err(_) => None,
+            },
+            slippage_percent: match env::var("position_slippage_percent") {
+                Ok(slippage) => slippage.parse()?,
+                Err(_) => default_exit_slippage_percent(),
+            },
+        })
+    }
+
+    /// Returns true when no rule is set, positions are then only tracked
+    pub fn is_empty(&self) -> bool {
+        self.take_profit_percent.is_none()
+            && self.stop_loss_percent.is_none()
+            && self.trailing_stop_percent.is_none()
+            && self.max_hold_secs.is_none()
+    }
+
+    /// Returns why the position should be sold at `price`, None to hold it.
+    /// The losing exits are checked first.
+    ///
+    /// * `position` - The position, its peak including `price`
+    /// * `price` - The exit price, in lamports per raw token
+    /// * `now` - The current unix timestamp
+    pub fn evaluate(&self, position: &Position, price: f64, now: u64) -> Option<ExitReason> {
+        let pnl_percent = position.pnl_percent(price);
+
+        if let Some(stop_loss) = self.stop_loss_percent {
+            if pnl_percent <= -stop_loss {
+                return Some(ExitReason::StopLoss { pnl_percent });
+            }
+        }
+        if let Some(trailing_stop) = self.trailing_stop_percent {
+            if position.peak_price > 0.0 {
+                let drawdown_percent = (1.0 - price / position.peak_price) * 100.0;
+                if drawdown_percent >= trailing_stop {
+                    return Some(ExitReason::TrailingStop { drawdown_percent });
+                }
+            }
+        }
+        if let Some(take_profit) = self.take_profit_percent {
+            if pnl_percent >= take_profit {
+                return Some(ExitReason::TakeProfit { pnl_percent });
+            }
+        }
+        if let Some(max_hold) = self.max_hold_secs {
+            let held_secs = now.saturating_sub(position.opened_at);
+            if held_secs >= max_hold {
+                return Some(ExitReason::MaxHoldTime { held_secs });
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position() -> Position {
+        // 1_000_000 tokens for 0.1 SOL, 100 lamports per token
+        Position::open(
+            Venue::PumpFun,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            1_000_000,
+            100_000_000,
+            1_000,
+        )
+    }
+
+    #[test]
+    fn test_position_cost_basis() {
+        let mut position = position();
+        assert_eq!(position.entry_price(), 100.0);
+        assert_eq!(position.peak_price, 100.0);
+
+        position.add(1_000_000, 300_000_000);
+        assert_eq!(position.entry_price(), 200.0);
+        assert_eq!(position.peak_price, 200.0);
+
+        assert_eq!(position.reduce(500_000), 300_000_000);
+        assert_eq!(position.size, 500_000);
+        assert_eq!(position.cost_basis, 100_000_000);
+        assert_eq!(position.entry_price(), 200.0);
+
+        assert!(position.observe(250.0));
+        assert!(!position.observe(220.0));
+        assert_eq!(position.peak_price, 250.0);
+    }
+
+    #[test]
+    fn test_daily_pnl() {
+        let mut pnl = DailyPnl::default();
+        pnl.record(-5_000, 86_400 * 10 + 60);
+        pnl.record(2_000, 86_400 * 10 + 120);
+        assert_eq!(pnl.today(86_400 * 10 + 180), -3_000);
+        assert_eq!(pnl.today(86_400 * 11), 0);
+
+        pnl.record(-1_000, 86_400 * 11 + 60);
+        assert_eq!(pnl.today(86_400 * 11 + 120), -1_000);
+    }
+
+    #[test]
+    fn test_position_record() {
+        let position = position();
+        assert_eq!(
+            Position::from_record(&position.to_record()).unwrap(),
+            position
+        );
+    }
+
+    #[test]
+    fn test_exit_rules() {
+        let rules = ExitRules {
+            take_profit_percent: Some(50.0),
+            stop_loss_percent: Some(20.0),
+            trailing_stop_percent: Some(30.0),
+            max_hold_secs: Some(600),
+            slippage_percent: 10,
+        };
+        let mut position = position();
+
+        assert_eq!(rules.evaluate(&position, 110.0, 1_060), None);
+        assert!(matches!(
+            rules.evaluate(&position, 75.0, 1_060),
+            Some(ExitReason::StopLoss { .. })
+        ));
+        assert!(matches!(
+            rules.evaluate(&position, 150.0, 1_060),
+            Some(ExitReason::TakeProfit { .. })
+        ));
+        assert_eq!(
+            rules.evaluate(&position, 100.0, 1_600),
+            Some(ExitReason::MaxHoldTime { held_secs: 600 })
+        );
+
+        // 30% below a peak of 200 is still 40% above the entry
+        position.observe(200.0);
+        assert!(matches!(
+            rules.evaluate(&position, 140.0, 1_060),
+            Some(ExitReason::TrailingStop { .. })
+        ));
+        assert_eq!(
+            ExitRules::default().evaluate(&position, 1.0, u64::MAX),
+            None
+        );
+    }
+}
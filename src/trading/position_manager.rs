@@ -0,0 +1,404 @@
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use std::{collections::HashMap, env, error::Error, sync::Arc, sync::Mutex, time::Duration};
+
+use crate::{
+    bot::{token_balance, Route, Router, SwapQuote, TradeSide, Venue, VenueContext},
+    copy_trading::unix_now,
+    db::Database,
+};
+
+use super::{DailyPnl, ExitReason, ExitRules, PnlLedger, Position};
+
+/// Tracks the tokens bought per mint and sells a position once an exit
+/// rule fires.
+///
+/// Positions are priced by quoting a sell of the whole position on the pool
+/// bought on, which reads the bonding curve or pool accounts, so the price
+/// includes the price impact of the exit. A pool no longer quoting, e.g. a
+/// completed Pump.fun curve, moves the position to the best venue the router
+/// finds. They are stored in the database and reloaded on restart.
+pub struct PositionManager {
+    ctx: VenueContext,
+    signer_prv_key: Arc<String>,
+    wallet: Pubkey,
+    rules: ExitRules,
+    /// Time between two price checks
+    check_interval: Duration,
+    /// Finds where a position trades once its pool no longer quotes
+    router: Router,
+    positions: Mutex<HashMap<Pubkey, Position>>,
+    /// Realized on the sells of the day, seeded from the fills booked then
+    /// as priced when they were sent
+    realized: Mutex<DailyPnl>,
+    db: Arc<Database>,
+    /// Books the fills of the exits
+    ledger: Arc<PnlLedger>,
+}
+
+impl PositionManager {
+    /// * `position_check_interval_ms` - Milliseconds between two price checks, 2000 by default
+    ///
+    /// Positions leaving their pool are routed with `Router::from_env`, unsplit.
+    pub fn new(
+        ctx: VenueContext,
+        signer_prv_key: Arc<String>,
+        rules: ExitRules,
+        db: Arc<Database>,
+        ledger: Arc<PnlLedger>,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let wallet = Keypair::from_base58_string(signer_prv_key.as_str()).try_pubkey()?;
+        let check_interval = match env::var("position_check_interval_ms") {
+            Ok(ms) => Duration::from_millis(ms.parse()?),
+            Err(_) => Duration::from_millis(2_000),
+        };
+
+        Ok(Self {
+            ctx,
+            signer_prv_key,
+            wallet,
+            rules,
+            check_interval,
+            router: Router {
+                split_parts: 1,
+                ..Router::from_env()?
+            },
+            positions: Mutex::new(HashMap::new()),
+            realized: Mutex::new(DailyPnl::default()),
+            db,
+            ledger,
+        })
+    }
+
+    pub fn ledger(&self) -> &Arc<PnlLedger> {
+        &self.ledger
+    }
+
+    /// Reloads the positions stored and the PnL realized today by the fills
+    /// booked, so a restart keeps the day's losses. Returns how many
+    /// positions are open.
+    pub async fn load(&self) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let now = unix_now();
+        let realized_today = self.ledger.realized_today(now).await?;
+        {
+            let mut realized = self.realized.lock().map_err(|e| e.to_string())?;
+            *realized = DailyPnl::default();
+            realized.record(realized_today, now);
+        }
+        println!("Realized today: {realized_today} lamports");
+
+        let records = self.db.get_positions().await?;
+        let mut positions = self.positions.lock().map_err(|e| e.to_string())?;
+
+        for record in &records {
+            let position = Position::from_record(record)?;
+            positions.insert(position.mint, position);
+        }
+        Ok(positions.len())
+    }
+
+    /// Returns a copy of the open positions
+    pub fn positions(&self) -> Vec<Position> {
+        self.positions
+            .lock()
+            .map(|positions| positions.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, mint: &Pubkey) -> Option<Position> {
+        self.positions.lock().ok()?.get(mint).cloned()
+    }
+
+    /// Returns the exit price of every open position, in lamports per raw
+    /// token keyed on the mint, leaving out those failing to quote
+    pub async fn mark_prices(&self) -> HashMap<String, f64> {
+        let mut marks = HashMap::new();
+        for position in self.positions() {
+            match self.quote_exit(&position).await {
+                Ok(quote) => {
+                    marks.insert(
+                        position.mint.to_string(),
+                        quote.amount_out as f64 / quote.amount_in as f64,
+                    );
+                }
+                Err(e) => eprintln!("Error marking position {}: {e:?}", position.mint),
+            }
+        }
+        marks
+    }
+
+    /// Quotes a sell of the whole position on its pool. When the pool no
+    /// longer quotes, e.g. the Pump.fun curve completed and the token
+    /// migrated to PumpSwap, the sell is routed and the position moved to
+    /// the venue and pool of the route.
+    ///
+    /// * `position` - The position to price
+    async fn quote_exit(
+        &self,
+        position: &Position,
+    ) -> Result<SwapQuote, Box<dyn Error + Send + Sync>> {
+        let error = match position
+            .venue
+            .quote_pool(
+                &self.ctx,
+                &position.pool_id,
+                &position.mint,
+                TradeSide::Sell,
+                position.size,
+            )
+            .await
+        {
+            Ok(quote) => return Ok(quote),
+            Err(e) => e,
+        };
+
+        let route = self
+            .router
+            .route(&self.ctx, &position.mint, TradeSide::Sell, position.size)
+            .await
+            .map_err(|e| format!("{error}, and no venue routes it: {e}"))?;
+        let quote = route
+            .legs
+            .into_iter()
+            .next()
+            .ok_or("The route has no leg")?;
+
+        let moved = {
+            let mut positions = self.positions.lock().map_err(|e| e.to_string())?;
+            positions.get_mut(&position.mint).map(|moved| {
+                moved.venue = quote.venue;
+                moved.pool_id = quote.pool_id;
+                moved.clone()
+            })
+        };
+        if let Some(moved) = moved {
+            self.db.upsert_position(&moved.to_record()).await?;
+            println!(
+                "Position {} moved from {} to {} ({}): {error}",
+                moved.mint, position.venue, moved.venue, moved.pool_id
+            );
+        }
+        Ok(quote)
+    }
+
+    /// Returns the lamports spent on the open positions, in total and on `mint`
+    pub fn exposure(&self, mint: &Pubkey) -> (u64, u64) {
+        let Ok(positions) = self.positions.lock() else {
+            return (0, 0);
+        };
+        let total = positions.values().map(|position| position.cost_basis).sum();
+        let on_mint = positions
+            .get(mint)
+            .map(|position| position.cost_basis)
+            .unwrap_or_default();
+        (total, on_mint)
+    }
+
+    /// Returns the lamports realized today, negative on a loss
+    ///
+    /// * `now` - The current unix timestamp
+    pub fn realized_today(&self, now: u64) -> i64 {
+        self.realized
+            .lock()
+            .map(|realized| realized.today(now))
+            .unwrap_or_default()
+    }
+
+    /// Records a confirmed buy, the tokens received being the wallet balance
+    /// above the size already tracked
+    ///
+    /// * `venue` - The venue bought on
+    /// * `pool_id` - The pool bought on, the position is priced and sold on it
+    /// * `mint` - The token bought
+    /// * `lamports` - Lamports spent
+    pub async fn record_buy(
+        &self,
+        venue: Venue,
+        pool_id: Pubkey,
+        mint: Pubkey,
+        lamports: u64,
+    ) -> Result<Option<Position>, Box<dyn Error + Send + Sync>> {
+        let balance = token_balance(&self.ctx, &self.wallet, &mint).await?;
+
+        let position = {
+            let mut positions = self.positions.lock().map_err(|e| e.to_string())?;
+            let known = positions.get(&mint).map(|p| p.size).unwrap_or_default();
+            if balance <= known {
+                println!("No tokens of {mint} received yet, position unchanged");
+                return Ok(None);
+            }
+            let tokens = balance - known;
+
+            let position = positions
+                .entry(mint)
+                .and_modify(|position| position.add(tokens, lamports))
+                .or_insert_with(|| {
+                    Position::open(venue, pool_id, mint, tokens, lamports, unix_now())
+                });
+            position.clone()
+        };
+
+        self.db.upsert_position(&position.to_record()).await?;
+        println!(
+            "Position {mint} | Size: {} | Cost: {} | Entry: {:.4} lamports/token",
+            position.size,
+            position.cost_basis,
+            position.entry_price()
+        );
+        Ok(Some(position))
+    }
+
+    /// Records a confirmed buy sent on a route. A buy split across venues is
+    /// priced and sold where a sell of all the tokens it got routes best, as
+    /// a position is sold in one go.
+    ///
+    /// * `route` - The route bought on
+    /// * `lamports` - Lamports spent
+    pub async fn record_route_buy(
+        &self,
+        route: &Route,
+        lamports: u64,
+    ) -> Result<Option<Position>, Box<dyn Error + Send + Sync>> {
+        let leg = match route.legs.as_slice() {
+            [leg] => leg.clone(),
+            _ => self
+                .router
+                .route(&self.ctx, &route.mint, TradeSide::Sell, route.amount_out())
+                .await?
+                .legs
+                .into_iter()
+                .next()
+                .ok_or("The route has no leg")?,
+        };
+        self.record_buy(leg.venue, leg.pool_id, route.mint, lamports)
+            .await
+    }
+
+    /// Shrinks a position to the wallet balance after a sell, closing it
+    /// once nothing is left. The tokens sold are realized at `exit_price`.
+    ///
+    /// * `mint` - The token sold
+    /// * `exit_price` - Lamports per raw token the sell was priced at, None when unknown
+    pub async fn sync(
+        &self,
+        mint: &Pubkey,
+        exit_price: Option<f64>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.get(mint).is_none() {
+            return Ok(());
+        }
+        let balance = token_balance(&self.ctx, &self.wallet, mint).await?;
+
+        let (position, sold, sold_cost) = {
+            let mut positions = self.positions.lock().map_err(|e| e.to_string())?;
+            let Some(position) = positions.get_mut(mint) else {
+                return Ok(());
+            };
+            let sold = position.size.saturating_sub(balance);
+            let sold_cost = position.reduce(balance);
+            if balance == 0 {
+                positions.remove(mint);
+                (None, sold, sold_cost)
+            } else {
+                (Some(position.clone()), sold, sold_cost)
+            }
+        };
+
+        if let Some(exit_price) = exit_price.filter(|_| sold > 0) {
+            let pnl = (sold as f64 * exit_price) as i64 - sold_cost as i64;
+            if let Ok(mut realized) = self.realized.lock() {
+                realized.record(pnl, unix_now());
+            }
+            println!("Position {mint} realized {pnl} lamports");
+        }
+
+        match position {
+            Some(position) => self.db.upsert_position(&position.to_record()).await?,
+            None => {
+                self.db.delete_position(&mint.to_string()).await?;
+                println!("Position {mint} closed");
+            }
+        }
+        Ok(())
+    }
+
+    /// Prices every open position and sells those an exit rule fires on,
+    /// forever
+    pub async fn run(self: Arc<Self>) {
+        if self.rules.is_empty() {
+            println!("No exit rule set, positions are only tracked");
+            return;
+        }
+
+        let mut interval = tokio::time::interval(self.check_interval);
+        loop {
+            interval.tick().await;
+            for position in self.positions() {
+                if let Err(e) = self.check(position.mint).await {
+                    eprintln!("Error checking position {}: {e:?}", position.mint);
+                }
+            }
+        }
+    }
+
+    /// Prices a position and sells it when an exit rule fires
+    ///
+    /// * `mint` - The token of the position
+    pub async fn check(
+        &self,
+        mint: Pubkey,
+    ) -> Result<Option<ExitReason>, Box<dyn Error + Send + Sync>> {
+        let Some(position) = self.get(&mint) else {
+            return Ok(None);
+        };
+
+        let quote = self.quote_exit(&position).await?;
+        let price = quote.amount_out as f64 / quote.amount_in as f64;
+
+        let (position, new_peak) = {
+            let mut positions = self.positions.lock().map_err(|e| e.to_string())?;
+            let Some(position) = positions.get_mut(&mint) else {
+                return Ok(None);
+            };
+            let new_peak = position.observe(price);
+            (position.clone(), new_peak)
+        };
+        if new_peak {
+            self.db.upsert_position(&position.to_record()).await?;
+        }
+
+        let Some(reason) = self.rules.evaluate(&position, price, unix_now()) else {
+            return Ok(None);
+        };
+        println!(
+            "Selling {mint}: {reason} | Size: {} | Out: {}",
+            position.size, quote.amount_out
+        );
+
+        let signature = Venue::swap(
+            &self.ctx,
+            Arc::clone(&self.signer_prv_key),
+            &quote,
+            self.rules.slippage_percent,
+            None,
+            None,
+        )
+        .await?;
+        println!("Position {mint} sold: {signature}");
+
+        // Realized at the confirmed amounts when they can be read
+        let (tokens, net_lamports) = self
+            .ledger
+            .book_all(&[signature], &mint, None, "exit")
+            .await;
+        let exit_price = if tokens > 0 {
+            net_lamports as f64 / tokens as f64
+        } else {
+            price
+        };
+        self.sync(&mint, Some(exit_price)).await?;
+        Ok(Some(reason))
+    }
+}
@@ -0,0 +1,533 @@
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    bot::VenueContext, constants::SOL_DECIMALS, copy_trading::unix_now, errors::RiskError,
+};
+
+use super::PositionManager;
+
+/// What a buy adds to, the open positions and the buys in flight
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Exposure {
+    /// Open positions and mints with a buy in flight
+    pub open_positions: usize,
+    /// Lamports spent on the open positions and the buys in flight
+    pub total: u64,
+    /// Lamports spent on the mint bought
+    pub mint: u64,
+}
+
+/// Limits every buy has to pass, whatever sent it. Sells are never limited.
+///
+/// Unset limits are not checked.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RiskLimits {
+    pub max_open_positions: Option<usize>,
+    /// Lamports spent on all the open positions
+    pub max_exposure: Option<u64>,
+    /// Lamports spent on a single mint
+    pub max_mint_exposure: Option<u64>,
+    /// Lamports lost on the sells of the UTC day, halts the buys once reached
+    pub max_daily_loss: Option<u64>,
+    /// Lamports left in the wallet after a buy, for the fees and the rent
+    pub min_sol_reserve: u64,
+    /// Sends failing in a row before the buys are halted
+    pub max_consecutive_failures: Option<u32>,
+    /// Buys are halted while the file exists
+    pub kill_switch_file: Option<PathBuf>,
+    /// Buys are halted from the start
+    pub halted: bool,
+}
+
+impl RiskLimits {
+    /// Loads the limits from the environment, unset variables disable their limit:
+    ///
+    /// * `risk_max_open_positions` - Positions open at once
+    /// * `risk_max_exposure_sol` - SOL spent on all the open positions
+    /// * `risk_max_mint_exposure_sol` - SOL spent on a single mint
+    /// * `risk_max_daily_loss_sol` - SOL lost on the day before the buys are halted
+    /// * `risk_min_sol_reserve` - SOL kept in the wallet, 0.05 by default
+    /// * `risk_max_consecutive_failures` - Failed sends in a row before the buys are halted
+    /// * `risk_kill_switch_file` - Buys are halted while this file exists
+    /// * `risk_kill_switch` - "true" halts the buys from the start
+    pub fn from_env() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let sol = |name: &str| -> Result<Option<u64>, Box<dyn Error + Send + Sync>> {
+            match env::var(name) {
+                Ok(sol) => Ok(Some((sol.parse::<f64>()? * SOL_DECIMALS as f64) as u64)),
+                Err(_) => Ok(None),
+            }
+        };
+
+        Ok(Self {
+            max_open_positions: match env::var("risk_max_open_positions") {
+                Ok(max) => Some(max.parse()?),
+                Err(_) => None,
+            },
+            max_exposure: sol("risk_max_exposure_sol")?,
+            max_mint_exposure: sol("risk_max_mint_exposure_sol")?,
+            max_daily_loss: sol("risk_max_daily_loss_sol")?,
+            min_sol_reserve: sol("risk_min_sol_reserve")?
+                .unwrap_or((0.05 * SOL_DECIMALS as f64) as u64),
+            max_consecutive_failures: match env::var("risk_max_consecutive_failures") {
+                Ok(max) => Some(max.parse()?),
+                Err(_) => None,
+            },
+            kill_switch_file: env::var("risk_kill_switch_file").ok().map(PathBuf::from),
+            halted: env::var("risk_kill_switch").is_ok_and(|halted| halted == "true"),
+        })
+    }
+
+    /// Checks a buy against the daily loss and the exposure limits
+    ///
+    /// * `exposure` - The exposure before the buy
+    /// * `mint` - The token bought
+    /// * `lamports` - Lamports the buy spends
+    /// * `new_position` - Whether the buy opens a position
+    /// * `realized_today` - Lamports realized today, negative on a loss
+    pub fn check(
+        &self,
+        exposure: &Exposure,
+        mint: &Pubkey,
+        lamports: u64,
+        new_position: bool,
+        realized_today: i64,
+    ) -> Result<(), RiskError> {
+        if let Some(max) = self.max_daily_loss {
+            let loss = realized_today.min(0).unsigned_abs();
+            if loss >= max {
+                return Err(RiskError::DailyLossCap { max, loss });
+            }
+        }
+        if let Some(max) = self.max_open_positions {
+            if new_position && exposure.open_positions >= max {
+                return Err(RiskError::TooManyPositions {
+                    max,
+                    open: exposure.open_positions,
+                });
+            }
+        }
+        if let Some(max) = self.max_exposure {
+            let exposure = exposure.total.saturating_add(lamports);
+            if exposure > max {
+                return Err(RiskError::MaxExposure { max, exposure });
+            }
+        }
+        if let Some(max) = self.max_mint_exposure {
+            let exposure = exposure.mint.saturating_add(lamports);
+            if exposure > max {
+                return Err(RiskError::MaxMintExposure {
+                    mint: *mint,
+                    max,
+                    exposure,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the SOL the wallet keeps after a buy
+    ///
+    /// * `balance` - Lamports in the wallet
+    /// * `lamports` - Lamports the buy spends
+    pub fn check_reserve(&self, balance: u64, lamports: u64) -> Result<(), RiskError> {
+        let remaining = balance.saturating_sub(lamports);
+        if remaining < self.min_sol_reserve {
+            return Err(RiskError::LowReserve {
+                min: self.min_sol_reserve,
+                remaining,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A pulled kill switch
+#[derive(Debug, Clone, PartialEq)]
+struct Halt {
+    reason: String,
+    /// UTC day of a halt pulled by a daily limit, lifted on the next day.
+    /// None on a manual halt, kept until `resume`
+    day: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct RiskState {
+    /// Why the buys are halted, None while they are allowed
+    halted: Option<Halt>,
+    /// Lamports of the buys approved and not yet recorded, per mint
+    in_flight: HashMap<Pubkey, u64>,
+    consecutive_failures: u32,
+}
+
+impl RiskState {
+    /// Returns why the buys are halted, lifting the limit halt of a previous day
+    ///
+    /// * `now` - The current unix timestamp
+    fn halted(&mut self, now: u64) -> Option<String> {
+        let halt = self.halted.as_ref()?;
+        if halt.day.is_some_and(|day| day != now / 86_400) {
+            println!("Kill switch released on the new day: {}", halt.reason);
+            self.halted = None;
+            self.consecutive_failures = 0;
+            return None;
+        }
+        Some(halt.reason.clone())
+    }
+
+    /// Halts the buys, a manual halt replacing a limit one
+    ///
+    /// * `reason` - Why the buys are halted
+    /// * `day` - UTC day of a limit halt, None on a manual one
+    fn halt(&mut self, reason: &str, day: Option<u64>) {
+        let replaced = match &self.halted {
+            None => true,
+            Some(halt) => day.is_none() && halt.day.is_some(),
+        };
+        if replaced {
+            eprintln!("Kill switch pulled, new buys are halted: {reason}");
+            self.halted = Some(Halt {
+                reason: reason.to_string(),
+                day,
+            });
+        }
+    }
+}
+
+/// Approves the buys of every order path against the risk limits, and
+/// holds the kill switch halting them.
+///
+/// The kill switch is pulled manually with `halt`, the kill switch file or
+/// `risk_kill_switch`, and stays pulled until `resume`. It is pulled
+/// automatically once the daily loss cap or the consecutive failures limit
+/// is reached, until `resume` or the next UTC day.
+pub struct RiskEngine {
+    ctx: VenueContext,
+    wallet: Pubkey,
+    limits: RiskLimits,
+    positions: Arc<PositionManager>,
+    state: Mutex<RiskState>,
+}
+
+/// An approved buy, counted in the exposure until it is dropped. Keep it
+/// until the position of the buy is recorded.
+#[derive(Debug)]
+pub struct BuyPermit {
+    risk: Arc<RiskEngine>,
+    mint: Pubkey,
+    lamports: u64,
+}
+
+impl Drop for BuyPermit {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.risk.state.lock() {
+            if let Some(in_flight) = state.in_flight.get_mut(&self.mint) {
+                *in_flight = in_flight.saturating_sub(self.lamports);
+                if *in_flight == 0 {
+                    state.in_flight.remove(&self.mint);
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for RiskEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RiskEngine")
+            .field("limits", &self.limits)
+            .field("halted", &self.halted())
+            .finish()
+    }
+}
+
+impl RiskEngine {
+    pub fn new(
+        ctx: VenueContext,
+        signer_prv_key: &str,
+        limits: RiskLimits,
+        positions: Arc<PositionManager>,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let wallet = Keypair::from_base58_string(signer_prv_key).try_pubkey()?;
+        let state = RiskState {
+            halted: limits.halted.then(|| Halt {
+                reason: "risk_kill_switch is set".to_string(),
+                day: None,
+            }),
+            ..RiskState::default()
+        };
+
+        Ok(Self {
+            ctx,
+            wallet,
+            limits,
+            positions,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    pub fn positions(&self) -> &Arc<PositionManager> {
+        &self.positions
+    }
+
+    /// Halts the new buys until `resume`
+    ///
+    /// * `reason` - Why the buys are halted
+    pub fn halt(&self, reason: &str) {
+        if let Ok(mut state) = self.state.lock() {
+            state.halt(reason, None);
+        }
+    }
+
+    /// Halts the new buys until `resume` or the next UTC day
+    ///
+    /// * `reason` - The limit reached
+    /// * `now` - The current unix timestamp
+    fn halt_for_today(&self, reason: &str, now: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            state.halt(reason, Some(now / 86_400));
+        }
+    }
+
+    /// Allows the buys again, the kill switch file aside
+    pub fn resume(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.halted = None;
+            state.consecutive_failures = 0;
+        }
+    }
+
+    /// Returns why the buys are halted, None while they are allowed
+    pub fn halted(&self) -> Option<String> {
+        if let Some(file) = &self.limits.kill_switch_file {
+            if file.exists() {
+                return Some(format!("{} exists", file.display()));
+            }
+        }
+        self.state.lock().ok()?.halted(unix_now())
+    }
+
+    /// Records the outcome of a sent order, halting the buys after too many
+    /// failures in a row
+    ///
+    /// * `success` - Whether the order was sent
+    pub fn record_send(&self, success: bool) {
+        let failures = {
+            let Ok(mut state) = self.state.lock() else {
+                return;
+            };
+            state.consecutive_failures = if success {
+                0
+            } else {
+                state.consecutive_failures + 1
+            };
+            state.consecutive_failures
+        };
+
+        if let Some(max) = self.limits.max_consecutive_failures {
+            if failures >= max {
+                self.halt_for_today(&format!("{failures} sends failed in a row"), unix_now());
+            }
+        }
+    }
+
+    /// Approves a buy, Ok(Err) holding the reason it is rejected. The buy is
+    /// counted in the exposure as long as the permit is kept.
+    ///
+    /// * `mint` - The token bought
+    /// * `lamports` - Lamports the buy spends at most
+    pub async fn approve_buy(
+        self: &Arc<Self>,
+        mint: &Pubkey,
+        lamports: u64,
+    ) -> Result<Result<BuyPermit, RiskError>, Box<dyn Error + Send + Sync>> {
+        if let Some(reason) = self.halted() {
+            return Ok(Err(RiskError::Halted(reason)));
+        }
+
+        let now = unix_now();
+        let realized_today = self.positions.realized_today(now);
+        if let Some(max) = self.limits.max_daily_loss {
+            if realized_today <= -(max as i64) {
+                self.halt_for_today("the daily loss cap is reached", now);
+            }
+        }
+
+        if self.limits.min_sol_reserve > 0 {
+            let balance = {
+                let connection = self.ctx.helius.lock().await.async_connection()?;
+                connection.get_balance(&self.wallet).await?
+            };
+            let in_flight: u64 = self
+                .state
+                .lock()
+                .map_err(|e| e.to_string())?
+                .in_flight
+                .values()
+                .sum();
+            if let Err(reason) = self
+                .limits
+                .check_reserve(balance.saturating_sub(in_flight), lamports)
+            {
+                return Ok(Err(reason));
+            }
+        }
+
+        let (total, on_mint) = self.positions.exposure(mint);
+        let open = self.positions.positions();
+
+        let mut state = self.state.lock().map_err(|e| e.to_string())?;
+        // Checked again, another buy may have pulled it meanwhile
+        if let Some(reason) = state.halted(now) {
+            return Ok(Err(RiskError::Halted(reason)));
+        }
+        let in_flight_new = state
+            .in_flight
+            .keys()
+            .filter(|in_flight| !open.iter().any(|position| position.mint == **in_flight))
+            .count();
+        let exposure = Exposure {
+            open_positions: open.len() + in_flight_new,
+            total: total + state.in_flight.values().sum::<u64>(),
+            mint: on_mint + state.in_flight.get(mint).copied().unwrap_or_default(),
+        };
+        let new_position = on_mint == 0 && !state.in_flight.contains_key(mint);
+        if let Err(reason) =
+            self.limits
+                .check(&exposure, mint, lamports, new_position, realized_today)
+        {
+            return Ok(Err(reason));
+        }
+
+        *state.in_flight.entry(*mint).or_default() += lamports;
+        Ok(Ok(BuyPermit {
+            risk: Arc::clone(self),
+            mint: *mint,
+            lamports,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> RiskLimits {
+        RiskLimits {
+            max_open_positions: Some(2),
+            max_exposure: Some(1_000),
+            max_mint_exposure: Some(400),
+            max_daily_loss: Some(500),
+            min_sol_reserve: 100,
+            ..RiskLimits::default()
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let limits = limits();
+        let mint = Pubkey::new_unique();
+        let exposure = Exposure {
+            open_positions: 1,
+            total: 600,
+            mint: 0,
+        };
+
+        assert_eq!(limits.check(&exposure, &mint, 300, true, -100), Ok(()));
+        assert_eq!(
+            limits.check(&exposure, &mint, 300, true, -500),
+            Err(RiskError::DailyLossCap {
+                max: 500,
+                loss: 500
+            })
+        );
+        assert_eq!(
+            limits.check(&exposure, &mint, 500, true, 0),
+            Err(RiskError::MaxExposure {
+                max: 1_000,
+                exposure: 1_100
+            })
+        );
+        assert_eq!(
+            limits.check(
+                &Exposure {
+                    mint: 200,
+                    ..exposure
+                },
+                &mint,
+                300,
+                false,
+                0
+            ),
+            Err(RiskError::MaxMintExposure {
+                mint,
+                max: 400,
+                exposure: 500
+            })
+        );
+
+        let full = Exposure {
+            open_positions: 2,
+            ..exposure
+        };
+        assert_eq!(
+            limits.check(&full, &mint, 100, true, 0),
+            Err(RiskError::TooManyPositions { max: 2, open: 2 })
+        );
+        // Adding to an open position opens none
+        assert_eq!(limits.check(&full, &mint, 100, false, 0), Ok(()));
+        assert_eq!(
+            RiskLimits::default().check(&full, &mint, u64::MAX, true, i64::MIN),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_halt() {
+        let mut state = RiskState::default();
+        let day = 86_400 * 10;
+
+        state.halt("the daily loss cap is reached", Some(10));
+        state.consecutive_failures = 3;
+        assert_eq!(
+            state.halted(day + 60),
+            Some("the daily loss cap is reached".to_string())
+        );
+        // Lifted on the next UTC day
+        assert_eq!(state.halted(day + 86_400), None);
+        assert_eq!(state.consecutive_failures, 0);
+
+        // A manual halt outlives the day and replaces a limit one
+        state.halt("3 sends failed in a row", Some(11));
+        state.halt("paused", None);
+        state.halt("the daily loss cap is reached", Some(11));
+        assert_eq!(state.halted(day + 86_400 * 5), Some("paused".to_string()));
+    }
+
+    #[test]
+    fn test_check_reserve() {
+        let limits = limits();
+
+        assert_eq!(limits.check_reserve(1_000, 900), Ok(()));
+        assert_eq!(
+            limits.check_reserve(1_000, 950),
+            Err(RiskError::LowReserve {
+                min: 100,
+                remaining: 50
+            })
+        );
+    }
+}
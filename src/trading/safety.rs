@@ -0,0 +1,569 @@
+use serde_json::{json, Value};
+use solana_client::{rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_request::RpcRequest};
+use solana_sdk::pubkey::Pubkey;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use std::{collections::HashMap, env, error::Error, fmt, sync::Mutex};
+
+use crate::{
+    bot::{
+        AccountDecoder, PumpFunBondingCurve, SplMintState, SplTokenAccountState, TokenMetadata,
+        VenueContext,
+    },
+    constants::PUMP_FUN_PROGRAM,
+    copy_trading::unix_now,
+    errors::SafetyError,
+};
+
+/// A risk the safety checks found on a token, each adding its weight to
+/// the risk score
+#[derive(Debug, Clone, PartialEq)]
+pub enum SafetyFinding {
+    /// More tokens can be minted
+    MintAuthority(Pubkey),
+    /// The token accounts of the holders can be frozen
+    FreezeAuthority(Pubkey),
+    /// The largest wallets hold too much of the supply
+    HolderConcentration { top: usize, percent: f64 },
+    /// The creator holds too much of the supply
+    CreatorHoldings { creator: Pubkey, percent: f64 },
+    /// The creator sold or moved tokens in its recent transactions
+    CreatorSells { creator: Pubkey, sells: usize },
+    /// The name, symbol and image can still be changed
+    MutableMetadata,
+}
+
+impl SafetyFinding {
+    /// Returns what the finding adds to the risk score, out of 100
+    pub fn weight(&self) -> u32 {
+        match self {
+            Self::MintAuthority(_) => 40,
+            Self::FreezeAuthority(_) => 40,
+            Self::HolderConcentration { .. } => 25,
+            Self::CreatorHoldings { .. } => 20,
+            Self::CreatorSells { .. } => 30,
+            Self::MutableMetadata => 10,
+        }
+    }
+}
+
+impl fmt::Display for SafetyFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MintAuthority(authority) => write!(f, "{authority} can mint"),
+            Self::FreezeAuthority(authority) => write!(f, "{authority} can freeze"),
+            Self::HolderConcentration { top, percent } => {
+                write!(f, "the top {top} wallets hold {percent:.1}%")
+            }
+            Self::CreatorHoldings { creator, percent } => {
+                write!(f, "the creator {creator} holds {percent:.1}%")
+            }
+            Self::CreatorSells { creator, sells } => {
+                write!(f, "the creator {creator} sold {sells} times recently")
+            }
+            Self::MutableMetadata => write!(f, "the metadata is mutable"),
+        }
+    }
+}
+
+/// What the safety checks concluded on a mint
+#[derive(Debug, Clone, PartialEq)]
+pub struct SafetyVerdict {
+    pub mint: Pubkey,
+    /// Sum of the weights of the findings, 100 at most
+    pub score: u32,
+    pub findings: Vec<SafetyFinding>,
+    /// Unix timestamp of the checks
+    pub checked_at: u64,
+}
+
+impl SafetyVerdict {
+    pub fn new(mint: Pubkey, findings: Vec<SafetyFinding>, now: u64) -> Self {
+        let score = findings
+            .iter()
+            .map(SafetyFinding::weight)
+            .sum::<u32>()
+            .min(100);
+        Self {
+            mint,
+            score,
+            findings,
+            checked_at: now,
+        }
+    }
+}
+
+impl fmt::Display for SafetyVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.findings.is_empty() {
+            return write!(f, "score {}, nothing found", self.score);
+        }
+        let findings: Vec<String> = self.findings.iter().map(ToString::to_string).collect();
+        write!(f, "score {}, {}", self.score, findings.join(", "))
+    }
+}
+
+/// What is fetched of a token to check it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenFacts {
+    pub mint_authority: Option<Pubkey>,
+    pub freeze_authority: Option<Pubkey>,
+    /// Raw supply
+    pub supply: u64,
+    /// Raw tokens of the largest wallets, largest first. Accounts owned by
+    /// programs, e.g. bonding curves and pool vaults, are left out.
+    pub top_holdings: Vec<u64>,
+    /// The Pump.fun curve creator, else the metadata update authority
+    pub creator: Option<Pubkey>,
+    /// Raw tokens in the creator associated token account
+    pub creator_holdings: u64,
+    /// Recent creator transactions lowering its balance
+    pub creator_sells: usize,
+    /// None when the token has no metadata account
+    pub metadata_mutable: Option<bool>,
+}
+
+/// Checks run on a token before it is bought, by the copy engine and the
+/// agent alike. Sells are never checked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SafetyPolicy {
+    /// Tokens scoring above it are not bought, None to not check tokens
+    pub max_score: Option<u32>,
+    /// Largest wallets summed for the concentration
+    pub top_holders: usize,
+    pub max_top_holders_percent: f64,
+    pub max_creator_percent: f64,
+    /// Transactions of the creator token account scanned for sells
+    pub creator_lookback: usize,
+    /// Seconds a verdict is reused before the mint is checked again
+    pub cache_secs: u64,
+}
+
+impl Default for SafetyPolicy {
+    fn default() -> Self {
+        Self {
+            max_score: None,
+            top_holders: 10,
+            max_top_holders_percent: 30.0,
+            max_creator_percent: 5.0,
+            creator_lookback: 10,
+            cache_secs: 300,
+        }
+    }
+}
+
+impl SafetyPolicy {
+    /// Loads the policy from the environment, unset variables keep the defaults:
+    ///
+    /// * `safety_max_score` - Risk score out of 100 above which buys are blocked, unset to not check tokens
+    /// * `safety_top_holders` - Largest wallets summed for the concentration, 10 by default
+    /// * `safety_max_top_holders_percent` - Percent of the supply they may hold, 30 by default
+    /// * `safety_max_creator_percent` - Percent of the supply the creator may hold, 5 by default
+    /// * `safety_creator_lookback` - Creator transactions scanned for sells, 10 by default
+    /// * `safety_cache_secs` - Seconds a verdict is reused, 300 by default
+    pub fn from_env() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut policy = Self::default();
+
+        if let Ok(max_score) = env::var("safety_max_score") {
+            policy.max_score = Some(max_score.parse()?);
+        }
+        if let Ok(top_holders) = env::var("safety_top_holders") {
+            policy.top_holders = top_holders.parse()?;
+        }
+        if let Ok(percent) = env::var("safety_max_top_holders_percent") {
+            policy.max_top_holders_percent = percent.parse()?;
+        }
+        if let Ok(percent) = env::var("safety_max_creator_percent") {
+            policy.max_creator_percent = percent.parse()?;
+        }
+        if let Ok(lookback) = env::var("safety_creator_lookback") {
+            policy.creator_lookback = lookback.parse()?;
+        }
+        if let Ok(secs) = env::var("safety_cache_secs") {
+            policy.cache_secs = secs.parse()?;
+        }
+
+        Ok(policy)
+    }
+
+    /// Returns what the policy finds risky in the facts of a token
+    ///
+    /// * `facts` - What was fetched of the token
+    pub fn findings(&self, facts: &TokenFacts) -> Vec<SafetyFinding> {
+        let mut findings = Vec::new();
+        let percent_of_supply = |amount: u64| {
+            if facts.supply == 0 {
+                return 0.0;
+            }
+            amount as f64 * 100.0 / facts.supply as f64
+        };
+
+        if let Some(authority) = facts.mint_authority {
+            findings.push(SafetyFinding::MintAuthority(authority));
+        }
+        if let Some(authority) = facts.freeze_authority {
+            findings.push(SafetyFinding::FreezeAuthority(authority));
+        }
+
+        let top = facts.top_holdings.len().min(self.top_holders);
+        let percent = percent_of_supply(facts.top_holdings.iter().take(top).sum());
+        if percent > self.max_top_holders_percent {
+            findings.push(SafetyFinding::HolderConcentration { top, percent });
+        }
+
+        if let Some(creator) = facts.creator {
+            let percent = percent_of_supply(facts.creator_holdings);
+            if percent > self.max_creator_percent {
+                findings.push(SafetyFinding::CreatorHoldings { creator, percent });
+            }
+            if facts.creator_sells > 0 {
+                findings.push(SafetyFinding::CreatorSells {
+                    creator,
+                    sells: facts.creator_sells,
+                });
+            }
+        }
+
+        if facts.metadata_mutable == Some(true) {
+            findings.push(SafetyFinding::MutableMetadata);
+        }
+
+        findings
+    }
+
+    /// Checks a verdict against the max score
+    ///
+    /// * `verdict` - The verdict of the token about to be bought
+    pub fn check(&self, verdict: &SafetyVerdict) -> Result<(), SafetyError> {
+        match self.max_score {
+            Some(max) if verdict.score > max => Err(SafetyError::TooRisky {
+                mint: verdict.mint,
+                score: verdict.score,
+                max,
+                findings: verdict
+                    .findings
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Scores the tokens about to be bought and blocks the risky ones. The
+/// verdicts are cached per mint.
+pub struct TokenSafety {
+    ctx: VenueContext,
+    policy: SafetyPolicy,
+    verdicts: Mutex<HashMap<Pubkey, SafetyVerdict>>,
+}
+
+impl TokenSafety {
+    pub fn new(ctx: VenueContext, policy: SafetyPolicy) -> Self {
+        Self {
+            ctx,
+            policy,
+            verdicts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn policy(&self) -> &SafetyPolicy {
+        &self.policy
+    }
+
+    /// Checks a token about to be bought, Ok(Err) holding why it is
+    /// blocked. Nothing is fetched when the policy has no max score.
+    ///
+    /// * `mint` - The token about to be bought
+    pub async fn check_buy(
+        &self,
+        mint: &Pubkey,
+    ) -> Result<Result<(), SafetyError>, Box<dyn Error + Send + Sync>> {
+        if self.policy.max_score.is_none() {
+            return Ok(Ok(()));
+        }
+        let verdict = self.verdict(mint).await?;
+        Ok(self.policy.check(&verdict))
+    }
+
+    /// Returns the verdict of a mint, checked again once the cached one is
+    /// older than the cache duration
+    ///
+    /// * `mint` - The token mint
+    pub async fn verdict(
+        &self,
+        mint: &Pubkey,
+    ) -> Result<SafetyVerdict, Box<dyn Error + Send + Sync>> {
+        let now = unix_now();
+        let cached = self
+            .verdicts
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get(mint)
+            .filter(|verdict| now.saturating_sub(verdict.checked_at) < self.policy.cache_secs)
+            .cloned();
+        if let Some(verdict) = cached {
+            return Ok(verdict);
+        }
+
+        let facts = self.fetch_facts(mint).await?;
+        let verdict = SafetyVerdict::new(*mint, self.policy.findings(&facts), now);
+        println!("Safety of {mint}: {verdict}");
+
+        self.verdicts
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(*mint, verdict.clone());
+        Ok(verdict)
+    }
+
+    /// Fetches the authorities, the largest holders, the creator and the
+    /// metadata of a mint
+    async fn fetch_facts(&self, mint: &Pubkey) -> Result<TokenFacts, Box<dyn Error + Send + Sync>> {
+        let mut facts = TokenFacts::default();
+
+        let creator_ata = {
+            let connection = self.ctx.helius.lock().await.async_connection()?;
+
+            let mint_account = connection.get_account(mint).await?;
+            let mint_state = SplMintState::decode_account(&mint_account)?;
+            facts.mint_authority = mint_state.mint_authority();
+            facts.freeze_authority = mint_state.freeze_authority();
+            facts.supply = mint_state.supply;
+
+            // Holdings summed per wallet, programs own their accounts through
+            // addresses off the curve
+            let largest: Vec<Pubkey> = connection
+                .get_token_largest_accounts(mint)
+                .await?
+                .iter()
+                .filter_map(|account| account.address.parse().ok())
+                .collect();
+            let mut holdings: HashMap<Pubkey, u64> = HashMap::new();
+            for account in connection
+                .get_multiple_accounts(&largest)
+                .await?
+                .into_iter()
+                .flatten()
+            {
+                if let Ok(token_account) = SplTokenAccountState::decode_account(&account) {
+                    let owner = token_account.owner;
+                    if owner.is_on_curve() {
+                        *holdings.entry(owner).or_default() += token_account.amount;
+                    }
+                }
+            }
+            facts.top_holdings = holdings.into_values().collect();
+            facts.top_holdings.sort_unstable_by(|a, b| b.cmp(a));
+
+            let accounts = connection
+                .get_multiple_accounts(&[
+                    PumpFunBondingCurve::address(mint),
+                    TokenMetadata::address(mint),
+                ])
+                .await?;
+            let curve_creator = accounts
+                .first()
+                .cloned()
+                .flatten()
+                .filter(|account| account.owner == PUMP_FUN_PROGRAM)
+                .and_then(|account| PumpFunBondingCurve::creator(&account.data));
+            let metadata = accounts
+                .get(1)
+                .cloned()
+                .flatten()
+                .and_then(|account| TokenMetadata::parse(&account.data));
+            facts.metadata_mutable = metadata.as_ref().map(|metadata| metadata.is_mutable);
+            facts.creator =
+                curve_creator.or(metadata.as_ref().map(|metadata| metadata.update_authority));
+
+            let creator_ata = facts.creator.map(|creator| {
+                get_associated_token_address_with_program_id(&creator, mint, &mint_account.owner)
+            });
+            if let Some(creator_ata) = creator_ata {
+                facts.creator_holdings =
+                    match connection.get_token_account_balance(&creator_ata).await {
+                        Ok(balance) => balance.amount.parse()?,
+                        Err(_) => 0,
+                    };
+            }
+            creator_ata
+        };
+
+        if let (Some(creator), Some(creator_ata)) = (facts.creator, creator_ata) {
+            facts.creator_sells = self.creator_sells(&creator, &creator_ata, mint).await?;
+        }
+        Ok(facts)
+    }
+
+    /// Counts the recent transactions of the creator token account lowering
+    /// the creator balance
+    async fn creator_sells(
+        &self,
+        creator: &Pubkey,
+        creator_ata: &Pubkey,
+        mint: &Pubkey,
+    ) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        if self.policy.creator_lookback == 0 {
+            return Ok(0);
+        }
+        let signatures = {
+            let connection = self.ctx.helius.lock().await.async_connection()?;
+            connection
+                .get_signatures_for_address_with_config(
+                    creator_ata,
+                    GetConfirmedSignaturesForAddress2Config {
+                        limit: Some(self.policy.creator_lookback),
+                        ..Default::default()
+                    },
+                )
+                .await?
+        };
+
+        let mut sells = 0;
+        for signature in signatures
+            .iter()
+            .filter(|signature| signature.err.is_none())
+        {
+            let transaction = {
+                let connection = self.ctx.helius.lock().await.async_connection()?;
+                connection
+                    .send::<Value>(
+                        RpcRequest::GetTransaction,
+                        json!([signature.signature, {
+                            "encoding": "json",
+                            "commitment": "confirmed",
+                            "maxSupportedTransactionVersion": 0,
+                        }]),
+                    )
+                    .await?
+            };
+            if owner_token_delta(&transaction, creator, mint) < 0 {
+                sells += 1;
+            }
+        }
+        Ok(sells)
+    }
+}
+
+/// Returns the change of the tokens of `mint` an owner holds across a
+/// confirmed transaction, negative when it sold or sent some
+///
+/// * `transaction` - The `getTransaction` result
+/// * `owner` - The wallet
+/// * `mint` - The token mint
+pub fn owner_token_delta(transaction: &Value, owner: &Pubkey, mint: &Pubkey) -> i128 {
+    let (owner, mint) = (owner.to_string(), mint.to_string());
+    let total = |balances: &str| -> i128 {
+        transaction["meta"][balances]
+            .as_array()
+            .map(|balances| {
+                balances
+                    .iter()
+                    .filter(|balance| {
+                        balance["owner"].as_str() == Some(owner.as_str())
+                            && balance["mint"].as_str() == Some(mint.as_str())
+                    })
+                    .filter_map(|balance| {
+                        balance["uiTokenAmount"]["amount"]
+                            .as_str()?
+                            .parse::<i128>()
+                            .ok()
+                    })
+                    .sum()
+            })
+            .unwrap_or_default()
+    };
+
+    total("postTokenBalances") - total("preTokenBalances")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn facts() -> TokenFacts {
+        TokenFacts {
+            supply: 1_000_000_000,
+            top_holdings: vec![50_000_000, 40_000_000, 30_000_000],
+            creator: Some(Pubkey::new_unique()),
+            creator_holdings: 10_000_000,
+            metadata_mutable: Some(false),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_findings() {
+        let policy = SafetyPolicy {
+            max_score: Some(50),
+            ..Default::default()
+        };
+
+        // 12% in the top wallets, 1% held by the creator
+        let safe = SafetyVerdict::new(Pubkey::new_unique(), policy.findings(&facts()), 0);
+        assert_eq!(safe.score, 0);
+        assert_eq!(policy.check(&safe), Ok(()));
+
+        let mut risky = facts();
+        risky.mint_authority = Some(Pubkey::new_unique());
+        risky.top_holdings = vec![200_000_000, 150_000_000];
+        risky.creator_holdings = 60_000_000;
+        risky.creator_sells = 2;
+        risky.metadata_mutable = Some(true);
+        let findings = policy.findings(&risky);
+        assert_eq!(findings.len(), 5);
+        assert!(findings.contains(&SafetyFinding::HolderConcentration {
+            top: 2,
+            percent: 35.0
+        }));
+        assert!(matches!(
+            findings[2],
+            SafetyFinding::CreatorHoldings { percent, .. } if percent == 6.0
+        ));
+
+        let verdict = SafetyVerdict::new(Pubkey::new_unique(), findings, 0);
+        assert_eq!(verdict.score, 100);
+        assert!(matches!(
+            policy.check(&verdict),
+            Err(SafetyError::TooRisky {
+                score: 100,
+                max: 50,
+                ..
+            })
+        ));
+
+        // A freeze authority alone stays under the max
+        let mut frozen = facts();
+        frozen.freeze_authority = Some(Pubkey::new_unique());
+        let verdict = SafetyVerdict::new(Pubkey::new_unique(), policy.findings(&frozen), 0);
+        assert_eq!(verdict.score, 40);
+        assert_eq!(policy.check(&verdict), Ok(()));
+        // Unless no max is set
+        assert_eq!(SafetyPolicy::default().check(&verdict), Ok(()));
+    }
+
+    #[test]
+    fn test_owner_token_delta() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let balance = |owner: &Pubkey, amount: &str| {
+            json!({
+                "owner": owner.to_string(),
+                "mint": mint.to_string(),
+                "uiTokenAmount": { "amount": amount }
+            })
+        };
+        let transaction = json!({ "meta": {
+            "preTokenBalances": [balance(&owner, "1000"), balance(&Pubkey::new_unique(), "5")],
+            "postTokenBalances": [balance(&owner, "400"), balance(&Pubkey::new_unique(), "605")]
+        } });
+
+        assert_eq!(owner_token_delta(&transaction, &owner, &mint), -600);
+        assert_eq!(
+            owner_token_delta(&transaction, &Pubkey::new_unique(), &mint),
+            0
+        );
+        assert_eq!(owner_token_delta(&Value::Null, &owner, &mint), 0);
+    }
+}
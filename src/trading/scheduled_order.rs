@@ -0,0 +1,542 @@
+use solana_sdk::pubkey::Pubkey;
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::{bot::TradeSide, db::ScheduledOrderRecord};
+
+/// Children failing in a row before a scheduled order is given up
+pub const SCHEDULED_ORDER_MAX_FAILURES: u64 = 3;
+
+/// Seconds a child sent before a restart may still land in: its bundle is
+/// polled for 60s and its blockhash expires after 150 blocks
+pub const SCHEDULED_ORDER_IN_FLIGHT_SECS: u64 = 150;
+
+/// How a parent order is split into child swaps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderSchedule {
+    /// The total in `slices` equal children spread over `duration_secs`
+    Twap { duration_secs: u64, slices: u64 },
+    /// The total in `slices` equal children, one every `interval_secs`
+    Dca { interval_secs: u64, slices: u64 },
+    /// Sells `sell_percent` of the total every time the price rises
+    /// `rise_percent` above the last sell, the first rise counted from the
+    /// price when the order starts
+    ScaleOut {
+        rise_percent: f64,
+        sell_percent: f64,
+    },
+}
+
+impl OrderSchedule {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Twap { .. } => "twap",
+            Self::Dca { .. } => "dca",
+            Self::ScaleOut { .. } => "scale_out",
+        }
+    }
+
+    /// Returns the seconds between two children of the time schedules
+    pub fn interval_secs(&self) -> u64 {
+        match *self {
+            Self::Twap {
+                duration_secs,
+                slices,
+            } => duration_secs / slices.max(1),
+            Self::Dca { interval_secs, .. } => interval_secs,
+            Self::ScaleOut { .. } => 0,
+        }
+    }
+}
+
+impl fmt::Display for OrderSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Twap {
+                duration_secs,
+                slices,
+            } => write!(f, "TWAP of {slices} slices over {duration_secs}s"),
+            Self::Dca {
+                interval_secs,
+                slices,
+            } => write!(f, "DCA of {slices} slices every {interval_secs}s"),
+            Self::ScaleOut {
+                rise_percent,
+                sell_percent,
+            } => write!(f, "sell {sell_percent}% every {rise_percent}% rise"),
+        }
+    }
+}
+
+/// Where a scheduled order stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduledOrderStatus {
+    Active,
+    /// The whole total was sent
+    Done,
+    Cancelled,
+    /// Given up after too many children failing in a row
+    Failed,
+}
+
+impl ScheduledOrderStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Done => "done",
+            Self::Cancelled => "cancelled",
+            Self::Failed => "failed",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<Self> {
+        match status {
+            "active" => Some(Self::Active),
+            "done" => Some(Self::Done),
+            "cancelled" => Some(Self::Cancelled),
+            "failed" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// A parent order split into child swaps over time, on the best route
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledOrder {
+    /// 0 until stored
+    pub id: i64,
+    pub mint: Pubkey,
+    pub side: TradeSide,
+    pub schedule: OrderSchedule,
+    /// Lamports spent on a buy, raw tokens sold on a sell
+    pub total: u64,
+    /// Part of the total sent so far
+    pub filled: u64,
+    /// Children sent so far
+    pub children: u64,
+    /// Unix timestamp the next child of a time schedule is due at
+    pub next_at: u64,
+    /// Price of the last scale out sell, in lamports per raw token, 0
+    /// until the order starts
+    pub reference_price: f64,
+    pub slippage_percent: u64,
+    pub status: ScheduledOrderStatus,
+    /// Children failing in a row
+    pub failures: u64,
+    /// Why the last child failed, empty otherwise
+    pub error: String,
+    /// Signatures of the children sent
+    pub signatures: Vec<String>,
+    pub created_at: u64,
+    /// Input of the child being sent, 0 when none is. Stored before the
+    /// send, so a restart reconciles the child instead of sending it again.
+    pub in_flight_amount: u64,
+    /// Unix timestamp the child in flight was sent at, its fills landing
+    /// after it
+    pub in_flight_at: u64,
+}
+
+impl ScheduledOrder {
+    /// Creates an order whose first child is due right away
+    ///
+    /// * `mint` - The token traded against SOL
+    /// * `side` - The swap direction, scale outs only sell
+    /// * `schedule` - How the total is split
+    /// * `total` - Lamports spent on a buy, raw tokens sold on a sell
+    /// * `slippage_percent` - The slippage tolerated on each child
+    /// * `now` - The current unix timestamp
+    pub fn new(
+        mint: Pubkey,
+        side: TradeSide,
+        schedule: OrderSchedule,
+        total: u64,
+        slippage_percent: u64,
+        now: u64,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        match schedule {
+            OrderSchedule::Twap { slices, .. } | OrderSchedule::Dca { slices, .. } => {
+                if slices == 0 {
+                    return Err("A scheduled order needs at least one slice".into());
+                }
+            }
+            OrderSchedule::ScaleOut {
+                rise_percent,
+                sell_percent,
+            } => {
+                if side != TradeSide::Sell {
+                    return Err("Only sells can be scaled out".into());
+                }
+                if rise_percent <= 0.0 || sell_percent <= 0.0 || sell_percent > 100.0 {
+                    return Err("A scale out needs a positive rise and a sell of 0-100%".into());
+                }
+            }
+        }
+        if total == 0 {
+            return Err("Nothing to schedule".into());
+        }
+
+        Ok(Self {
+            id: 0,
+            mint,
+            side,
+            schedule,
+            total,
+            filled: 0,
+            children: 0,
+            next_at: now,
+            reference_price: 0.0,
+            slippage_percent,
+            status: ScheduledOrderStatus::Active,
+            failures: 0,
+            error: String::new(),
+            signatures: vec![],
+            created_at: now,
+            in_flight_amount: 0,
+            in_flight_at: 0,
+        })
+    }
+
+    /// Returns what the fills of the children are booked under, e.g. "scheduled_twap"
+    pub fn strategy(&self) -> String {
+        format!("scheduled_{}", self.schedule.kind())
+    }
+
+    /// Returns the part of the total not sent yet
+    pub fn remaining(&self) -> u64 {
+        self.total.saturating_sub(self.filled)
+    }
+
+    /// Returns the input of the next child, the last one taking the
+    /// rounding leftover
+    pub fn child_amount(&self) -> u64 {
+        let amount = match self.schedule {
+            OrderSchedule::Twap { slices, .. } | OrderSchedule::Dca { slices, .. } => {
+                if self.children + 1 >= slices {
+                    self.remaining()
+                } else {
+                    self.total / slices.max(1)
+                }
+            }
+            OrderSchedule::ScaleOut { sell_percent, .. } => {
+                (self.total as f64 * sell_percent / 100.0).ceil() as u64
+            }
+        };
+        amount.min(self.remaining())
+    }
+
+    /// Returns the input of the child due now, None when none is
+    ///
+    /// * `now` - The current unix timestamp
+    /// * `price` - The current price of a child, in lamports per raw
+    ///   token, only read by scale outs
+    pub fn due(&self, now: u64, price: Option<f64>) -> Option<u64> {
+        if self.status != ScheduledOrderStatus::Active
+            || self.remaining() == 0
+            || self.in_flight_amount > 0
+        {
+            return None;
+        }
+        let due = match self.schedule {
+            OrderSchedule::ScaleOut { rise_percent, .. } => {
+                let price = price?;
+                self.reference_price > 0.0
+                    && price >= self.reference_price * (1.0 + rise_percent / 100.0)
+            }
+            _ => now >= self.next_at,
+        };
+        due.then(|| self.child_amount())
+            .filter(|amount| *amount > 0)
+    }
+
+    /// Marks a child as being sent, until it is recorded or failed
+    ///
+    /// * `amount_in` - The input of the child
+    /// * `now` - The current unix timestamp
+    pub fn start_child(&mut self, amount_in: u64, now: u64) {
+        self.in_flight_amount = amount_in;
+        self.in_flight_at = now;
+    }
+
+    /// Records a child sent, the order being done once the total is sent
+    ///
+    /// * `amount_in` - The input of the child
+    /// * `signatures` - The transactions of the child
+    /// * `price` - The price the child was quoted at, in lamports per raw token
+    /// * `now` - The current unix timestamp
+    pub fn record_child(&mut self, amount_in: u64, signatures: &[String], price: f64, now: u64) {
+        self.filled = self.filled.saturating_add(amount_in).min(self.total);
+        self.children += 1;
+        self.next_at = now + self.schedule.interval_secs();
+        self.reference_price = price;
+        self.failures = 0;
+        self.error.clear();
+        self.signatures.extend_from_slice(signatures);
+        self.in_flight_amount = 0;
+        if self.remaining() == 0 {
+            self.status = ScheduledOrderStatus::Done;
+        }
+    }
+
+    /// Records a child failing, retried on the next interval. The order is
+    /// given up after `SCHEDULED_ORDER_MAX_FAILURES` failures in a row.
+    ///
+    /// * `error` - Why the child failed
+    /// * `now` - The current unix timestamp
+    pub fn record_failure(&mut self, error: &str, now: u64) {
+        self.failures += 1;
+        self.error = error.to_string();
+        self.in_flight_amount = 0;
+        self.next_at = now + self.schedule.interval_secs();
+        if self.failures >= SCHEDULED_ORDER_MAX_FAILURES {
+            self.status = ScheduledOrderStatus::Failed;
+        }
+    }
+
+    pub fn to_record(&self) -> ScheduledOrderRecord {
+        let (interval_secs, slices, trigger_percent, sell_percent) = match self.schedule {
+            OrderSchedule::Twap {
+                duration_secs,
+                slices,
+            } => (duration_secs, slices, 0.0, 0.0),
+            OrderSchedule::Dca {
+                interval_secs,
+                slices,
+            } => (interval_secs, slices, 0.0, 0.0),
+            OrderSchedule::ScaleOut {
+                rise_percent,
+                sell_percent,
+            } => (0, 0, rise_percent, sell_percent),
+        };
+
+        ScheduledOrderRecord {
+            id: self.id,
+            mint: self.mint.to_string(),
+            side: self.side.to_string().to_ascii_lowercase(),
+            kind: self.schedule.kind().to_string(),
+            interval_secs,
+            slices,
+            trigger_percent,
+            sell_percent,
+            total: self.total,
+            filled: self.filled,
+            children: self.children,
+            next_at: self.next_at,
+            reference_price: self.reference_price,
+            slippage_percent: self.slippage_percent,
+            status: self.status.as_str().to_string(),
+            failures: self.failures,
+            error: self.error.clone(),
+            signatures: self.signatures.join(","),
+            created_at: self.created_at,
+            in_flight_amount: self.in_flight_amount,
+            in_flight_at: self.in_flight_at,
+        }
+    }
+
+    pub fn from_record(
+        record: &ScheduledOrderRecord,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let schedule = match record.kind.as_str() {
+            "twap" => OrderSchedule::Twap {
+                duration_secs: record.interval_secs,
+                slices: record.slices,
+            },
+            "dca" => OrderSchedule::Dca {
+                interval_secs: record.interval_secs,
+                slices: record.slices,
+            },
+            "scale_out" => OrderSchedule::ScaleOut {
+                rise_percent: record.trigger_percent,
+                sell_percent: record.sell_percent,
+            },
+            kind => return Err(format!("Unknown scheduled order kind: {kind}").into()),
+        };
+
+        Ok(Self {
+            id: record.id,
+            mint: Pubkey::from_str(&record.mint)?,
+            side: match record.side.as_str() {
+                "buy" => TradeSide::Buy,
+                "sell" => TradeSide::Sell,
+                side => return Err(format!("Unknown side: {side}").into()),
+            },
+            schedule,
+            total: record.total,
+            filled: record.filled,
+            children: record.children,
+            next_at: record.next_at,
+            reference_price: record.reference_price,
+            slippage_percent: record.slippage_percent,
+            status: ScheduledOrderStatus::parse(&record.status)
+                .ok_or_else(|| format!("Unknown status: {}", record.status))?,
+            failures: record.failures,
+            error: record.error.clone(),
+            signatures: record
+                .signatures
+                .split(',')
+                .filter(|signature| !signature.is_empty())
+                .map(String::from)
+                .collect(),
+            created_at: record.created_at,
+            in_flight_amount: record.in_flight_amount,
+            in_flight_at: record.in_flight_at,
+        })
+    }
+}
+
+impl fmt::Display for ScheduledOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = match self.side {
+            TradeSide::Buy => "lamports",
+            TradeSide::Sell => "tokens",
+        };
+        write!(
+            f,
+            "#{} {} {} | {} | {} of {} {unit} sent in {} children | {}",
+            self.id,
+            self.side,
+            self.mint,
+            self.schedule,
+            self.filled,
+            self.total,
+            self.children,
+            self.status.as_str()
+        )?;
+        if !self.error.is_empty() {
+            write!(f, " | Last error: {}", self.error)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn twap() -> ScheduledOrder {
+        ScheduledOrder::new(
+            Pubkey::new_unique(),
+            TradeSide::Buy,
+            OrderSchedule::Twap {
+                duration_secs: 300,
+                slices: 3,
+            },
+            1_000,
+            10,
+            1_000,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_time_schedule() {
+        let mut order = twap();
+        assert_eq!(order.due(1_000, None), Some(333));
+
+        order.record_child(333, &["a".to_string()], 1.0, 1_000);
+        assert_eq!(order.next_at, 1_100);
+        assert_eq!(order.due(1_099, None), None);
+        assert_eq!(order.due(1_100, None), Some(333));
+
+        order.record_child(333, &["b".to_string()], 1.0, 1_100);
+        // The last child takes the leftover
+        assert_eq!(order.due(1_200, None), Some(334));
+        order.record_child(334, &["c".to_string()], 1.0, 1_200);
+        assert_eq!(order.status, ScheduledOrderStatus::Done);
+        assert_eq!(order.due(1_300, None), None);
+        assert_eq!(order.signatures, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_in_flight() {
+        let mut order = twap();
+        order.start_child(333, 1_000);
+        // Not sent again until the child in flight is settled
+        assert_eq!(order.due(1_000, None), None);
+        assert_eq!(
+            ScheduledOrder::from_record(&order.to_record()).unwrap(),
+            order
+        );
+
+        order.record_child(333, &["a".to_string()], 1.0, 1_000);
+        assert_eq!(order.in_flight_amount, 0);
+        assert_eq!(order.due(1_100, None), Some(333));
+
+        order.start_child(333, 1_100);
+        order.record_failure("Not landed", 1_100);
+        assert_eq!(order.due(1_200, None), Some(333));
+    }
+
+    #[test]
+    fn test_failures() {
+        let mut order = twap();
+        order.record_failure("No route", 1_000);
+        assert_eq!(order.due(1_000, None), None);
+        assert_eq!(order.due(1_100, None), Some(333));
+
+        order.record_failure("No route", 1_100);
+        order.record_failure("No route", 1_200);
+        assert_eq!(order.status, ScheduledOrderStatus::Failed);
+        assert_eq!(order.due(1_300, None), None);
+    }
+
+    #[test]
+    fn test_scale_out() {
+        let schedule = OrderSchedule::ScaleOut {
+            rise_percent: 20.0,
+            sell_percent: 10.0,
+        };
+        assert!(
+            ScheduledOrder::new(Pubkey::new_unique(), TradeSide::Buy, schedule, 1_000, 10, 0)
+                .is_err()
+        );
+
+        let mut order = ScheduledOrder::new(
+            Pubkey::new_unique(),
+            TradeSide::Sell,
+            schedule,
+            1_000,
+            10,
+            0,
+        )
+        .unwrap();
+        // Nothing sold before the reference price is set
+        assert_eq!(order.due(0, Some(1.0)), None);
+        order.reference_price = 1.0;
+
+        assert_eq!(order.due(0, Some(1.1)), None);
+        assert_eq!(order.due(0, Some(1.2)), Some(100));
+        order.record_child(100, &[], 1.2, 0);
+        // The next rise is counted from the last sell
+        assert_eq!(order.due(0, Some(1.3)), None);
+        assert_eq!(order.due(0, Some(1.44)), Some(100));
+    }
+
+    #[test]
+    fn test_record() {
+        let mut order = twap();
+        order.id = 7;
+        order.record_child(333, &["a".to_string(), "b".to_string()], 1.0, 1_000);
+        assert_eq!(
+            ScheduledOrder::from_record(&order.to_record()).unwrap(),
+            order
+        );
+
+        let mut scale_out = ScheduledOrder::new(
+            Pubkey::new_unique(),
+            TradeSide::Sell,
+            OrderSchedule::ScaleOut {
+                rise_percent: 20.0,
+                sell_percent: 30.0,
+            },
+            1_000,
+            10,
+            0,
+        )
+        .unwrap();
+        scale_out.record_failure("No route", 0);
+        assert_eq!(
+            ScheduledOrder::from_record(&scale_out.to_record()).unwrap(),
+            scale_out
+        );
+    }
+}
@@ -2,25 +2,37 @@
 #![deny(clippy::expect_used)]
 #![deny(clippy::panic)]
 #![deny(unused_must_use)]
+mod backtest;
 mod bot;
 mod constants;
+mod copy_trading;
+mod db;
 mod errors;
 mod fast_websocket_client;
+mod trading;
 
-use bot::{subscribe_raydium, PumpFunTxSend, RaydiumMemeTxSend};
-use constants::SOL_DECIMALS;
+use backtest::Recorder;
+use bot::{
+    subscribe_wallets, JupiterClient, PriceImpactGuard, Router, TokenFilter, VenueContext, WsolMode,
+};
+use copy_trading::{CopyEngine, CopyPipeline, CopyTradingConfig, PipelineConfig, SignalFilter};
+use db::Database;
 use dotenv::dotenv;
 use fast_websocket_client::{client, connect, OpCode};
 use helius::{types::Cluster, Helius};
 use std::{
     env,
-    ops::Mul,
+    path::Path,
     sync::Arc,
     time::{Duration, Instant},
 };
+use tokio::sync::Mutex;
+use trading::{
+    ExitRules, PnlLedger, PositionManager, RiskEngine, RiskLimits, SafetyPolicy, TokenSafety,
+};
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     print_bfg9000();
 
     dotenv().ok();
@@ -29,40 +41,152 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("\nStarted at: {:?}", started_at);
 
     // User wallet
-    let singer_prv_key = Arc::new(env::var("signer_prv_key").unwrap());
-
-    // Copy wallet(s)
-    let copy_wallet = env::var("copy_wallet").unwrap();
-    println!("Copy wallet(s): {copy_wallet}");
-
-    // Max SOL put in a buy order
-    let max_sol_buy = env::var("max_sol_buy")
-        .unwrap()
-        .parse::<f64>()
-        .unwrap()
-        .mul(SOL_DECIMALS as f64) as u64;
-    println!("Max buy: {max_sol_buy} SOL");
-
-    // Slippage
-    let slippage_percent = env::var("slippage_percent")
-        .unwrap()
-        .parse::<f64>()
-        .unwrap() as u64;
+    let singer_prv_key = Arc::new(env::var("signer_prv_key")?);
+
+    // Leader wallets, with their sizing, slippage and venues
+    let copy_trading_config = CopyTradingConfig::from_env()?;
+    for leader in &copy_trading_config.leaders {
+        println!(
+            "Leader: {} | Enabled: {} | Sizing: {:?} | Slippage: {}% | Sells: {}",
+            leader.name(),
+            leader.enabled,
+            leader.sizing,
+            leader.slippage_percent,
+            leader.copy_sells
+        );
+    }
+
+    // Leader buys worth copying, by size, token age, curve progress,
+    // liquidity, market cap and repeat buys
+    let signal_filter = SignalFilter::from_env()?;
+    println!("Signal filter: {signal_filter:?}");
+
+    // WSOL funding for AMM trades: "per_trade" (default) or "persistent"
+    let wsol_mode = WsolMode::from_env()?;
+    println!("WSOL mode: {wsol_mode:?}");
+
+    // Tokens eligible for a buy, shared by all the venues
+    let token_filter = Arc::new(TokenFilter::from_env()?);
+    println!("Token filter: {token_filter:?}");
 
     // Max compute unit price in uLamports
     // let max_compute_unit_price = env::var("max_compute_unit_price_ulamports").unwrap().parse::<u64>().unwrap();
 
     // Helius client
-    let helius = Arc::new(
-        Helius::new_with_async_solana(
-            env::var("helius_prod_api_key").unwrap().as_str(),
-            Cluster::MainnetBeta,
-        )
-        .unwrap(),
-    );
+    let helius = Arc::new(Mutex::new(Helius::new_with_async_solana(
+        env::var("helius_prod_api_key")?.as_str(),
+        Cluster::MainnetBeta,
+    )?));
+
+    // Shared by all the venues
+    let venue_context = VenueContext {
+        helius: Arc::clone(&helius),
+        token_filter: Arc::clone(&token_filter),
+        wsol_mode,
+        jupiter: JupiterClient::from_env(),
+        price_impact: PriceImpactGuard::from_env()?,
+    };
+
+    // Copies on the leader pool (default), or on the best route with "best"
+    let router = match env::var("copy_routing").as_deref() {
+        Ok("best") => Some(Arc::new(Router::from_env()?)),
+        _ => None,
+    };
+    println!("Copy routing: {router:?}");
+
+    // Orders placed per copied signal, so a restart never copies a trade twice
+    let db_path = env::var("copy_db_path").unwrap_or_else(|_| "src/db/meme_coins.db".to_string());
+    let db = Arc::new(Database::new(Path::new(&db_path)).await?);
+
+    // Fills read from the confirmed transactions, costs included
+    let pnl_ledger = Arc::new(PnlLedger::new(
+        venue_context.clone(),
+        &singer_prv_key,
+        Arc::clone(&db),
+    )?);
+
+    // Copied buys, sold on the exit rules
+    let exit_rules = ExitRules::from_env()?;
+    println!("Exit rules: {exit_rules:?}");
+    let position_manager = Arc::new(PositionManager::new(
+        venue_context.clone(),
+        Arc::clone(&singer_prv_key),
+        exit_rules,
+        Arc::clone(&db),
+        pnl_ledger,
+    )?);
+    let open_positions = position_manager.load().await?;
+    println!("Open positions: {open_positions}");
+    tokio::spawn(Arc::clone(&position_manager).run());
+
+    // Realized and unrealized PnL of the fills booked
+    {
+        let position_manager = Arc::clone(&position_manager);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(300));
+            loop {
+                interval.tick().await;
+                let marks = position_manager.mark_prices().await;
+                match position_manager.ledger().report(&marks).await {
+                    Ok(report) => print!("PnL:\n{report}"),
+                    Err(e) => eprintln!("Error reporting the PnL: {e:?}"),
+                }
+            }
+        });
+    }
+
+    // Limits every buy, and halts them on the kill switch
+    let risk_limits = RiskLimits::from_env()?;
+    println!("Risk limits: {risk_limits:?}");
+    let risk_engine = Arc::new(RiskEngine::new(
+        venue_context.clone(),
+        &singer_prv_key,
+        risk_limits,
+        position_manager,
+    )?);
+
+    // Blocks the copied buys of the tokens scoring too risky
+    let safety_policy = SafetyPolicy::from_env()?;
+    println!("Safety policy: {safety_policy:?}");
+    let token_safety = Arc::new(TokenSafety::new(venue_context.clone(), safety_policy));
+
+    let copy_engine = Arc::new(CopyEngine::new(
+        venue_context,
+        router,
+        Arc::clone(&singer_prv_key),
+        copy_trading_config,
+        signal_filter,
+        db,
+        risk_engine,
+        token_safety,
+    )?);
+
+    // Bounded stages between the websocket and the sent copies
+    let pipeline_config = PipelineConfig::from_env()?;
+    println!("Pipeline: {pipeline_config:?}");
+    let copy_pipeline = CopyPipeline::start(Arc::clone(&copy_engine), pipeline_config);
+
+    // Per stage latency of the copies, from the websocket frame to the landed slot
+    {
+        let copy_engine = Arc::clone(&copy_engine);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                print!("Copy latency:\n{}", copy_engine.latency());
+            }
+        });
+    }
+
+    // Notifications appended to a file, to be replayed by the backtester
+    let recorder = env::var("record_path")
+        .ok()
+        .map(|path| Recorder::open(Path::new(&path)))
+        .transpose()?;
+    println!("Recording: {}", recorder.is_some());
 
     // WSS URL
-    let url = env::var("helius_prod_wss").unwrap();
+    let url = env::var("helius_prod_wss")?;
 
     'reconnect_loop: loop {
         let future = connect(&url);
@@ -82,8 +206,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         // without pong, this app stops in about 15 minutes.(by the binance API spec.)
         client.set_auto_pong(true);
 
-        // subscribe
-        if let Err(e) = subscribe_raydium(&mut client, started_at).await {
+        // subscribe, every leader in one subscription
+        if let Err(e) =
+            subscribe_wallets(&mut client, started_at, &copy_engine.config().wallets()).await
+        {
             eprintln!("Reconnecting from an Error: {e:?}");
             let _ = client.send_close(&[]).await;
             // tokio::time::sleep(Duration::from_secs(10)).await;
@@ -92,11 +218,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         // message processing loop
         loop {
-            let message = if let Ok(result) =
+            let (message, received_at) = if let Ok(result) =
                 tokio::time::timeout(Duration::from_secs(20), client.receive_frame()).await
             {
                 match result {
-                    Ok(message) => message,
+                    Ok(message) => (message, Instant::now()),
                     Err(e) => {
                         eprintln!("Reconnecting from an Error: {e:?}");
                         let _ = client.send_close(&[]).await;
@@ -122,41 +248,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     // println!("\n---------------\n{payload}\n----------------------\n");
                     println!("\n>>>> got message >>>>\n");
 
-                    let helius_clone = Arc::clone(&helius);
-                    let signer_prv_key_clone = Arc::clone(&singer_prv_key);
-                    let payload_clone = String::from(payload);
-
-                    tokio::spawn(async move {
-                        if let Err(e) = PumpFunTxSend::compose_and_send(
-                            helius_clone,
-                            signer_prv_key_clone,
-                            max_sol_buy,
-                            slippage_percent,
-                            payload_clone,
-                        )
-                        .await
-                        {
-                            eprintln!("Error sending Pump.fun Meme Tx: {e:?}");
+                    if let Some(recorder) = &recorder {
+                        if let Err(e) = recorder.record(payload) {
+                            eprintln!("Error recording a notification: {e:?}");
                         }
-                    });
-
-                    let helius_clone = Arc::clone(&helius);
-                    let signer_prv_key_clone = Arc::clone(&singer_prv_key);
-                    let payload_clone = String::from(payload);
-
-                    tokio::spawn(async move {
-                        if let Err(e) = RaydiumMemeTxSend::compose_and_send(
-                            helius_clone,
-                            signer_prv_key_clone,
-                            max_sol_buy,
-                            slippage_percent,
-                            payload_clone,
-                        )
-                        .await
-                        {
-                            eprintln!("Error sending Raydium Meme Tx: {e:?}");
-                        }
-                    });
+                    }
+
+                    // Decoded, filtered then copied by the pipeline stages,
+                    // the receive loop never waits on them
+                    copy_pipeline.ingest(String::from(payload), received_at);
                 }
                 OpCode::Close => {
                     println!("{:?}", String::from_utf8_lossy(message.payload.as_ref()));